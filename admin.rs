@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::datapack::{DatapackError, import_datapack};
+use crate::interface::{Command, CommandArg, CommandError, CommandRegistry, TaskReporter};
+use crate::localization::{Language, LanguageList, LocalizationError, Translator};
+use crate::registry::{ID, RegistrableEntity, RegistrationPipeline, RegistryError, RegistryKey, with_registry, with_registry_mut};
+
+// Admin commands any app embedding the prompt can register, working on the
+// global REGISTRY:
+//
+//   registry list <type> [namespace]   IDs of a registry, e.g. "registry list items"
+//   registry inspect <id>              Everything registered under an ID and its history
+//   registry reload datapacks          Imports the datapacks added to ADMIN again
+//   lang set <code>                    Loads <lang dir>/<code>.yaml, see take_translator
+//
+//   ADMIN.lock().unwrap().add_datapack("datapacks/vanilla");
+//   admin::reload_datapacks(None)?; // Initial import, so reloads can replace it
+//   commands.register_admin_commands()?;
+//   // Once per frame, unless the commands run in an InteractivePrompt or
+//   // ConsoleScene, which use the translator themselves:
+//   if let Some(t) = ADMIN.lock().unwrap().take_translator() { translator = t; }
+//
+// The commands are in the "ruztex" namespace and need the permissions
+// "ruztex.registry" and "ruztex.lang".
+
+pub static ADMIN: Lazy<Mutex<Admin>> = Lazy::new(|| Mutex::new(Admin::new("lang")));
+
+pub struct Admin {
+    datapacks: Vec<PathBuf>,
+    imported: Vec<RegistrableEntity>, // Registered by the last reload, removed by the next
+    lang_dir: PathBuf,
+    languages: LanguageList,        // Names of languages, the code is used for others
+    translator: Option<Translator>, // Loaded by "lang set", until taken
+}
+
+impl Admin {
+    pub fn new<P: Into<PathBuf>>(lang_dir: P) -> Self {
+        Admin { datapacks: vec![], imported: vec![], lang_dir: lang_dir.into(), languages: LanguageList::new(), translator: None }
+    }
+
+    pub fn add_datapack<P: Into<PathBuf>>(&mut self, root: P) {
+        self.datapacks.push(root.into());
+    }
+
+    pub fn datapacks(&self) -> &[PathBuf] {
+        &self.datapacks
+    }
+
+    pub fn set_lang_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.lang_dir = dir.into();
+    }
+
+    pub fn set_languages(&mut self, languages: LanguageList) {
+        self.languages = languages;
+    }
+
+    // The translator "lang set" loaded since the last call
+    pub fn take_translator(&mut self) -> Option<Translator> {
+        self.translator.take()
+    }
+}
+
+// ------
+// ERRORS
+// ------
+
+#[derive(Debug)]
+pub enum AdminError {
+    Import(PathBuf, DatapackError),
+    Register(RegistryError), // The registry was restored
+    InvalidLanguageCode(String),
+    Language(PathBuf, LocalizationError),
+}
+
+impl Display for AdminError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminError::Import(root, e) => write!(f, "Could not import {}: {}", root.display(), e),
+            AdminError::Register(e) => write!(f, "Could not register the datapacks: {}", e),
+            AdminError::InvalidLanguageCode(code) => write!(f, "Invalid language code '{}', expected e.g. en_US", code),
+            AdminError::Language(path, e) => write!(f, "Could not load {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl Error for AdminError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AdminError::Import(_, e) => Some(e),
+            AdminError::Register(e) => Some(e),
+            AdminError::InvalidLanguageCode(_) => None,
+            AdminError::Language(_, e) => Some(e),
+        }
+    }
+}
+
+// -------
+// HELPERS
+// -------
+
+// Imports every datapack added to ADMIN and replaces what the previous call
+// registered. The registry is unchanged if a datapack can't be read or its
+// content can't be registered.
+pub fn reload_datapacks(reporter: Option<&TaskReporter>) -> Result<String, AdminError> {
+    let (datapacks, imported) = {
+        let admin = ADMIN.lock().unwrap();
+        (admin.datapacks.clone(), admin.imported.clone())
+    };
+    let mut imports = vec![];
+    for (i, root) in datapacks.iter().enumerate() {
+        let import = import_datapack(root).map_err(|e| AdminError::Import(root.clone(), e))?;
+        imports.push(import);
+        if let Some(reporter) = reporter {
+            reporter.progress(i as u64 + 1, datapacks.len() as u64);
+        }
+    }
+
+    let mut entities: Vec<RegistrableEntity> = vec![];
+    for import in &imports {
+        entities.extend(import.tags.iter().cloned().map(RegistrableEntity::Tag));
+        entities.extend(import.recipes.iter().cloned().map(RegistrableEntity::Recipe));
+        entities.extend(import.loot_tables.iter().cloned().map(RegistrableEntity::LootTable));
+    }
+    let mut pipeline = RegistrationPipeline::new();
+    entities.iter().for_each(|entity| pipeline.add(entity.clone()));
+    let registered = with_registry_mut(|registry| {
+        let snapshot = registry.snapshot();
+        // Newest first, so recipes go before the tags they use
+        for entity in imported.iter().rev() {
+            let _ = registry.remove(entity);
+        }
+        pipeline.run(registry).inspect_err(|_| registry.restore(snapshot))
+    });
+    let registered = registered.map_err(AdminError::Register)?;
+    ADMIN.lock().unwrap().imported = entities;
+
+    let skipped: usize = imports.iter().map(|import| import.report.skipped()).sum();
+    Ok(format!("Reloaded {} datapacks: {} entries registered, {} files skipped", datapacks.len(), registered, skipped))
+}
+
+// Loads the language file of `code` for take_translator, also used by
+// InteractivePrompt and ConsoleScene after a command
+pub fn set_language(code: &str) -> Result<String, AdminError> {
+    if !LanguageList::is_valid_code(code) {
+        return Err(AdminError::InvalidLanguageCode(code.to_string()));
+    }
+    let mut admin = ADMIN.lock().unwrap();
+    let language = admin.languages.get(code).cloned().unwrap_or(Language { name: code.to_string(), code: code.to_string() });
+    let path = admin.lang_dir.join(format!("{}.yaml", code));
+    let translator = Translator::load(language.clone(), &path).map_err(|e| AdminError::Language(path.clone(), e))?;
+    let count = translator.translations.len();
+    admin.translator = Some(translator);
+    Ok(format!("Language set to {} ({} translations)", language.name, count))
+}
+
+// Registry named like the key in lowercase, singular or plural, e.g. "item",
+// "items", "loot_tables" or "entities"
+fn registry_key(name: &str) -> Option<RegistryKey> {
+    let name = name.to_lowercase().replace('_', "");
+    RegistryKey::BUILTIN.into_iter().find(|key| {
+        let singular = key.name().to_lowercase();
+        let plural = match singular.strip_suffix('y') {
+            Some(stem) => format!("{}ies", stem),
+            None => format!("{}s", singular),
+        };
+        name == singular || name == plural
+    })
+}
+
+// --------
+// COMMANDS
+// --------
+
+fn arg(name: &str, arg_type: &str, optional: bool) -> CommandArg {
+    CommandArg { name: name.to_string(), arg_type: arg_type.to_string(), range: None, optional, default: None, help: None }
+}
+
+fn list_handler(args: HashMap<String, String>) -> Result<String, String> {
+    let Some(key) = registry_key(&args["type"]) else {
+        let names: Vec<String> = RegistryKey::BUILTIN.iter().map(|k| k.name().to_lowercase()).collect();
+        return Err(format!("Unknown registry '{}', expected one of {}", args["type"], names.join(", ")));
+    };
+    let namespace = args.get("namespace");
+    let ids: Vec<ID> = with_registry(|registry| registry.ids(key));
+    let ids: Vec<String> =
+        ids.into_iter().filter(|id| namespace.is_none_or(|ns| id.namespace() == ns)).map(|id| id.to_string()).collect();
+    if ids.is_empty() {
+        return Ok(format!("No {} registered", key));
+    }
+    Ok(format!("{} ({}):\n  {}", key, ids.len(), ids.join("\n  ")))
+}
+
+fn inspect_handler(args: HashMap<String, String>) -> Result<String, String> {
+    let id = ID::parse(&args["id"]).map_err(|e| format!("Invalid ID '{}': {}", args["id"], e))?;
+    with_registry(|registry| {
+        let mut sections: Vec<String> =
+            RegistryKey::BUILTIN.iter().filter_map(|key| registry.entity(*key, &id)).map(|entity| format!("{:#?}", entity)).collect();
+        if sections.is_empty() {
+            return Err(format!("Nothing is registered under {}", id));
+        }
+        let history: Vec<String> = registry.history_of(&id).map(|event| format!("  {}", event)).collect();
+        if !history.is_empty() {
+            sections.push(format!("History:\n{}", history.join("\n")));
+        }
+        Ok(sections.join("\n"))
+    })
+}
+
+fn reload_handler(_: HashMap<String, String>, reporter: TaskReporter) -> Result<String, String> {
+    reload_datapacks(Some(&reporter)).map_err(|e| e.to_string())
+}
+
+// "registry list|inspect|reload datapacks"
+pub fn registry_command() -> Command {
+    Command::new("registry")
+        .with_namespace("ruztex")
+        .with_permission("ruztex.registry")
+        .with_subcommand(
+            Command::new("list")
+                .with_args(vec![arg("type", "string", false), arg("namespace", "string", true)])
+                .with_shared_handler(list_handler),
+        )
+        .with_subcommand(Command::new("inspect").with_args(vec![arg("id", "id", false)]).with_shared_handler(inspect_handler))
+        .with_subcommand(Command::new("reload").with_subcommand(Command::new("datapacks").with_task_handler(reload_handler)))
+}
+
+// "lang set <code>"
+pub fn lang_command() -> Command {
+    Command::new("lang")
+        .with_namespace("ruztex")
+        .with_permission("ruztex.lang")
+        .with_subcommand(
+            Command::new("set").with_args(vec![arg("code", "string", false)]).with_shared_handler(|args| set_language(&args["code"]).map_err(|e| e.to_string())),
+        )
+}
+
+impl CommandRegistry {
+    // Registers registry_command and lang_command. Their short names are
+    // left to commands of the app registered before.
+    pub fn register_admin_commands(&mut self) -> Result<(), CommandError> {
+        self.register_command(registry_command())?;
+        self.register_command(lang_command())
+    }
+}
@@ -0,0 +1,418 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::conditions::Parser;
+use crate::ecs::{Ai, Entities, EntityId, Health, Position};
+use crate::game::{BlockBroken, EventBus};
+use crate::pathfinding::{Navigator, Pathfinder};
+use crate::random::RuzRng;
+use crate::registry::{ID, PatternKey, Registry};
+use crate::world::{BlockPos, BreakResult, World};
+
+// Behavior trees for entities with Ai, run once per tick. Ai::behavior names
+// the tree, registered entity kinds with a behavior spawn with Ai. Trees
+// are written like conditions, also in YAML files of name: tree. Mobs move
+// through the pathfinding module on the 2-D grid, one step per tick.
+//
+//   let behaviors = Behaviors::new().with_behavior(
+//       "zombie",
+//       "selector(sequence(health_below(0.3), flee(8)), sequence(player_within(1), attack()), chase(12), wander(5))".parse()?,
+//   );
+//   let mut context = AiContext::new(&mut world, &registry, &pathfinder, &mut rng, &mut events).with_player(player);
+//   behaviors.tick(&mut entities, &mut context);
+
+// What a node did this tick
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Success,
+    Failure,
+    Running, // Not done yet, e.g. walking
+}
+
+// What the nodes act on
+pub struct AiContext<'a> {
+    pub world: &'a mut World,
+    pub registry: &'a Registry,
+    pub pathfinder: &'a Pathfinder,
+    pub player: Option<EntityId>, // Chased, fled from and attacked
+    pub rng: &'a mut RuzRng,
+    pub events: &'a mut EventBus, // Gets BlockBroken for blocks mobs break
+}
+
+impl<'a> AiContext<'a> {
+    pub fn new(
+        world: &'a mut World,
+        registry: &'a Registry,
+        pathfinder: &'a Pathfinder,
+        rng: &'a mut RuzRng,
+        events: &'a mut EventBus,
+    ) -> Self {
+        AiContext { world, registry, pathfinder, player: None, rng, events }
+    }
+
+    pub fn with_player(mut self, player: EntityId) -> Self {
+        self.player = Some(player);
+        self
+    }
+}
+
+// -----
+// NODES
+// -----
+
+// Tree syntax, numbers are grid distances or shares from 0.0 to 1.0:
+//   sequence(...)        Runs the nodes in order while they succeed
+//   selector(...)        Runs the nodes in order until one doesn't fail
+//   not(node)            Swaps success and failure
+//   player_within(n)     Condition: the player is at most n steps away
+//   health_below(f)      Condition: health is below f of max health
+//   chance(f)            Condition: random with probability f
+//   idle()               Does nothing and succeeds
+//   wander(n)            Walks to random spots within n steps
+//   flee(n)              Steps away from the player until n steps away
+//   chase(n)             Walks next to the player, fails if farther than n
+//   attack()             Hits the player if next to it
+//   break_block(tag)     Breaks a neighboring block in the tag
+#[derive(Clone, Debug, PartialEq)]
+pub enum BehaviorNode {
+    Sequence(Vec<BehaviorNode>),
+    Selector(Vec<BehaviorNode>),
+    Not(Box<BehaviorNode>),
+    Condition(AiCondition),
+    Action(AiAction),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AiCondition {
+    PlayerWithin(u32),
+    HealthBelow(f32),
+    Chance(f32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AiAction {
+    Idle,
+    Wander(u32),
+    Flee(u32),
+    Chase(u32),
+    Attack,
+    BreakBlock(ID),
+}
+
+fn distance(a: BlockPos, b: BlockPos) -> u32 {
+    a.x.abs_diff(b.x) + a.y.abs_diff(b.y)
+}
+
+fn position(entities: &Entities, entity: EntityId) -> Option<BlockPos> {
+    entities.get::<Position>(entity).map(|p| BlockPos::new(p.x, p.y))
+}
+
+fn player_position(entities: &Entities, context: &AiContext) -> Option<BlockPos> {
+    context.player.filter(|p| entities.is_alive(*p)).and_then(|p| position(entities, p))
+}
+
+fn move_to(entities: &mut Entities, entity: EntityId, pos: BlockPos) {
+    entities.insert(entity, Position { x: pos.x, y: pos.y });
+}
+
+// One step along the entity's Navigator
+fn walk(entities: &mut Entities, entity: EntityId, from: BlockPos, context: &AiContext) -> Status {
+    let navigator = entities.get_mut::<Navigator>(entity).unwrap();
+    match navigator.next_step(from, context.world, context.pathfinder, context.registry) {
+        Some(next) => {
+            let arrived = next == navigator.goal();
+            move_to(entities, entity, next);
+            if arrived { Status::Success } else { Status::Running }
+        }
+        None if navigator.goal() == from => Status::Success,
+        None => Status::Failure,
+    }
+}
+
+impl BehaviorNode {
+    pub fn run(&self, entity: EntityId, entities: &mut Entities, context: &mut AiContext) -> Status {
+        match self {
+            BehaviorNode::Sequence(nodes) => {
+                for node in nodes {
+                    match node.run(entity, entities, context) {
+                        Status::Success => {}
+                        status => return status,
+                    }
+                }
+                Status::Success
+            }
+            BehaviorNode::Selector(nodes) => {
+                for node in nodes {
+                    match node.run(entity, entities, context) {
+                        Status::Failure => {}
+                        status => return status,
+                    }
+                }
+                Status::Failure
+            }
+            BehaviorNode::Not(node) => match node.run(entity, entities, context) {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                Status::Running => Status::Running,
+            },
+            BehaviorNode::Condition(condition) => match condition.test(entity, entities, context) {
+                true => Status::Success,
+                false => Status::Failure,
+            },
+            BehaviorNode::Action(action) => action.run(entity, entities, context),
+        }
+    }
+}
+
+impl AiCondition {
+    pub fn test(&self, entity: EntityId, entities: &Entities, context: &mut AiContext) -> bool {
+        match self {
+            AiCondition::PlayerWithin(steps) => match (position(entities, entity), player_position(entities, context)) {
+                (Some(pos), Some(player)) => distance(pos, player) <= *steps,
+                _ => false,
+            },
+            AiCondition::HealthBelow(share) => {
+                entities.get::<Health>(entity).is_some_and(|h| (h.current as f32) < h.max as f32 * share)
+            }
+            AiCondition::Chance(probability) => context.rng.chance(*probability),
+        }
+    }
+}
+
+impl AiAction {
+    pub fn run(&self, entity: EntityId, entities: &mut Entities, context: &mut AiContext) -> Status {
+        let Some(pos) = position(entities, entity) else {
+            return Status::Failure;
+        };
+        let player = player_position(entities, context);
+        match self {
+            AiAction::Idle => Status::Success,
+            AiAction::Wander(radius) => {
+                // Keeps walking to the current goal if it's close enough, e.g.
+                // where the player was last chased to
+                let current = entities.get::<Navigator>(entity).map(Navigator::goal);
+                if current.is_none_or(|goal| goal == pos || distance(goal, pos) > *radius) {
+                    let mut offset = || context.rng.range(0, 2 * radius) as i32 - *radius as i32;
+                    let targets: Vec<BlockPos> = (0..8).map(|_| pos.offset(offset(), offset(), 0)).collect();
+                    let target = targets.into_iter().find(|t| *t != pos && context.pathfinder.is_walkable(context.world, *t, context.registry));
+                    let Some(target) = target else {
+                        return Status::Failure;
+                    };
+                    entities.insert(entity, Navigator::new(target));
+                }
+                walk(entities, entity, pos, context)
+            }
+            AiAction::Flee(steps) => {
+                let Some(player) = player.filter(|p| distance(pos, *p) < *steps) else {
+                    return Status::Success;
+                };
+                let away = context
+                    .world
+                    .neighbor_positions(pos)
+                    .into_iter()
+                    .filter(|next| distance(*next, player) > distance(pos, player))
+                    .find(|next| context.pathfinder.is_walkable(context.world, *next, context.registry));
+                match away {
+                    Some(next) => {
+                        move_to(entities, entity, next);
+                        Status::Running
+                    }
+                    None => Status::Failure,
+                }
+            }
+            AiAction::Chase(range) => match player.map(|p| (p, distance(pos, p))) {
+                Some((_, d)) if d <= 1 => Status::Success,
+                Some((player, d)) if d <= *range => {
+                    if entities.get::<Navigator>(entity).is_none_or(|n| n.goal() != player) {
+                        entities.insert(entity, Navigator::new(player));
+                    }
+                    match walk(entities, entity, pos, context) {
+                        Status::Failure => Status::Failure,
+                        _ => Status::Running,
+                    }
+                }
+                _ => Status::Failure,
+            },
+            AiAction::Attack => {
+                let target = context.player.filter(|_| player.is_some_and(|p| distance(pos, p) <= 1));
+                let hit = target.and_then(|target| entities.attack(entity, target, None, context.registry, context.rng));
+                if hit.is_some() { Status::Success } else { Status::Failure }
+            }
+            AiAction::BreakBlock(tag) => {
+                for next in context.world.neighbor_positions(pos) {
+                    if !context.world.get(next).is_some_and(|s| PatternKey::Tag(*tag).matches(&s.block, context.registry)) {
+                        continue;
+                    }
+                    if let BreakResult::Broken { block, .. } = context.world.break_block(next, None, context.registry, context.rng) {
+                        context.events.publish(BlockBroken { block: block.block, position: Some((next.x, next.y)) });
+                        entities.block_changed(next);
+                        return Status::Success;
+                    }
+                }
+                Status::Failure
+            }
+        }
+    }
+}
+
+// ------
+// SYNTAX
+// ------
+
+fn join(nodes: &[BehaviorNode]) -> String {
+    nodes.iter().map(BehaviorNode::to_string).collect::<Vec<_>>().join(", ")
+}
+
+impl Display for BehaviorNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BehaviorNode::Sequence(nodes) => write!(f, "sequence({})", join(nodes)),
+            BehaviorNode::Selector(nodes) => write!(f, "selector({})", join(nodes)),
+            BehaviorNode::Not(node) => write!(f, "not({})", node),
+            BehaviorNode::Condition(AiCondition::PlayerWithin(steps)) => write!(f, "player_within({})", steps),
+            BehaviorNode::Condition(AiCondition::HealthBelow(share)) => write!(f, "health_below({})", share),
+            BehaviorNode::Condition(AiCondition::Chance(probability)) => write!(f, "chance({})", probability),
+            BehaviorNode::Action(AiAction::Idle) => write!(f, "idle()"),
+            BehaviorNode::Action(AiAction::Wander(radius)) => write!(f, "wander({})", radius),
+            BehaviorNode::Action(AiAction::Flee(steps)) => write!(f, "flee({})", steps),
+            BehaviorNode::Action(AiAction::Chase(range)) => write!(f, "chase({})", range),
+            BehaviorNode::Action(AiAction::Attack) => write!(f, "attack()"),
+            BehaviorNode::Action(AiAction::BreakBlock(tag)) => write!(f, "break_block({})", tag),
+        }
+    }
+}
+
+impl Parser<'_> {
+    fn behavior(&mut self) -> Result<BehaviorNode, String> {
+        let (name, start) = self.call()?;
+        let node = match name {
+            "sequence" => BehaviorNode::Sequence(self.list(Self::behavior)?),
+            "selector" => BehaviorNode::Selector(self.list(Self::behavior)?),
+            "not" => BehaviorNode::Not(Box::new(self.behavior()?)),
+            "player_within" => BehaviorNode::Condition(AiCondition::PlayerWithin(self.number()?)),
+            "health_below" => BehaviorNode::Condition(AiCondition::HealthBelow(self.number()?)),
+            "chance" => BehaviorNode::Condition(AiCondition::Chance(self.number()?)),
+            "idle" => BehaviorNode::Action(AiAction::Idle),
+            "wander" => BehaviorNode::Action(AiAction::Wander(self.number()?)),
+            "flee" => BehaviorNode::Action(AiAction::Flee(self.number()?)),
+            "chase" => BehaviorNode::Action(AiAction::Chase(self.number()?)),
+            "attack" => BehaviorNode::Action(AiAction::Attack),
+            "break_block" => BehaviorNode::Action(AiAction::BreakBlock(self.id()?)),
+            _ => return Err(format!("unknown behavior '{}' at position {}", name, start)),
+        };
+        self.expect(')')?;
+        Ok(node)
+    }
+}
+
+impl FromStr for BehaviorNode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        let node = parser.behavior()?;
+        parser.finish()?;
+        Ok(node)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for BehaviorNode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for BehaviorNode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// ---------
+// BEHAVIORS
+// ---------
+
+// A behavior file that couldn't be read
+#[derive(Debug)]
+pub enum BehaviorError {
+    Yaml(serde_yaml::Error),
+    InvalidTree { name: String, error: String }, // Syntax error in the tree of `name`
+}
+
+impl Display for BehaviorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BehaviorError::Yaml(e) => write!(f, "invalid YAML: {}", e),
+            BehaviorError::InvalidTree { name, error } => write!(f, "behavior {}: {}", name, error),
+        }
+    }
+}
+
+impl Error for BehaviorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BehaviorError::Yaml(e) => Some(e),
+            BehaviorError::InvalidTree { .. } => None,
+        }
+    }
+}
+
+// Trees by the name Ai::behavior refers to
+#[derive(Clone, Debug, Default)]
+pub struct Behaviors {
+    trees: HashMap<String, BehaviorNode>,
+}
+
+impl Behaviors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_behavior(mut self, name: &str, tree: BehaviorNode) -> Self {
+        self.insert(name, tree);
+        self
+    }
+
+    pub fn insert(&mut self, name: &str, tree: BehaviorNode) {
+        self.trees.insert(name.to_string(), tree);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&BehaviorNode> {
+        self.trees.get(name)
+    }
+
+    // "name: tree" per line, e.g. `zombie: selector(chase(12), wander(5))`
+    pub fn parse_yaml(content: &str) -> Result<Behaviors, BehaviorError> {
+        let raw: BTreeMap<String, String> = serde_yaml::from_str(content).map_err(BehaviorError::Yaml)?;
+        let mut behaviors = Behaviors::new();
+        for (name, tree) in raw {
+            let tree = tree.parse().map_err(|error| BehaviorError::InvalidTree { name: name.clone(), error })?;
+            behaviors.insert(&name, tree);
+        }
+        Ok(behaviors)
+    }
+
+    // Runs the tree of every entity with Ai and a Position once. Entities
+    // whose behavior isn't known are skipped.
+    pub fn tick(&self, entities: &mut Entities, context: &mut AiContext) -> Vec<(EntityId, Status)> {
+        let mobs: Vec<(EntityId, String)> = entities.query2::<Ai, Position>().map(|(e, ai, _)| (e, ai.behavior.clone())).collect();
+        let mut statuses = vec![];
+        for (entity, behavior) in mobs {
+            let Some(tree) = self.trees.get(&behavior) else {
+                log::debug!("Entity {:?} has unknown behavior {}", entity, behavior);
+                continue;
+            };
+            if entities.is_alive(entity) {
+                statuses.push((entity, tree.run(entity, entities, context)));
+            }
+        }
+        statuses
+    }
+}
@@ -0,0 +1,58 @@
+// Compares the previous gradient rendering (a format! and a color code per
+// grapheme) with gradient_text, which emits a code only when the color
+// changes, and gradient_text_into, which also reuses its buffer.
+//
+//   cargo bench --bench color
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use ruztex::color::{Color, ColorRef, GradientDirection, gradient_text, gradient_text_into, interpolate_multi_color};
+use unicode_segmentation::UnicodeSegmentation;
+
+fn colors() -> Vec<ColorRef<'static>> {
+    vec![ColorRef::Direct(Color::from_hex("#ff0000")), ColorRef::Direct(Color::from_hex("#0000ff"))]
+}
+
+// 40 lines of 200 characters, like a large banner or log pane
+fn text() -> String {
+    vec!["The quick brown fox jumps over the lazy dog. ".repeat(5)[..200].to_string(); 40].join("\n")
+}
+
+fn per_grapheme(text: &str, colors: &[Color]) -> String {
+    text.lines()
+        .map(|line| {
+            let graphemes: Vec<&str> = line.graphemes(true).collect();
+            let range = (graphemes.len() - 1).max(1) as f32;
+            let mut result = String::with_capacity(graphemes.len() * 10);
+            for (i, grapheme) in graphemes.iter().enumerate() {
+                let color = interpolate_multi_color(colors, (i as f32 / range) as f64);
+                result.push_str(&format!("\x1b[38;2;{};{};{}m{}", color.r, color.g, color.b, grapheme));
+            }
+            result.push_str("\x1b[0m");
+            result
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn gradient(c: &mut Criterion) {
+    let text = text();
+    let refs = colors();
+    let rgb = [Color::from_hex("#ff0000"), Color::from_hex("#0000ff")];
+    let mut buffer = String::new();
+    c.bench_function("gradient/per_grapheme", |b| b.iter(|| per_grapheme(black_box(&text), &rgb)));
+    c.bench_function("gradient/gradient_text", |b| {
+        b.iter(|| gradient_text(black_box(&text), &refs, GradientDirection::Horizontal, None).unwrap())
+    });
+    c.bench_function("gradient/gradient_text_into", |b| {
+        b.iter(|| {
+            buffer.clear();
+            gradient_text_into(&mut buffer, black_box(&text), &refs, GradientDirection::Horizontal, None).unwrap();
+            buffer.len()
+        })
+    });
+}
+
+criterion_group!(benches, gradient);
+criterion_main!(benches);
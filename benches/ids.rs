@@ -0,0 +1,57 @@
+// Compares interned IDs with the previous representation (two Strings) for
+// the operations registry and crafting code does most: cloning IDs into
+// tags and loot stacks, and looking them up in maps.
+//
+//   cargo bench --bench ids
+
+use std::collections::HashMap;
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use ruztex::intern::Symbol;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct StringId {
+    namespace: String,
+    name: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct InternedId {
+    namespace: Symbol,
+    name: Symbol,
+}
+
+const NAMES: [&str; 8] = ["coal", "iron_ore", "gold_ore", "stick", "plank", "pickaxe", "furnace", "apple"];
+
+fn string_ids() -> Vec<StringId> {
+    NAMES.iter().map(|n| StringId { namespace: "ruztex".to_string(), name: n.to_string() }).collect()
+}
+
+fn interned_ids() -> Vec<InternedId> {
+    NAMES.iter().map(|n| InternedId { namespace: Symbol::intern("ruztex"), name: Symbol::intern(n) }).collect()
+}
+
+fn clone_ids(c: &mut Criterion) {
+    let strings = string_ids();
+    let interned = interned_ids();
+    c.bench_function("clone/string", |b| b.iter(|| black_box(&strings).to_vec()));
+    c.bench_function("clone/interned", |b| b.iter(|| black_box(&interned).to_vec()));
+}
+
+fn lookup_ids(c: &mut Criterion) {
+    let strings: HashMap<StringId, u32> = string_ids().into_iter().zip(0..).collect();
+    let interned: HashMap<InternedId, u32> = interned_ids().into_iter().zip(0..).collect();
+    let string_keys = string_ids();
+    let interned_keys = interned_ids();
+    c.bench_function("lookup/string", |b| b.iter(|| string_keys.iter().map(|k| strings[black_box(k)]).sum::<u32>()));
+    c.bench_function("lookup/interned", |b| b.iter(|| interned_keys.iter().map(|k| interned[black_box(k)]).sum::<u32>()));
+}
+
+fn create_ids(c: &mut Criterion) {
+    c.bench_function("create/string", |b| b.iter(string_ids));
+    c.bench_function("create/interned", |b| b.iter(interned_ids));
+}
+
+criterion_group!(benches, clone_ids, lookup_ids, create_ids);
+criterion_main!(benches);
@@ -0,0 +1,86 @@
+// Compares rolling loot with a freshly collected candidate list per roll,
+// like LootTable::roll_with used to, with roll_with, which takes the list
+// from the LOOT_ENTRIES pool. game_loop/step measures a tick with repeating
+// and newly scheduled tasks, whose task lists GameLoop pools.
+//
+//   cargo bench --bench pool
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use ruztex::conditions::{Condition, ConditionContext};
+use ruztex::game::GameLoop;
+use ruztex::random::{RuzRng, WeightedList};
+use ruztex::registry::{ID, LootEntry, LootStack, LootTable};
+
+const ENTRIES: usize = 32;
+const TASKS: usize = 64;
+
+fn id(name: &str) -> ID {
+    ID::parse(&format!("pack:{}", name)).unwrap()
+}
+
+// Half of the entries only count with a chance
+fn table() -> LootTable {
+    let entries = (0..ENTRIES)
+        .map(|i| {
+            let entry = LootEntry::new(vec![id("stone"), id("coal")], 1, 4, 0.9, Some(1 + i as u32 % 5));
+            match i % 2 {
+                0 => entry.with_condition(Condition::RandomChance(0.5)),
+                _ => entry,
+            }
+        })
+        .collect();
+    LootTable::new(id("ore"), entries)
+}
+
+fn roll_collected(table: &LootTable, context: &ConditionContext, rng: &mut RuzRng) -> Vec<LootStack> {
+    let entries: WeightedList<&LootEntry> = table
+        .entries
+        .iter()
+        .filter(|e| e.condition.as_ref().is_none_or(|c| c.test(context, rng)))
+        .map(|e| (e, e.weight))
+        .collect();
+    let Some(entry) = entries.sample(rng) else {
+        return vec![];
+    };
+    if !rng.chance(entry.chance) {
+        return vec![];
+    }
+    let item = &entry.items[rng.range(0, entry.items.len() as u32 - 1) as usize];
+    let mut stack = LootStack::new(*item, rng.range(entry.min, entry.max));
+    for function in entry.functions.iter().chain(&table.functions) {
+        function.apply(&mut stack, context, rng);
+    }
+    if stack.count == 0 {
+        return vec![];
+    }
+    vec![stack]
+}
+
+fn loot(c: &mut Criterion) {
+    let table = table();
+    let context = ConditionContext::new();
+    let mut rng = RuzRng::new(42);
+    c.bench_function("loot/collected", |b| b.iter(|| roll_collected(black_box(&table), &context, &mut rng)));
+    c.bench_function("loot/pooled", |b| b.iter(|| black_box(&table).roll_with(&context, &mut rng)));
+}
+
+fn game_loop(c: &mut Criterion) {
+    let mut game_loop = GameLoop::new(20);
+    for i in 0..TASKS {
+        game_loop.scheduler.schedule_repeating_at(1, 1 + i as u64 % 4, |ctx| {
+            ctx.schedule(1, |_| {});
+        });
+    }
+    c.bench_function("game_loop/step", |b| {
+        b.iter(|| {
+            game_loop.step(&mut |ctx| {
+                black_box(ctx.tick);
+            })
+        })
+    });
+}
+
+criterion_group!(benches, loot, game_loop);
+criterion_main!(benches);
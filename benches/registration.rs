@@ -0,0 +1,80 @@
+// Compares registering a large datapack entry by entry, locking the global
+// registry for every entry like loaders used to, with RegistryBuilder, which
+// parses off the lock (in parallel with the `parallel` feature) and commits
+// once.
+//
+//   cargo bench --bench registration
+
+use std::hint::black_box;
+use std::sync::RwLock;
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use ruztex::registry::{ID, Item, RegistrableEntity, Registry, RegistryBuilder, RegistryError, Tag};
+
+const ENTRIES: usize = 5000;
+
+// IDs can't contain digits, so entry names are spelled with letters
+fn name(mut i: usize) -> String {
+    let mut name = String::new();
+    loop {
+        name.push((b'a' + (i % 26) as u8) as char);
+        i /= 26;
+        if i == 0 {
+            return name;
+        }
+    }
+}
+
+// "tag <id>" per tag, then "item <id> <stack size> <tag>,<tag>" per item
+fn datapack() -> Vec<String> {
+    let tags = (0..8).map(|i| format!("tag pack:ore_{}", name(i)));
+    let items = (0..ENTRIES).map(|i| format!("item pack:item_{} {} pack:ore,pack:ore_{}", name(i), 1 + i % 64, name(i % 8)));
+    std::iter::once("tag pack:ore".to_string()).chain(tags).chain(items).collect()
+}
+
+fn parse(line: &str) -> Result<RegistrableEntity, RegistryError> {
+    let mut parts = line.split_whitespace();
+    let kind = parts.next();
+    let id = ID::parse(parts.next().unwrap_or(""))?;
+    if kind == Some("tag") {
+        return Ok(RegistrableEntity::Tag(Tag::new(id)));
+    }
+    let stack_size = parts.next().and_then(|s| s.parse().ok()).unwrap_or(64);
+    let tags = match parts.next() {
+        Some(tags) => tags.split(',').map(ID::parse).collect::<Result<Vec<_>, _>>()?,
+        None => vec![],
+    };
+    Ok(RegistrableEntity::Item(Item::new(id, tags, stack_size)))
+}
+
+fn register(c: &mut Criterion) {
+    let lines = datapack();
+    c.bench_function("register/per_entry", |b| {
+        b.iter_batched(
+            || RwLock::new(Registry::new()),
+            |registry| {
+                for line in black_box(&lines) {
+                    let entity = parse(line).unwrap();
+                    registry.write().unwrap().register(entity).unwrap();
+                }
+                registry
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    c.bench_function("register/builder", |b| {
+        b.iter_batched(
+            || RwLock::new(Registry::new()),
+            |registry| {
+                let mut builder = RegistryBuilder::new();
+                builder.parse(black_box(&lines), |line| parse(line));
+                builder.commit(&registry).unwrap();
+                registry
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, register);
+criterion_main!(benches);
@@ -1,12 +1,15 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter, Write};
 use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use unicode_segmentation::UnicodeSegmentation;
 use lazy_static::lazy_static;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -17,7 +20,7 @@ impl Color {
     pub fn from_hex(hex: &str) -> Color {
         let hex = hex.trim_start_matches('#');
         let hex = match hex.len() {
-            3 => hex.chars().flat_map(|c| std::iter::repeat(c).take(2)).collect::<String>(),
+            3 => hex.chars().flat_map(|c| std::iter::repeat_n(c, 2)).collect::<String>(),
             6 => hex.to_string(),
             _ => return Color { r: 0, g: 0, b: 0 },
         };
@@ -28,6 +31,136 @@ impl Color {
 
         Color { r, g, b }
     }
+
+    // Linear blend towards `other`, factor 0.0 = self, 1.0 = other
+    pub fn mix(&self, other: Color, factor: f64) -> Color {
+        let f = factor.clamp(0.0, 1.0);
+        let blend = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * f).round() as u8;
+        Color {
+            r: blend(self.r, other.r),
+            g: blend(self.g, other.g),
+            b: blend(self.b, other.b),
+        }
+    }
+
+    // Multiplies every channel, e.g. with a light level (0.0 = black)
+    pub fn scale(&self, factor: f64) -> Color {
+        let f = factor.max(0.0);
+        let scale = |c: u8| (c as f64 * f).round().min(255.0) as u8;
+        Color { r: scale(self.r), g: scale(self.g), b: scale(self.b) }
+    }
+}
+
+// ------------
+// AMBIENT TINT
+// ------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weather {
+    Clear,
+    Rain,
+    Snow,
+    Storm,
+    Fog,
+}
+
+impl Weather {
+    // Color the scene is pulled towards and how strongly
+    fn tint(&self) -> Option<(Color, f64)> {
+        match self {
+            Weather::Clear => None,
+            Weather::Rain => Some((Color { r: 70, g: 90, b: 130 }, 0.25)),
+            Weather::Snow => Some((Color { r: 235, g: 240, b: 255 }, 0.3)),
+            Weather::Storm => Some((Color { r: 40, g: 40, b: 70 }, 0.45)),
+            Weather::Fog => Some((Color { r: 180, g: 180, b: 180 }, 0.5)),
+        }
+    }
+}
+
+// Which tint stages are applied, so overlays can switch them off individually
+#[derive(Clone, Copy, Debug)]
+pub struct TintOverlays {
+    pub biome: bool,
+    pub light: bool,
+    pub weather: bool,
+}
+
+impl Default for TintOverlays {
+    fn default() -> Self {
+        TintOverlays { biome: true, light: true, weather: true }
+    }
+}
+
+// Modulates map colors by biome palette, time-of-day light level and weather
+#[derive(Clone, Debug)]
+pub struct AmbientTint {
+    pub biome: Option<(Color, f64)>, // Biome palette color and strength (0.0 - 1.0)
+    pub light: f64,                  // 0.0 = night, 1.0 = full daylight
+    pub min_light: f64,              // Darkest the scene gets, so night stays readable
+    pub weather: Weather,
+    pub overlays: TintOverlays,
+}
+
+impl AmbientTint {
+    pub fn new() -> Self {
+        AmbientTint {
+            biome: None,
+            light: 1.0,
+            min_light: 0.35,
+            weather: Weather::Clear,
+            overlays: TintOverlays::default(),
+        }
+    }
+
+    pub fn with_biome(mut self, color: Color, strength: f64) -> Self {
+        self.biome = Some((color, strength));
+        self
+    }
+
+    pub fn with_light(mut self, light: f64) -> Self {
+        self.light = light.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_weather(mut self, weather: Weather) -> Self {
+        self.weather = weather;
+        self
+    }
+
+    pub fn with_overlays(mut self, overlays: TintOverlays) -> Self {
+        self.overlays = overlays;
+        self
+    }
+
+    // Light level for a time of day in hours (0.0 - 24.0), brightest at noon
+    pub fn daylight(hour: f64) -> f64 {
+        let angle = (hour.rem_euclid(24.0) - 6.0) / 12.0 * std::f64::consts::PI;
+        angle.sin().max(0.0)
+    }
+
+    pub fn apply(&self, color: Color) -> Color {
+        let mut c = color;
+        if self.overlays.biome
+            && let Some((biome, strength)) = self.biome
+        {
+            c = c.mix(biome, strength);
+        }
+        if self.overlays.weather
+            && let Some((tint, strength)) = self.weather.tint()
+        {
+            c = c.mix(tint, strength);
+        }
+        if self.overlays.light {
+            c = c.scale(self.min_light + (1.0 - self.min_light) * self.light);
+        }
+        c
+    }
+}
+
+impl Default for AmbientTint {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 lazy_static! {
@@ -66,6 +199,48 @@ lazy_static! {
 pub enum ColorRef<'a> {
     Direct(Color),
     Named(&'a str, &'a str),
+    Owned(String, String), // Named, for names only known at runtime (e.g. from config files)
+}
+
+impl ColorRef<'_> {
+    // "#rrggbb" or "namespace::name", None if it's neither
+    pub fn parse(value: &str) -> Option<ColorRef<'static>> {
+        let value = value.trim();
+        if let Some(hex) = value.strip_prefix('#') {
+            return (hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit())).then(|| ColorRef::Direct(Color::from_hex(hex)));
+        }
+        let (namespace, name) = value.split_once("::")?;
+        (is_valid_identifier(namespace) && is_valid_identifier(name))
+            .then(|| ColorRef::Owned(namespace.to_string(), name.to_string()))
+    }
+
+    // Namespace and name of a named color
+    pub fn name(&self) -> Option<(&str, &str)> {
+        match self {
+            ColorRef::Direct(_) => None,
+            ColorRef::Named(ns, name) => Some((ns, name)),
+            ColorRef::Owned(ns, name) => Some((ns, name)),
+        }
+    }
+
+    pub fn into_owned(self) -> ColorRef<'static> {
+        match self {
+            ColorRef::Direct(c) => ColorRef::Direct(c),
+            ColorRef::Named(ns, name) => ColorRef::Owned(ns.to_string(), name.to_string()),
+            ColorRef::Owned(ns, name) => ColorRef::Owned(ns, name),
+        }
+    }
+}
+
+// "namespace::name" or "#rrggbb"
+impl Display for ColorRef<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorRef::Direct(c) => write!(f, "#{:02x}{:02x}{:02x}", c.r, c.g, c.b),
+            ColorRef::Named(ns, name) => write!(f, "{}::{}", ns, name),
+            ColorRef::Owned(ns, name) => write!(f, "{}::{}", ns, name),
+        }
+    }
 }
 
 fn is_valid_identifier(s: &str) -> bool {
@@ -75,86 +250,154 @@ fn is_valid_identifier(s: &str) -> bool {
 pub fn resolve_color_ref(c: &ColorRef) -> Option<Color> {
     match c {
         ColorRef::Direct(color) => Some(*color),
-        ColorRef::Named(ns, name) => {
-            if !is_valid_identifier(ns) || !is_valid_identifier(name) {
-                return None;
+        ColorRef::Named(ns, name) => resolve_named(ns, name),
+        ColorRef::Owned(ns, name) => resolve_named(ns, name),
+    }
+}
+
+fn resolve_named(ns: &str, name: &str) -> Option<Color> {
+    if !is_valid_identifier(ns) || !is_valid_identifier(name) {
+        return None;
+    }
+    let map = COLORS.read().ok()?;
+    map.get(ns)?.get(name).copied()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ColorError {
+    ProtectedNamespace(String),                   // "default" can't be changed
+    InvalidIdentifier(&'static str, String),      // Part ("namespace" or "name") and value
+    AlreadyExists(String, String),                // Namespace and name
+    UnknownColor(String, String),                 // Namespace and name
+    UnknownNamespace(String),
+    Unresolved(String),                           // A color reference that couldn't be resolved
+    TooFewColors,                                 // Gradients need at least two colors
+    AlignedVerticalGradient,
+}
+
+impl Display for ColorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorError::ProtectedNamespace(ns) => write!(f, "cannot modify the {} namespace", ns),
+            ColorError::InvalidIdentifier(part, value) => {
+                write!(f, "{} '{}' must be lowercase and contain only [a-z_]", part, value)
             }
-            let map = COLORS.read().ok()?;
-            map.get(*ns)?.get(*name).copied()
+            ColorError::AlreadyExists(ns, name) => {
+                write!(f, "color '{}::{}' already exists - use change_color() instead", ns, name)
+            }
+            ColorError::UnknownColor(ns, name) => write!(f, "color '{}::{}' does not exist", ns, name),
+            ColorError::UnknownNamespace(ns) => write!(f, "namespace '{}' does not exist", ns),
+            ColorError::Unresolved(color) => write!(f, "could not resolve color reference {}", color),
+            ColorError::TooFewColors => write!(f, "at least two colors are required"),
+            ColorError::AlignedVerticalGradient => write!(f, "align_gradient must be None for vertical gradients"),
         }
     }
 }
 
-pub fn add_color(namespace: &str, name: &str, c: Color) -> Result<(), String> {
+impl Error for ColorError {}
+
+// Namespace and name must be valid and the namespace not protected
+fn check_color_name(namespace: &str, name: &str) -> Result<(), ColorError> {
     if namespace == "default" {
-        return Err("cannot modify default or pastel namespace".into());
+        return Err(ColorError::ProtectedNamespace(namespace.to_string()));
     }
     if !is_valid_identifier(namespace) {
-        return Err("namespace must be lowercase and contain only [a-z_]".into());
+        return Err(ColorError::InvalidIdentifier("namespace", namespace.to_string()));
     }
     if !is_valid_identifier(name) {
-        return Err("name must be lowercase and contain only [a-z_]".into());
+        return Err(ColorError::InvalidIdentifier("name", name.to_string()));
     }
+    Ok(())
+}
 
+pub fn add_color(namespace: &str, name: &str, c: Color) -> Result<(), ColorError> {
+    check_color_name(namespace, name)?;
     let mut colors = COLORS.write().unwrap();
     let ns_entry = colors.entry(namespace.to_string()).or_default();
-
     if ns_entry.contains_key(name) {
-        panic!("color '{}::{}' already exists - use change_color() instead", namespace, name);
+        return Err(ColorError::AlreadyExists(namespace.to_string(), name.to_string()));
     }
-
     ns_entry.insert(name.to_string(), c);
     Ok(())
 }
 
-pub fn remove_color(namespace: &str, name: &str) -> Result<(), String> {
-    if namespace == "default" {
-        return Err("cannot modify default or pastel namespace".into());
+pub fn remove_color(namespace: &str, name: &str) -> Result<(), ColorError> {
+    check_color_name(namespace, name)?;
+    let mut colors = COLORS.write().unwrap();
+    let Some(ns) = colors.get_mut(namespace) else {
+        return Err(ColorError::UnknownNamespace(namespace.to_string()));
+    };
+    if ns.remove(name).is_none() {
+        return Err(ColorError::UnknownColor(namespace.to_string(), name.to_string()));
     }
-    if !is_valid_identifier(namespace) {
-        return Err("namespace must be lowercase and contain only [a-z_]".into());
-    }
-    if !is_valid_identifier(name) {
-        return Err("name must be lowercase and contain only [a-z_]".into());
+    if ns.is_empty() {
+        colors.remove(namespace);
     }
+    Ok(())
+}
+
+pub fn change_color(namespace: &str, name: &str, c: Color) -> Result<(), ColorError> {
+    check_color_name(namespace, name)?;
     let mut colors = COLORS.write().unwrap();
-    if let Some(ns) = colors.get_mut(namespace) {
-        if ns.remove(name).is_none() {
-            return Err("color does not exist in namespace".into());
-        }
-        if ns.is_empty() {
-            colors.remove(namespace);
+    let Some(ns) = colors.get_mut(namespace) else {
+        return Err(ColorError::UnknownNamespace(namespace.to_string()));
+    };
+    match ns.get_mut(name) {
+        Some(color) => {
+            *color = c;
+            Ok(())
         }
-        Ok(())
-    } else {
-        Err("namespace does not exist".into())
+        None => Err(ColorError::UnknownColor(namespace.to_string(), name.to_string())),
     }
 }
 
-pub fn change_color(namespace: &str, name: &str, c: Color) -> Result<(), String> {
-    if namespace == "default" {
-        return Err("cannot modify default or pastel namespace".into());
-    }
-    if !is_valid_identifier(namespace) {
-        return Err("namespace must be lowercase and contain only [a-z_]".into());
+#[derive(Clone, Debug, Default)]
+pub struct ColorUsage {
+    pub used: Vec<String>,    // Referenced and registered
+    pub unused: Vec<String>,  // Registered but never referenced
+    pub missing: Vec<String>, // Referenced but not registered
+}
+
+/// Reports per namespace which named colors are referenced by `refs` (themes,
+/// code-registered ColorRefs, ...), which registered colors are never used and
+/// which referenced colors do not exist.
+pub fn color_usage_report(refs: &[ColorRef]) -> HashMap<String, ColorUsage> {
+    let colors = COLORS.read().unwrap();
+    let mut referenced: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for c in refs {
+        if let Some((ns, name)) = c.name() {
+            referenced.entry(ns).or_default().insert(name);
+        }
     }
-    if !is_valid_identifier(name) {
-        return Err("name must be lowercase and contain only [a-z_]".into());
+
+    let mut report: HashMap<String, ColorUsage> = HashMap::new();
+    for (ns, palette) in colors.iter() {
+        let usage = report.entry(ns.clone()).or_default();
+        for name in palette.keys() {
+            if referenced.get(ns.as_str()).is_some_and(|r| r.contains(name.as_str())) {
+                usage.used.push(name.clone());
+            } else {
+                usage.unused.push(name.clone());
+            }
+        }
     }
-    let mut colors = COLORS.write().unwrap();
-    if let Some(ns) = colors.get_mut(namespace) {
-        if ns.contains_key(name) {
-            ns.insert(name.to_string(), c);
-            Ok(())
-        } else {
-            Err("color does not exist in namespace".into())
+    for (ns, names) in referenced {
+        for name in names {
+            if !colors.get(ns).is_some_and(|p| p.contains_key(name)) {
+                report.entry(ns.to_string()).or_default().missing.push(name.to_string());
+            }
         }
-    } else {
-        Err("namespace does not exist".into())
     }
+
+    for usage in report.values_mut() {
+        usage.used.sort();
+        usage.unused.sort();
+        usage.missing.sort();
+    }
+    report
 }
 
-fn interpolate_multi_color(colors: &[Color], factor: f64) -> Color {
+pub fn interpolate_multi_color(colors: &[Color], factor: f64) -> Color {
     if factor <= 0.0 {
         return colors[0];
     }
@@ -176,21 +419,36 @@ fn interpolate_multi_color(colors: &[Color], factor: f64) -> Color {
     }
 }
 
-fn apply_gradient(lines: &[&str], colors: &[Color]) -> Vec<String> {
-    let total = (lines.len() - 1).max(1) as f32;
+// WCAG relative luminance (0.0 - 1.0)
+pub fn relative_luminance(c: Color) -> f64 {
+    let channel = |v: u8| {
+        let v = v as f64 / 255.0;
+        if v <= 0.03928 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * channel(c.r) + 0.7152 * channel(c.g) + 0.0722 * channel(c.b)
+}
+
+// WCAG contrast ratio (1.0 - 21.0), 4.5 is the usual minimum for text
+pub fn contrast_ratio(a: Color, b: Color) -> f64 {
+    let la = relative_luminance(a);
+    let lb = relative_luminance(b);
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
 
-    lines
-        .iter()
-        .enumerate()
-        .map(|(i, line)| {
-            let pos = i as f32 / total;
-            let color = interpolate_multi_color(colors, pos as f64);
-            format!(
-                "\x1b[38;2;{};{};{}m{}",
-                color.r, color.g, color.b, line
-            )
-        })
-        .collect()
+// Moves fg towards black or white (whichever contrasts better with bg) until
+// the contrast ratio reaches min_ratio
+pub fn adjust_for_contrast(fg: Color, bg: Color, min_ratio: f64) -> Color {
+    if contrast_ratio(fg, bg) >= min_ratio {
+        return fg;
+    }
+    let black = Color { r: 0, g: 0, b: 0 };
+    let white = Color { r: 255, g: 255, b: 255 };
+    let target = if contrast_ratio(white, bg) >= contrast_ratio(black, bg) { white } else { black };
+
+    (1..=20)
+        .map(|step| interpolate_multi_color(&[fg, target], step as f64 / 20.0))
+        .find(|c| contrast_ratio(*c, bg) >= min_ratio)
+        .unwrap_or(target)
 }
 
 pub enum GradientDirection {
@@ -198,25 +456,93 @@ pub enum GradientDirection {
     Vertical,
 }
 
-fn apply_gradient_fixed_len(
-    graphemes: &[&str],
-    colors: &[Color],
-    target_len: usize,
-) -> String {
-    let mut result = String::with_capacity(graphemes.len() * 10);
-    let range = (target_len - 1).max(1) as f32;
+// Whether colors are written at all. Starts out from the environment:
+// CLICOLOR_FORCE (not "0") forces them on, otherwise NO_COLOR (non-empty),
+// CLICOLOR=0 or TERM=dumb turn them off.
+static COLORS_ENABLED: Lazy<AtomicBool> = Lazy::new(|| AtomicBool::new(colors_from_env()));
+
+fn colors_from_env() -> bool {
+    let var = |name| std::env::var(name).ok();
+    if var("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+        return true;
+    }
+    !(var("NO_COLOR").is_some_and(|v| !v.is_empty()) || var("CLICOLOR").as_deref() == Some("0") || var("TERM").as_deref() == Some("dumb"))
+}
+
+// Turns colors off (or back on) everywhere: colored and gradient text,
+// StyledText, progress bars, the prompt and logging. E.g. for tests or when
+// output is piped.
+pub fn set_enabled(enabled: bool) {
+    COLORS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    COLORS_ENABLED.load(Ordering::Relaxed)
+}
+
+// Foreground color escape code
+fn push_color(out: &mut String, color: Color) {
+    // Writing to a String can't fail
+    let _ = write!(out, "\x1b[38;2;{};{};{}m", color.r, color.g, color.b);
+}
+
+// Receives a gradient grapheme by grapheme, so the same code renders it to
+// ANSI or to StyledText
+trait GradientSink {
+    fn push(&mut self, text: &str, color: Color);
+    fn end_line(&mut self, last: bool);
+}
+
+// Neighbours often end up with the same color, so a code is only emitted
+// when the color changes, and empty lines get none
+struct AnsiSink<'a> {
+    out: &'a mut String,
+    colored: bool, // Plain text if colors are disabled
+    current: Option<Color>,
+}
+
+impl GradientSink for AnsiSink<'_> {
+    fn push(&mut self, text: &str, color: Color) {
+        if text.is_empty() {
+            return;
+        }
+        if self.colored && self.current != Some(color) {
+            push_color(self.out, color);
+            self.current = Some(color);
+        }
+        self.out.push_str(text);
+    }
+
+    fn end_line(&mut self, last: bool) {
+        if self.current.take().is_some() {
+            self.out.push_str("\x1b[0m");
+        }
+        if !last {
+            self.out.push('\n');
+        }
+    }
+}
+
+impl GradientSink for StyledText {
+    fn push(&mut self, text: &str, color: Color) {
+        StyledText::push(self, text, Style::new().fg(color));
+    }
+
+    fn end_line(&mut self, last: bool) {
+        if !last {
+            StyledText::push(self, "\n", Style::new());
+        }
+    }
+}
+
+// Colors each grapheme by its position in target_len
+fn apply_gradient_fixed_len(sink: &mut impl GradientSink, graphemes: &[&str], colors: &[Color], target_len: usize) {
+    let range = (target_len.max(1) - 1).max(1) as f32;
 
     for (i, grapheme) in graphemes.iter().enumerate() {
         let pos = i as f32 / range;
-        let color = interpolate_multi_color(colors, pos as f64);
-        result.push_str(&format!(
-            "\x1b[38;2;{};{};{}m{}",
-            color.r, color.g, color.b, grapheme
-        ));
+        sink.push(grapheme, interpolate_multi_color(colors, pos as f64));
     }
-
-    result.push_str("\x1b[0m");
-    result
 }
 
 pub fn gradient_text(
@@ -224,30 +550,67 @@ pub fn gradient_text(
     color_refs: &[ColorRef],
     direction: GradientDirection,
     align_gradient: Option<bool>,
-) -> Result<String, String> {
+) -> Result<String, ColorError> {
+    let mut out = String::with_capacity(text.len() * 2);
+    gradient_text_into(&mut out, text, color_refs, direction, align_gradient)?;
+    Ok(out)
+}
+
+// Like gradient_text, but appends to `out`, so a buffer can be reused across
+// frames instead of allocating a String per call
+pub fn gradient_text_into(
+    out: &mut String,
+    text: &str,
+    color_refs: &[ColorRef],
+    direction: GradientDirection,
+    align_gradient: Option<bool>,
+) -> Result<(), ColorError> {
+    render_gradient(&mut AnsiSink { out, colored: is_enabled(), current: None }, text, color_refs, direction, align_gradient)
+}
+
+// Like gradient_text, but keeps the colors as spans instead of ANSI codes
+pub fn gradient_styled(
+    text: &str,
+    color_refs: &[ColorRef],
+    direction: GradientDirection,
+    align_gradient: Option<bool>,
+) -> Result<StyledText, ColorError> {
+    let mut styled = StyledText::new();
+    render_gradient(&mut styled, text, color_refs, direction, align_gradient)?;
+    Ok(styled)
+}
+
+fn render_gradient(
+    sink: &mut impl GradientSink,
+    text: &str,
+    color_refs: &[ColorRef],
+    direction: GradientDirection,
+    align_gradient: Option<bool>,
+) -> Result<(), ColorError> {
     if color_refs.len() < 2 {
-        return Err("at least two colors are required".into());
+        return Err(ColorError::TooFewColors);
     }
 
     let rgb_colors: Vec<_> = color_refs
         .iter()
-        .map(|c| resolve_color_ref(c).ok_or("could not resolve all colors"))
+        .map(|c| resolve_color_ref(c).ok_or_else(|| ColorError::Unresolved(c.to_string())))
         .collect::<Result<_, _>>()?;
 
     let lines: Vec<&str> = text.lines().collect();
+    let last = lines.len().saturating_sub(1);
 
     match direction {
         GradientDirection::Vertical => {
             if align_gradient.is_some() {
-                return Err("align_gradient must be None for vertical gradients".into());
+                return Err(ColorError::AlignedVerticalGradient);
             }
 
-            let colored_lines = apply_gradient(&lines, &rgb_colors)
-                .into_iter()
-                .map(|l| l + "\x1b[0m")
-                .collect::<Vec<_>>();
-
-            Ok(colored_lines.join("\n"))
+            let total = last.max(1) as f32;
+            for (i, line) in lines.iter().enumerate() {
+                let pos = i as f32 / total;
+                sink.push(line, interpolate_multi_color(&rgb_colors, pos as f64));
+                sink.end_line(i == last);
+            }
         }
         GradientDirection::Horizontal => {
             let align = align_gradient.unwrap_or(false);
@@ -255,37 +618,32 @@ pub fn gradient_text(
             let max_len = if align {
                 lines
                     .iter()
-                    .map(|l| strip_ansi_codes(l).graphemes(true).count())
+                    .map(|l| visible_length(l))
                     .max()
                     .unwrap_or(0)
             } else {
                 0
             };
 
-            let result = lines
-                .iter()
-                .map(|line| {
-                    let graphemes: Vec<&str> = line.graphemes(true).collect();
-                    let gradient_basis = if align {
-                        max_len
-                    } else {
-                        graphemes.len()
-                    };
-                    apply_gradient_fixed_len(&graphemes, &rgb_colors, gradient_basis)
-                })
-                .collect::<Vec<_>>();
-
-            Ok(result.join("\n"))
+            let mut graphemes: Vec<&str> = vec![];
+            for (i, line) in lines.iter().enumerate() {
+                graphemes.clear();
+                graphemes.extend(line.graphemes(true));
+                let gradient_basis = if align {
+                    max_len
+                } else {
+                    graphemes.len()
+                };
+                apply_gradient_fixed_len(sink, &graphemes, &rgb_colors, gradient_basis);
+                sink.end_line(i == last);
+            }
         }
     }
+    Ok(())
 }
 
-pub fn rainbow_text(
-    text: &str,
-    direction: GradientDirection,
-    align_gradient: Option<bool>,
-) -> Result<String, String> {
-    let rainbow: Vec<ColorRef> = vec![
+fn rainbow_colors() -> Vec<ColorRef<'static>> {
+    vec![
         ColorRef::Direct(Color::from_hex("#ff0000")),
         ColorRef::Direct(Color::from_hex("#ff7f00")),
         ColorRef::Direct(Color::from_hex("#ffff00")),
@@ -293,20 +651,59 @@ pub fn rainbow_text(
         ColorRef::Direct(Color::from_hex("#0000ff")),
         ColorRef::Direct(Color::from_hex("#4b0082")),
         ColorRef::Direct(Color::from_hex("#9400d3")),
-    ];
-    gradient_text(text, &rainbow, direction, align_gradient)
+    ]
+}
+
+pub fn rainbow_text(
+    text: &str,
+    direction: GradientDirection,
+    align_gradient: Option<bool>,
+) -> Result<String, ColorError> {
+    gradient_text(text, &rainbow_colors(), direction, align_gradient)
+}
+
+pub fn rainbow_styled(
+    text: &str,
+    direction: GradientDirection,
+    align_gradient: Option<bool>,
+) -> Result<StyledText, ColorError> {
+    gradient_styled(text, &rainbow_colors(), direction, align_gradient)
 }
 
 pub fn colored_text(
     text: &str,
     color_ref: &ColorRef,
-) -> Result<String, String> {
+) -> Result<String, ColorError> {
+    let mut out = String::with_capacity(text.len() + 24);
+    colored_text_into(&mut out, text, color_ref)?;
+    Ok(out)
+}
+
+// Like colored_text, but appends to `out`
+pub fn colored_text_into(
+    out: &mut String,
+    text: &str,
+    color_ref: &ColorRef,
+) -> Result<(), ColorError> {
     let color = resolve_color_ref(color_ref)
-        .ok_or("could not resolve color reference")?;
-    Ok(format!(
-        "\x1b[38;2;{};{};{}m{}\x1b[0m",
-        color.r, color.g, color.b, text
-    ))
+        .ok_or_else(|| ColorError::Unresolved(color_ref.to_string()))?;
+    if !is_enabled() {
+        out.push_str(text);
+        return Ok(());
+    }
+    push_color(out, color);
+    out.push_str(text);
+    out.push_str("\x1b[0m");
+    Ok(())
+}
+
+pub fn colored_styled(
+    text: &str,
+    color_ref: &ColorRef,
+) -> Result<StyledText, ColorError> {
+    let color = resolve_color_ref(color_ref)
+        .ok_or_else(|| ColorError::Unresolved(color_ref.to_string()))?;
+    Ok(StyledText::styled(text, Style::new().fg(color)))
 }
 
 static ANSI_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x1B\[[0-9;]*m").unwrap());
@@ -317,4 +714,251 @@ pub fn strip_ansi_codes(s: &str) -> String {
 
 pub fn visible_length(s: &str) -> usize {
     strip_ansi_codes(s).graphemes(true).count()
-}
\ No newline at end of file
+}
+
+// -----------
+// STYLED TEXT
+// -----------
+
+// Colors of a span, None keeps the terminal's default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+}
+
+impl Style {
+    pub fn new() -> Self {
+        Style { fg: None, bg: None }
+    }
+
+    pub fn fg(mut self, color: Color) -> Self {
+        self.fg = Some(color);
+        self
+    }
+
+    pub fn bg(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    pub fn is_plain(&self) -> bool {
+        self.fg.is_none() && self.bg.is_none()
+    }
+
+    fn write_ansi(&self, out: &mut String) {
+        if let Some(c) = self.fg {
+            push_color(out, c);
+        }
+        if let Some(c) = self.bg {
+            let _ = write!(out, "\x1b[48;2;{};{};{}m", c.r, c.g, c.b);
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+impl From<Style> for ratatui::style::Style {
+    fn from(style: Style) -> Self {
+        let mut result = ratatui::style::Style::default();
+        if let Some(c) = style.fg {
+            result = result.fg(ratatui::style::Color::Rgb(c.r, c.g, c.b));
+        }
+        if let Some(c) = style.bg {
+            result = result.bg(ratatui::style::Color::Rgb(c.r, c.g, c.b));
+        }
+        result
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StyledSpan {
+    pub text: String,
+    pub style: Style,
+}
+
+// Text with its colors kept as spans, so it can be measured and drawn by
+// ratatui without going through ANSI codes. Flattened to ANSI with to_ansi
+// (or Display) only when printed to a terminal.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StyledText {
+    spans: Vec<StyledSpan>, // Adjacent spans never share a style
+}
+
+impl StyledText {
+    pub fn new() -> Self {
+        StyledText { spans: vec![] }
+    }
+
+    pub fn plain(text: &str) -> Self {
+        Self::styled(text, Style::new())
+    }
+
+    pub fn styled(text: &str, style: Style) -> Self {
+        let mut styled = Self::new();
+        styled.push(text, style);
+        styled
+    }
+
+    pub fn push(&mut self, text: &str, style: Style) {
+        if text.is_empty() {
+            return;
+        }
+        match self.spans.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(text),
+            _ => self.spans.push(StyledSpan { text: text.to_string(), style }),
+        }
+    }
+
+    pub fn append(&mut self, other: &StyledText) {
+        for span in &other.spans {
+            self.push(&span.text, span.style);
+        }
+    }
+
+    pub fn spans(&self) -> &[StyledSpan] {
+        &self.spans
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    // Text without colors
+    pub fn plain_text(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    // Graphemes in the widest line
+    pub fn width(&self) -> usize {
+        self.plain_text().lines().map(|l| l.graphemes(true).count()).max().unwrap_or(0)
+    }
+
+    // Split at newlines like str::lines
+    pub fn lines(&self) -> Vec<StyledText> {
+        let mut lines = vec![];
+        let mut line = StyledText::new();
+        for span in &self.spans {
+            let mut parts = span.text.split('\n');
+            if let Some(first) = parts.next() {
+                line.push(first, span.style);
+            }
+            for part in parts {
+                lines.push(std::mem::take(&mut line));
+                line.push(part, span.style);
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    // Appends the text with ANSI codes, resetting before every newline and at
+    // the end like colored_text and gradient_text do. Plain if colors are
+    // disabled.
+    pub fn write_ansi(&self, out: &mut String) {
+        if !is_enabled() {
+            out.extend(self.spans.iter().map(|s| s.text.as_str()));
+            return;
+        }
+        let mut current = Style::new();
+        for span in &self.spans {
+            for (i, part) in span.text.split('\n').enumerate() {
+                if i > 0 {
+                    if !current.is_plain() {
+                        out.push_str("\x1b[0m");
+                        current = Style::new();
+                    }
+                    out.push('\n');
+                }
+                if part.is_empty() {
+                    continue;
+                }
+                if span.style != current {
+                    // Codes only add colors, dropping one needs a reset
+                    if (current.fg.is_some() && span.style.fg.is_none()) || (current.bg.is_some() && span.style.bg.is_none()) {
+                        out.push_str("\x1b[0m");
+                    }
+                    span.style.write_ansi(out);
+                    current = span.style;
+                }
+                out.push_str(part);
+            }
+        }
+        if !current.is_plain() {
+            out.push_str("\x1b[0m");
+        }
+    }
+
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        self.write_ansi(&mut out);
+        out
+    }
+
+    // Parses text with the ANSI codes produced by this module (24-bit colors
+    // and resets), other codes reset the style
+    pub fn from_ansi(text: &str) -> Self {
+        let mut styled = StyledText::new();
+        let mut style = Style::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("\x1b[") {
+            styled.push(&rest[..start], style);
+            let Some(len) = rest[start..].find('m') else {
+                break;
+            };
+            let codes: Vec<u8> = rest[start + 2..start + len].split(';').filter_map(|c| c.parse().ok()).collect();
+            style = match codes.as_slice() {
+                [38, 2, r, g, b] => style.fg(Color { r: *r, g: *g, b: *b }),
+                [48, 2, r, g, b] => style.bg(Color { r: *r, g: *g, b: *b }),
+                _ => Style::new(),
+            };
+            rest = &rest[start + len + 1..];
+        }
+        styled.push(rest, style);
+        styled
+    }
+
+    // Unstyled if colors are disabled
+    #[cfg(feature = "tui")]
+    pub fn to_lines(&self) -> Vec<ratatui::text::Line<'static>> {
+        let colored = is_enabled();
+        self.lines()
+            .into_iter()
+            .map(|line| {
+                line.spans
+                    .into_iter()
+                    .map(|s| {
+                        let style = if colored { ratatui::style::Style::from(s.style) } else { ratatui::style::Style::default() };
+                        ratatui::text::Span::styled(s.text, style)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Display for StyledText {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_ansi())
+    }
+}
+
+impl From<&str> for StyledText {
+    fn from(text: &str) -> Self {
+        StyledText::plain(text)
+    }
+}
+
+impl From<String> for StyledText {
+    fn from(text: String) -> Self {
+        StyledText::plain(&text)
+    }
+}
+
+#[cfg(feature = "tui")]
+impl From<StyledText> for ratatui::text::Text<'static> {
+    fn from(text: StyledText) -> Self {
+        ratatui::text::Text::from(text.to_lines())
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::conditions::ConditionContext;
+use crate::ecs::{EffectMap, Entities, EntityEvent, EntityId, Health};
+use crate::inventory::Slot;
+use crate::random::RuzRng;
+use crate::registry::{ID, LootStack, Registry};
+use crate::stats::{Attribute, Stats};
+
+// Damage between entities: the attacker's ATTACK_DAMAGE (with its weapon's
+// modifiers) is reduced by the target's ARMOR (with its effects' modifiers).
+// Entities whose health reaches 0 are despawned and drop the loot table
+// registered under their kind's ID, like blocks do.
+//
+//   let hit = entities.attack(player, zombie, Some(&mut sword), &registry, &mut rng);
+//   if let Some(hit) = hit.filter(|h| h.killed) { inventory.add_loot(&hit.drops, &registry); }
+//   entities.publish_events(&mut events); // EntityDamaged, EntityKilled
+
+// Share of damage one point of armor blocks, and the most armor can block
+const ARMOR_REDUCTION: f32 = 0.04;
+const MAX_ARMOR_REDUCTION: f32 = 0.8;
+
+pub fn damage_after_armor(damage: f32, armor: f32) -> u32 {
+    let reduction = (armor.max(0.0) * ARMOR_REDUCTION).min(MAX_ARMOR_REDUCTION);
+    (damage.max(0.0) * (1.0 - reduction)).round() as u32
+}
+
+// ------
+// EVENTS
+// ------
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DamageSource {
+    Entity(EntityId),
+    Effect(ID),          // e.g. poison from an effect's on_tick
+    Environment(String), // e.g. "fall" or "lava"
+}
+
+impl Display for DamageSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DamageSource::Entity(entity) => write!(f, "entity {:?}", entity),
+            DamageSource::Effect(effect) => write!(f, "effect {}", effect),
+            DamageSource::Environment(cause) => write!(f, "{}", cause),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EntityDamaged {
+    pub entity: EntityId,
+    pub source: DamageSource,
+    pub amount: u32, // After armor
+    pub remaining: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct EntityKilled {
+    pub entity: EntityId,
+    pub kind: Option<ID>,
+    pub source: DamageSource,
+    pub drops: Vec<LootStack>,
+}
+
+// Outcome of one hit
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DamageResult {
+    pub dealt: u32,
+    pub killed: bool,
+    pub drops: Vec<LootStack>, // Rolled if killed
+    pub weapon_broken: bool,   // The weapon's durability reached 0, see Inventory::remove_broken
+}
+
+// ------
+// COMBAT
+// ------
+
+impl Entities {
+    // `amount` minus the target's ARMOR, after refreshing its effect
+    // modifiers. Kills the target at 0 health. None if it has no Health.
+    pub fn damage(
+        &mut self,
+        target: EntityId,
+        amount: f32,
+        source: DamageSource,
+        registry: &Registry,
+        rng: &mut RuzRng,
+    ) -> Option<DamageResult> {
+        self.deal(target, amount, source, None, None, registry, rng)
+    }
+
+    // The attacker hits the target with its ATTACK_DAMAGE, including the
+    // weapon's tool and enchantment modifiers. A weapon that is a tool takes
+    // one point of damage. The killer's LUCK adds loot rolls. None if either
+    // is dead or the target has no Health.
+    pub fn attack(
+        &mut self,
+        attacker: EntityId,
+        target: EntityId,
+        weapon: Option<&mut Slot>,
+        registry: &Registry,
+        rng: &mut RuzRng,
+    ) -> Option<DamageResult> {
+        if !self.is_alive(attacker) || !self.is_alive(target) {
+            return None;
+        }
+        let mut stats = self.get::<Stats>(attacker).cloned().unwrap_or_default();
+        stats.set_equipment(weapon.as_deref(), registry);
+        let damage = stats.get(Attribute::ATTACK_DAMAGE);
+        let mut result = self.deal(target, damage, DamageSource::Entity(attacker), Some(&stats), weapon.as_deref(), registry, rng)?;
+        if let Some(slot) = weapon
+            && registry.tools.contains_key(&slot.item.id)
+        {
+            result.weapon_broken = slot.damage(1, rng);
+        }
+        Some(result)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn deal(
+        &mut self,
+        target: EntityId,
+        amount: f32,
+        source: DamageSource,
+        killer: Option<&Stats>,
+        weapon: Option<&Slot>,
+        registry: &Registry,
+        rng: &mut RuzRng,
+    ) -> Option<DamageResult> {
+        let effects = self.get::<EffectMap>(target).cloned().unwrap_or_default();
+        let armor = match self.get_mut::<Stats>(target) {
+            Some(stats) => {
+                stats.set_effects(&effects, registry);
+                stats.get(Attribute::ARMOR)
+            }
+            None => Attribute::ARMOR.default_base(),
+        };
+        let health = self.get_mut::<Health>(target)?;
+        if health.is_dead() {
+            return None;
+        }
+        let dealt = damage_after_armor(amount, armor).min(health.current);
+        health.current -= dealt;
+        let remaining = health.current;
+        self.push_event(EntityEvent::Damaged(EntityDamaged { entity: target, source: source.clone(), amount: dealt, remaining }));
+        if remaining > 0 {
+            return Some(DamageResult { dealt, ..DamageResult::default() });
+        }
+
+        // Dead: roll the kind's loot table, then despawn
+        let kind = self.kind(target).copied();
+        let mut drops = vec![];
+        if let Some(loot_table) = kind.and_then(|kind| registry.loot_tables.get(&kind)) {
+            let mut context = ConditionContext::new().with_registry(registry);
+            if let Some(slot) = weapon {
+                context = context.with_held(slot);
+            }
+            let rolls = 1 + killer.map_or(0, |stats| stats.extra_loot_rolls(rng));
+            for _ in 0..rolls {
+                drops.extend(loot_table.roll_with(&context, rng));
+            }
+        }
+        self.despawn(target);
+        self.push_event(EntityEvent::Killed(EntityKilled { entity: target, kind, source, drops: drops.clone() }));
+        Some(DamageResult { dealt, killed: true, drops, weapon_broken: false })
+    }
+}
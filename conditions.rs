@@ -0,0 +1,298 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::inventory::Slot;
+use crate::random::RuzRng;
+use crate::registry::{Block, ID, Registry, Tool};
+use crate::savegame::PlayerProfile;
+use crate::world::BlockPos;
+
+// -------
+// CONTEXT
+// -------
+
+// What a condition is tested against, e.g. the tool and block when rolling
+// loot for a broken block. Missing parts make the conditions using them false.
+#[derive(Clone, Copy, Default)]
+pub struct ConditionContext<'a> {
+    pub registry: Option<&'a Registry>,
+    pub tool: Option<&'a Tool>,
+    pub held: Option<&'a Slot>, // Slot of the tool, for its enchantments
+    pub block: Option<&'a Block>,
+    pub player: Option<&'a PlayerProfile>,
+    pub position: Option<BlockPos>,
+    pub biome: Option<ID>, // Of the position's column, see World::biome
+    pub loot_filter: Option<&'a dyn LootFilter>, // Asked before a loot table rolls
+}
+
+// Decides whether a loot table drops anything at all in a context, e.g.
+// the loot conditions of datapack scripts (ScriptHost)
+pub trait LootFilter {
+    fn allows(&self, table: &ID, context: &ConditionContext) -> bool;
+}
+
+impl<'a> ConditionContext<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_registry(mut self, registry: &'a Registry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    pub fn with_tool(mut self, tool: &'a Tool) -> Self {
+        self.tool = Some(tool);
+        self
+    }
+
+    pub fn with_held(mut self, held: &'a Slot) -> Self {
+        self.held = Some(held);
+        self
+    }
+
+    pub fn with_block(mut self, block: &'a Block) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    pub fn with_player(mut self, player: &'a PlayerProfile) -> Self {
+        self.player = Some(player);
+        self
+    }
+
+    pub fn with_position(mut self, position: BlockPos) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    pub fn with_biome(mut self, biome: ID) -> Self {
+        self.biome = Some(biome);
+        self
+    }
+
+    pub fn with_loot_filter(mut self, filter: &'a dyn LootFilter) -> Self {
+        self.loot_filter = Some(filter);
+        self
+    }
+}
+
+// ----------
+// CONDITIONS
+// ----------
+
+// Predicate tree, written as e.g.
+//
+//   all_of(has_tag(ruztex:ores), not(random_chance(0.5)), tool_level_at_least(2))
+//
+// Display and FromStr convert between the tree and this syntax, serde uses it too.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    HasTag(ID),                              // The tool or block has the tag
+    RandomChance(f32),                       // 0.0 - 1.0
+    ToolLevelAtLeast(u32),                   // Without a tool the level is 0
+    QuestCompleted(ID),                      // By the player
+    HasItem(ID),                             // In the player's inventory
+    FlagSet(String),                         // PlayerProfile::flags, e.g. set by a dialogue
+    InArea { from: BlockPos, to: BlockPos }, // Position inside the box, inclusive
+    InBiome(ID),                             // The biome or a tag of registered biomes
+    AllOf(Vec<Condition>),                   // True if empty
+    AnyOf(Vec<Condition>),                   // False if empty
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    pub fn test(&self, context: &ConditionContext, rng: &mut RuzRng) -> bool {
+        match self {
+            Condition::HasTag(tag) => {
+                context.tool.is_some_and(|t| t.tags.contains(tag)) || context.block.is_some_and(|b| b.tags.contains(tag))
+            }
+            Condition::RandomChance(chance) => rng.chance(*chance),
+            Condition::ToolLevelAtLeast(level) => context.tool.map_or(0, |t| t.level) >= *level,
+            Condition::QuestCompleted(quest) => context.player.is_some_and(|p| p.quests.is_completed(quest)),
+            Condition::HasItem(item) => context.player.is_some_and(|p| p.inventory.count_of(item) > 0),
+            Condition::FlagSet(flag) => context.player.is_some_and(|p| p.flags.contains(flag)),
+            Condition::InArea { from, to } => context.position.is_some_and(|pos| {
+                (from.x.min(to.x)..=from.x.max(to.x)).contains(&pos.x)
+                    && (from.y.min(to.y)..=from.y.max(to.y)).contains(&pos.y)
+                    && (from.z.min(to.z)..=from.z.max(to.z)).contains(&pos.z)
+            }),
+            Condition::InBiome(id) => context.biome.is_some_and(|biome| match context.registry {
+                Some(registry) => registry.biome_matches(&biome, id),
+                None => biome == *id,
+            }),
+            Condition::AllOf(conditions) => conditions.iter().all(|c| c.test(context, rng)),
+            Condition::AnyOf(conditions) => conditions.iter().any(|c| c.test(context, rng)),
+            Condition::Not(condition) => !condition.test(context, rng),
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let list = |conditions: &[Condition]| conditions.iter().map(Condition::to_string).collect::<Vec<String>>().join(", ");
+        match self {
+            Condition::HasTag(tag) => write!(f, "has_tag({})", tag),
+            Condition::RandomChance(chance) => write!(f, "random_chance({})", chance),
+            Condition::ToolLevelAtLeast(level) => write!(f, "tool_level_at_least({})", level),
+            Condition::QuestCompleted(quest) => write!(f, "quest_completed({})", quest),
+            Condition::HasItem(item) => write!(f, "has_item({})", item),
+            Condition::FlagSet(flag) => write!(f, "flag_set({})", flag),
+            Condition::InArea { from, to } => write!(f, "in_area({}, {})", from, to),
+            Condition::InBiome(biome) => write!(f, "in_biome({})", biome),
+            Condition::AllOf(conditions) => write!(f, "all_of({})", list(conditions)),
+            Condition::AnyOf(conditions) => write!(f, "any_of({})", list(conditions)),
+            Condition::Not(condition) => write!(f, "not({})", condition),
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        let condition = parser.condition()?;
+        parser.finish()?;
+        Ok(condition)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for Condition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Condition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// Recursive descent parser for the condition syntax, also used for
+// behavior::BehaviorNode
+pub(crate) struct Parser<'a> {
+    pub(crate) input: &'a str,
+    pub(crate) pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Parser { input, pos: 0 }
+    }
+
+    // Fails on anything left after the parsed value
+    pub(crate) fn finish(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.pos < self.input.len() {
+            return Err(format!("unexpected '{}' at position {}", &self.input[self.pos..], self.pos));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    pub(crate) fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.input[self.pos..].starts_with(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at position {}", c, self.pos))
+        }
+    }
+
+    // Text up to the next ',' or ')'
+    pub(crate) fn argument(&mut self) -> Result<&'a str, String> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        let end = rest.find([',', ')']).unwrap_or(rest.len());
+        let argument = rest[..end].trim();
+        if argument.is_empty() {
+            return Err(format!("missing argument at position {}", self.pos));
+        }
+        self.pos += end;
+        Ok(argument)
+    }
+
+    pub(crate) fn id(&mut self) -> Result<ID, String> {
+        let argument = self.argument()?;
+        ID::parse(argument).map_err(|e| format!("invalid ID '{}': {}", argument, e))
+    }
+
+    pub(crate) fn number<T: FromStr>(&mut self) -> Result<T, String> {
+        let argument = self.argument()?;
+        argument.parse().map_err(|_| format!("invalid number '{}'", argument))
+    }
+
+    // "x y z"
+    fn position(&mut self) -> Result<BlockPos, String> {
+        let argument = self.argument()?;
+        let coordinates: Vec<i32> = argument.split_whitespace().filter_map(|c| c.parse().ok()).collect();
+        match coordinates[..] {
+            [x, y, z] if argument.split_whitespace().count() == 3 => Ok(BlockPos::new_3d(x, y, z)),
+            _ => Err(format!("invalid position '{}', expected 'x y z'", argument)),
+        }
+    }
+
+    // Comma separated values up to the closing ')'
+    pub(crate) fn list<T>(&mut self, value: impl Fn(&mut Self) -> Result<T, String>) -> Result<Vec<T>, String> {
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.input[self.pos..].starts_with(')') {
+            return Ok(values);
+        }
+        loop {
+            values.push(value(self)?);
+            self.skip_whitespace();
+            if !self.input[self.pos..].starts_with(',') {
+                return Ok(values);
+            }
+            self.pos += 1;
+        }
+    }
+
+    // "name(" of a call, with the position of the name
+    pub(crate) fn call(&mut self) -> Result<(&'a str, usize), String> {
+        self.skip_whitespace();
+        let rest = &self.input[self.pos..];
+        let name_len = rest.find(|c: char| !(c.is_ascii_lowercase() || c == '_')).unwrap_or(rest.len());
+        let start = self.pos;
+        self.pos += name_len;
+        self.expect('(')?;
+        Ok((&rest[..name_len], start))
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let (name, start) = self.call()?;
+        let condition = match name {
+            "has_tag" => Condition::HasTag(self.id()?),
+            "random_chance" => Condition::RandomChance(self.number()?),
+            "tool_level_at_least" => Condition::ToolLevelAtLeast(self.number()?),
+            "quest_completed" => Condition::QuestCompleted(self.id()?),
+            "has_item" => Condition::HasItem(self.id()?),
+            "flag_set" => Condition::FlagSet(self.argument()?.to_string()),
+            "in_area" => {
+                let from = self.position()?;
+                self.expect(',')?;
+                Condition::InArea { from, to: self.position()? }
+            }
+            "in_biome" => Condition::InBiome(self.id()?),
+            "all_of" => Condition::AllOf(self.list(Self::condition)?),
+            "any_of" => Condition::AnyOf(self.list(Self::condition)?),
+            "not" => Condition::Not(Box::new(self.condition()?)),
+            _ => return Err(format!("unknown condition '{}' at position {}", name, start)),
+        };
+        self.expect(')')?;
+        Ok(condition)
+    }
+}
@@ -0,0 +1,569 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_yaml::Value;
+
+use crate::registry::{
+    ID, LootEntry, LootFunction, LootTable, Recipe, RecipeComponent, RecipeShape, RegistrableEntity, RegistrationPipeline,
+    RegistryKey, SMELTING_TAG, Tag,
+};
+
+// Imports recipes, tags and loot tables of vanilla Minecraft datapacks, for
+// prototyping with existing content. Only what maps onto ruztex's types is
+// imported; everything else ends up in the report instead of failing the
+// import. Most vanilla IDs need IdPolicy::RELAXED (digits, paths, length).
+//
+//   ID::set_policy(IdPolicy::RELAXED);
+//   let import = import_datapack("packs/vanilla")?;
+//   println!("{}", import.report);
+//   import.pipeline().run(&mut registry)?;
+//
+// JSON is read with serde_yaml, JSON being valid YAML.
+
+// ------
+// ERRORS
+// ------
+
+#[derive(Debug)]
+pub enum DatapackError {
+    Io(PathBuf, io::Error),
+    MissingData(PathBuf), // No data directory in the datapack
+}
+
+impl Display for DatapackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DatapackError::Io(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            DatapackError::MissingData(path) => write!(f, "{} has no data directory", path.display()),
+        }
+    }
+}
+
+impl Error for DatapackError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DatapackError::Io(_, e) => Some(e),
+            DatapackError::MissingData(_) => None,
+        }
+    }
+}
+
+// ------
+// REPORT
+// ------
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportIssue {
+    Skipped { file: PathBuf, reason: String },  // Nothing of the file was imported
+    Ignored { file: PathBuf, feature: String }, // Imported without the feature
+}
+
+impl Display for ImportIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportIssue::Skipped { file, reason } => write!(f, "skipped {}: {}", file.display(), reason),
+            ImportIssue::Ignored { file, feature } => write!(f, "ignored in {}: {}", file.display(), feature),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub imported: usize, // Files
+    pub issues: Vec<ImportIssue>,
+}
+
+impl ImportReport {
+    pub fn skipped(&self) -> usize {
+        self.issues.iter().filter(|i| matches!(i, ImportIssue::Skipped { .. })).count()
+    }
+
+    fn skip(&mut self, file: &Path, reason: String) {
+        self.issues.push(ImportIssue::Skipped { file: file.to_path_buf(), reason });
+    }
+
+    fn ignore(&mut self, file: &Path, ignored: Vec<String>) {
+        self.issues.extend(ignored.into_iter().map(|feature| ImportIssue::Ignored { file: file.to_path_buf(), feature }));
+    }
+}
+
+impl Display for ImportReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "imported {} files, skipped {}", self.imported, self.skipped())?;
+        for issue in &self.issues {
+            write!(f, "\n  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+// ----------
+// CONVERSION
+// ----------
+
+// One converted file and the features of it that were left out
+#[derive(Clone, Debug)]
+pub struct Converted<T> {
+    pub value: T,
+    pub ignored: Vec<String>,
+}
+
+// IDs without a namespace are in "minecraft"
+fn parse_id(value: &str) -> Result<ID, String> {
+    let value = if value.contains(':') { value.to_string() } else { format!("minecraft:{}", value) };
+    ID::parse(&value).map_err(|e| format!("'{}' is no valid ID: {}", value, e))
+}
+
+fn parse_json(json: &str) -> Result<Value, String> {
+    serde_yaml::from_str(json).map_err(|e| format!("invalid JSON: {}", e))
+}
+
+fn string<'v>(value: &'v Value, field: &str) -> Result<&'v str, String> {
+    value.get(field).and_then(Value::as_str).ok_or_else(|| format!("missing '{}'", field))
+}
+
+fn count(value: &Value, field: &str) -> Option<u32> {
+    value.get(field).and_then(Value::as_u64).and_then(|n| u32::try_from(n).ok())
+}
+
+enum Ingredient {
+    Item(ID),
+    Tag(ID),
+}
+
+// "id", "#tag", {"item": id}, {"tag": id} or a list of alternatives, of
+// which the first is used
+fn ingredient(value: &Value, ignored: &mut Vec<String>) -> Result<Ingredient, String> {
+    if let Some(list) = value.as_sequence() {
+        if list.len() > 1 {
+            ignored.push("alternative ingredients, using the first".to_string());
+        }
+        return ingredient(list.first().ok_or("empty ingredient list")?, ignored);
+    }
+    if let Some(value) = value.as_str() {
+        return match value.strip_prefix('#') {
+            Some(tag) => Ok(Ingredient::Tag(parse_id(tag)?)),
+            None => Ok(Ingredient::Item(parse_id(value)?)),
+        };
+    }
+    if let Some(item) = value.get("item").and_then(Value::as_str) {
+        return Ok(Ingredient::Item(parse_id(item)?));
+    }
+    if let Some(tag) = value.get("tag").and_then(Value::as_str) {
+        return Ok(Ingredient::Tag(parse_id(tag)?));
+    }
+    Err("ingredient is neither an item nor a tag".to_string())
+}
+
+// "id" or {"item" or "id": id, "count": n}
+fn result(value: &Value, ignored: &mut Vec<String>) -> Result<RecipeComponent, String> {
+    if let Some(id) = value.as_str() {
+        return Ok(RecipeComponent::new(parse_id(id)?, 1));
+    }
+    let id = value.get("id").or_else(|| value.get("item")).and_then(Value::as_str).ok_or("result has no item")?;
+    if value.get("components").is_some() || value.get("nbt").is_some() {
+        ignored.push("result components".to_string());
+    }
+    Ok(RecipeComponent::new(parse_id(id)?, count(value, "count").unwrap_or(1)))
+}
+
+// Shaped and shapeless crafting, cooking and stonecutting recipes. Shaped
+// recipes are mirrorable like in Minecraft. Shapeless recipes can't use tags.
+pub fn convert_recipe(id: ID, json: &str) -> Result<Converted<Recipe>, String> {
+    let json = parse_json(json)?;
+    let kind = string(&json, "type")?;
+    let mut ignored = vec![];
+    let recipe = match kind.strip_prefix("minecraft:").unwrap_or(kind) {
+        "crafting_shaped" => {
+            let rows: Vec<&str> = json
+                .get("pattern")
+                .and_then(Value::as_sequence)
+                .ok_or("missing 'pattern'")?
+                .iter()
+                .map(|row| row.as_str().ok_or("pattern rows must be strings"))
+                .collect::<Result<_, _>>()?;
+            let keys = json.get("key").and_then(Value::as_mapping).ok_or("missing 'key'")?;
+            let mut shape = RecipeShape::new(&rows).with_mirrored(true);
+            for (key, value) in keys {
+                let mut chars = key.as_str().unwrap_or_default().chars();
+                let (Some(c), None) = (chars.next(), chars.next()) else {
+                    return Err(format!("key {:?} is not a single character", key));
+                };
+                shape = match ingredient(value, &mut ignored)? {
+                    Ingredient::Item(item) => shape.with_key(c, item),
+                    Ingredient::Tag(tag) => shape.with_tag_key(c, tag),
+                };
+            }
+            let pattern = rows.iter().flat_map(|row| row.chars());
+            if let Some(c) = pattern.clone().find(|c| *c != ' ' && !shape.keys.contains_key(c)) {
+                return Err(format!("pattern uses '{}' without a key", c));
+            }
+            if pattern.clone().all(|c| c == ' ') {
+                return Err("empty pattern".to_string());
+            }
+            let results = vec![result(json.get("result").ok_or("missing 'result'")?, &mut ignored)?];
+            Recipe::shaped(id, shape, results)
+        }
+        "crafting_shapeless" => {
+            let mut ingredients: Vec<RecipeComponent> = vec![];
+            for value in json.get("ingredients").and_then(Value::as_sequence).ok_or("missing 'ingredients'")? {
+                let Ingredient::Item(item) = ingredient(value, &mut ignored)? else {
+                    return Err("tag ingredients in shapeless recipes are not supported".to_string());
+                };
+                match ingredients.iter_mut().find(|c| c.id == item) {
+                    Some(component) => component.add_count(1),
+                    None => ingredients.push(RecipeComponent::new(item, 1)),
+                }
+            }
+            let results = vec![result(json.get("result").ok_or("missing 'result'")?, &mut ignored)?];
+            Recipe::new(id, ingredients, results)
+        }
+        "smelting" | "blasting" | "smoking" | "campfire_cooking" | "stonecutting" => {
+            let Ingredient::Item(item) = ingredient(json.get("ingredient").ok_or("missing 'ingredient'")?, &mut ignored)? else {
+                return Err(format!("tag ingredients in {} recipes are not supported", kind));
+            };
+            let mut result = result(json.get("result").ok_or("missing 'result'")?, &mut ignored)?;
+            // Older stonecutting recipes keep the count next to the result
+            if let Some(n) = count(&json, "count") {
+                result.count = n;
+                result.max = n;
+            }
+            for field in ["experience", "cookingtime"] {
+                if json.get(field).is_some() {
+                    ignored.push(field.to_string());
+                }
+            }
+            Recipe::new(id, vec![RecipeComponent::new(item, 1)], vec![result])
+        }
+        _ => return Err(format!("unsupported recipe type '{}'", kind)),
+    };
+    Ok(Converted { value: recipe, ignored })
+}
+
+// Entries of a tag file. Nested "#tag" entries are returned separately, the
+// caller decides how to resolve them.
+fn tag_values(json: &str, ignored: &mut Vec<String>) -> Result<(Vec<ID>, Vec<ID>), String> {
+    let json = parse_json(json)?;
+    if json.get("replace").and_then(Value::as_bool) == Some(true) {
+        ignored.push("replace".to_string());
+    }
+    let (mut ids, mut nested) = (vec![], vec![]);
+    for value in json.get("values").and_then(Value::as_sequence).ok_or("missing 'values'")? {
+        // {"id": ..., "required": false}
+        let entry = value.as_str().or_else(|| value.get("id").and_then(Value::as_str)).ok_or("invalid tag entry")?;
+        let parsed = match entry.strip_prefix('#') {
+            Some(tag) => parse_id(tag).map(|id| nested.push(id)),
+            None => parse_id(entry).map(|id| ids.push(id)),
+        };
+        if let Err(e) = parsed {
+            ignored.push(e);
+        }
+    }
+    Ok((ids, nested))
+}
+
+// Entries of `key`, e.g. RegistryKey::ITEM for tags/item. Nested tags are
+// left out, import_datapack resolves them.
+pub fn convert_tag(id: ID, key: RegistryKey, json: &str) -> Result<Converted<Tag>, String> {
+    let mut ignored = vec![];
+    let (ids, nested) = tag_values(json, &mut ignored)?;
+    ignored.extend(nested.iter().map(|tag| format!("nested tag #{}", tag)));
+    let mut tag = Tag::new(id);
+    for entry in &ids {
+        tag.add(key, entry);
+    }
+    Ok(Converted { value: tag, ignored })
+}
+
+// Number or {"min": n, "max": n} of a uniform distribution
+fn count_range(value: &Value) -> Option<(u32, u32)> {
+    if let Some(n) = value.as_f64() {
+        return Some((n as u32, n as u32));
+    }
+    let min = value.get("min").and_then(Value::as_f64)?;
+    let max = value.get("max").and_then(Value::as_f64)?;
+    (value.get("type").and_then(Value::as_str).is_none_or(|t| t.ends_with("uniform"))).then_some((min as u32, max as u32))
+}
+
+// Functions that map onto LootFunction, the others are ignored
+fn loot_functions(value: &Value, ignored: &mut Vec<String>) -> Vec<LootFunction> {
+    let mut functions = vec![];
+    for function in value.get("functions").and_then(Value::as_sequence).into_iter().flatten() {
+        let name = function.get("function").and_then(Value::as_str).unwrap_or_default();
+        let converted = match name.strip_prefix("minecraft:").unwrap_or(name) {
+            "set_count" => function
+                .get("count")
+                .and_then(count_range)
+                .map(|(min, max)| LootFunction::SetCount { min, max: max.max(min) }),
+            "furnace_smelt" => Some(LootFunction::FurnaceSmelt),
+            "looting_enchant" | "enchanted_count_increase" => {
+                let enchantment = function.get("enchantment").and_then(Value::as_str).unwrap_or("minecraft:looting");
+                let per_level = function.get("count").and_then(count_range).map(|(_, max)| max);
+                match (parse_id(enchantment), per_level) {
+                    (Ok(enchantment), Some(per_level)) => Some(LootFunction::LootingBonus { enchantment, per_level }),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match converted {
+            Some(converted) => functions.push(converted),
+            None => ignored.push(format!("loot function '{}'", name)),
+        }
+    }
+    functions
+}
+
+// Chance of the random_chance conditions, the others are ignored
+fn loot_chance(value: &Value, ignored: &mut Vec<String>) -> f32 {
+    let mut chance = 1.0;
+    for condition in value.get("conditions").and_then(Value::as_sequence).into_iter().flatten() {
+        let name = condition.get("condition").and_then(Value::as_str).unwrap_or_default();
+        match (name.strip_prefix("minecraft:").unwrap_or(name), condition.get("chance").and_then(Value::as_f64)) {
+            ("random_chance", Some(c)) => chance *= c.clamp(0.0, 1.0) as f32,
+            _ => ignored.push(format!("loot condition '{}'", name)),
+        }
+    }
+    chance
+}
+
+// Item entries of all pools, picked by weight like one pool rolled once.
+// Other entry types, rolls and pool conditions are ignored.
+pub fn convert_loot_table(id: ID, json: &str) -> Result<Converted<LootTable>, String> {
+    let json = parse_json(json)?;
+    let mut ignored = vec![];
+    let pools = json.get("pools").and_then(Value::as_sequence).ok_or("missing 'pools'")?;
+    if pools.len() > 1 {
+        ignored.push(format!("{} pools, merged into one", pools.len()));
+    }
+    let mut entries = vec![];
+    for pool in pools {
+        if pool.get("rolls").is_some_and(|rolls| rolls.as_f64() != Some(1.0)) {
+            ignored.push("rolls other than 1".to_string());
+        }
+        let pool_chance = loot_chance(pool, &mut ignored);
+        let pool_functions = loot_functions(pool, &mut ignored);
+        for entry in pool.get("entries").and_then(Value::as_sequence).into_iter().flatten() {
+            let kind = entry.get("type").and_then(Value::as_str).unwrap_or_default();
+            if kind.strip_prefix("minecraft:").unwrap_or(kind) != "item" {
+                ignored.push(format!("loot entry type '{}'", kind));
+                continue;
+            }
+            let item = parse_id(string(entry, "name")?)?;
+            let weight = count(entry, "weight").unwrap_or(1);
+            let chance = pool_chance * loot_chance(entry, &mut ignored);
+            let mut loot_entry = LootEntry::new(vec![item], 1, 1, chance, Some(weight));
+            for function in loot_functions(entry, &mut ignored).into_iter().chain(pool_functions.iter().cloned()) {
+                loot_entry = loot_entry.with_function(function);
+            }
+            entries.push(loot_entry);
+        }
+    }
+    if entries.is_empty() {
+        return Err("no item entries".to_string());
+    }
+    let mut table = LootTable::new(id, entries);
+    for function in loot_functions(&json, &mut ignored) {
+        table = table.with_function(function);
+    }
+    Ok(Converted { value: table, ignored })
+}
+
+// ---------
+// DATAPACKS
+// ---------
+
+#[derive(Clone, Debug, Default)]
+pub struct DatapackImport {
+    pub tags: Vec<Tag>,
+    pub recipes: Vec<Recipe>,
+    pub loot_tables: Vec<LootTable>,
+    pub scripts: Vec<(String, PathBuf)>, // (namespace, .rhai file), see ScriptHost::load_datapack
+    pub report: ImportReport,
+}
+
+impl DatapackImport {
+    // Everything imported, ready to be registered
+    pub fn pipeline(&self) -> RegistrationPipeline {
+        let mut pipeline = RegistrationPipeline::new();
+        self.tags.iter().for_each(|t| pipeline.add(RegistrableEntity::Tag(t.clone())));
+        self.recipes.iter().for_each(|r| pipeline.add(RegistrableEntity::Recipe(r.clone())));
+        self.loot_tables.iter().for_each(|t| pipeline.add(RegistrableEntity::LootTable(t.clone())));
+        pipeline
+    }
+}
+
+// JSON files below `dir`, sorted, with their path relative to `dir` without
+// the extension, e.g. "blocks/stone"
+fn json_files(dir: &Path) -> Result<Vec<(PathBuf, String)>, DatapackError> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        let entries = fs::read_dir(&current).map_err(|e| DatapackError::Io(current.clone(), e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| DatapackError::Io(current.clone(), e))?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                let name = path.strip_prefix(dir).unwrap_or(&path).with_extension("");
+                let name = name.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+                files.push((path, name));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn is_smelting(json: &str) -> bool {
+    let kind = parse_json(json).ok().and_then(|json| json.get("type").and_then(Value::as_str).map(str::to_string));
+    kind.is_some_and(|kind| kind.strip_prefix("minecraft:").unwrap_or(&kind) == "smelting")
+}
+
+// Directory `name` below `dir`, or its older plural form
+fn subdir(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    names.iter().map(|name| dir.join(name)).find(|path| path.is_dir())
+}
+
+// Tag read from item and block tag files, before nested tags are flattened
+struct PendingTag {
+    id: ID,
+    entries: Vec<(RegistryKey, ID)>,
+    nested: Vec<(RegistryKey, ID)>,
+}
+
+// Reads data/<namespace>/{recipe, tags/item, tags/block, loot_table} of the
+// datapack at `root`, also in their pre-1.21 plural forms, and lists the
+// Rhai scripts in data/<namespace>/scripts. Item and block
+// tags with the same ID become one tag, nested tags are flattened. Smelting
+// recipes are put in a "<namespace>:smelting" tag (see SMELTING_TAG).
+// Files that can't be converted are skipped and reported.
+pub fn import_datapack<P: AsRef<Path>>(root: P) -> Result<DatapackImport, DatapackError> {
+    let data = root.as_ref().join("data");
+    if !data.is_dir() {
+        return Err(DatapackError::MissingData(root.as_ref().to_path_buf()));
+    }
+    let mut import = DatapackImport::default();
+    let mut namespaces: Vec<PathBuf> = fs::read_dir(&data)
+        .map_err(|e| DatapackError::Io(data.clone(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    namespaces.sort();
+
+    let mut tags: BTreeMap<String, PendingTag> = BTreeMap::new();
+    let mut smelting: BTreeMap<String, Tag> = BTreeMap::new();
+    for dir in namespaces {
+        let namespace = dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let id = |file: &Path, name: &str, report: &mut ImportReport| match ID::try_new(&namespace, name) {
+            Ok(id) => Some(id),
+            Err(e) => {
+                report.skip(file, format!("no ID for {}:{}: {}", namespace, name, e));
+                None
+            }
+        };
+        let read = |file: &Path| fs::read_to_string(file).map_err(|e| DatapackError::Io(file.to_path_buf(), e));
+
+        for (key, names) in [(RegistryKey::ITEM, ["tags/item", "tags/items"]), (RegistryKey::BLOCK, ["tags/block", "tags/blocks"])] {
+            let Some(tag_dir) = subdir(&dir, &names) else {
+                continue;
+            };
+            for (file, name) in json_files(&tag_dir)? {
+                let Some(tag_id) = id(&file, &name, &mut import.report) else {
+                    continue;
+                };
+                let mut ignored = vec![];
+                match tag_values(&read(&file)?, &mut ignored) {
+                    Ok((ids, nested)) => {
+                        let tag =
+                            tags.entry(tag_id.to_string()).or_insert(PendingTag { id: tag_id, entries: vec![], nested: vec![] });
+                        tag.entries.extend(ids.into_iter().map(|id| (key, id)));
+                        tag.nested.extend(nested.into_iter().map(|id| (key, id)));
+                        import.report.imported += 1;
+                        import.report.ignore(&file, ignored);
+                    }
+                    Err(reason) => import.report.skip(&file, reason),
+                }
+            }
+        }
+
+        if let Some(recipe_dir) = subdir(&dir, &["recipe", "recipes"]) {
+            for (file, name) in json_files(&recipe_dir)? {
+                let Some(recipe_id) = id(&file, &name, &mut import.report) else {
+                    continue;
+                };
+                let content = read(&file)?;
+                match convert_recipe(recipe_id, &content) {
+                    Ok(converted) => {
+                        if is_smelting(&content) {
+                            let tag = smelting.entry(namespace.clone()).or_insert_with(|| Tag::new(ID::new(&namespace, SMELTING_TAG)));
+                            tag.add(RegistryKey::RECIPE, &recipe_id);
+                        }
+                        import.recipes.push(converted.value);
+                        import.report.imported += 1;
+                        import.report.ignore(&file, converted.ignored);
+                    }
+                    Err(reason) => import.report.skip(&file, reason),
+                }
+            }
+        }
+
+        if let Some(loot_dir) = subdir(&dir, &["loot_table", "loot_tables"]) {
+            for (file, name) in json_files(&loot_dir)? {
+                let Some(table_id) = id(&file, &name, &mut import.report) else {
+                    continue;
+                };
+                match convert_loot_table(table_id, &read(&file)?) {
+                    Ok(converted) => {
+                        import.loot_tables.push(converted.value);
+                        import.report.imported += 1;
+                        import.report.ignore(&file, converted.ignored);
+                    }
+                    Err(reason) => import.report.skip(&file, reason),
+                }
+            }
+        }
+
+        if let Some(script_dir) = subdir(&dir, &["scripts"]) {
+            let mut scripts: Vec<PathBuf> = fs::read_dir(&script_dir)
+                .map_err(|e| DatapackError::Io(script_dir.clone(), e))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+                .collect();
+            scripts.sort();
+            import.report.imported += scripts.len();
+            import.scripts.extend(scripts.into_iter().map(|path| (namespace.clone(), path)));
+        }
+    }
+
+    // Nested tags are flattened into the entries of the same kind
+    for pending_tag in tags.values() {
+        let mut tag = Tag::new(pending_tag.id);
+        let mut pending: Vec<(RegistryKey, ID)> = pending_tag.nested.clone();
+        let mut visited: Vec<(RegistryKey, ID)> = vec![];
+        pending_tag.entries.iter().for_each(|(key, id)| tag.add(*key, id));
+        while let Some((key, nested_id)) = pending.pop() {
+            if visited.contains(&(key, nested_id)) {
+                continue;
+            }
+            visited.push((key, nested_id));
+            match tags.get(&nested_id.to_string()) {
+                Some(nested_tag) => {
+                    nested_tag.entries.iter().filter(|(k, _)| *k == key).for_each(|(key, id)| tag.add(*key, id));
+                    pending.extend(nested_tag.nested.iter().filter(|(k, _)| *k == key));
+                }
+                None => import.report.issues.push(ImportIssue::Ignored {
+                    file: data.clone(),
+                    feature: format!("tag #{} in #{} is not in the datapack", nested_id, pending_tag.id),
+                }),
+            }
+        }
+        import.tags.push(tag);
+    }
+    import.tags.extend(smelting.into_values());
+    Ok(import)
+}
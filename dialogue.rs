@@ -0,0 +1,125 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::conditions::ConditionContext;
+use crate::random::RuzRng;
+use crate::registry::{Consequence, Dialogue, DialogueChoice, DialogueNode, Registry, RegistryKey};
+use crate::savegame::PlayerProfile;
+
+// ------
+// ERRORS
+// ------
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DialogueError {
+    Finished,
+    UnknownChoice(usize),
+    Failed(Consequence, String), // Consequence that couldn't be applied and why
+}
+
+impl Display for DialogueError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DialogueError::Finished => write!(f, "the dialogue is over"),
+            DialogueError::UnknownChoice(index) => write!(f, "there is no choice {}", index),
+            DialogueError::Failed(consequence, reason) => write!(f, "could not {}: {}", consequence, reason),
+        }
+    }
+}
+
+impl Error for DialogueError {}
+
+// ------------
+// CONSEQUENCES
+// ------------
+
+// Applies a consequence to the player. Items and quests are looked up in
+// `registry`.
+pub fn apply(consequence: &Consequence, player: &mut PlayerProfile, registry: &Registry) -> Result<(), DialogueError> {
+    let failed = |reason: String| DialogueError::Failed(consequence.clone(), reason);
+    match consequence {
+        Consequence::GiveItem { item, count } => {
+            let item = registry.items.get(item).ok_or_else(|| failed(format!("{} {} does not exist", RegistryKey::ITEM, item)))?;
+            player.inventory.add_item(item.clone(), *count).map_err(|e| failed(e.to_string()))
+        }
+        Consequence::StartQuest(quest) => {
+            if !registry.quests.contains_key(quest) {
+                return Err(failed(format!("{} {} does not exist", RegistryKey::QUEST, quest)));
+            }
+            player.quests.start(quest);
+            Ok(())
+        }
+        Consequence::SetFlag(flag) => {
+            player.flags.insert(flag.clone());
+            Ok(())
+        }
+        Consequence::ClearFlag(flag) => {
+            player.flags.remove(flag);
+            Ok(())
+        }
+    }
+}
+
+// ------------
+// CONVERSATION
+// ------------
+
+// A dialogue being played, from its first node until a node without choices
+// or a choice without a next node
+#[derive(Clone, Debug)]
+pub struct Conversation<'d> {
+    dialogue: &'d Dialogue,
+    node: Option<&'d DialogueNode>,
+}
+
+impl<'d> Conversation<'d> {
+    pub fn new(dialogue: &'d Dialogue) -> Self {
+        Conversation { dialogue, node: Some(dialogue.start()) }
+    }
+
+    pub fn dialogue(&self) -> &'d Dialogue {
+        self.dialogue
+    }
+
+    // None once the conversation is over
+    pub fn node(&self) -> Option<&'d DialogueNode> {
+        self.node
+    }
+
+    // Also true on a node without choices, which is shown but can't be left
+    pub fn is_finished(&self) -> bool {
+        self.node.is_none_or(|n| n.choices.is_empty())
+    }
+
+    // Choices of the current node whose condition holds, with their index
+    // for choose
+    pub fn choices(&self, context: &ConditionContext, rng: &mut RuzRng) -> Vec<(usize, &'d DialogueChoice)> {
+        let Some(node) = self.node else {
+            return vec![];
+        };
+        node.choices
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.condition.as_ref().is_none_or(|condition| condition.test(context, rng)))
+            .collect()
+    }
+
+    // Applies the consequences of the choice in order and moves on to its
+    // next node. Stops at the first consequence that fails, the conversation
+    // stays on the current node then.
+    pub fn choose(&mut self, index: usize, player: &mut PlayerProfile, registry: &Registry) -> Result<(), DialogueError> {
+        let node = self.node.ok_or(DialogueError::Finished)?;
+        let choice = node.choices.get(index).ok_or(DialogueError::UnknownChoice(index))?;
+        for consequence in &choice.consequences {
+            apply(consequence, player, registry)?;
+        }
+        // Dialogue::new checked that the next node exists
+        self.node = choice.next.as_deref().and_then(|name| self.dialogue.node(name));
+        Ok(())
+    }
+
+    // Leaves the conversation without choosing
+    pub fn end(&mut self) {
+        self.node = None;
+    }
+}
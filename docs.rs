@@ -0,0 +1,489 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::localization::{TranslationID, Translator};
+use crate::registry::{ID, LootTable, PatternKey, Recipe, RecipeComponent, Registry, RegistryKey};
+
+// Browsable reference of a registry's items, blocks, tools, recipes and tags,
+// e.g. for generating a wiki page from a content pack. Names are translated
+// like in index_display_names, entries link to each other (ingredients to
+// their items, tags to their entries and back).
+//
+//   registry.export_docs("docs/content.md", &translator)?;   // Markdown
+//   registry.export_docs("docs/content.html", &translator)?; // HTML
+//
+// export_graphviz draws how items flow through recipes and into tags, e.g.
+// `dot -Tsvg content.dot > content.svg`.
+
+// -----
+// MODEL
+// -----
+
+// Piece of a line, written the same way in both formats
+enum Span {
+    Text(String),
+    Code(String),
+    Link(String, String), // Text, anchor
+}
+
+struct Entry {
+    anchor: String,
+    name: String,
+    id: ID,
+    lines: Vec<Vec<Span>>,
+}
+
+struct Section {
+    title: &'static str,
+    entries: Vec<Entry>,
+}
+
+fn anchor(key: RegistryKey, id: &ID) -> String {
+    format!("{}-{}-{}", key.name(), id.namespace(), id.name()).to_lowercase().replace('/', "-")
+}
+
+fn text(value: impl Into<String>) -> Span {
+    Span::Text(value.into())
+}
+
+struct DocBuilder<'a> {
+    registry: &'a Registry,
+    translator: &'a Translator,
+}
+
+impl DocBuilder<'_> {
+    // Translated name, the ID if there's no translation
+    fn name(&self, key: RegistryKey, id: &ID) -> String {
+        let translation_id = TranslationID::from_id(id, &key.name().to_lowercase());
+        self.translator.try_translate(&translation_id, None).unwrap_or_else(|| id.to_string())
+    }
+
+    // Link to the item, block or tag with the ID, plain text if it isn't registered
+    fn link(&self, id: &ID) -> Span {
+        let key = [RegistryKey::ITEM, RegistryKey::BLOCK, RegistryKey::TOOL, RegistryKey::TAG]
+            .into_iter()
+            .find(|key| self.registry.contains(*key, id));
+        match key {
+            Some(key) => Span::Link(self.name(key, id), anchor(key, id)),
+            None => Span::Code(id.to_string()),
+        }
+    }
+
+    fn tag_link(&self, id: &ID) -> Span {
+        match self.registry.tags.contains_key(id) {
+            true => Span::Link(format!("#{}", self.name(RegistryKey::TAG, id)), anchor(RegistryKey::TAG, id)),
+            false => Span::Code(format!("#{}", id)),
+        }
+    }
+
+    fn list(&self, label: &str, spans: Vec<Span>) -> Vec<Span> {
+        let mut line = vec![text(format!("{}: ", label))];
+        if spans.is_empty() {
+            line.push(text("none"));
+        }
+        for (i, span) in spans.into_iter().enumerate() {
+            if i > 0 {
+                line.push(text(", "));
+            }
+            line.push(span);
+        }
+        line
+    }
+
+    fn tags_line(&self, key: RegistryKey, id: &ID, own: &[ID]) -> Vec<Span> {
+        let mut tags: Vec<&ID> = own.iter().collect();
+        for tag in self.registry.tags.values() {
+            if tag.contains(key, id) && !tags.contains(&&tag.id) {
+                tags.push(&tag.id);
+            }
+        }
+        tags.sort_by_key(|t| t.to_string());
+        self.list("Tags", tags.into_iter().map(|t| self.tag_link(t)).collect())
+    }
+
+    // Recipes that produce or use the ID
+    fn recipe_lines(&self, id: &ID) -> Vec<Vec<Span>> {
+        let recipe_link = |r: &&Recipe| Span::Link(self.name(RegistryKey::RECIPE, &r.id), anchor(RegistryKey::RECIPE, &r.id));
+        let mut made_by: Vec<&Recipe> = self.registry.recipes.values().filter(|r| r.results.iter().any(|c| c.id == *id)).collect();
+        let mut used_in: Vec<&Recipe> = self
+            .registry
+            .recipes
+            .values()
+            .filter(|r| {
+                r.ingredients.iter().any(|c| c.id == *id)
+                    || r.shape.iter().flat_map(|s| s.keys.values()).any(|key| key.matches(id, self.registry))
+            })
+            .collect();
+        made_by.sort_by_key(|r| r.id.to_string());
+        used_in.sort_by_key(|r| r.id.to_string());
+        let mut lines = vec![];
+        if !made_by.is_empty() {
+            lines.push(self.list("Made by", made_by.iter().map(recipe_link).collect()));
+        }
+        if !used_in.is_empty() {
+            lines.push(self.list("Used in", used_in.iter().map(recipe_link).collect()));
+        }
+        lines
+    }
+
+    // "2x <link>", "1-3x <link> (10%)", like RecipeComponent's Display
+    fn component(&self, component: &RecipeComponent) -> Vec<Span> {
+        let count = match component.max > component.count {
+            true => format!("{}-{}x ", component.count, component.max),
+            false => format!("{}x ", component.count),
+        };
+        let mut spans = vec![text(count), self.link(&component.id)];
+        if component.chance < 1.0 {
+            spans.push(text(format!(" ({}%)", (component.chance * 100.0).round())));
+        }
+        spans
+    }
+
+    fn drops(&self, table: &LootTable) -> Vec<Span> {
+        let total: u32 = table.entries.iter().map(|e| e.weight).sum();
+        let mut spans = vec![];
+        for entry in &table.entries {
+            let share = entry.weight as f32 / total.max(1) as f32 * entry.chance * 100.0;
+            let count = if entry.min == entry.max { entry.min.to_string() } else { format!("{}-{}", entry.min, entry.max) };
+            for item in &entry.items {
+                if !spans.is_empty() {
+                    spans.push(text(", "));
+                }
+                spans.extend([text(format!("{}x ", count)), self.link(item), text(format!(" ({}%)", share.round()))]);
+            }
+        }
+        let mut line = vec![text("Drops: ")];
+        line.extend(spans);
+        line
+    }
+
+    fn sorted<T>(values: impl Iterator<Item = T>, id: impl Fn(&T) -> &ID) -> Vec<T> {
+        let mut values: Vec<T> = values.collect();
+        values.sort_by_key(|v| id(v).to_string());
+        values
+    }
+
+    fn entry(&self, key: RegistryKey, id: &ID, lines: Vec<Vec<Span>>) -> Entry {
+        Entry { anchor: anchor(key, id), name: self.name(key, id), id: *id, lines }
+    }
+
+    fn sections(&self) -> Vec<Section> {
+        let registry = self.registry;
+        let mut sections = vec![];
+
+        let mut entries = vec![];
+        for item in Self::sorted(registry.items.values(), |i| &i.id) {
+            let mut lines = vec![vec![text(format!("Stack size: {}", item.stack_size()))]];
+            lines.push(self.tags_line(RegistryKey::ITEM, &item.id, item.tags()));
+            lines.extend(self.recipe_lines(&item.id));
+            entries.push(self.entry(RegistryKey::ITEM, &item.id, lines));
+        }
+        sections.push(Section { title: "Items", entries });
+
+        let mut entries = vec![];
+        for block in Self::sorted(registry.blocks.values(), |b| &b.id) {
+            let mut lines = vec![vec![text(format!("Hardness: {}", block.hardness))]];
+            // Loot tables registered after the block aren't attached to it
+            if let Some(table) = block.loot_table.as_ref().or_else(|| registry.loot_tables.get(&block.id)) {
+                lines.push(self.drops(table));
+            }
+            lines.push(self.tags_line(RegistryKey::BLOCK, &block.id, block.tags()));
+            lines.extend(self.recipe_lines(&block.id));
+            entries.push(self.entry(RegistryKey::BLOCK, &block.id, lines));
+        }
+        sections.push(Section { title: "Blocks", entries });
+
+        let mut entries = vec![];
+        for tool in Self::sorted(registry.tools.values(), |t| &t.id) {
+            let mut lines = vec![vec![text(format!(
+                "Durability: {}, level: {}, speed: {}",
+                tool.durability, tool.level, tool.speed
+            ))]];
+            lines.push(self.tags_line(RegistryKey::TOOL, &tool.id, tool.tags()));
+            lines.extend(self.recipe_lines(&tool.id));
+            entries.push(self.entry(RegistryKey::TOOL, &tool.id, lines));
+        }
+        sections.push(Section { title: "Tools", entries });
+
+        let mut entries = vec![];
+        for recipe in Self::sorted(registry.recipes.values(), |r| &r.id) {
+            let mut lines = vec![];
+            match &recipe.shape {
+                Some(shape) => {
+                    let title = if shape.mirrored { "Pattern (mirrorable):" } else { "Pattern:" };
+                    lines.push(vec![text(title)]);
+                    lines.extend(shape.pattern.iter().map(|row| vec![Span::Code(row.clone())]));
+                    for (c, key) in &shape.keys {
+                        let link = match key {
+                            PatternKey::Item(id) => self.link(id),
+                            PatternKey::Tag(tag) => self.tag_link(tag),
+                        };
+                        lines.push(vec![Span::Code(c.to_string()), text(" = "), link]);
+                    }
+                }
+                None => {
+                    let mut line = vec![text("Ingredients: ")];
+                    for (i, component) in recipe.ingredients.iter().enumerate() {
+                        if i > 0 {
+                            line.push(text(", "));
+                        }
+                        line.extend(self.component(component));
+                    }
+                    lines.push(line);
+                }
+            }
+            let mut line = vec![text("Results: ")];
+            for (i, component) in recipe.results.iter().enumerate() {
+                if i > 0 {
+                    line.push(text(", "));
+                }
+                line.extend(self.component(component));
+            }
+            lines.push(line);
+            if let Some(condition) = &recipe.unlock_condition {
+                lines.push(vec![text("Unlocked by: "), Span::Code(condition.to_string())]);
+            }
+            entries.push(self.entry(RegistryKey::RECIPE, &recipe.id, lines));
+        }
+        sections.push(Section { title: "Recipes", entries });
+
+        let mut entries = vec![];
+        for tag in Self::sorted(registry.tags.values(), |t| &t.id) {
+            let mut lines = vec![];
+            for key in [RegistryKey::ITEM, RegistryKey::BLOCK, RegistryKey::TOOL] {
+                let members: Vec<Span> =
+                    tag.iter().filter(|(k, _)| *k == key).map(|(_, id)| Span::Link(self.name(key, &id), anchor(key, &id))).collect();
+                if !members.is_empty() {
+                    lines.push(self.list(&format!("{}s", key.name()), members));
+                }
+            }
+            let recipes: Vec<Span> = tag
+                .iter()
+                .filter(|(k, _)| *k == RegistryKey::RECIPE)
+                .map(|(_, id)| Span::Link(self.name(RegistryKey::RECIPE, &id), anchor(RegistryKey::RECIPE, &id)))
+                .collect();
+            if !recipes.is_empty() {
+                lines.push(self.list("Recipes", recipes));
+            }
+            if lines.is_empty() {
+                lines.push(vec![text("Entries: none")]);
+            }
+            entries.push(self.entry(RegistryKey::TAG, &tag.id, lines));
+        }
+        sections.push(Section { title: "Tags", entries });
+        sections
+    }
+}
+
+// -------
+// WRITING
+// -------
+
+fn escape_markdown(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_html(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn markdown(sections: &[Section]) -> String {
+    let span = |span: &Span| match span {
+        Span::Text(t) => escape_markdown(t),
+        Span::Code(c) => format!("`{}`", c),
+        Span::Link(t, anchor) => format!("[{}](#{})", escape_markdown(t), anchor),
+    };
+    let mut out = String::from("# Registry reference\n\n");
+    for section in sections {
+        out.push_str(&format!("- [{}](#{}) ({})\n", section.title, section.title.to_lowercase(), section.entries.len()));
+    }
+    for section in sections {
+        out.push_str(&format!("\n## {}\n", section.title));
+        for entry in &section.entries {
+            out.push_str(&format!("\n<a id=\"{}\"></a>\n### {}\n\n`{}`\n\n", entry.anchor, escape_markdown(&entry.name), entry.id));
+            for line in &entry.lines {
+                out.push_str(&format!("- {}\n", line.iter().map(span).collect::<String>()));
+            }
+        }
+    }
+    out
+}
+
+fn html(sections: &[Section]) -> String {
+    let span = |span: &Span| match span {
+        Span::Text(t) => escape_html(t),
+        Span::Code(c) => format!("<code>{}</code>", escape_html(c)),
+        Span::Link(t, anchor) => format!("<a href=\"#{}\">{}</a>", anchor, escape_html(t)),
+    };
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Registry reference</title>\n</head>\n<body>\n<h1>Registry reference</h1>\n<ul>\n",
+    );
+    for section in sections {
+        let id = section.title.to_lowercase();
+        out.push_str(&format!("<li><a href=\"#{}\">{}</a> ({})</li>\n", id, section.title, section.entries.len()));
+    }
+    out.push_str("</ul>\n");
+    for section in sections {
+        out.push_str(&format!("<h2 id=\"{}\">{}</h2>\n", section.title.to_lowercase(), section.title));
+        for entry in &section.entries {
+            out.push_str(&format!("<h3 id=\"{}\">{}</h3>\n", entry.anchor, escape_html(&entry.name)));
+            out.push_str(&format!("<p><code>{}</code></p>\n<ul>\n", entry.id));
+            for line in &entry.lines {
+                out.push_str(&format!("<li>{}</li>\n", line.iter().map(span).collect::<String>()));
+            }
+            out.push_str("</ul>\n");
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+impl Registry {
+    pub fn docs_markdown(&self, translator: &Translator) -> String {
+        markdown(&DocBuilder { registry: self, translator }.sections())
+    }
+
+    pub fn docs_html(&self, translator: &Translator) -> String {
+        html(&DocBuilder { registry: self, translator }.sections())
+    }
+
+    // HTML for .html and .htm paths, Markdown otherwise
+    pub fn export_docs<P: AsRef<Path>>(&self, path: P, translator: &Translator) -> io::Result<()> {
+        let path = path.as_ref();
+        let is_html = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm"));
+        let docs = if is_html { self.docs_html(translator) } else { self.docs_markdown(translator) };
+        fs::write(path, docs)
+    }
+}
+
+// -----
+// GRAPH
+// -----
+
+fn dot_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn sorted(mut ids: Vec<&ID>) -> Vec<&ID> {
+    ids.sort_by_key(|id| id.to_string());
+    ids
+}
+
+// "4", "1-3 (10%)"
+fn amount(component: &RecipeComponent) -> String {
+    let mut amount = component.count.to_string();
+    if component.max > component.count {
+        amount.push_str(&format!("-{}", component.max));
+    }
+    if component.chance < 1.0 {
+        amount.push_str(&format!(" ({}%)", (component.chance * 100.0).round()));
+    }
+    amount
+}
+
+fn node(key: RegistryKey, id: &ID) -> String {
+    format!("\"{}:{}\"", key.name().to_lowercase(), dot_escape(&id.to_string()))
+}
+
+impl Registry {
+    // Registered key of an item, block or tool ID, in that order
+    fn content_key(&self, id: &ID) -> Option<RegistryKey> {
+        [RegistryKey::ITEM, RegistryKey::BLOCK, RegistryKey::TOOL].into_iter().find(|key| self.contains(*key, id))
+    }
+
+    // Items, blocks and tools no recipe makes or uses, sorted
+    pub fn orphaned_content(&self) -> Vec<(RegistryKey, ID)> {
+        let mut connected: HashSet<ID> = HashSet::new();
+        for recipe in self.recipes.values() {
+            connected.extend(recipe.ingredients.iter().chain(&recipe.results).map(|c| c.id));
+        }
+        let tag_keys: Vec<&PatternKey> = self.recipes.values().flat_map(|r| r.shape.iter().flat_map(|s| s.keys.values())).collect();
+        let mut orphaned: Vec<(RegistryKey, ID)> = self
+            .items
+            .keys()
+            .map(|id| (RegistryKey::ITEM, *id))
+            .chain(self.blocks.keys().map(|id| (RegistryKey::BLOCK, *id)))
+            .chain(self.tools.keys().map(|id| (RegistryKey::TOOL, *id)))
+            .filter(|(_, id)| !connected.contains(id) && !tag_keys.iter().any(|key| key.matches(id, self)))
+            .collect();
+        orphaned.sort_by_key(|(key, id)| (*key, id.to_string()));
+        orphaned
+    }
+
+    // DOT graph: ingredient -> recipe -> result edges labeled with counts,
+    // dashed member -> tag edges, and tag -> recipe edges for tag keys of
+    // shaped recipes. Orphaned content is filled red, IDs recipes refer to
+    // that aren't registered are dashed.
+    pub fn export_graphviz(&self) -> String {
+        let mut out = String::from("digraph registry {\n    rankdir=LR;\n    node [fontname=\"monospace\"];\n");
+        let orphaned = self.orphaned_content();
+
+        out.push_str("\n    // Content\n");
+        for (key, shape, ids) in [
+            (RegistryKey::ITEM, "box", sorted(self.items.keys().collect())),
+            (RegistryKey::BLOCK, "box3d", sorted(self.blocks.keys().collect())),
+            (RegistryKey::TOOL, "component", sorted(self.tools.keys().collect())),
+        ] {
+            for id in ids {
+                let style = if orphaned.contains(&(key, *id)) { ", style=filled, fillcolor=\"#f4cccc\"" } else { "" };
+                out.push_str(&format!("    {} [label=\"{}\", shape={}{}];\n", node(key, id), dot_escape(&id.to_string()), shape, style));
+            }
+        }
+        for id in sorted(self.tags.keys().collect()) {
+            out.push_str(&format!("    {} [label=\"#{}\", shape=ellipse, style=dashed];\n", node(RegistryKey::TAG, id), dot_escape(&id.to_string())));
+        }
+
+        // Recipe components that aren't registered get nodes of their own
+        let mut missing: Vec<ID> = vec![];
+        let mut component_node = |id: &ID| match self.content_key(id) {
+            Some(key) => node(key, id),
+            None if self.tags.contains_key(id) => node(RegistryKey::TAG, id),
+            None => {
+                if !missing.contains(id) {
+                    missing.push(*id);
+                }
+                format!("\"missing:{}\"", dot_escape(&id.to_string()))
+            }
+        };
+
+        out.push_str("\n    // Recipes\n");
+        let mut edges = String::new();
+        for recipe in sorted(self.recipes.keys().collect()).into_iter().filter_map(|id| self.recipes.get(id)) {
+            let recipe_node = node(RegistryKey::RECIPE, &recipe.id);
+            out.push_str(&format!("    {} [label=\"{}\", shape=diamond];\n", recipe_node, dot_escape(&recipe.id.to_string())));
+            // Tag keys of shaped recipes are ingredients too
+            for component in &recipe.ingredients {
+                edges.push_str(&format!("    {} -> {} [label=\"{}\"];\n", component_node(&component.id), recipe_node, component.count));
+            }
+            for component in &recipe.results {
+                let style = if component.chance < 1.0 { ", style=dashed" } else { "" };
+                let label = amount(component);
+                edges.push_str(&format!("    {} -> {} [label=\"{}\"{}];\n", recipe_node, component_node(&component.id), label, style));
+            }
+        }
+        missing.sort_by_key(|id| id.to_string());
+        for id in &missing {
+            out.push_str(&format!("    \"missing:{}\" [label=\"{}\", shape=box, style=dashed, color=red];\n", dot_escape(&id.to_string()), dot_escape(&id.to_string())));
+        }
+        out.push('\n');
+        out.push_str(&edges);
+
+        out.push_str("\n    // Tag membership\n");
+        for tag in sorted(self.tags.keys().collect()).into_iter().filter_map(|id| self.tags.get(id)) {
+            for (key, id) in tag.iter().filter(|(key, _)| *key != RegistryKey::RECIPE) {
+                out.push_str(&format!("    {} -> {} [style=dashed, arrowhead=empty];\n", node(key, &id), node(RegistryKey::TAG, &tag.id)));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
@@ -0,0 +1,463 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::combat::{EntityDamaged, EntityKilled};
+use crate::game::EventBus;
+use crate::inventory::Inventory;
+use crate::localization::Translator;
+use crate::pathfinding::Navigator;
+use crate::registry::{Effect, ID, Registry, RegistryError, RegistryKey};
+use crate::stats::{Attribute, Stats};
+use crate::format::{DEFAULT_LOCALE, format_duration};
+
+// ----------
+// COMPONENTS
+// ----------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Health {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Health {
+    pub fn new(max: u32) -> Self {
+        Health { current: max, max }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+}
+
+// Marks entities controlled by the game, `behavior` selects what they do
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ai {
+    pub behavior: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ActiveEffect {
+    pub remaining: u32, // Ticks until the effect ends
+    pub amplifier: u32,
+    elapsed: u32,       // Ticks since applied, for the effect's tick interval
+}
+
+// Active status effects of an entity, ticked down by Entities::tick_effects
+#[derive(Clone, Debug, Default)]
+pub struct EffectMap {
+    effects: HashMap<ID, ActiveEffect>,
+}
+
+impl EffectMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Applies an effect for `duration` ticks (the effect's default if None).
+    // An already active effect keeps the higher amplifier and the longer
+    // remaining duration.
+    pub fn add(&mut self, effect: &Effect, duration: Option<u32>, amplifier: u32) {
+        let remaining = duration.unwrap_or(effect.duration);
+        let amplifier = amplifier.min(effect.max_amplifier);
+        let active = self.effects.entry(effect.id).or_insert(ActiveEffect { remaining: 0, amplifier, elapsed: 0 });
+        active.remaining = active.remaining.max(remaining);
+        active.amplifier = active.amplifier.max(amplifier);
+    }
+
+    pub fn remove(&mut self, effect: &ID) -> Option<ActiveEffect> {
+        self.effects.remove(effect)
+    }
+
+    pub fn get(&self, effect: &ID) -> Option<&ActiveEffect> {
+        self.effects.get(effect)
+    }
+
+    pub fn has(&self, effect: &ID) -> bool {
+        self.effects.contains_key(effect)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    // Active effects, sorted by ID
+    pub fn iter(&self) -> impl Iterator<Item = (&ID, &ActiveEffect)> {
+        let mut effects: Vec<(&ID, &ActiveEffect)> = self.effects.iter().collect();
+        effects.sort_by_key(|(id, _)| id.to_string());
+        effects.into_iter()
+    }
+
+    // One line for the HUD, e.g. "Regeneration II 0:15 · Speed 1:30", with
+    // translated and colored names. `tick` is the game loop's tick duration.
+    pub fn hud(&self, registry: &Registry, translator: &Translator, tick: Duration) -> String {
+        self.iter()
+            .map(|(id, active)| {
+                let name = match registry.effects.get(id) {
+                    Some(effect) => effect.display_name(active.amplifier, translator),
+                    None => id.to_string(),
+                };
+                format!("{} {}", name, format_duration(tick * active.remaining, DEFAULT_LOCALE))
+            })
+            .collect::<Vec<String>>()
+            .join(" · ")
+    }
+}
+
+// Storage of one component type, indexed by entity slot
+pub struct ComponentStore<T> {
+    components: Vec<Option<T>>,
+}
+
+impl<T> ComponentStore<T> {
+    fn new() -> Self {
+        ComponentStore { components: vec![] }
+    }
+
+    fn insert(&mut self, index: usize, component: T) {
+        if index >= self.components.len() {
+            self.components.resize_with(index + 1, || None);
+        }
+        self.components[index] = Some(component);
+    }
+
+    fn remove(&mut self, index: usize) -> Option<T> {
+        self.components.get_mut(index).and_then(Option::take)
+    }
+
+    fn get(&self, index: usize) -> Option<&T> {
+        self.components.get(index).and_then(Option::as_ref)
+    }
+
+    fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.components.get_mut(index).and_then(Option::as_mut)
+    }
+}
+
+// Types that can be attached to entities
+pub trait Component: Sized + 'static {
+    fn store(entities: &Entities) -> &ComponentStore<Self>;
+    fn store_mut(entities: &mut Entities) -> &mut ComponentStore<Self>;
+}
+
+macro_rules! component {
+    ($type:ty, $field:ident) => {
+        impl Component for $type {
+            fn store(entities: &Entities) -> &ComponentStore<Self> {
+                &entities.$field
+            }
+
+            fn store_mut(entities: &mut Entities) -> &mut ComponentStore<Self> {
+                &mut entities.$field
+            }
+        }
+    };
+}
+
+component!(Position, positions);
+component!(Health, healths);
+component!(Inventory, inventories);
+component!(Ai, ai);
+component!(EffectMap, effects);
+component!(Stats, stats);
+component!(Navigator, navigators);
+
+// ------
+// EVENTS
+// ------
+
+#[derive(Clone, Debug)]
+pub struct EntitySpawned {
+    pub entity: EntityId,
+    pub kind: Option<ID>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EntityDespawned {
+    pub entity: EntityId,
+    pub kind: Option<ID>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EffectExpired {
+    pub entity: EntityId,
+    pub effect: ID,
+}
+
+// --------
+// ENTITIES
+// --------
+
+// Handle of a spawned entity. Slots are reused after despawn, the generation
+// tells old handles apart from the new entity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+// "<index>v<generation>", e.g. "12v3"
+impl Display for EntityId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+impl FromStr for EntityId {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid entity '{}'", value);
+        let (index, generation) = value.split_once('v').ok_or_else(invalid)?;
+        Ok(EntityId { index: index.parse().map_err(|_| invalid())?, generation: generation.parse().map_err(|_| invalid())? })
+    }
+}
+
+pub(crate) enum EntityEvent {
+    Spawned(EntitySpawned),
+    Despawned(EntityDespawned),
+    EffectExpired(EffectExpired),
+    Damaged(EntityDamaged),
+    Killed(EntityKilled),
+}
+
+// Spawned entities and their components
+pub struct Entities {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    kinds: Vec<Option<ID>>, // Registered Entity the entity was spawned from
+    free: Vec<u32>,
+    positions: ComponentStore<Position>,
+    healths: ComponentStore<Health>,
+    inventories: ComponentStore<Inventory>,
+    ai: ComponentStore<Ai>,
+    effects: ComponentStore<EffectMap>,
+    stats: ComponentStore<Stats>,
+    navigators: ComponentStore<Navigator>,
+    events: Vec<EntityEvent>, // Not yet published, see publish_events
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Entities {
+            generations: vec![],
+            alive: vec![],
+            kinds: vec![],
+            free: vec![],
+            positions: ComponentStore::new(),
+            healths: ComponentStore::new(),
+            inventories: ComponentStore::new(),
+            ai: ComponentStore::new(),
+            effects: ComponentStore::new(),
+            stats: ComponentStore::new(),
+            navigators: ComponentStore::new(),
+            events: vec![],
+        }
+    }
+
+    // Entity without kind and components
+    pub fn spawn_empty(&mut self) -> EntityId {
+        self.allocate(None)
+    }
+
+    // Entity of a registered kind, starting with full Health, Stats with its
+    // max health as MAX_HEALTH base and Ai if the kind has a behavior
+    pub fn spawn(&mut self, kind: &ID, registry: &Registry) -> Result<EntityId, RegistryError> {
+        let definition = registry.entities.get(kind).ok_or(RegistryError::NotFound(RegistryKey::ENTITY, *kind))?;
+        let max_health = definition.max_health;
+        let entity = self.allocate(Some(*kind));
+        self.insert(entity, Health::new(max_health));
+        self.insert(entity, Stats::new().with_base(Attribute::MAX_HEALTH, max_health as f32));
+        if let Some(behavior) = &definition.behavior {
+            self.insert(entity, Ai { behavior: behavior.clone() });
+        }
+        Ok(entity)
+    }
+
+    fn allocate(&mut self, kind: Option<ID>) -> EntityId {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.generations.push(0);
+                self.alive.push(false);
+                self.kinds.push(None);
+                (self.generations.len() - 1) as u32
+            }
+        };
+        let i = index as usize;
+        self.alive[i] = true;
+        self.kinds[i] = kind;
+        let entity = EntityId { index, generation: self.generations[i] };
+        self.events.push(EntityEvent::Spawned(EntitySpawned { entity, kind }));
+        entity
+    }
+
+    // Removes the entity and all its components
+    pub fn despawn(&mut self, entity: EntityId) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        let i = entity.index as usize;
+        self.alive[i] = false;
+        self.generations[i] += 1;
+        self.free.push(entity.index);
+        self.positions.remove(i);
+        self.healths.remove(i);
+        self.inventories.remove(i);
+        self.ai.remove(i);
+        self.effects.remove(i);
+        self.stats.remove(i);
+        self.navigators.remove(i);
+        let kind = self.kinds[i].take();
+        self.events.push(EntityEvent::Despawned(EntityDespawned { entity, kind }));
+        true
+    }
+
+    pub fn is_alive(&self, entity: EntityId) -> bool {
+        let i = entity.index as usize;
+        self.alive.get(i).copied().unwrap_or(false) && self.generations[i] == entity.generation
+    }
+
+    pub fn kind(&self, entity: EntityId) -> Option<&ID> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.kinds[entity.index as usize].as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.alive.iter().filter(|a| **a).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // All living entities
+    pub fn iter(&self) -> impl Iterator<Item = EntityId> + '_ {
+        (0..self.alive.len())
+            .filter(|&i| self.alive[i])
+            .map(|i| EntityId { index: i as u32, generation: self.generations[i] })
+    }
+
+    // Adds or replaces a component, ignored for despawned entities
+    pub fn insert<T: Component>(&mut self, entity: EntityId, component: T) {
+        if self.is_alive(entity) {
+            T::store_mut(self).insert(entity.index as usize, component);
+        }
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: EntityId) -> Option<T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        T::store_mut(self).remove(entity.index as usize)
+    }
+
+    pub fn get<T: Component>(&self, entity: EntityId) -> Option<&T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        T::store(self).get(entity.index as usize)
+    }
+
+    pub fn get_mut<T: Component>(&mut self, entity: EntityId) -> Option<&mut T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        T::store_mut(self).get_mut(entity.index as usize)
+    }
+
+    // Entities with a T, e.g. `for (entity, health) in entities.query::<Health>()`
+    pub fn query<T: Component>(&self) -> impl Iterator<Item = (EntityId, &T)> + '_ {
+        self.iter().filter_map(move |entity| self.get::<T>(entity).map(|c| (entity, c)))
+    }
+
+    // Entities with both an A and a B
+    pub fn query2<A: Component, B: Component>(&self) -> impl Iterator<Item = (EntityId, &A, &B)> + '_ {
+        self.iter()
+            .filter_map(move |entity| Some((entity, self.get::<A>(entity)?, self.get::<B>(entity)?)))
+    }
+
+    // Calls `f` for every entity with a T, with mutable access to the component
+    pub fn for_each_mut<T: Component>(&mut self, mut f: impl FnMut(EntityId, &mut T)) {
+        let entities: Vec<EntityId> = self.iter().collect();
+        for entity in entities {
+            if let Some(component) = self.get_mut::<T>(entity) {
+                f(entity, component);
+            }
+        }
+    }
+
+    // Advances every EffectMap by one tick: runs the effects' on_tick
+    // callbacks at their interval and removes expired effects. Call once per
+    // tick from the game loop's update.
+    pub fn tick_effects(&mut self, registry: &Registry) {
+        let mut due = vec![];
+        let mut expired = vec![];
+        for entity in self.iter().collect::<Vec<EntityId>>() {
+            let Some(map) = self.get_mut::<EffectMap>(entity) else {
+                continue;
+            };
+            for (id, active) in map.effects.iter_mut() {
+                let effect = registry.effects.get(id);
+                active.elapsed += 1;
+                active.remaining = active.remaining.saturating_sub(1);
+                if let Some(effect) = effect
+                    && let Some(on_tick) = effect.on_tick
+                    && active.elapsed % effect.interval == 0
+                {
+                    due.push((entity, on_tick, active.amplifier));
+                }
+                if active.remaining == 0 {
+                    expired.push((entity, *id));
+                }
+            }
+        }
+        for (entity, on_tick, amplifier) in due {
+            if self.is_alive(entity) {
+                on_tick(entity, self, amplifier);
+            }
+        }
+        for (entity, effect) in expired {
+            if let Some(map) = self.get_mut::<EffectMap>(entity) {
+                map.effects.remove(&effect);
+                self.events.push(EntityEvent::EffectExpired(EffectExpired { entity, effect }));
+            }
+        }
+    }
+
+    // Queues an event for the next publish_events
+    pub(crate) fn push_event(&mut self, event: EntityEvent) {
+        self.events.push(event);
+    }
+
+    // Publishes EntitySpawned/EntityDespawned/EffectExpired and the combat
+    // module's EntityDamaged/EntityKilled for changes since the last call,
+    // e.g. once per tick from the game loop
+    pub fn publish_events(&mut self, events: &mut EventBus) {
+        for event in self.events.drain(..) {
+            match event {
+                EntityEvent::Spawned(e) => events.publish(e),
+                EntityEvent::Despawned(e) => events.publish(e),
+                EntityEvent::EffectExpired(e) => events.publish(e),
+                EntityEvent::Damaged(e) => events.publish(e),
+                EntityEvent::Killed(e) => events.publish(e),
+            }
+        }
+    }
+}
+
+impl Default for Entities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
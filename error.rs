@@ -0,0 +1,209 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+#[cfg(feature = "tui")]
+use crate::admin::AdminError;
+use crate::behavior::BehaviorError;
+use crate::color::ColorError;
+use crate::datapack::DatapackError;
+use crate::dialogue::DialogueError;
+#[cfg(feature = "tui")]
+use crate::input::InputError;
+#[cfg(feature = "tui")]
+use crate::interface::CommandError;
+use crate::inventory::InventoryError;
+use crate::localization::LocalizationError;
+use crate::registry::RegistryError;
+use crate::sprite::SpriteError;
+use crate::theme::ThemeError;
+use crate::world::WorldError;
+
+// -----
+// ERROR
+// -----
+
+// A line-based text format (save files, macros, ...) that couldn't be read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    MissingHeader(&'static str),               // Expected first line, e.g. "quests <player>"
+    InvalidLine { line: usize, text: String }, // 1-based line number
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingHeader(header) => write!(f, "missing '{}' header", header),
+            ParseError::InvalidLine { line, text } => write!(f, "invalid line {}: {}", line, text),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+// Any error the crate's APIs return. Each module has its own error type
+// (ColorError, RegistryError, ...), which converts into this one with `?`.
+#[derive(Debug)]
+pub enum RuztexError {
+    Color(ColorError),
+    Registry(RegistryError),
+    Localization(LocalizationError),
+    #[cfg(feature = "tui")]
+    Command(CommandError),
+    Inventory(InventoryError),
+    Theme(ThemeError),
+    Dialogue(DialogueError),
+    Sprite(SpriteError),
+    Datapack(DatapackError),
+    #[cfg(feature = "tui")]
+    Input(InputError),
+    World(WorldError),
+    Behavior(BehaviorError),
+    #[cfg(feature = "tui")]
+    Admin(AdminError),
+    Parse(ParseError),
+    Io(io::Error),
+}
+
+impl Display for RuztexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RuztexError::Color(e) => write!(f, "color error: {}", e),
+            RuztexError::Registry(e) => write!(f, "registry error: {}", e),
+            RuztexError::Localization(e) => write!(f, "localization error: {}", e),
+            #[cfg(feature = "tui")]
+            RuztexError::Command(e) => write!(f, "command error: {}", e),
+            RuztexError::Inventory(e) => write!(f, "inventory error: {}", e),
+            RuztexError::Theme(e) => write!(f, "theme error: {}", e),
+            RuztexError::Dialogue(e) => write!(f, "dialogue error: {}", e),
+            RuztexError::Sprite(e) => write!(f, "sprite error: {}", e),
+            RuztexError::Datapack(e) => write!(f, "datapack error: {}", e),
+            #[cfg(feature = "tui")]
+            RuztexError::Input(e) => write!(f, "input error: {}", e),
+            RuztexError::World(e) => write!(f, "world error: {}", e),
+            RuztexError::Behavior(e) => write!(f, "behavior error: {}", e),
+            #[cfg(feature = "tui")]
+            RuztexError::Admin(e) => write!(f, "admin error: {}", e),
+            RuztexError::Parse(e) => write!(f, "parse error: {}", e),
+            RuztexError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl Error for RuztexError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RuztexError::Color(e) => Some(e),
+            RuztexError::Registry(e) => Some(e),
+            RuztexError::Localization(e) => Some(e),
+            #[cfg(feature = "tui")]
+            RuztexError::Command(e) => Some(e),
+            RuztexError::Inventory(e) => Some(e),
+            RuztexError::Theme(e) => Some(e),
+            RuztexError::Dialogue(e) => Some(e),
+            RuztexError::Sprite(e) => Some(e),
+            RuztexError::Datapack(e) => Some(e),
+            #[cfg(feature = "tui")]
+            RuztexError::Input(e) => Some(e),
+            RuztexError::World(e) => Some(e),
+            RuztexError::Behavior(e) => Some(e),
+            #[cfg(feature = "tui")]
+            RuztexError::Admin(e) => Some(e),
+            RuztexError::Parse(e) => Some(e),
+            RuztexError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<ColorError> for RuztexError {
+    fn from(e: ColorError) -> Self {
+        RuztexError::Color(e)
+    }
+}
+
+impl From<RegistryError> for RuztexError {
+    fn from(e: RegistryError) -> Self {
+        RuztexError::Registry(e)
+    }
+}
+
+impl From<LocalizationError> for RuztexError {
+    fn from(e: LocalizationError) -> Self {
+        RuztexError::Localization(e)
+    }
+}
+
+#[cfg(feature = "tui")]
+impl From<CommandError> for RuztexError {
+    fn from(e: CommandError) -> Self {
+        RuztexError::Command(e)
+    }
+}
+
+impl From<InventoryError> for RuztexError {
+    fn from(e: InventoryError) -> Self {
+        RuztexError::Inventory(e)
+    }
+}
+
+impl From<ThemeError> for RuztexError {
+    fn from(e: ThemeError) -> Self {
+        RuztexError::Theme(e)
+    }
+}
+
+impl From<DialogueError> for RuztexError {
+    fn from(e: DialogueError) -> Self {
+        RuztexError::Dialogue(e)
+    }
+}
+
+impl From<SpriteError> for RuztexError {
+    fn from(e: SpriteError) -> Self {
+        RuztexError::Sprite(e)
+    }
+}
+
+impl From<DatapackError> for RuztexError {
+    fn from(e: DatapackError) -> Self {
+        RuztexError::Datapack(e)
+    }
+}
+
+#[cfg(feature = "tui")]
+impl From<InputError> for RuztexError {
+    fn from(e: InputError) -> Self {
+        RuztexError::Input(e)
+    }
+}
+
+impl From<WorldError> for RuztexError {
+    fn from(e: WorldError) -> Self {
+        RuztexError::World(e)
+    }
+}
+
+impl From<BehaviorError> for RuztexError {
+    fn from(e: BehaviorError) -> Self {
+        RuztexError::Behavior(e)
+    }
+}
+
+#[cfg(feature = "tui")]
+impl From<AdminError> for RuztexError {
+    fn from(e: AdminError) -> Self {
+        RuztexError::Admin(e)
+    }
+}
+
+impl From<ParseError> for RuztexError {
+    fn from(e: ParseError) -> Self {
+        RuztexError::Parse(e)
+    }
+}
+
+impl From<io::Error> for RuztexError {
+    fn from(e: io::Error) -> Self {
+        RuztexError::Io(e)
+    }
+}
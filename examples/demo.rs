@@ -1,18 +1,22 @@
-mod color;
-mod registries;
-mod register;
-mod localization;
+// Colors, registry, translations, inventories and commands in action:
+//
+//   cargo run --example demo
 
 #[allow(unused_imports)]
 use std::{thread, time::Duration};
 use std::collections::HashMap;
 use std::borrow::Cow;
 
-use registries::REGISTRY;
-use localization::{Language, Translator, TranslationID};
-use color::{Color, ColorRef, GradientDirection};
+use ruztex::color::{self, Color, ColorRef, GradientDirection};
+use ruztex::interface::{Command, CommandArg, CommandRegistry, Executor};
+use ruztex::inventory::Inventory;
+use ruztex::localization::{Language, Translator};
+use ruztex::registry::with_registry;
+use ruztex::{RuztexError, id, logging, register, tid};
+
+fn main() -> Result<(), RuztexError> {
+    logging::init(logging::LogConfig::new().with_file("ruztex.log"))?;
 
-fn main() -> Result<(), String> {
     // Add custom colors
     let _ = color::add_color("custom", "my_red", Color::from_hex("#ff0055"));
     let _ = color::add_color("custom", "my_blue", Color::from_hex("#1e90ff"));
@@ -91,31 +95,31 @@ fn main() -> Result<(), String> {
     register::register();
 
     // Print registered tags
-    let registry = REGISTRY.lock().unwrap();
-
-    for (tag_id, tag) in &registry.tags {
-        println!("Tag: {}", tag);
-        for (typ, entity_id) in &tag.entries {
-            println!("  {}: {}", typ, entity_id);
+    with_registry(|registry| {
+        for tag in registry.tags.values() {
+            println!("Tag: {}", tag);
+            for (typ, entity_id) in &tag.entries {
+                println!("  {}: {}", typ, entity_id);
+            }
         }
-    }
+    });
 
     let lang = Language { name: "Deutsch".to_string(), code: "en_US".to_string() };
-    let translator = Translator::load(lang.clone(), format!("lang/{}.yaml", lang.code)).unwrap();
+    let translator = Translator::load_or_default(lang.clone(), format!("lang/{}.yaml", lang.code));
 
     // Ohne Platzhalter
-    println!("{}", translator.translate(&TranslationID::from("examplemod:item.hammer"), None)); // z.B. "Hammer" oder fallback "examplemod:item.hammer"
+    println!("{}", translator.translate(&tid!("examplemod:item.hammer"), None)); // z.B. "Hammer" oder fallback "examplemod:item.hammer"
 
     // Mit Platzhalter
-    println!("{}", translator.translate(&TranslationID::from("examplemod:misc.greeting"), Some(&HashMap::from([
+    println!("{}", translator.translate(&tid!("examplemod:misc.greeting"), Some(&HashMap::from([
         ("p", Cow::Owned(color::colored_text("Kuhly", &ColorRef::Named("custom", "my_red")).unwrap())),
     ])))); // z.B. "Hallo, Kuhly!"
 
-    println!("{}", translator.translate(&TranslationID::from("examplemod:misc.greeting"), Some(&HashMap::from([
+    println!("{}", translator.translate(&tid!("examplemod:misc.greeting"), Some(&HashMap::from([
         ("p", Cow::Owned(color::rainbow_text("Kuhly", GradientDirection::Horizontal, Some(true)).unwrap())),
     ])))); // z.B. "Hallo, Kuhly!"
 
-    println!("{}", translator.translate(&TranslationID::from("examplemod:misc.coca_cola"), Some(&HashMap::from([
+    println!("{}", translator.translate(&tid!("examplemod:misc.coca_cola"), Some(&HashMap::from([
         ("c", Cow::Owned(format!("{}, {} - {}",
         color::gradient_text("Coca Cola Light", &[
             ColorRef::Direct(Color::from_hex("#2A7B9B")),
@@ -133,5 +137,29 @@ fn main() -> Result<(), String> {
             ColorRef::Direct(Color::from_hex("#53C9ED")),
         ], GradientDirection::Horizontal, Some(true)).unwrap()))),
     ]))));
+
+    // Fill an inventory with registered items
+    let coal = with_registry(|registry| registry.items[&id!("ruztex:coal")].clone());
+    let mut inventory = Inventory::new(Some(100));
+    inventory.add_item(coal, 80)?;
+    for slot in inventory.stacks() {
+        println!("{} x{}", slot.item.id, slot.count);
+    }
+
+    // Run a command without the interactive prompt
+    let mut commands = CommandRegistry::new();
+    commands.register_command(
+        Command::new("greet")
+            .with_args(vec![CommandArg {
+                name: "name".to_string(),
+                arg_type: "string".to_string(),
+                range: None,
+                optional: false,
+                default: None,
+                help: None,
+            }])
+            .with_handler(|args| format!("Hello, {}!", args["name"])),
+    )?;
+    println!("{}", commands.execute_command(&Executor::console(), "greet Kuhly")?);
     Ok(())
 }
\ No newline at end of file
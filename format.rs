@@ -0,0 +1,200 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Locale-aware formatting keyed off Language.code ("en_US", "de_DE", ...).
+// Locales without rules of their own are formatted like en_US.
+
+// For output that isn't tied to a language
+pub const DEFAULT_LOCALE: &str = "en_US";
+
+fn language(locale: &str) -> &str {
+    locale.split('_').next().unwrap_or(locale)
+}
+
+// -------
+// NUMBERS
+// -------
+
+// Digit group and decimal separator
+fn separators(locale: &str) -> (&'static str, char) {
+    match language(locale) {
+        "de" | "es" | "it" | "nl" | "pt" | "da" | "id" | "tr" => (".", ','),
+        "fr" | "ru" | "pl" | "cs" | "sv" | "fi" | "nb" | "uk" => ("\u{a0}", ','),
+        _ => (",", '.'),
+    }
+}
+
+fn group_digits(digits: &str, group: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 * group.len());
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push_str(group);
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+// "1,234,567" (en_US), "1.234.567" (de_DE), "1 234 567" (fr_FR)
+pub fn format_number(value: i64, locale: &str) -> String {
+    let (group, _) = separators(locale);
+    let digits = group_digits(&value.unsigned_abs().to_string(), group);
+    if value < 0 { format!("-{}", digits) } else { digits }
+}
+
+// "1,234.57" (en_US), "1.234,57" (de_DE)
+pub fn format_decimal(value: f64, decimals: usize, locale: &str) -> String {
+    let (group, decimal) = separators(locale);
+    let formatted = format!("{:.*}", decimals, value.abs());
+    let (int, frac) = formatted.split_once('.').unwrap_or((&formatted, ""));
+    let mut result = group_digits(int, group);
+    if !frac.is_empty() {
+        result.push(decimal);
+        result.push_str(frac);
+    }
+    // No "-0.0"
+    if value < 0.0 && result.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        result.insert(0, '-');
+    }
+    result
+}
+
+// "1st", "2nd", "11th" (en_US), "1." (de_DE), "1er", "2e" (fr_FR), "1º" (es_ES)
+pub fn format_ordinal(value: i64, locale: &str) -> String {
+    match language(locale) {
+        "de" | "da" | "nb" | "fi" | "cs" | "pl" | "tr" => format!("{}.", value),
+        "fr" => format!("{}{}", value, if value == 1 { "er" } else { "e" }),
+        "es" | "it" | "pt" => format!("{}º", value),
+        _ => {
+            let suffix = match (value.unsigned_abs() % 10, value.unsigned_abs() % 100) {
+                (_, 11..=13) => "th",
+                (1, _) => "st",
+                (2, _) => "nd",
+                (3, _) => "rd",
+                _ => "th",
+            };
+            format!("{}{}", value, suffix)
+        }
+    }
+}
+
+// -----
+// UNITS
+// -----
+
+// "512 B", "1.5 KiB" (en_US), "1,5 KiB" (de_DE)
+pub fn format_bytes(bytes: f64, locale: &str) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", format_number(value as i64, locale), UNITS[0])
+    } else {
+        format!("{} {}", format_decimal(value, 1, locale), UNITS[unit])
+    }
+}
+
+// Day, hour, minute and second symbols and whether they follow a space
+fn duration_units(locale: &str) -> ([&'static str; 4], bool) {
+    match language(locale) {
+        "de" => (["d", "h", "min", "s"], true),
+        "fr" => (["j", "h", "min", "s"], true),
+        "es" | "it" | "pt" => (["d", "h", "min", "s"], true),
+        _ => (["d", "h", "m", "s"], false),
+    }
+}
+
+// The largest unit and the next one if it isn't zero: "2h 13m", "5m 3s",
+// "42s" (en_US), "2 h 13 min" (de_DE)
+pub fn format_duration(duration: Duration, locale: &str) -> String {
+    let (units, spaced) = duration_units(locale);
+    let secs = duration.as_secs();
+    let parts = [secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60];
+    let first = parts.iter().position(|&p| p > 0).unwrap_or(3);
+    let space = if spaced { " " } else { "" };
+    let mut result = format!("{}{}{}", parts[first], space, units[first]);
+    if first < 3 && parts[first + 1] > 0 {
+        result.push_str(&format!(" {}{}{}", parts[first + 1], space, units[first + 1]));
+    }
+    result
+}
+
+// -----
+// DATES
+// -----
+
+// Year, month and day of a day count since 1970-01-01
+// (http://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// The (UTC) date: "10/17/2026" (en_US), "17/10/2026" (en_GB, fr_FR),
+// "17.10.2026" (de_DE), "2026-10-17" (sv_SE, ja_JP)
+pub fn format_date(time: SystemTime, locale: &str) -> String {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+    };
+    let (y, m, d) = civil_from_days(secs.div_euclid(86400));
+    match language(locale) {
+        "en" if locale != "en_US" => format!("{:02}/{:02}/{}", d, m, y),
+        "de" | "da" | "nb" | "fi" | "ru" | "pl" | "cs" | "tr" | "uk" => format!("{:02}.{:02}.{}", d, m, y),
+        "fr" | "es" | "it" | "pt" => format!("{:02}/{:02}/{}", d, m, y),
+        "nl" => format!("{:02}-{:02}-{}", d, m, y),
+        "sv" | "ja" | "zh" | "ko" => format!("{}-{:02}-{:02}", y, m, d),
+        _ => format!("{:02}/{:02}/{}", m, d, y),
+    }
+}
+
+// ------------------
+// TYPED PLACEHOLDERS
+// ------------------
+
+// Formats a translation variable for a typed placeholder like "%{count:number}":
+//   number   - integer or decimal number
+//   bytes    - byte count
+//   duration - seconds
+//   ordinal  - integer
+//   date     - seconds since the Unix epoch
+// None for unknown types or values that don't parse
+pub fn format_value(kind: &str, value: &str, locale: &str) -> Option<String> {
+    let value = value.trim();
+    match kind {
+        "number" => match value.parse::<i64>() {
+            Ok(n) => Some(format_number(n, locale)),
+            Err(_) => {
+                let decimals = value.split_once('.').map_or(0, |(_, frac)| frac.len());
+                value.parse::<f64>().ok().map(|n| format_decimal(n, decimals, locale))
+            }
+        },
+        "bytes" => value.parse::<f64>().ok().map(|n| format_bytes(n, locale)),
+        "duration" => value
+            .parse::<f64>()
+            .ok()
+            .and_then(|s| Duration::try_from_secs_f64(s).ok())
+            .map(|d| format_duration(d, locale)),
+        "ordinal" => value.parse::<i64>().ok().map(|n| format_ordinal(n, locale)),
+        "date" => value.parse::<i64>().ok().map(|secs| {
+            let time = if secs >= 0 {
+                UNIX_EPOCH + Duration::from_secs(secs as u64)
+            } else {
+                UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+            };
+            format_date(time, locale)
+        }),
+        _ => None,
+    }
+}
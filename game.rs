@@ -0,0 +1,331 @@
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, VecDeque};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::pool::Pool;
+use crate::registry::ID;
+
+// ------
+// EVENTS
+// ------
+
+#[derive(Clone, Debug)]
+pub struct ItemCrafted {
+    pub recipe: ID,
+    pub results: Vec<(ID, u32)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockBroken {
+    pub block: ID,
+    pub position: Option<(i32, i32)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ToolBroken {
+    pub tool: ID,
+}
+
+// Returned by Inventory::select for the caller to publish
+#[derive(Clone, Debug)]
+pub struct HotbarSelected {
+    pub previous: usize,
+    pub selected: usize,
+    pub item: Option<ID>, // The new active stack, None if the slot is empty
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+type Handler = Box<dyn FnMut(&dyn Any, &mut VecDeque<Box<dyn Any>>)>;
+
+// Publish/subscribe by event type. Published events are queued and delivered
+// by dispatch(), handlers may publish further events.
+#[derive(Default)]
+pub struct EventBus {
+    handlers: HashMap<TypeId, Vec<(SubscriptionId, Handler)>>,
+    queue: VecDeque<Box<dyn Any>>,
+    next_id: u64,
+}
+
+// Lets handlers publish events while the bus is dispatching
+pub struct Publisher<'a> {
+    queue: &'a mut VecDeque<Box<dyn Any>>,
+}
+
+impl Publisher<'_> {
+    pub fn publish<E: 'static>(&mut self, event: E) {
+        self.queue.push_back(Box::new(event));
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe<E: 'static>(&mut self, mut handler: impl FnMut(&E, &mut Publisher) + 'static) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        let handler: Handler = Box::new(move |event, queue| {
+            if let Some(event) = event.downcast_ref::<E>() {
+                handler(event, &mut Publisher { queue });
+            }
+        });
+        self.handlers.entry(TypeId::of::<E>()).or_default().push((id, handler));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) {
+        for handlers in self.handlers.values_mut() {
+            handlers.retain(|(handler_id, _)| *handler_id != id);
+        }
+    }
+
+    pub fn publish<E: 'static>(&mut self, event: E) {
+        self.queue.push_back(Box::new(event));
+    }
+
+    // Delivers queued events, including ones published by handlers meanwhile.
+    // Returns the number of delivered events.
+    pub fn dispatch(&mut self) -> usize {
+        let mut delivered = 0;
+        let mut published = VecDeque::new();
+        while let Some(event) = self.queue.pop_front() {
+            let type_id = (*event).type_id();
+            if let Some(handlers) = self.handlers.get_mut(&type_id) {
+                for (_, handler) in handlers.iter_mut() {
+                    handler(event.as_ref(), &mut published);
+                }
+            }
+            self.queue.append(&mut published);
+            delivered += 1;
+        }
+        delivered
+    }
+
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+// ---------
+// SCHEDULER
+// ---------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TaskId(u64);
+
+type Task = Box<dyn FnMut(&mut TickContext)>;
+
+// Scheduled during a tick: due tick, interval and task
+type NewTask = (u64, Option<u64>, Task);
+
+struct ScheduledTask {
+    id: TaskId,
+    due: u64,              // Tick the task runs next
+    interval: Option<u64>, // Ticks between runs of repeating tasks
+    task: Task,
+}
+
+// Runs tasks after a number of ticks, once or repeatedly
+#[derive(Default)]
+pub struct Scheduler {
+    tasks: Vec<ScheduledTask>,
+    next_id: u64,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(&mut self, due: u64, interval: Option<u64>, task: Task) -> TaskId {
+        let id = TaskId(self.next_id);
+        self.next_id += 1;
+        self.tasks.push(ScheduledTask { id, due, interval, task });
+        id
+    }
+
+    // Runs `task` once at tick `tick`
+    pub fn schedule_at(&mut self, tick: u64, task: impl FnMut(&mut TickContext) + 'static) -> TaskId {
+        self.add(tick, None, Box::new(task))
+    }
+
+    // Runs `task` at tick `tick` and then every `interval` ticks
+    pub fn schedule_repeating_at(
+        &mut self,
+        tick: u64,
+        interval: u64,
+        task: impl FnMut(&mut TickContext) + 'static,
+    ) -> TaskId {
+        self.add(tick, Some(interval.max(1)), Box::new(task))
+    }
+
+    pub fn cancel(&mut self, id: TaskId) -> bool {
+        let len = self.tasks.len();
+        self.tasks.retain(|t| t.id != id);
+        self.tasks.len() != len
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    // Moves the tasks due at `tick` into `due`, ordered by their scheduling
+    fn take_due(&mut self, tick: u64, due: &mut Vec<ScheduledTask>) {
+        due.extend(self.tasks.extract_if(.., |t| t.due <= tick));
+    }
+}
+
+// ---------
+// GAME LOOP
+// ---------
+
+// Passed to update functions and scheduled tasks for the current tick
+pub struct TickContext<'a> {
+    pub tick: u64,
+    pub delta: Duration, // Fixed time per tick
+    pub events: &'a mut EventBus,
+    scheduled: &'a mut Vec<NewTask>,
+    stop: bool,
+}
+
+impl TickContext<'_> {
+    // Runs `task` after `delay` ticks
+    pub fn schedule(&mut self, delay: u64, task: impl FnMut(&mut TickContext) + 'static) {
+        self.scheduled.push((self.tick + delay.max(1), None, Box::new(task)));
+    }
+
+    // Runs `task` after `delay` ticks and then every `interval` ticks
+    pub fn schedule_repeating(&mut self, delay: u64, interval: u64, task: impl FnMut(&mut TickContext) + 'static) {
+        self.scheduled.push((self.tick + delay.max(1), Some(interval.max(1)), Box::new(task)));
+    }
+
+    // Ends GameLoop::run after this tick
+    pub fn stop(&mut self) {
+        self.stop = true;
+    }
+}
+
+// Fixed-timestep loop: update runs tick_rate times per second, independent of
+// how long rendering or input handling takes. Falls behind at most
+// max_catch_up ticks before skipping time. The task lists of a tick are
+// pooled, so steps don't allocate once the loop is warmed up.
+pub struct GameLoop {
+    pub tick: u64,
+    pub events: EventBus,
+    pub scheduler: Scheduler,
+    tick_rate: u32,
+    max_catch_up: u32,
+    running: bool,
+    due: Pool<Vec<ScheduledTask>>,
+    scheduled: Pool<Vec<NewTask>>,
+}
+
+impl GameLoop {
+    pub fn new(tick_rate: u32) -> Self {
+        GameLoop {
+            tick: 0,
+            events: EventBus::new(),
+            scheduler: Scheduler::new(),
+            tick_rate: tick_rate.max(1),
+            max_catch_up: 5,
+            running: false,
+            due: Pool::new(1),
+            scheduled: Pool::new(1),
+        }
+    }
+
+    pub fn with_tick_rate(mut self, tick_rate: u32) -> Self {
+        self.tick_rate = tick_rate.max(1);
+        self
+    }
+
+    pub fn with_max_catch_up(mut self, ticks: u32) -> Self {
+        self.max_catch_up = ticks.max(1);
+        self
+    }
+
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_secs(1) / self.tick_rate
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    // For loops driving step themselves, to notice TickContext::stop
+    #[cfg(feature = "tui")]
+    pub(crate) fn set_running(&mut self, running: bool) {
+        self.running = running;
+    }
+
+    // Advances one tick: due tasks, then update, then event dispatch
+    pub fn step(&mut self, update: &mut impl FnMut(&mut TickContext)) {
+        self.tick += 1;
+        let mut due = self.due.get();
+        let mut scheduled = self.scheduled.get();
+        self.scheduler.take_due(self.tick, &mut due);
+        let mut ctx = TickContext {
+            tick: self.tick,
+            delta: self.tick_duration(),
+            events: &mut self.events,
+            scheduled: &mut scheduled,
+            stop: false,
+        };
+        for task in due.iter_mut() {
+            (task.task)(&mut ctx);
+        }
+        update(&mut ctx);
+        let stop = ctx.stop;
+        self.events.dispatch();
+
+        // Repeating tasks go back into the scheduler
+        for mut task in due.drain(..) {
+            if let Some(interval) = task.interval {
+                task.due = self.tick + interval;
+                self.scheduler.tasks.push(task);
+            }
+        }
+        for (due, interval, task) in scheduled.drain(..) {
+            self.scheduler.add(due, interval, task);
+        }
+        if stop {
+            self.running = false;
+        }
+    }
+
+    // Runs `update` every tick until a TickContext::stop call. `frame` runs
+    // once per loop iteration, e.g. for rendering and input.
+    pub fn run(&mut self, mut update: impl FnMut(&mut TickContext), mut frame: impl FnMut(&GameLoop)) {
+        self.running = true;
+        let tick_duration = self.tick_duration();
+        let mut previous = Instant::now();
+        let mut lag = Duration::ZERO;
+        while self.running {
+            let now = Instant::now();
+            lag += now - previous;
+            previous = now;
+
+            let mut ticks = 0;
+            while lag >= tick_duration && self.running {
+                self.step(&mut update);
+                lag -= tick_duration;
+                ticks += 1;
+                if ticks >= self.max_catch_up {
+                    lag = Duration::ZERO;
+                }
+            }
+            frame(self);
+            if let Some(wait) = tick_duration.checked_sub(lag) {
+                thread::sleep(wait);
+            }
+        }
+    }
+}
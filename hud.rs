@@ -0,0 +1,299 @@
+use crate::color::{Color, Style, StyledText, interpolate_multi_color};
+use crate::ecs::{Entities, EntityId, Health};
+use crate::inventory::{HOTBAR_SIZE, Inventory, Slot};
+use crate::localization::{TranslationID, Translator};
+use crate::stats::{Attribute, Stats};
+
+// Player HUD: segmented bars for health, hunger and stamina and a
+// strip of the hotbar positions. Rendered to StyledText for printing,
+// or drawn as ratatui widgets with the `tui` feature:
+//
+//   let health = StatBar::health(entities.get::<Health>(player).unwrap());
+//   println!("{}", health.render()); // ███████▌░░ 15/20
+//   let hotbar = Hotbar::new(&inventory).with_translator(&translator);
+//   let hud = Hud::for_player(&entities, player); // All of the above
+//   frame.render_widget(&hud, area);
+
+// Bar colors from empty to full
+fn health_gradient() -> Vec<Color> {
+    vec![Color::from_hex("#d9372b"), Color::from_hex("#e8c33a"), Color::from_hex("#4bb543")]
+}
+
+fn highlight() -> Style {
+    Style::new().fg(Color::from_hex("#ffd966"))
+}
+
+fn dimmed() -> Style {
+    Style::new().fg(Color::from_hex("#5a5a5a"))
+}
+
+// ---------
+// STAT BARS
+// ---------
+
+// One cell per `segment` points of the value (2 by default, like hearts),
+// half filled cells for the rest. Filled cells take the gradient's color at
+// current / max, so a health bar turns from green to red as it empties.
+#[derive(Clone, Debug)]
+pub struct StatBar {
+    current: f32,
+    max: f32,
+    segment: f32,
+    symbols: [char; 3], // Full, half, empty
+    gradient: Vec<Color>,
+    label: Option<String>,
+    show_value: bool,
+}
+
+impl StatBar {
+    pub fn new(current: f32, max: f32) -> Self {
+        StatBar {
+            current: current.clamp(0.0, max.max(0.0)),
+            max: max.max(0.0),
+            segment: 2.0,
+            symbols: ['█', '▌', '░'],
+            gradient: health_gradient(),
+            label: None,
+            show_value: true,
+        }
+    }
+
+    pub fn health(health: &Health) -> Self {
+        StatBar::new(health.current as f32, health.max as f32)
+    }
+
+    // The attribute's value out of its default base, e.g. Attribute::HUNGER
+    pub fn attribute(stats: &Stats, attribute: Attribute) -> Self {
+        StatBar::new(stats.get(attribute), attribute.default_base())
+    }
+
+    pub fn hunger(stats: &Stats) -> Self {
+        StatBar::attribute(stats, Attribute::HUNGER).with_gradient(vec![Color::from_hex("#7a4a22"), Color::from_hex("#d08a3c")])
+    }
+
+    pub fn stamina(stats: &Stats) -> Self {
+        StatBar::attribute(stats, Attribute::STAMINA).with_gradient(vec![Color::from_hex("#8a8a2a"), Color::from_hex("#e6e14a")])
+    }
+
+    // Points of the value per cell, at least 0.1
+    pub fn with_segment(mut self, segment: f32) -> Self {
+        self.segment = segment.max(0.1);
+        self
+    }
+
+    pub fn with_symbols(mut self, full: char, half: char, empty: char) -> Self {
+        self.symbols = [full, half, empty];
+        self
+    }
+
+    // A single color keeps the bar in it
+    pub fn with_gradient(mut self, gradient: Vec<Color>) -> Self {
+        if !gradient.is_empty() {
+            self.gradient = gradient;
+        }
+        self
+    }
+
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    // Appends "current/max", on by default
+    pub fn with_value(mut self, show_value: bool) -> Self {
+        self.show_value = show_value;
+        self
+    }
+
+    pub fn ratio(&self) -> f32 {
+        if self.max > 0.0 { self.current / self.max } else { 0.0 }
+    }
+
+    pub fn color(&self) -> Color {
+        interpolate_multi_color(&self.gradient, self.ratio() as f64)
+    }
+
+    // One symbol per segment
+    pub fn cells(&self) -> Vec<char> {
+        let [full, half, empty] = self.symbols;
+        let count = (self.max / self.segment).ceil() as usize;
+        (0..count)
+            .map(|i| match self.current - i as f32 * self.segment {
+                v if v >= self.segment => full,
+                v if v >= self.segment / 2.0 => half,
+                _ => empty,
+            })
+            .collect()
+    }
+
+    pub fn render(&self) -> StyledText {
+        let mut text = StyledText::new();
+        if let Some(label) = &self.label {
+            text.push(&format!("{} ", label), Style::new());
+        }
+        let filled = Style::new().fg(self.color());
+        let mut symbol = [0; 4];
+        for cell in self.cells() {
+            let style = if cell == self.symbols[2] { dimmed() } else { filled };
+            text.push(cell.encode_utf8(&mut symbol), style);
+        }
+        if self.show_value {
+            text.push(&format!(" {}/{}", self.current.round(), self.max.round()), Style::new());
+        }
+        text
+    }
+}
+
+// ------
+// HOTBAR
+// ------
+
+// Slots 0 to `size` (the hotbar positions by default) side by side,
+// "[Oak L  12]" each. The selected one (Inventory::selected unless set) is
+// highlighted and drawn as ">Oak L  12<". Names are the translated item
+// names if a translator is set, otherwise the ID names.
+pub struct Hotbar<'a> {
+    inventory: &'a Inventory,
+    translator: Option<&'a Translator>,
+    selected: Option<usize>,
+    size: usize,
+    label_width: usize, // Characters of the name per slot
+}
+
+impl<'a> Hotbar<'a> {
+    pub fn new(inventory: &'a Inventory) -> Self {
+        Hotbar { inventory, translator: None, selected: Some(inventory.selected), size: HOTBAR_SIZE, label_width: 5 }
+    }
+
+    pub fn with_translator(mut self, translator: &'a Translator) -> Self {
+        self.translator = Some(translator);
+        self
+    }
+
+    pub fn with_selected(mut self, selected: usize) -> Self {
+        self.selected = Some(selected);
+        self
+    }
+
+    pub fn with_size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_label_width(mut self, label_width: usize) -> Self {
+        self.label_width = label_width;
+        self
+    }
+
+    pub fn selected_slot(&self) -> Option<&'a Slot> {
+        self.selected.filter(|&i| i < self.size).and_then(|i| self.inventory.slot(i))
+    }
+
+    fn name(&self, slot: &Slot) -> String {
+        self.translator
+            .and_then(|t| t.try_translate(&TranslationID::from_id(&slot.item.id, "item"), None))
+            .unwrap_or_else(|| slot.item.id.name().to_string())
+    }
+
+    // The name cut to label_width, then the count (or the durability left
+    // in percent for tools), blank for empty slots
+    fn slot_label(&self, slot: Option<&Slot>) -> String {
+        let Some(slot) = slot else {
+            return " ".repeat(self.label_width + 4);
+        };
+        let name: String = self.name(slot).chars().take(self.label_width).collect();
+        let amount = match slot.durability {
+            Some(d) if d.max > 0 => format!("{}%", d.current * 100 / d.max),
+            _ if slot.count > 1 => slot.count.to_string(),
+            _ => String::new(),
+        };
+        format!("{:<width$}{:>4}", name, amount, width = self.label_width)
+    }
+
+    pub fn render(&self) -> StyledText {
+        let mut text = StyledText::new();
+        for i in 0..self.size {
+            if i > 0 {
+                text.push(" ", Style::new());
+            }
+            let label = self.slot_label(self.inventory.slot(i));
+            if self.selected == Some(i) {
+                text.push(&format!(">{}<", label), highlight());
+            } else {
+                text.push(&format!("[{}]", label), Style::new());
+            }
+        }
+        text
+    }
+}
+
+// ---
+// HUD
+// ---
+
+// Bars and hotbar of one entity, each line left out if the entity lacks the
+// component: health from Health, hunger and stamina from Stats, the hotbar
+// from Inventory
+pub struct Hud<'a> {
+    pub health: Option<StatBar>,
+    pub hunger: Option<StatBar>,
+    pub stamina: Option<StatBar>,
+    pub hotbar: Option<Hotbar<'a>>,
+}
+
+impl<'a> Hud<'a> {
+    pub fn for_player(entities: &'a Entities, player: EntityId) -> Self {
+        let stats = entities.get::<Stats>(player);
+        Hud {
+            health: entities.get::<Health>(player).map(|h| StatBar::health(h).with_label("HP")),
+            hunger: stats.map(|s| StatBar::hunger(s).with_label("FD")),
+            stamina: stats.map(|s| StatBar::stamina(s).with_label("ST")),
+            hotbar: entities.get::<Inventory>(player).map(Hotbar::new),
+        }
+    }
+
+    pub fn with_selected(mut self, selected: usize) -> Self {
+        self.hotbar = self.hotbar.map(|h| h.with_selected(selected));
+        self
+    }
+
+    pub fn with_translator(mut self, translator: &'a Translator) -> Self {
+        self.hotbar = self.hotbar.map(|h| h.with_translator(translator));
+        self
+    }
+
+    // One line per bar, then the hotbar
+    pub fn render(&self) -> StyledText {
+        let mut text = StyledText::new();
+        let bars = [&self.health, &self.hunger, &self.stamina].into_iter().flatten().map(StatBar::render);
+        for (i, line) in bars.chain(self.hotbar.as_ref().map(Hotbar::render)).enumerate() {
+            if i > 0 {
+                text.push("\n", Style::new());
+            }
+            text.append(&line);
+        }
+        text
+    }
+}
+
+// Drawn from the top left of the area, lines past its size are cut off
+#[cfg(feature = "tui")]
+impl ratatui::widgets::Widget for &StatBar {
+    fn render(self, area: ratatui::layout::Rect, buf: &mut ratatui::buffer::Buffer) {
+        ratatui::widgets::Widget::render(ratatui::text::Text::from(self.render()), area, buf);
+    }
+}
+
+#[cfg(feature = "tui")]
+impl ratatui::widgets::Widget for &Hotbar<'_> {
+    fn render(self, area: ratatui::layout::Rect, buf: &mut ratatui::buffer::Buffer) {
+        ratatui::widgets::Widget::render(ratatui::text::Text::from(self.render()), area, buf);
+    }
+}
+
+#[cfg(feature = "tui")]
+impl ratatui::widgets::Widget for &Hud<'_> {
+    fn render(self, area: ratatui::layout::Rect, buf: &mut ratatui::buffer::Buffer) {
+        ratatui::widgets::Widget::render(ratatui::text::Text::from(self.render()), area, buf);
+    }
+}
@@ -0,0 +1,482 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+    PushKeyboardEnhancementFlags,
+};
+use crossterm::{execute, terminal};
+
+// Game controls: named actions bound to keys, with pressed/released/held
+// state per tick. Unlike the prompt's line editing, keys are read without
+// waiting, e.g. once per GameLoop tick:
+//
+//   let mut input = InputState::new(InputMap::wasd());
+//   game.run(|ctx| {
+//       input.update().ok();
+//       let dx = input.axis("move_left", "move_right");
+//       if input.is_pressed("quit") { ctx.stop() }
+//   }, |_| {});
+
+// -----
+// KEYS
+// -----
+
+// Key with the modifiers held with it, written as e.g. "w", "shift+w",
+// "ctrl+c", "space", "up" or "f1"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers, // Only ctrl, alt and shift
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Terminals report shifted letters as uppercase characters
+        let (code, modifiers) = match code {
+            KeyCode::Char(c) if c.is_uppercase() => (KeyCode::Char(c.to_ascii_lowercase()), modifiers | KeyModifiers::SHIFT),
+            _ => (code, modifiers),
+        };
+        KeyChord { code, modifiers: modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT) }
+    }
+
+    pub fn key(code: KeyCode) -> Self {
+        Self::new(code, KeyModifiers::NONE)
+    }
+
+    pub fn from_event(event: &KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+
+    pub fn parse(value: &str) -> Result<KeyChord, InputError> {
+        let invalid = || InputError::InvalidChord(value.to_string());
+        let parts: Vec<&str> = value.trim().split('+').map(str::trim).collect();
+        let (key, modifier_names) = parts.split_last().ok_or_else(invalid)?;
+        let mut modifiers = KeyModifiers::NONE;
+        for name in modifier_names {
+            modifiers |= match name.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+        }
+        let code = match key.to_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            name if name.len() > 1 && name.starts_with('f') => KeyCode::F(name[1..].parse().map_err(|_| invalid())?),
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if c != '+' => KeyCode::Char(c),
+                    _ => return Err(invalid()),
+                }
+            }
+        };
+        Ok(KeyChord::new(code, modifiers))
+    }
+}
+
+impl Display for KeyChord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [(KeyModifiers::CONTROL, "ctrl"), (KeyModifiers::ALT, "alt"), (KeyModifiers::SHIFT, "shift")] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+// ------
+// ERRORS
+// ------
+
+#[derive(Debug)]
+pub enum InputError {
+    Io(PathBuf, io::Error),
+    InvalidChord(String),
+    InvalidBinding { line: usize, text: String }, // Line number starting at 1
+}
+
+impl Display for InputError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Io(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            InputError::InvalidChord(chord) => write!(f, "invalid key '{}'", chord),
+            InputError::InvalidBinding { line, text } => write!(f, "invalid binding on line {}: {}", line, text),
+        }
+    }
+}
+
+impl Error for InputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InputError::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// --------
+// BINDINGS
+// --------
+
+// Keys per action. A key can trigger several actions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputMap {
+    bindings: BTreeMap<String, Vec<KeyChord>>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // move_up/move_left/move_down/move_right on WASD and the arrow keys,
+    // interact on e and Enter, quit on q and Esc
+    pub fn wasd() -> Self {
+        let mut map = InputMap::new();
+        for (action, keys) in [
+            ("move_up", [KeyCode::Char('w'), KeyCode::Up]),
+            ("move_left", [KeyCode::Char('a'), KeyCode::Left]),
+            ("move_down", [KeyCode::Char('s'), KeyCode::Down]),
+            ("move_right", [KeyCode::Char('d'), KeyCode::Right]),
+            ("interact", [KeyCode::Char('e'), KeyCode::Enter]),
+            ("quit", [KeyCode::Char('q'), KeyCode::Esc]),
+        ] {
+            for key in keys {
+                map.bind(action, KeyChord::key(key));
+            }
+        }
+        map
+    }
+
+    pub fn bind(&mut self, action: &str, chord: KeyChord) {
+        let chords = self.bindings.entry(action.to_string()).or_default();
+        if !chords.contains(&chord) {
+            chords.push(chord);
+        }
+    }
+
+    // Like bind, with the key written as for KeyChord::parse. Panics on an invalid key.
+    pub fn with(mut self, action: &str, chord: &str) -> Self {
+        let chord = KeyChord::parse(chord).unwrap_or_else(|e| panic!("{}", e));
+        self.bind(action, chord);
+        self
+    }
+
+    // Removes every key of the action
+    pub fn unbind(&mut self, action: &str) {
+        self.bindings.remove(action);
+    }
+
+    pub fn keys(&self, action: &str) -> &[KeyChord] {
+        self.bindings.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str)
+    }
+
+    // Actions the key is bound to
+    pub fn actions_for(&self, chord: &KeyChord) -> impl Iterator<Item = &str> {
+        self.bindings.iter().filter(move |(_, chords)| chords.contains(chord)).map(|(action, _)| action.as_str())
+    }
+
+    // One "action = key, key" line per action, '#' starts a comment:
+    //
+    //   move_up = w, up
+    //   sprint = shift+w
+    pub fn parse(content: &str) -> Result<InputMap, InputError> {
+        let mut map = InputMap::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let invalid = || InputError::InvalidBinding { line: i + 1, text: line.to_string() };
+            let (action, keys) = line.split_once('=').ok_or_else(invalid)?;
+            let action = action.trim();
+            if action.is_empty() || action.contains(char::is_whitespace) {
+                return Err(invalid());
+            }
+            for key in keys.split(',') {
+                map.bind(action, KeyChord::parse(key)?);
+            }
+        }
+        Ok(map)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<InputMap, InputError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| InputError::Io(path.to_path_buf(), e))?;
+        InputMap::parse(&content)
+    }
+}
+
+// The format read by InputMap::parse
+impl Display for InputMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (action, chords) in &self.bindings {
+            let keys: Vec<String> = chords.iter().map(KeyChord::to_string).collect();
+            writeln!(f, "{} = {}", action, keys.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// -----
+// STATE
+// -----
+
+// Most terminals only report key presses, repeating them while a key is held
+const DEFAULT_RELEASE_AFTER: Duration = Duration::from_millis(550);
+
+// Which actions are held, and which were pressed or released since the
+// previous update. Without release events from the terminal (see
+// enable_key_release), a key counts as released when it wasn't repeated for
+// release_after, which has to cover the keyboard's repeat delay.
+pub struct InputState {
+    map: InputMap,
+    held: HashMap<KeyChord, Instant>, // Last press or repeat
+    pressed: HashSet<KeyChord>,
+    released: HashSet<KeyChord>,
+    release_events: bool, // Whether the terminal reported a release yet
+    release_after: Duration,
+}
+
+impl InputState {
+    pub fn new(map: InputMap) -> Self {
+        InputState {
+            map,
+            held: HashMap::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            release_events: false,
+            release_after: DEFAULT_RELEASE_AFTER,
+        }
+    }
+
+    pub fn with_release_after(mut self, release_after: Duration) -> Self {
+        self.release_after = release_after;
+        self
+    }
+
+    pub fn map(&self) -> &InputMap {
+        &self.map
+    }
+
+    // Rebinding keeps the state of held keys
+    pub fn map_mut(&mut self) -> &mut InputMap {
+        &mut self.map
+    }
+
+    // Reads every pending terminal event without waiting, call once per tick
+    pub fn update(&mut self) -> io::Result<()> {
+        let mut events = vec![];
+        while event::poll(Duration::ZERO)? {
+            events.push(event::read()?);
+        }
+        self.step(events);
+        Ok(())
+    }
+
+    // Like update, with events read by the caller
+    pub fn step(&mut self, events: impl IntoIterator<Item = Event>) {
+        self.pressed.clear();
+        self.released.clear();
+        let now = Instant::now();
+        for event in events {
+            if let Event::Key(key) = event {
+                self.handle_key(&key, now);
+            }
+        }
+        if !self.release_events {
+            let expired: Vec<KeyChord> =
+                self.held.iter().filter(|(_, seen)| now.duration_since(**seen) >= self.release_after).map(|(c, _)| *c).collect();
+            for chord in expired {
+                self.held.remove(&chord);
+                self.released.insert(chord);
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent, now: Instant) {
+        let chord = KeyChord::from_event(key);
+        match key.kind {
+            KeyEventKind::Release => {
+                self.release_events = true;
+                if self.held.remove(&chord).is_some() {
+                    self.released.insert(chord);
+                }
+            }
+            // Without release events, repeats arrive as presses too
+            KeyEventKind::Press | KeyEventKind::Repeat => {
+                if self.held.insert(chord, now).is_none() {
+                    self.pressed.insert(chord);
+                }
+            }
+        }
+    }
+
+    // Forgets all held keys, e.g. after the game lost focus or was paused
+    pub fn clear(&mut self) {
+        self.held.clear();
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    // A key of the action went down since the previous update
+    pub fn is_pressed(&self, action: &str) -> bool {
+        self.map.keys(action).iter().any(|c| self.pressed.contains(c))
+    }
+
+    // A key of the action went up since the previous update
+    pub fn is_released(&self, action: &str) -> bool {
+        self.map.keys(action).iter().any(|c| self.released.contains(c))
+    }
+
+    pub fn is_held(&self, action: &str) -> bool {
+        self.map.keys(action).iter().any(|c| self.held.contains_key(c))
+    }
+
+    // -1 while only `negative` is held, 1 while only `positive` is, else 0
+    pub fn axis(&self, negative: &str, positive: &str) -> i32 {
+        self.is_held(positive) as i32 - self.is_held(negative) as i32
+    }
+
+    // Actions pressed since the previous update, sorted
+    pub fn pressed_actions(&self) -> Vec<&str> {
+        self.map.actions().filter(|a| self.is_pressed(a)).collect()
+    }
+}
+
+// Asks the terminal to report key releases (kitty keyboard protocol), so
+// InputState doesn't need to guess them. Returns false if the terminal
+// doesn't support it. Undo with disable_key_release before leaving.
+pub fn enable_key_release() -> io::Result<bool> {
+    if !terminal::supports_keyboard_enhancement()? {
+        return Ok(false);
+    }
+    let flags = KeyboardEnhancementFlags::REPORT_EVENT_TYPES | KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES;
+    execute!(io::stdout(), PushKeyboardEnhancementFlags(flags))?;
+    Ok(true)
+}
+
+pub fn disable_key_release() -> io::Result<()> {
+    execute!(io::stdout(), PopKeyboardEnhancementFlags)
+}
+
+// --------------
+// SCRIPTED INPUT
+// --------------
+
+// Terminal events for runs without a terminal (SceneManager::run_headless),
+// one batch per frame. Keys are pressed and released explicitly, so
+// InputState doesn't wait for releases in real time.
+//
+//   let input = ScriptedInput::new()
+//       .with_press(KeyChord::key(KeyCode::Char('d')))
+//       .with_idle(20) // Held for 20 frames
+//       .with_release(KeyChord::key(KeyCode::Char('d')))
+//       .with_text("/give diamond\n");
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedInput {
+    frames: VecDeque<Vec<Event>>,
+}
+
+fn key_event(chord: KeyChord, kind: KeyEventKind) -> Event {
+    Event::Key(KeyEvent::new_with_kind(chord.code, chord.modifiers, kind))
+}
+
+impl ScriptedInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_frame(mut self, events: Vec<Event>) -> Self {
+        self.frames.push_back(events);
+        self
+    }
+
+    // Frames without events
+    pub fn with_idle(mut self, frames: usize) -> Self {
+        self.frames.extend((0..frames).map(|_| vec![]));
+        self
+    }
+
+    pub fn with_press(self, chord: KeyChord) -> Self {
+        self.with_frame(vec![key_event(chord, KeyEventKind::Press)])
+    }
+
+    pub fn with_release(self, chord: KeyChord) -> Self {
+        self.with_frame(vec![key_event(chord, KeyEventKind::Release)])
+    }
+
+    // Pressed in one frame, released in the next
+    pub fn with_tap(self, chord: KeyChord) -> Self {
+        self.with_press(chord).with_release(chord)
+    }
+
+    // Typed in one frame, '\n' as Enter
+    pub fn with_text(self, text: &str) -> Self {
+        let events = text
+            .chars()
+            .map(|c| match c {
+                '\n' => KeyChord::key(KeyCode::Enter),
+                c => KeyChord::key(KeyCode::Char(c)),
+            })
+            .flat_map(|chord| [key_event(chord, KeyEventKind::Press), key_event(chord, KeyEventKind::Release)])
+            .collect();
+        self.with_frame(events)
+    }
+
+    pub fn next_frame(&mut self) -> Option<Vec<Event>> {
+        self.frames.pop_front()
+    }
+
+    // Frames left
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
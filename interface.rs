@@ -0,0 +1,3444 @@
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crossterm::{
+    cursor, event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
+    execute, queue, style::{Color as CrosstermColor, Print, SetForegroundColor},
+    terminal::{self, Clear, ClearType},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Terminal,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::color::{
+    self, ColorError, ColorRef, Style as TextStyle, StyledText, colored_styled, colored_text, interpolate_multi_color,
+    resolve_color_ref, visible_length,
+};
+use crate::error::ParseError;
+use crate::format;
+use crate::localization::{TranslationID, Translator};
+use crate::logging;
+use crate::pool::STRINGS;
+use crate::registry::{ID, REGISTRY, RegistryKey};
+pub use crate::theme::{ColorTheme, ColorThemeSelectedSuggestion, MIN_SELECTION_CONTRAST};
+use crate::theme::current_theme;
+
+// Registry search hits offered for an ID argument
+const MAX_ID_SUGGESTIONS: usize = 10;
+// How often a chain checks its running command for progress
+const CHAIN_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+impl ColorTheme {
+    // ratatui style of the selected suggestion or menu item. Reversed if
+    // colors are disabled, so the selection stays visible.
+    pub fn selected_style(&self) -> Style {
+        if !color::is_enabled() {
+            return Style::default().add_modifier(Modifier::REVERSED);
+        }
+        Style::default()
+            .fg(theme_color(&self.selected_suggestion_color.fg, Color::Yellow))
+            .bg(theme_color(&self.selected_suggestion_color.bg, Color::DarkGray))
+    }
+}
+
+// Command argument definition
+#[derive(Debug, Clone)]
+pub struct CommandArg {
+    pub name: String,
+    pub arg_type: String, // e.g., "int", "string"
+    pub range: Option<(i32, i32)>, // For int: (min, max)
+    pub optional: bool,
+    pub default: Option<String>,
+    pub help: Option<TranslationID>, // Help text, Command::arg_key if None
+}
+
+impl CommandArg {
+    pub fn with_help(mut self, help: TranslationID) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    // "<name:type {min..max}>?default"
+    pub fn hint(&self) -> String {
+        let mut hint = format!("<{}:{}", self.name, self.arg_type);
+        if let Some((min, max)) = self.range {
+            hint.push_str(&format!(" {{{}..{}}}", min, max));
+        }
+        hint.push('>');
+        if self.optional {
+            hint.push_str(&format!("?{}", self.default.as_deref().unwrap_or("none")));
+        }
+        hint
+    }
+
+    // Example values offered while typing the argument
+    pub fn value_suggestions(&self) -> Vec<String> {
+        let mut values: Vec<String> = self.default.iter().cloned().collect();
+        if self.arg_type == "int" {
+            for v in [0, 1, 10, 100] {
+                let in_range = self.range.is_none_or(|(min, max)| (min..=max).contains(&v));
+                if in_range && !values.contains(&v.to_string()) {
+                    values.push(v.to_string());
+                }
+            }
+        }
+        values
+    }
+
+    // Registries an ID argument takes values from: all for "id", one for
+    // its lowercase name, e.g. "item" or "loottable"
+    fn registry_keys(&self) -> Option<Vec<RegistryKey>> {
+        if self.arg_type == "id" {
+            return Some(RegistryKey::BUILTIN.to_vec());
+        }
+        RegistryKey::BUILTIN.iter().find(|k| k.name().to_lowercase() == self.arg_type).map(|k| vec![*k])
+    }
+
+    // Values for a partially typed argument: registered IDs found by
+    // Registry::search for ID arguments, otherwise the example values
+    // starting with it
+    pub fn suggestions_for(&self, partial: &str) -> Vec<String> {
+        let Some(keys) = self.registry_keys() else {
+            return self.value_suggestions().into_iter().filter(|v| v.starts_with(partial)).collect();
+        };
+        // The registry may be write-locked by whoever asks for suggestions
+        let Ok(registry) = REGISTRY.try_read() else {
+            return vec![];
+        };
+        let mut ids: Vec<String> = vec![];
+        for hit in registry.search(partial, usize::MAX).into_iter().filter(|h| keys.contains(&h.key)) {
+            let id = hit.id.to_string();
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+            if ids.len() == MAX_ID_SUGGESTIONS {
+                break;
+            }
+        }
+        ids
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Command {
+    pub name: String,
+    pub namespace: Option<String>, // e.g. "ruztex" for "ruztex:give"
+    pub aliases: Vec<String>,
+    pub args: Vec<CommandArg>,
+    pub subcommands: Vec<Command>,
+    pub level: u32,                 // Required permission level, 0 = everyone
+    pub permission: Option<String>, // Required permission node, e.g. "ruztex.give"
+    pub handler: Option<fn(HashMap<String, String>) -> String>, // Function to handle command
+    pub shared_handler: Option<SharedHandler>, // Like handler, for handlers with state that can fail
+    pub task_handler: Option<TaskHandler>, // Runs on its own thread
+    pub description: Option<TranslationID>, // Command::description_key if None
+}
+
+impl Command {
+    pub fn new(name: &str) -> Self {
+        Command {
+            name: name.to_string(),
+            namespace: None,
+            aliases: vec![],
+            args: vec![],
+            subcommands: vec![],
+            level: 0,
+            permission: None,
+            handler: None,
+            shared_handler: None,
+            task_handler: None,
+            description: None,
+        }
+    }
+
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+
+    pub fn with_aliases<I, S>(mut self, aliases: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.aliases = aliases.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_args(mut self, args: Vec<CommandArg>) -> Self {
+        self.args = args;
+        self
+    }
+
+    pub fn with_subcommand(mut self, subcommand: Command) -> Self {
+        self.subcommands.push(subcommand);
+        self
+    }
+
+    pub fn with_level(mut self, level: u32) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn with_permission(mut self, permission: &str) -> Self {
+        self.permission = Some(permission.to_string());
+        self
+    }
+
+    pub fn with_handler(mut self, handler: fn(HashMap<String, String>) -> String) -> Self {
+        self.handler = Some(handler);
+        self
+    }
+
+    // For handlers that need state, e.g. the script of a ScriptHost command.
+    // Errors become CommandError::Failed.
+    pub fn with_shared_handler(
+        mut self,
+        handler: impl Fn(HashMap<String, String>) -> Result<String, String> + Send + Sync + 'static,
+    ) -> Self {
+        self.shared_handler = Some(SharedHandler(Arc::new(handler)));
+        self
+    }
+
+    // For long-running commands: the handler runs on a separate thread and
+    // reports its progress back to the prompt through the TaskReporter
+    pub fn with_task_handler(mut self, handler: TaskHandler) -> Self {
+        self.task_handler = Some(handler);
+        self
+    }
+
+    pub fn with_description(mut self, description: TranslationID) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    // "namespace:name" or just "name" if the command has no namespace
+    pub fn qualified_name(&self) -> String {
+        match &self.namespace {
+            Some(ns) => format!("{}:{}", ns, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    // Translation key for the description, under the command's namespace
+    // ("ruztex" for built-in commands). Subcommands pass the path of their parents.
+    pub fn description_key(&self, parents: &[&str]) -> TranslationID {
+        TranslationID::command(self.translation_namespace(), &self.key_path(parents))
+    }
+
+    pub fn arg_key(&self, parents: &[&str], arg: &str) -> TranslationID {
+        TranslationID::command_arg(self.translation_namespace(), &self.key_path(parents), arg)
+    }
+
+    // The description set with with_description, otherwise description_key
+    pub fn description_id(&self, parents: &[&str]) -> TranslationID {
+        self.description.clone().unwrap_or_else(|| self.description_key(parents))
+    }
+
+    // The help set with CommandArg::with_help, otherwise arg_key
+    pub fn arg_help_id(&self, parents: &[&str], arg: &CommandArg) -> TranslationID {
+        arg.help.clone().unwrap_or_else(|| self.arg_key(parents, &arg.name))
+    }
+
+    fn translation_namespace(&self) -> &str {
+        self.namespace.as_deref().unwrap_or("ruztex")
+    }
+
+    fn key_path(&self, parents: &[&str]) -> String {
+        let mut path = parents.join("_");
+        if !path.is_empty() {
+            path.push('_');
+        }
+        path.push_str(&self.name);
+        path
+    }
+
+    // Name and aliases, without namespace
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+
+    // Matches "name", "alias", "namespace:name" and "namespace:alias"
+    pub fn matches(&self, name: &str) -> bool {
+        match name.split_once(':') {
+            Some((ns, short)) => self.namespace.as_deref() == Some(ns) && self.names().any(|n| n == short),
+            None => self.names().any(|n| n == name),
+        }
+    }
+
+    // Ensure optional args are at the end (recursively for subcommands)
+    fn normalized(mut self) -> Self {
+        let (required, optional): (Vec<_>, Vec<_>) = self.args.into_iter().partition(|a| !a.optional);
+        self.args = required.into_iter().chain(optional).collect();
+        self.subcommands = self.subcommands.into_iter().map(Command::normalized).collect();
+        self
+    }
+}
+
+// Handler closure, shared by clones of the command
+pub type SharedHandlerFn = dyn Fn(HashMap<String, String>) -> Result<String, String> + Send + Sync;
+
+#[derive(Clone)]
+pub struct SharedHandler(pub Arc<SharedHandlerFn>);
+
+impl fmt::Debug for SharedHandler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SharedHandler")
+    }
+}
+
+// Background tasks for long-running commands. Errors become
+// CommandError::Failed.
+pub type TaskHandler = fn(HashMap<String, String>, TaskReporter) -> Result<String, String>;
+
+pub enum TaskUpdate {
+    Progress(u64, u64), // (current, total)
+    Message(String),
+    Done(Result<String, CommandError>),
+}
+
+#[derive(Clone)]
+pub struct TaskReporter {
+    sender: Sender<TaskUpdate>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl TaskReporter {
+    pub fn progress(&self, current: u64, total: u64) {
+        let _ = self.sender.send(TaskUpdate::Progress(current, total));
+    }
+
+    pub fn message(&self, message: &str) {
+        let _ = self.sender.send(TaskUpdate::Message(message.to_string()));
+    }
+
+    // Whether CommandTask::cancel was called; handlers that can stop early
+    // should check it now and then
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+// A command running in the background
+pub struct CommandTask {
+    pub name: String,
+    pub progress: Option<(u64, u64)>,
+    pub message: String,
+    pub result: Option<Result<String, CommandError>>,
+    receiver: Receiver<TaskUpdate>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CommandTask {
+    pub fn spawn(name: &str, handler: TaskHandler, args: HashMap<String, String>) -> Self {
+        let failed = name.to_string();
+        Self::spawn_with(name, move |reporter| handler(args, reporter).map_err(|e| CommandError::Failed(failed, e)))
+    }
+
+    // Runs `f` on its own thread, e.g. a chain of commands
+    pub fn spawn_with(name: &str, f: impl FnOnce(TaskReporter) -> Result<String, CommandError> + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let reporter = TaskReporter { sender: sender.clone(), cancelled: cancelled.clone() };
+        thread::spawn(move || {
+            let result = f(reporter);
+            let _ = sender.send(TaskUpdate::Done(result));
+        });
+        CommandTask {
+            name: name.to_string(),
+            progress: None,
+            message: String::new(),
+            result: None,
+            receiver,
+            cancelled,
+        }
+    }
+
+    // Asks the task to stop, see TaskReporter::is_cancelled. It keeps
+    // running until its handler returns.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    // Applies all pending updates, returns true once the task has finished
+    // (also if its handler panicked)
+    pub fn poll(&mut self) -> bool {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(update) => self.apply(update),
+                Err(TryRecvError::Disconnected) if self.result.is_none() => {
+                    self.result = Some(Err(CommandError::Failed(self.name.clone(), "stopped without a result".to_string())));
+                }
+                Err(_) => break,
+            }
+        }
+        self.result.is_some()
+    }
+
+    // Blocks until the task has finished
+    pub fn wait(mut self) -> Result<String, CommandError> {
+        while self.result.is_none() {
+            match self.receiver.recv() {
+                Ok(update) => self.apply(update),
+                Err(_) => return Err(CommandError::Failed(self.name, "stopped without a result".to_string())),
+            }
+        }
+        self.result.unwrap()
+    }
+
+    fn apply(&mut self, update: TaskUpdate) {
+        match update {
+            TaskUpdate::Progress(current, total) => self.progress = Some((current, total)),
+            TaskUpdate::Message(message) => self.message = message,
+            TaskUpdate::Done(result) => self.result = Some(result),
+        }
+    }
+}
+
+pub enum CommandOutcome {
+    Done(String),
+    Task(CommandTask),
+}
+
+// Who executes a command, e.g. the server console or a player
+#[derive(Debug, Clone)]
+pub struct Executor {
+    pub name: String,
+    pub level: u32,
+    pub permissions: HashSet<String>, // "*" grants every permission
+}
+
+impl Executor {
+    pub fn console() -> Self {
+        Executor {
+            name: "console".to_string(),
+            level: u32::MAX,
+            permissions: HashSet::from(["*".to_string()]),
+        }
+    }
+
+    pub fn player(name: &str, level: u32) -> Self {
+        Executor {
+            name: name.to_string(),
+            level,
+            permissions: HashSet::new(),
+        }
+    }
+
+    pub fn with_permission(mut self, permission: &str) -> Self {
+        self.permissions.insert(permission.to_string());
+        self
+    }
+
+    pub fn can_run(&self, command: &Command) -> bool {
+        self.level >= command.level
+            && command.permission.as_ref().is_none_or(|p| self.permissions.contains(p) || self.permissions.contains("*"))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommandError {
+    InvalidNamespace(String),
+    InvalidName(String),              // A name or alias of the command is empty or contains ':' or spaces
+    AlreadyRegistered(String),
+    NameConflict(String, String),     // Short name and the command that already uses it
+    UnknownCommand(String),
+    NotAllowed(String, String),       // Executor and command line
+    UnexpectedArgument(String),
+    MissingArgument(String),
+    OutOfRange { arg: String, value: String, min: i32, max: i32 },
+    NoHandler(String),                // Only the subcommands can be run
+    UnclosedQuote(String),            // Input with an odd number of unescaped '"'
+    UnknownVariable(String),          // "{name}" without a capture of that name before it
+    InvalidCapture(String),           // Text after "->" that isn't a variable name, or nothing before it
+    Failed(String, String),           // Command and the error its handler returned
+    Cancelled(String),                // Command of a chain that didn't run anymore
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::InvalidNamespace(ns) => write!(f, "invalid command namespace '{}'", ns),
+            CommandError::InvalidName(command) => write!(f, "command '{}' has an invalid name or alias", command),
+            CommandError::AlreadyRegistered(command) => write!(f, "command '{}' is already registered", command),
+            CommandError::NameConflict(name, existing) => write!(f, "command name '{}' conflicts with '{}'", name, existing),
+            CommandError::UnknownCommand(input) => write!(f, "Unknown command: {}", input),
+            CommandError::NotAllowed(executor, command) => write!(f, "{} is not allowed to run '{}'", executor, command),
+            CommandError::UnexpectedArgument(token) => write!(f, "Unexpected argument: {}", token),
+            CommandError::MissingArgument(name) => write!(f, "Missing required argument: {}", name),
+            CommandError::OutOfRange { arg, value, min, max } => {
+                write!(f, "Argument {} must be between {} and {}, got {}", arg, min, max, value)
+            }
+            CommandError::NoHandler(command) => write!(f, "'{}' needs a subcommand", command),
+            CommandError::UnclosedQuote(input) => write!(f, "Unclosed quote in: {}", input),
+            CommandError::UnknownVariable(name) => write!(f, "Unknown variable: {{{}}}", name),
+            CommandError::InvalidCapture(capture) => write!(f, "Invalid capture: -> {}", capture),
+            CommandError::Failed(command, message) => write!(f, "{} failed: {}", command, message),
+            CommandError::Cancelled(command) => write!(f, "Cancelled before: {}", command),
+        }
+    }
+}
+
+impl Error for CommandError {}
+
+impl CommandError {
+    // The "ruztex:command_error.<key>" translation with the fields of the error
+    // as variables, e.g. "%{arg}". The Display text if there is none.
+    pub fn localized(&self, translator: &Translator) -> String {
+        let (key, vars): (&str, Vec<(&str, String)>) = match self {
+            CommandError::InvalidNamespace(ns) => ("invalid_namespace", vec![("namespace", ns.clone())]),
+            CommandError::InvalidName(command) => ("invalid_name", vec![("command", command.clone())]),
+            CommandError::AlreadyRegistered(command) => ("already_registered", vec![("command", command.clone())]),
+            CommandError::NameConflict(name, existing) => {
+                ("name_conflict", vec![("name", name.clone()), ("existing", existing.clone())])
+            }
+            CommandError::UnknownCommand(input) => ("unknown_command", vec![("input", input.clone())]),
+            CommandError::NotAllowed(executor, command) => {
+                ("not_allowed", vec![("executor", executor.clone()), ("command", command.clone())])
+            }
+            CommandError::UnexpectedArgument(token) => ("unexpected_argument", vec![("arg", token.clone())]),
+            CommandError::MissingArgument(name) => ("missing_argument", vec![("arg", name.clone())]),
+            CommandError::OutOfRange { arg, value, min, max } => (
+                "out_of_range",
+                vec![("arg", arg.clone()), ("value", value.clone()), ("min", min.to_string()), ("max", max.to_string())],
+            ),
+            CommandError::NoHandler(command) => ("no_handler", vec![("command", command.clone())]),
+            CommandError::UnclosedQuote(input) => ("unclosed_quote", vec![("input", input.clone())]),
+            CommandError::UnknownVariable(name) => ("unknown_variable", vec![("variable", name.clone())]),
+            CommandError::InvalidCapture(capture) => ("invalid_capture", vec![("capture", capture.clone())]),
+            CommandError::Failed(command, message) => {
+                ("failed", vec![("command", command.clone()), ("message", message.clone())])
+            }
+            CommandError::Cancelled(command) => ("cancelled", vec![("command", command.clone())]),
+        };
+        let vars: HashMap<&str, Cow<str>> = vars.into_iter().map(|(k, v)| (k, Cow::Owned(v))).collect();
+        translator
+            .try_translate(&TranslationID::command_error("ruztex", key), Some(&vars))
+            .unwrap_or_else(|| self.to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandRegistry {
+    commands: Vec<Command>,
+}
+
+impl CommandRegistry {
+    pub fn new() -> Self {
+        CommandRegistry { commands: vec![] }
+    }
+
+    pub fn register_command(&mut self, command: Command) -> Result<(), CommandError> {
+        if let Some(ns) = &command.namespace
+            && !ID::is_valid_identifier(ns, Some((1, 16)), false)
+        {
+            return Err(CommandError::InvalidNamespace(ns.clone()));
+        }
+        if command.names().any(|n| n.is_empty() || n.contains(':') || n.contains(char::is_whitespace)) {
+            return Err(CommandError::InvalidName(command.qualified_name()));
+        }
+
+        let qualified = command.qualified_name();
+        if self.commands.iter().any(|c| c.qualified_name() == qualified) {
+            return Err(CommandError::AlreadyRegistered(qualified));
+        }
+
+        // Short names (name + aliases) must be unique among commands without a namespace.
+        // A namespaced command may lose its short names to an earlier command and stays
+        // reachable via "namespace:name".
+        for name in command.names() {
+            if let Some(existing) = self.commands.iter().find(|c| c.names().any(|n| n == name)) {
+                if command.namespace.is_none() {
+                    return Err(CommandError::NameConflict(name.to_string(), existing.qualified_name()));
+                }
+                log::warn!(
+                    "Command name '{}' is already taken by '{}', use '{}' instead",
+                    name, existing.qualified_name(), qualified
+                );
+            }
+        }
+
+        self.commands.push(command.normalized());
+        Ok(())
+    }
+
+    pub fn find_command(&self, name: &str) -> Option<&Command> {
+        let parts: Vec<&str> = name.split_whitespace().collect();
+        // Earlier registrations win for short names
+        let first = parts.first()?;
+        let mut current = self.commands.iter().find(|c| c.matches(first))?;
+        for part in parts.iter().skip(1) {
+            current = current.subcommands.iter().find(|c| c.matches(part))?;
+        }
+        Some(current)
+    }
+
+    // Like find_command, but only if the executor may run every command on the path
+    pub fn find_command_for(&self, executor: &Executor, name: &str) -> Option<&Command> {
+        let parts: Vec<&str> = name.split_whitespace().collect();
+        let first = parts.first()?;
+        let mut current = self.commands.iter().find(|c| c.matches(first))?;
+        for part in parts.iter().skip(1) {
+            if !executor.can_run(current) {
+                return None;
+            }
+            current = current.subcommands.iter().find(|c| c.matches(part))?;
+        }
+        executor.can_run(current).then_some(current)
+    }
+
+    // Resolves the command path and assigns argument tokens. Named tokens
+    // ("count:3") fill the argument with that name, all other tokens fill the
+    // next argument that is still empty.
+    fn parse<'r>(&'r self, tokens: &[&str]) -> ParseState<'r> {
+        let mut state = ParseState {
+            path: vec![],
+            filled: HashMap::new(),
+            args_started: false,
+            extra: vec![],
+        };
+
+        let mut rest = tokens;
+        while let Some((token, tail)) = rest.split_first() {
+            let next = match state.path.last() {
+                None => self.commands.iter().find(|c| c.matches(token)),
+                Some(current) => current.subcommands.iter().find(|c| c.matches(token)),
+            };
+            match next {
+                Some(command) => state.path.push(command),
+                None => break,
+            }
+            rest = tail;
+        }
+
+        let Some(command) = state.command() else {
+            return state;
+        };
+        for token in rest {
+            state.args_started = true;
+            if let Some((key, value)) = token.split_once(':')
+                && command.args.iter().any(|a| a.name == key)
+            {
+                state.filled.insert(key.to_string(), value.to_string());
+                continue;
+            }
+            match command.args.iter().find(|a| !state.filled.contains_key(&a.name)) {
+                Some(arg) => {
+                    state.filled.insert(arg.name.clone(), token.to_string());
+                }
+                None => state.extra.push(token.to_string()),
+            }
+        }
+        state
+    }
+
+    pub fn get_suggestions(&self, executor: &Executor, input: &str) -> (Vec<String>, String) {
+        self.localized_suggestions(executor, input, None)
+    }
+
+    // Like get_suggestions, with the argument help from the translator appended to the hint
+    pub fn localized_suggestions(
+        &self,
+        executor: &Executor,
+        input: &str,
+        translator: Option<&Translator>,
+    ) -> (Vec<String>, String) {
+        let tokens: Vec<&str> = input.split_whitespace().collect();
+        // The token under the cursor is empty if the input ends with whitespace
+        let (completed, current) = match tokens.split_last() {
+            Some((last, init)) if !input.ends_with(char::is_whitespace) => (init, *last),
+            _ => (&tokens[..], ""),
+        };
+        STRINGS.with(|strings| {
+            // The completed tokens are the start of every suggestion
+            let mut line = strings.get();
+            for token in completed {
+                line.push_str(token);
+                line.push(' ');
+            }
+            self.suggestions_after(executor, &line, completed, current, translator)
+        })
+    }
+
+    fn suggestions_after(
+        &self,
+        executor: &Executor,
+        line: &str,
+        completed: &[&str],
+        current: &str,
+        translator: Option<&Translator>,
+    ) -> (Vec<String>, String) {
+        let state = self.parse(completed);
+        let Some(command) = state.command() else {
+            if !completed.is_empty() {
+                return (vec![], String::new());
+            }
+            return (self.command_suggestions(executor, current), String::new());
+        };
+        if !state.path.iter().all(|c| executor.can_run(c)) {
+            return (vec![], String::new());
+        }
+
+        let mut suggestions = vec![];
+
+        // Named argument with a (partial) value
+        if let Some((key, value)) = current.split_once(':')
+            && let Some(arg) = command.args.iter().find(|a| a.name == key)
+        {
+            suggestions = arg.suggestions_for(value).into_iter().map(|v| format!("{}{}:{}", line, key, v)).collect();
+            return (suggestions, arg_hint(command, &state.parents(), arg, translator));
+        }
+
+        if !state.args_started {
+            suggestions.extend(
+                command
+                    .subcommands
+                    .iter()
+                    .filter(|c| executor.can_run(c))
+                    .flat_map(|c| c.names())
+                    .filter(|n| n.starts_with(current))
+                    .map(|n| format!("{}{}", line, n)),
+            );
+        }
+
+        let next = command.args.iter().find(|a| !state.filled.contains_key(&a.name));
+        let hint = next.map(|a| arg_hint(command, &state.parents(), a, translator)).unwrap_or_default();
+        if let Some(arg) = next
+            && !current.is_empty()
+        {
+            suggestions.extend(arg.suggestions_for(current).into_iter().map(|v| format!("{}{}", line, v)));
+        }
+        suggestions.extend(
+            command
+                .args
+                .iter()
+                .filter(|a| !state.filled.contains_key(&a.name) && a.name.starts_with(current))
+                .map(|a| format!("{}{}:", line, a.name)),
+        );
+
+        (suggestions, hint)
+    }
+
+    fn command_suggestions(&self, executor: &Executor, prefix: &str) -> Vec<String> {
+        // Suggest "namespace:name" once the user starts typing a namespace
+        if prefix.contains(':') {
+            return self
+                .commands
+                .iter()
+                .filter(|c| c.namespace.is_some() && executor.can_run(c))
+                .flat_map(|c| c.names().map(move |n| format!("{}:{}", c.namespace.as_ref().unwrap(), n)))
+                .filter(|n| n.starts_with(prefix))
+                .collect();
+        }
+        self.commands
+            .iter()
+            .filter(|c| executor.can_run(c))
+            .flat_map(|c| c.names())
+            .filter(|n| n.starts_with(prefix))
+            .map(String::from)
+            .fold(vec![], |mut acc: Vec<String>, n| {
+                // Shadowed short names only show up once
+                if !acc.contains(&n) {
+                    acc.push(n);
+                }
+                acc
+            })
+    }
+
+    // Runs the command to completion, waiting for task handlers to finish.
+    // Commands can be chained with ';' and their output captured with
+    // "-> name" for "{name}" in the later commands of the chain, e.g.
+    //   registry list items -> items; say "{items}"
+    // Returns the output of the commands that weren't captured, one per line.
+    // Stops at the first error.
+    pub fn execute_command(&self, executor: &Executor, input: &str) -> Result<String, CommandError> {
+        self.run_chain(executor, input, None)
+    }
+
+    // Like execute_command in the background, reporting the command being run
+    // and the progress of its task. Stops before the next command once
+    // cancelled.
+    pub fn start_chain(&self, executor: &Executor, input: &str) -> CommandTask {
+        let (registry, executor, chain) = (self.clone(), executor.clone(), input.to_string());
+        CommandTask::spawn_with(input.trim(), move |reporter| registry.run_chain(&executor, &chain, Some(&reporter)))
+    }
+
+    fn run_chain(&self, executor: &Executor, input: &str, reporter: Option<&TaskReporter>) -> Result<String, CommandError> {
+        let mut vars = HashMap::new();
+        let mut output = vec![];
+        let steps = split_chain(input)?;
+        for (i, step) in steps.iter().enumerate() {
+            if let Some(reporter) = reporter {
+                if reporter.is_cancelled() {
+                    return Err(CommandError::Cancelled(step.command.trim().to_string()));
+                }
+                reporter.progress(i as u64, steps.len() as u64);
+                reporter.message(step.command.trim());
+            }
+            let tokens = tokenize(step.command, Some(&vars))?;
+            let result = match (self.run_tokens(executor, step.command, &tokens)?, reporter) {
+                (CommandOutcome::Done(result), _) => result,
+                (CommandOutcome::Task(task), None) => task.wait()?,
+                (CommandOutcome::Task(mut task), Some(reporter)) => {
+                    while !task.poll() {
+                        if reporter.is_cancelled() {
+                            task.cancel();
+                        }
+                        if let Some((current, total)) = task.progress {
+                            reporter.progress(current, total);
+                        }
+                        thread::sleep(CHAIN_POLL_INTERVAL);
+                    }
+                    task.result.take().unwrap()?
+                }
+            };
+            match step.capture {
+                Some(name) => {
+                    vars.insert(name.to_string(), result);
+                }
+                None => output.push(result),
+            }
+        }
+        Ok(output.join("\n"))
+    }
+
+    // Whether the input chains commands or captures output, which only
+    // execute_command and start_chain run
+    pub fn is_chain(input: &str) -> bool {
+        match split_chain(input) {
+            Ok(steps) => steps.len() > 1 || steps[0].capture.is_some(),
+            Err(_) => true,
+        }
+    }
+
+    // Like execute_command for a single command, but task handlers are
+    // returned as a running CommandTask. Quotes group words into one argument.
+    pub fn run_command(&self, executor: &Executor, input: &str) -> Result<CommandOutcome, CommandError> {
+        let tokens = tokenize(input, None)?;
+        self.run_tokens(executor, input, &tokens)
+    }
+
+    fn run_tokens(&self, executor: &Executor, input: &str, tokens: &[String]) -> Result<CommandOutcome, CommandError> {
+        let tokens: Vec<&str> = tokens.iter().map(String::as_str).collect();
+        let state = self.parse(&tokens);
+        let command = state.command().ok_or_else(|| CommandError::UnknownCommand(input.trim().to_string()))?;
+
+        for (i, cmd) in state.path.iter().enumerate() {
+            if !executor.can_run(cmd) {
+                return Err(CommandError::NotAllowed(executor.name.clone(), tokens[..=i].join(" ")));
+            }
+        }
+        if let Some(token) = state.extra.first() {
+            return Err(CommandError::UnexpectedArgument(token.clone()));
+        }
+
+        let mut args = state.filled.clone();
+        for arg in command.args.iter() {
+            if args.contains_key(&arg.name) {
+                continue;
+            }
+            if let Some(default) = &arg.default {
+                args.insert(arg.name.clone(), default.clone());
+            } else if !arg.optional {
+                return Err(CommandError::MissingArgument(arg.name.clone()));
+            }
+        }
+        for arg in command.args.iter() {
+            if let (Some((min, max)), Some(value)) = (arg.range, args.get(&arg.name))
+                && !value.parse::<i32>().is_ok_and(|v| (min..=max).contains(&v))
+            {
+                return Err(CommandError::OutOfRange { arg: arg.name.clone(), value: value.clone(), min, max });
+            }
+        }
+
+        // Full path for subcommands, e.g. "registry reload datapacks"
+        let name = state.path.iter().map(|c| c.qualified_name()).collect::<Vec<_>>().join(" ");
+        if let Some(f) = command.task_handler {
+            return Ok(CommandOutcome::Task(CommandTask::spawn(&name, f, args)));
+        }
+        if let Some(SharedHandler(f)) = &command.shared_handler {
+            let result = f(args).map_err(|e| CommandError::Failed(name, e))?;
+            return Ok(CommandOutcome::Done(result));
+        }
+        let f = command.handler.ok_or_else(|| CommandError::NoHandler(command.qualified_name()))?;
+        Ok(CommandOutcome::Done(f(args)))
+    }
+
+    // Without a command: every command the executor can run with its
+    // description. Otherwise usage, description, arguments and subcommands of
+    // the given command, e.g. "give" or "team add". Descriptions and argument
+    // help come from the translator and are left out if untranslated.
+    pub fn help(&self, executor: &Executor, command: &str, translator: Option<&Translator>) -> Result<String, CommandError> {
+        let describe = |id: TranslationID| translator.and_then(|t| t.try_translate(&id, None));
+        let entry = |name: &str, description: Option<String>| match description {
+            Some(description) => format!("  {} - {}", name, description),
+            None => format!("  {}", name),
+        };
+
+        let tokens: Vec<&str> = command.split_whitespace().collect();
+        if tokens.is_empty() {
+            let lines: Vec<String> = self
+                .commands
+                .iter()
+                .filter(|c| executor.can_run(c))
+                .map(|c| entry(&c.qualified_name(), describe(c.description_id(&[]))))
+                .collect();
+            return Ok(lines.join("\n"));
+        }
+
+        let state = self.parse(&tokens);
+        let cmd = state.command().ok_or_else(|| CommandError::UnknownCommand(command.trim().to_string()))?;
+        if !state.path.iter().all(|c| executor.can_run(c)) {
+            return Err(CommandError::NotAllowed(executor.name.clone(), command.trim().to_string()));
+        }
+        if let Some(token) = state.extra.first().or(state.filled.keys().next()) {
+            return Err(CommandError::UnexpectedArgument(token.clone()));
+        }
+
+        let parents = state.parents();
+        let mut path: Vec<&str> = parents.clone();
+        path.push(&cmd.name);
+        let mut usage = path.join(" ");
+        for arg in cmd.args.iter() {
+            usage.push(' ');
+            usage.push_str(&arg.hint());
+        }
+        let mut lines = vec![usage];
+        if let Some(description) = describe(cmd.description_id(&parents)) {
+            lines.push(description);
+        }
+        lines.extend(cmd.args.iter().map(|a| entry(&a.hint(), describe(cmd.arg_help_id(&parents, a)))));
+        lines.extend(
+            cmd.subcommands
+                .iter()
+                .filter(|c| executor.can_run(c))
+                .map(|c| entry(&c.name, describe(c.description_id(&path)))),
+        );
+        Ok(lines.join("\n"))
+    }
+}
+
+// Hint of the argument, followed by its help text if the translator has one
+fn arg_hint(command: &Command, parents: &[&str], arg: &CommandArg, translator: Option<&Translator>) -> String {
+    let mut hint = arg.hint();
+    if let Some(help) = translator.and_then(|t| t.try_translate(&command.arg_help_id(parents, arg), None)) {
+        hint.push_str(" - ");
+        hint.push_str(&help);
+    }
+    hint
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ---------
+// TOKENIZER
+// ---------
+
+// One command of a chain, "<command> [-> <capture>]"
+struct ChainStep<'a> {
+    command: &'a str,
+    capture: Option<&'a str>,
+}
+
+// Characters a backslash escapes, before others it's kept, e.g. in paths
+fn is_escapable(c: char) -> bool {
+    matches!(c, '"' | '\\' | ';' | '{')
+}
+
+fn is_variable_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+// Splits the input at ';' and "->" outside of quotes. Empty commands are
+// skipped, unless the input is empty.
+fn split_chain(input: &str) -> Result<Vec<ChainStep<'_>>, CommandError> {
+    fn step(text: &str, capture_at: Option<usize>) -> Result<ChainStep<'_>, CommandError> {
+        let Some(at) = capture_at else {
+            return Ok(ChainStep { command: text, capture: None });
+        };
+        let (command, capture) = (&text[..at], text[at + 2..].trim());
+        if command.trim().is_empty() || !is_variable_name(capture) {
+            return Err(CommandError::InvalidCapture(capture.to_string()));
+        }
+        Ok(ChainStep { command, capture: Some(capture) })
+    }
+
+    let mut steps = vec![];
+    let (mut start, mut capture_at, mut quoted) = (0, None, false);
+    let mut chars = input.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if chars.peek().is_some_and(|(_, next)| is_escapable(*next)) => {
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                steps.push(step(&input[start..i], capture_at.map(|at| at - start))?);
+                (start, capture_at) = (i + 1, None);
+            }
+            '-' if !quoted
+                && input[i..].starts_with("->")
+                && (i == start || input[..i].ends_with(|c: char| c.is_whitespace()))
+                && input[i + 2..].starts_with(|c: char| c.is_whitespace()) =>
+            {
+                capture_at = Some(i);
+            }
+            _ => {}
+        }
+    }
+    steps.push(step(&input[start..], capture_at.map(|at| at - start))?);
+    if steps.len() > 1 {
+        steps.retain(|s| !s.command.trim().is_empty());
+    }
+    Ok(steps)
+}
+
+// Splits a command into tokens at whitespace. Double quotes group words into
+// one token, a backslash takes '"', '\\', ';' and '{' literally. With `vars`,
+// "{name}" is replaced by the variable's value, also inside quotes, without
+// splitting it into more tokens.
+fn tokenize(input: &str, vars: Option<&HashMap<String, String>>) -> Result<Vec<String>, CommandError> {
+    let mut tokens = vec![];
+    let mut token: Option<String> = None; // None between tokens
+    let mut quoted = false;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek().is_some_and(|next| is_escapable(*next)) => {
+                token.get_or_insert_default().push(chars.next().unwrap());
+            }
+            '"' => {
+                quoted = !quoted;
+                token.get_or_insert_default();
+            }
+            c if c.is_whitespace() && !quoted => tokens.extend(token.take()),
+            '{' if let Some(vars) = vars => {
+                let name: String = chars.clone().take_while(|c| *c != '}').collect();
+                let closed = chars.clone().nth(name.chars().count()) == Some('}');
+                if !closed || !is_variable_name(&name) {
+                    token.get_or_insert_default().push('{');
+                    continue;
+                }
+                let value = vars.get(&name).ok_or_else(|| CommandError::UnknownVariable(name.clone()))?;
+                token.get_or_insert_default().push_str(value);
+                chars.nth(name.chars().count()); // The name and '}'
+            }
+            c => token.get_or_insert_default().push(c),
+        }
+    }
+    if quoted {
+        return Err(CommandError::UnclosedQuote(input.trim().to_string()));
+    }
+    tokens.extend(token);
+    Ok(tokens)
+}
+
+// Parsed (possibly incomplete) command input
+struct ParseState<'r> {
+    path: Vec<&'r Command>,          // Command and its parents, outermost first
+    filled: HashMap<String, String>, // Argument name -> value
+    args_started: bool,              // Whether any token after the command path was parsed
+    extra: Vec<String>,              // Tokens without a matching argument
+}
+
+impl<'r> ParseState<'r> {
+    fn command(&self) -> Option<&'r Command> {
+        self.path.last().copied()
+    }
+
+    // Names of the parents of the command, for its translation keys
+    fn parents(&self) -> Vec<&'r str> {
+        let parents = &self.path[..self.path.len().saturating_sub(1)];
+        parents.iter().map(|c| c.name.as_str()).collect()
+    }
+}
+
+// Progress bar configuration
+pub struct ProgressBar {
+    total: u64,
+    current: u64,
+    width: usize,
+    symbol: char,
+    color_ref: ColorRef<'static>,
+    gradient: Vec<ColorRef<'static>>, // Colors along the bar, overrides color_ref for the fill
+    template: String,
+    started: Instant,
+    locale: Option<String>, // Language code the numbers and times are formatted for, DEFAULT_LOCALE if None
+}
+
+// Placeholders: {bar}, {percent}, {current}, {total}, {elapsed}, {eta}, {rate},
+// and {bytes}, {total_bytes}, {byte_rate} for byte counts ("1.5 MiB")
+pub const DEFAULT_PROGRESS_TEMPLATE: &str = "[{bar}] {percent}%";
+pub const BYTES_PROGRESS_TEMPLATE: &str = "[{bar}] {percent}% {bytes}/{total_bytes} {byte_rate}/s";
+
+impl ProgressBar {
+    pub fn new(total: u64) -> Self {
+        ProgressBar {
+            total,
+            current: 0,
+            width: 50,
+            symbol: '█',
+            color_ref: current_theme().progress_color,
+            gradient: vec![],
+            template: DEFAULT_PROGRESS_TEMPLATE.to_string(),
+            started: Instant::now(),
+            locale: None,
+        }
+    }
+
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_symbol(mut self, symbol: char) -> Self {
+        self.symbol = symbol;
+        self
+    }
+
+    pub fn with_color(mut self, color_ref: ColorRef<'static>) -> Self {
+        self.color_ref = color_ref;
+        self
+    }
+
+    // Fill colors from the start to the end of the bar, needs at least two colors
+    pub fn with_gradient(mut self, color_refs: Vec<ColorRef<'static>>) -> Self {
+        self.gradient = color_refs;
+        self
+    }
+
+    // e.g. "[{bar}] {percent}% {current}/{total} {rate}/s eta {eta}"
+    pub fn with_template(mut self, template: &str) -> Self {
+        self.template = template.to_string();
+        self
+    }
+
+    // Formats numbers, times and byte counts for the language, e.g.
+    // Language.code
+    pub fn with_locale(mut self, locale: &str) -> Self {
+        self.locale = Some(locale.to_string());
+        self
+    }
+
+    pub fn advance(&mut self, delta: u64) {
+        self.current = (self.current + delta).min(self.total);
+        self.render();
+    }
+
+    // Sets the position without rendering, e.g. when the bar is drawn by the prompt
+    pub fn set_position(&mut self, current: u64) {
+        self.current = current.min(self.total);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    // Items per second since the bar was created
+    pub fn rate(&self) -> f64 {
+        let secs = self.elapsed().as_secs_f64();
+        if secs > 0.0 { self.current as f64 / secs } else { 0.0 }
+    }
+
+    // Estimated remaining time, None until there is progress to estimate from
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.rate();
+        if self.current == 0 || rate <= 0.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64((self.total - self.current) as f64 / rate))
+    }
+
+    fn progress(&self) -> f64 {
+        if self.total == 0 { 1.0 } else { self.current as f64 / self.total as f64 }
+    }
+
+    fn filled(&self) -> usize {
+        (self.width as f64 * self.progress()) as usize
+    }
+
+    // Template with every placeholder except {bar} filled in
+    fn fill_fields(&self, template: &str) -> String {
+        let locale = self.locale.as_deref().unwrap_or(format::DEFAULT_LOCALE);
+        let eta = self.eta().map_or("--".to_string(), |eta| format::format_duration(eta, locale));
+        template
+            .replace("{percent}", &((self.progress() * 100.0) as u32).to_string())
+            .replace("{current}", &format::format_number(self.current as i64, locale))
+            .replace("{total}", &format::format_number(self.total as i64, locale))
+            .replace("{elapsed}", &format::format_duration(self.elapsed(), locale))
+            .replace("{eta}", &eta)
+            .replace("{rate}", &format::format_decimal(self.rate(), 1, locale))
+            .replace("{bytes}", &format::format_bytes(self.current as f64, locale))
+            .replace("{total_bytes}", &format::format_bytes(self.total as f64, locale))
+            .replace("{byte_rate}", &format::format_bytes(self.rate(), locale))
+    }
+
+    // "[█████     ] 50%" without colors
+    pub fn text(&self) -> String {
+        let filled = self.filled();
+        let bar: String = std::iter::repeat_n(self.symbol, filled)
+            .chain(std::iter::repeat_n(' ', self.width - filled))
+            .collect();
+        self.fill_fields(&self.template).replace("{bar}", &bar)
+    }
+
+    // Bar with each filled cell colored by its position in the gradient
+    fn gradient_bar(&self) -> Option<StyledText> {
+        let colors: Vec<_> = self.gradient.iter().map(resolve_color_ref).collect::<Option<_>>()?;
+        if colors.len() < 2 {
+            return None;
+        }
+        let mut bar = StyledText::new();
+        let range = (self.width.max(2) - 1) as f64;
+        let mut symbol = [0; 4];
+        let symbol: &str = self.symbol.encode_utf8(&mut symbol);
+        for i in 0..self.filled() {
+            let color = interpolate_multi_color(&colors, i as f64 / range);
+            bar.push(symbol, TextStyle::new().fg(color));
+        }
+        bar.push(&" ".repeat(self.width - self.filled()), TextStyle::new());
+        Some(bar)
+    }
+
+    // text() with colors
+    pub fn styled(&self) -> Result<StyledText, ColorError> {
+        let Some(bar) = self.gradient_bar() else {
+            return colored_styled(&self.text(), &self.color_ref);
+        };
+        let mut styled = StyledText::new();
+        for (i, part) in self.template.split("{bar}").enumerate() {
+            if i > 0 {
+                styled.append(&bar);
+            }
+            styled.append(&colored_styled(&self.fill_fields(part), &self.color_ref)?);
+        }
+        Ok(styled)
+    }
+
+    // styled() as ANSI
+    pub fn colored(&self) -> Result<String, ColorError> {
+        self.styled().map(|s| s.to_ansi())
+    }
+
+    pub fn render(&self) {
+        if let Ok(colored) = self.colored() {
+            draw_line(&colored);
+        }
+    }
+
+    pub fn finish(&self) {
+        if !is_headless() {
+            println!();
+        }
+    }
+}
+
+// True if stdout isn't a terminal, e.g. in tests, pipes or on a dedicated
+// server. Prompts then read lines from stdin and print their output plainly,
+// menus and dialogs return as cancelled, progress bars and spinners only
+// print final messages, and fullscreen fails.
+pub fn is_headless() -> bool {
+    !io::stdout().is_terminal()
+}
+
+// Redraws the current terminal line, used by ProgressBar and Spinner
+fn draw_line(line: &str) {
+    if is_headless() {
+        return;
+    }
+    print!("\r\x1b[2K{}", line);
+    io::stdout().flush().unwrap();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpinnerStyle {
+    Dots,
+    Line,
+    Bounce,
+}
+
+impl SpinnerStyle {
+    pub fn frames(&self) -> &'static [&'static str] {
+        match self {
+            SpinnerStyle::Dots => &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+            SpinnerStyle::Line => &["-", "\\", "|", "/"],
+            SpinnerStyle::Bounce => &["⠁", "⠂", "⠄", "⠂"],
+        }
+    }
+}
+
+// Indeterminate progress indicator: "⠋ message"
+pub struct Spinner {
+    frames: &'static [&'static str],
+    frame: usize,
+    color_ref: ColorRef<'static>,
+    message: String,
+}
+
+impl Spinner {
+    pub fn new() -> Self {
+        Spinner {
+            frames: SpinnerStyle::Dots.frames(),
+            frame: 0,
+            color_ref: current_theme().progress_color,
+            message: String::new(),
+        }
+    }
+
+    pub fn with_style(mut self, style: SpinnerStyle) -> Self {
+        self.frames = style.frames();
+        self
+    }
+
+    pub fn with_frames(mut self, frames: &'static [&'static str]) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    pub fn with_color(mut self, color_ref: ColorRef<'static>) -> Self {
+        self.color_ref = color_ref;
+        self
+    }
+
+    pub fn with_message(mut self, message: &str) -> Self {
+        self.message = message.to_string();
+        self
+    }
+
+    pub fn set_message(&mut self, message: &str) {
+        self.message = message.to_string();
+    }
+
+    // Shows the next frame
+    pub fn tick(&mut self) {
+        self.frame = (self.frame + 1) % self.frames.len().max(1);
+        self.render();
+    }
+
+    pub fn text(&self) -> String {
+        let frame = self.frames.get(self.frame).copied().unwrap_or_default();
+        if self.message.is_empty() {
+            frame.to_string()
+        } else {
+            format!("{} {}", frame, self.message)
+        }
+    }
+
+    pub fn render(&self) {
+        if let Ok(colored) = colored_text(&self.text(), &self.color_ref) {
+            draw_line(&colored);
+        }
+    }
+
+    // Replaces the spinner with a final message
+    pub fn finish(&self, message: &str) {
+        draw_line(message);
+        println!("{}", if is_headless() { message } else { "" });
+    }
+
+    // Ticks on a background thread until the returned handle is finished
+    pub fn auto_tick(self, interval: Duration) -> SpinnerHandle {
+        let spinner = Arc::new(Mutex::new(self));
+        let running = Arc::new(AtomicBool::new(true));
+        let thread = {
+            let spinner = spinner.clone();
+            let running = running.clone();
+            thread::spawn(move || {
+                while running.load(Ordering::Relaxed) {
+                    spinner.lock().unwrap().tick();
+                    thread::sleep(interval);
+                }
+            })
+        };
+        SpinnerHandle { spinner, running, thread: Some(thread) }
+    }
+}
+
+impl Default for Spinner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Spinner ticking on its own thread, see Spinner::auto_tick
+pub struct SpinnerHandle {
+    spinner: Arc<Mutex<Spinner>>,
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl SpinnerHandle {
+    pub fn set_message(&self, message: &str) {
+        self.spinner.lock().unwrap().set_message(message);
+    }
+
+    // Stops ticking and replaces the spinner with a final message
+    pub fn finish(mut self, message: &str) {
+        self.stop();
+        self.spinner.lock().unwrap().finish(message);
+    }
+
+    fn stop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SpinnerHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BarState {
+    Running,
+    Finished,
+    Abandoned,
+}
+
+struct MultiProgressState {
+    bars: Vec<(ProgressBar, BarState)>,
+    lines_drawn: usize,
+}
+
+// Several progress bars drawn on their own lines. Cloning shares the bars, so
+// handles can be moved to worker threads.
+#[derive(Clone)]
+pub struct MultiProgress {
+    state: Arc<Mutex<MultiProgressState>>,
+}
+
+impl MultiProgress {
+    pub fn new() -> Self {
+        MultiProgress {
+            state: Arc::new(Mutex::new(MultiProgressState { bars: vec![], lines_drawn: 0 })),
+        }
+    }
+
+    // Adds a bar below the existing ones
+    pub fn add(&self, bar: ProgressBar) -> ProgressHandle {
+        let mut state = self.state.lock().unwrap();
+        state.bars.push((bar, BarState::Running));
+        let index = state.bars.len() - 1;
+        drop(state);
+        self.draw();
+        ProgressHandle { multi: self.clone(), index }
+    }
+
+    // True when no bar is running anymore
+    pub fn is_done(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.bars.iter().all(|(_, bar_state)| *bar_state != BarState::Running)
+    }
+
+    fn update(&self, index: usize, f: impl FnOnce(&mut ProgressBar, &mut BarState)) {
+        let mut state = self.state.lock().unwrap();
+        if let Some((bar, bar_state)) = state.bars.get_mut(index) {
+            f(bar, bar_state);
+        }
+        drop(state);
+        self.draw();
+    }
+
+    // Moves the cursor back to the first bar and redraws all of them
+    fn draw(&self) {
+        let mut state = self.state.lock().unwrap();
+        let mut out = String::new();
+        if state.lines_drawn > 0 {
+            out.push_str(&format!("\x1b[{}A", state.lines_drawn));
+        }
+        for (bar, bar_state) in &state.bars {
+            let line = bar.colored().unwrap_or_else(|_| bar.text());
+            let suffix = match bar_state {
+                BarState::Running => "",
+                BarState::Finished => " ✓",
+                BarState::Abandoned => " ✗",
+            };
+            out.push_str(&format!("\r\x1b[2K{}{}\n", line, suffix));
+        }
+        state.lines_drawn = state.bars.len();
+        print!("{}", out);
+        io::stdout().flush().unwrap();
+    }
+}
+
+impl Default for MultiProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A bar owned by a MultiProgress
+#[derive(Clone)]
+pub struct ProgressHandle {
+    multi: MultiProgress,
+    index: usize,
+}
+
+impl ProgressHandle {
+    pub fn advance(&self, delta: u64) {
+        self.multi.update(self.index, |bar, _| bar.current = (bar.current + delta).min(bar.total));
+    }
+
+    pub fn set_position(&self, current: u64) {
+        self.multi.update(self.index, |bar, _| bar.set_position(current));
+    }
+
+    // Fills the bar and marks it as finished
+    pub fn finish(&self) {
+        self.multi.update(self.index, |bar, state| {
+            bar.current = bar.total;
+            *state = BarState::Finished;
+        });
+    }
+
+    // Keeps the bar at its position and marks it as abandoned
+    pub fn abandon(&self) {
+        self.multi.update(self.index, |_, state| *state = BarState::Abandoned);
+    }
+
+    pub fn state(&self) -> BarState {
+        let state = self.multi.state.lock().unwrap();
+        state.bars[self.index].1
+    }
+}
+
+// -----------------
+// PROGRESS ADAPTERS
+// -----------------
+
+// Adapters advance their bar per item or read, but redraw it at most every
+// ADAPTER_RENDER_INTERVAL unless the percentage changed
+const ADAPTER_RENDER_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+struct RenderThrottle {
+    last: Option<(Instant, u32)>, // Time and percentage of the last render
+}
+
+impl RenderThrottle {
+    fn advance(&mut self, bar: &mut ProgressBar, delta: u64) {
+        bar.set_position(bar.current + delta);
+        let percent = (bar.progress() * 100.0) as u32;
+        if self.last.is_some_and(|(at, last)| last == percent && at.elapsed() < ADAPTER_RENDER_INTERVAL) {
+            return;
+        }
+        bar.render();
+        self.last = Some((Instant::now(), percent));
+    }
+
+    // Draws the final position, which may have been skipped, and ends the line
+    fn finish(&self, bar: &ProgressBar) {
+        bar.render();
+        bar.finish();
+    }
+}
+
+// Iterator that advances a ProgressBar per item and finishes it at the end
+pub struct ProgressIter<I> {
+    iter: I,
+    bar: ProgressBar,
+    throttle: RenderThrottle,
+    finished: bool,
+}
+
+impl<I: Iterator> Iterator for ProgressIter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.iter.next();
+        match item {
+            Some(_) => self.throttle.advance(&mut self.bar, 1),
+            None if !self.finished => {
+                self.finished = true;
+                self.throttle.finish(&self.bar);
+            }
+            None => {}
+        }
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+// for x in items.iter().progress_with(bar) { ... }
+pub trait ProgressIterator: Iterator + Sized {
+    fn progress_with(self, bar: ProgressBar) -> ProgressIter<Self> {
+        ProgressIter { iter: self, bar, throttle: RenderThrottle::default(), finished: false }
+    }
+
+    // Bar sized by the iterator's length
+    fn progress(self) -> ProgressIter<Self>
+    where
+        Self: ExactSizeIterator,
+    {
+        let bar = ProgressBar::new(self.len() as u64);
+        self.progress_with(bar)
+    }
+}
+
+impl<I: Iterator> ProgressIterator for I {}
+
+// Reader that advances a ProgressBar by the bytes read, e.g. while loading a file
+pub struct ProgressRead<R> {
+    inner: R,
+    bar: ProgressBar,
+    throttle: RenderThrottle,
+}
+
+impl<R: io::Read> ProgressRead<R> {
+    // Uses BYTES_PROGRESS_TEMPLATE, total is the expected size in bytes
+    pub fn new(inner: R, total: u64) -> Self {
+        Self::with_bar(inner, ProgressBar::new(total).with_template(BYTES_PROGRESS_TEMPLATE))
+    }
+
+    pub fn with_bar(inner: R, bar: ProgressBar) -> Self {
+        ProgressRead { inner, bar, throttle: RenderThrottle::default() }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.throttle.finish(&self.bar);
+        self.inner
+    }
+}
+
+impl<R: io::Read> io::Read for ProgressRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.throttle.advance(&mut self.bar, n as u64);
+        Ok(n)
+    }
+}
+
+// Writer that advances a ProgressBar by the bytes written
+pub struct ProgressWrite<W> {
+    inner: W,
+    bar: ProgressBar,
+    throttle: RenderThrottle,
+}
+
+impl<W: Write> ProgressWrite<W> {
+    // Uses BYTES_PROGRESS_TEMPLATE, total is the expected size in bytes
+    pub fn new(inner: W, total: u64) -> Self {
+        Self::with_bar(inner, ProgressBar::new(total).with_template(BYTES_PROGRESS_TEMPLATE))
+    }
+
+    pub fn with_bar(inner: W, bar: ProgressBar) -> Self {
+        ProgressWrite { inner, bar, throttle: RenderThrottle::default() }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.throttle.finish(&self.bar);
+        self.inner
+    }
+}
+
+impl<W: Write> Write for ProgressWrite<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.throttle.advance(&mut self.bar, n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Areas the prompt draws its parts into
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PromptAreas {
+    pub output: Rect,
+    pub input: Rect,
+    pub suggestions: Rect,
+    pub hint: Rect,
+    pub tasks: Rect,
+}
+
+// Heights the prompt needs for its parts in the current frame
+#[derive(Clone, Copy, Debug)]
+pub struct PromptHeights {
+    pub input: u16,
+    pub suggestions: u16,
+    pub hint: u16,
+    pub tasks: u16,
+}
+
+// Decides where the prompt's parts go inside the area it is drawn into
+pub trait PromptLayout {
+    fn split(&self, area: Rect, heights: PromptHeights) -> PromptAreas;
+
+    // Whether the input line is centered horizontally in its area
+    fn center_input(&self) -> bool {
+        false
+    }
+}
+
+// Shell-like: output on top, suggestions and hint above the input on the last line
+#[derive(Clone, Debug)]
+pub struct BottomLayout {
+    pub output: Constraint,
+}
+
+impl BottomLayout {
+    pub fn new() -> Self {
+        BottomLayout { output: Constraint::Min(1) }
+    }
+
+    pub fn with_output(mut self, output: Constraint) -> Self {
+        self.output = output;
+        self
+    }
+}
+
+impl Default for BottomLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PromptLayout for BottomLayout {
+    fn split(&self, area: Rect, heights: PromptHeights) -> PromptAreas {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                self.output,
+                Constraint::Length(heights.tasks),
+                Constraint::Length(heights.suggestions),
+                Constraint::Length(heights.hint),
+                Constraint::Length(heights.input),
+            ])
+            .split(area);
+        PromptAreas { output: chunks[0], tasks: chunks[1], suggestions: chunks[2], hint: chunks[3], input: chunks[4] }
+    }
+}
+
+// Input on the first line, suggestions below it, output at the bottom
+#[derive(Clone, Debug)]
+pub struct TopLayout {
+    pub output: Constraint,
+}
+
+impl TopLayout {
+    pub fn new() -> Self {
+        TopLayout { output: Constraint::Min(1) }
+    }
+
+    pub fn with_output(mut self, output: Constraint) -> Self {
+        self.output = output;
+        self
+    }
+}
+
+impl Default for TopLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PromptLayout for TopLayout {
+    fn split(&self, area: Rect, heights: PromptHeights) -> PromptAreas {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(heights.input),
+                Constraint::Length(heights.hint),
+                Constraint::Length(heights.suggestions),
+                Constraint::Length(heights.tasks),
+                self.output,
+            ])
+            .split(area);
+        PromptAreas { input: chunks[0], hint: chunks[1], suggestions: chunks[2], tasks: chunks[3], output: chunks[4] }
+    }
+}
+
+// Output on top, below it the horizontally centered input with suggestions and hint (default)
+#[derive(Clone, Debug)]
+pub struct CenteredLayout {
+    pub output: Constraint,
+}
+
+impl CenteredLayout {
+    pub fn new() -> Self {
+        CenteredLayout { output: Constraint::Min(1) }
+    }
+
+    pub fn with_output(mut self, output: Constraint) -> Self {
+        self.output = output;
+        self
+    }
+}
+
+impl Default for CenteredLayout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PromptLayout for CenteredLayout {
+    fn split(&self, area: Rect, heights: PromptHeights) -> PromptAreas {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                self.output,
+                Constraint::Length(heights.input),
+                Constraint::Length(heights.suggestions),
+                Constraint::Length(heights.hint),
+                Constraint::Length(heights.tasks),
+            ])
+            .split(area);
+        PromptAreas { output: chunks[0], input: chunks[1], suggestions: chunks[2], hint: chunks[3], tasks: chunks[4] }
+    }
+
+    fn center_input(&self) -> bool {
+        true
+    }
+}
+
+// One line of the history or macro file: newlines become \n and backslashes
+// \\, so commands that contain a literal \n survive a round trip
+fn escape_line(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape_line(line: &str) -> String {
+    let mut entry = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                entry.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                entry.push('\\');
+                chars.next();
+            }
+            _ => entry.push(c),
+        }
+    }
+    entry
+}
+
+// Named sequences of prompt lines, recorded with "macro record <name>" and
+// replayed with "macro play <name> [delay_ms]". Stored as text:
+//
+//   macro setup
+//     give coal 10
+//     tp 0 64 0
+//
+// Commands are indented below their macro, newlines in multi-line commands
+// escaped like in the history file.
+#[derive(Clone, Debug, Default)]
+pub struct Macros {
+    macros: BTreeMap<String, Vec<String>>,
+}
+
+impl Macros {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: &str, lines: Vec<String>) {
+        self.macros.insert(name.to_string(), lines);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.macros.get(name).map(Vec::as_slice)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Vec<String>> {
+        self.macros.remove(name)
+    }
+
+    // Sorted
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.macros.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.macros.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.macros.is_empty()
+    }
+
+    // Adds the macros of `other`, replacing ones with the same name
+    pub fn merge(&mut self, other: Macros) {
+        self.macros.extend(other.macros);
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut output = String::new();
+        for (name, lines) in &self.macros {
+            output += &format!("macro {}\n", name);
+            for line in lines {
+                output += &format!("  {}\n", escape_line(line));
+            }
+        }
+        output
+    }
+
+    pub fn parse(content: &str) -> Result<Macros, ParseError> {
+        let mut macros = Macros::new();
+        let mut current: Option<&mut Vec<String>> = None;
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("macro ") {
+                current = Some(macros.macros.entry(name.trim().to_string()).or_default());
+                continue;
+            }
+            match (&mut current, line.strip_prefix("  ")) {
+                (Some(lines), Some(command)) => lines.push(unescape_line(command)),
+                _ => return Err(ParseError::InvalidLine { line: i + 1, text: line.to_string() }),
+            }
+        }
+        Ok(macros)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Macros> {
+        let content = fs::read_to_string(path)?;
+        Macros::parse(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.serialize())
+    }
+}
+
+// Prompt configuration
+#[derive(Clone)]
+pub struct PromptConfig<'a> {
+    prompt: &'a str,
+    registry: CommandRegistry,
+    executor: Executor,
+    history: Vec<String>,
+    max_history: usize,
+    history_file: Option<PathBuf>,
+    macros: Macros,
+    macro_file: Option<PathBuf>,
+    theme: ColorTheme,
+    max_suggestions: usize,
+    max_output: usize,
+    layout: Arc<dyn PromptLayout>,
+    translator: Option<Rc<Translator>>, // Hints, help and error messages
+}
+
+impl<'a> PromptConfig<'a> {
+    pub fn new(prompt: &'a str, registry: CommandRegistry) -> Self {
+        PromptConfig {
+            prompt,
+            registry,
+            executor: Executor::console(),
+            history: vec![],
+            max_history: 50,
+            history_file: None,
+            macros: Macros::new(),
+            macro_file: None,
+            theme: current_theme(),
+            max_suggestions: 5,
+            max_output: 1000,
+            layout: Arc::new(CenteredLayout::new()),
+            translator: None,
+        }
+    }
+
+    pub fn with_executor(mut self, executor: Executor) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    pub fn with_history(mut self, history: Vec<String>) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn with_max_history(mut self, max: usize) -> Self {
+        self.max_history = max;
+        self.trim_history();
+        self
+    }
+
+    // Drops the oldest entries beyond max_history
+    fn trim_history(&mut self) {
+        let excess = self.history.len().saturating_sub(self.max_history);
+        self.history.drain(..excess);
+    }
+
+    // Loads the history from the file (if it exists, keeping the newest
+    // max_history entries) and saves every new entry to it
+    pub fn with_history_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                for line in content.lines().filter(|l| !l.trim().is_empty()) {
+                    let entry = unescape_line(line);
+                    if self.history.last() != Some(&entry) {
+                        self.history.push(entry);
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("Could not read history file {}: {}", path.display(), e),
+        }
+        self.trim_history();
+        self.history_file = Some(path);
+        self
+    }
+
+    // Adds an entry (skipping consecutive duplicates), trims the history to
+    // max_history and writes it to the history file
+    pub fn push_history(&mut self, entry: &str) {
+        if self.history.last().map(String::as_str) == Some(entry) {
+            return;
+        }
+        self.history.push(entry.to_string());
+        self.trim_history();
+        if let Some(path) = &self.history_file {
+            let mut content = self.history.iter().map(|e| escape_line(e)).collect::<Vec<_>>().join("\n");
+            content.push('\n');
+            if let Err(e) = fs::write(path, content) {
+                log::warn!("Could not write history file {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    pub fn with_macros(mut self, macros: Macros) -> Self {
+        self.macros = macros;
+        self
+    }
+
+    // Loads macros from the file (if it exists) and saves them to it after
+    // every change
+    pub fn with_macro_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        match Macros::load(&path) {
+            Ok(macros) => self.macros.merge(macros),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("Could not read macro file {}: {}", path.display(), e),
+        }
+        self.macro_file = Some(path);
+        self
+    }
+
+    pub fn macros(&self) -> &Macros {
+        &self.macros
+    }
+
+    fn save_macros(&self) {
+        if let Some(path) = &self.macro_file
+            && let Err(e) = self.macros.save(path)
+        {
+            log::warn!("Could not write macro file {}: {}", path.display(), e);
+        }
+    }
+
+    // Index of the newest entry before `before` that contains `query`
+    pub fn search_history(&self, query: &str, before: Option<usize>) -> Option<usize> {
+        let end = before.unwrap_or(self.history.len()).min(self.history.len());
+        self.history[..end].iter().rposition(|entry| entry.contains(query))
+    }
+
+    pub fn with_theme(mut self, theme: ColorTheme) -> Self {
+        self.theme = theme.with_adjusted_contrast();
+        self
+    }
+
+    pub fn with_max_suggestions(mut self, max: usize) -> Self {
+        self.max_suggestions = max;
+        self
+    }
+
+    pub fn with_layout<L: PromptLayout + 'static>(mut self, layout: L) -> Self {
+        self.layout = Arc::new(layout);
+        self
+    }
+
+    // Number of lines the output pane keeps
+    pub fn with_max_output(mut self, max: usize) -> Self {
+        self.max_output = max;
+        self
+    }
+
+    // Localizes argument help in hints, the "help" output and command errors
+    pub fn with_translator(mut self, translator: Rc<Translator>) -> Self {
+        self.translator = Some(translator);
+        self
+    }
+}
+
+// Interactive prompt
+pub struct InteractivePrompt<'a> {
+    config: PromptConfig<'a>,
+    input: String,
+    cursor_pos: usize, // Grapheme index into input
+    history_index: Option<usize>,
+    suggestions: Vec<String>,
+    selected_suggestion: Option<usize>,
+    terminal: Option<Terminal<CrosstermBackend<io::Stdout>>>, // None if headless
+    stdin_lines: Option<Receiver<String>>,                     // Input lines if headless
+    running: bool,
+    hint: String,
+    tasks: Vec<CommandTask>,
+    tick: usize,
+    search: Option<String>,      // Query while in reverse search (Ctrl+R)
+    search_match: Option<usize>, // History index of the current match
+    kill_buffer: String,         // Text removed by Ctrl+K/U/W, inserted again by Ctrl+Y
+    output: Vec<StyledText>,     // Lines of the output pane
+    scroll: usize,               // Output lines scrolled up from the bottom
+    areas: PromptAreas,          // Pane areas of the last render, for mouse events
+    suggestion_offset: usize,    // First suggestion shown by the last render
+    input_x: u16,                // Column where the input starts
+    submitted: Option<String>,   // Line submitted with Enter, not yet returned
+    started: bool,               // Alternate screen entered
+    completion: Option<(Vec<String>, usize)>, // Candidates and index while cycling with Tab
+    recording: Option<(String, Vec<String>)>,  // Macro name and the lines executed since "macro record"
+    playback: VecDeque<String>,                // Lines of the playing macro, submitted by poll_event
+    playback_delay: Duration,
+    next_step: Instant,                        // When the next playback line is due
+}
+
+// Returned by InteractivePrompt::poll_event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptEvent {
+    Line(String), // Submitted input, not executed yet
+    Exit,         // "exit" was entered
+}
+
+// Lines scrolled by PageUp/PageDown in the output pane
+const OUTPUT_PAGE: usize = 10;
+// Lines scrolled per mouse wheel step
+const MOUSE_SCROLL: usize = 3;
+
+
+impl<'a> InteractivePrompt<'a> {
+    // Without a terminal (see is_headless) the prompt reads commands line by
+    // line from stdin and prints results to stdout
+    pub fn new(config: PromptConfig<'a>) -> io::Result<Self> {
+        let terminal = match is_headless() {
+            true => None,
+            false => {
+                terminal::enable_raw_mode()?;
+                let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+                terminal.clear()?;
+                Some(terminal)
+            }
+        };
+        Ok(InteractivePrompt {
+            config,
+            input: String::new(),
+            cursor_pos: 0,
+            history_index: None,
+            suggestions: vec![],
+            selected_suggestion: None,
+            terminal,
+            stdin_lines: None,
+            running: true,
+            hint: String::new(),
+            tasks: vec![],
+            tick: 0,
+            search: None,
+            search_match: None,
+            kill_buffer: String::new(),
+            output: vec![],
+            scroll: 0,
+            areas: PromptAreas::default(),
+            suggestion_offset: 0,
+            input_x: 0,
+            submitted: None,
+            started: false,
+            completion: None,
+            recording: None,
+            playback: VecDeque::new(),
+            playback_delay: Duration::ZERO,
+            next_step: Instant::now(),
+        })
+    }
+
+    fn update_suggestions(&mut self) {
+        let translator = self.config.translator.as_deref();
+        let (suggestions, hint) = self.config.registry.localized_suggestions(&self.config.executor, &self.input, translator);
+        self.suggestions = suggestions;
+        self.hint = hint;
+        self.selected_suggestion = if self.suggestions.is_empty() {
+            None
+        } else {
+            Some(0)
+        };
+    }
+
+    // Adds a command result to the output pane
+    fn print_result(&mut self, result: &str) {
+        let text = format!("Result: {}", result);
+        let colored_result = colored_styled(&text, &self.config.theme.success_color).unwrap_or_else(|_| text.into());
+        self.print_styled(colored_result);
+    }
+
+    fn print_command_error(&mut self, error: &CommandError) {
+        let message = match &self.config.translator {
+            Some(translator) => error.localized(translator),
+            None => error.to_string(),
+        };
+        self.print_error(&message);
+    }
+
+    fn print_error(&mut self, error: &str) {
+        let text = format!("Error: {}", error);
+        let colored_error = colored_styled(&text, &self.config.theme.error_color).unwrap_or_else(|_| text.into());
+        self.print_styled(colored_error);
+    }
+
+    // Appends (possibly ANSI colored) text to the output pane
+    pub fn print_output(&mut self, text: &str) {
+        self.print_styled(StyledText::from_ansi(text));
+    }
+
+    pub fn print_styled(&mut self, text: StyledText) {
+        if self.terminal.is_none() {
+            println!("{}", text.plain_text());
+            return;
+        }
+        let lines = text.lines();
+        let count = lines.len();
+        self.output.extend(lines);
+        if self.output.len() > self.config.max_output {
+            let excess = self.output.len() - self.config.max_output;
+            self.output.drain(..excess);
+        }
+        // Stay at the bottom unless the user scrolled up
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + count).min(self.output.len());
+        }
+    }
+
+    // Collects updates of background tasks and prints the results of finished ones
+    fn poll_tasks(&mut self) {
+        let mut i = 0;
+        while i < self.tasks.len() {
+            if self.tasks[i].poll() {
+                let task = self.tasks.remove(i);
+                match task.result {
+                    Some(Ok(result)) => self.print_result(&result),
+                    Some(Err(e)) => self.print_command_error(&e),
+                    None => {}
+                }
+                self.apply_language();
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    // The translator of hints, help and errors, replaced by "lang set"
+    pub fn translator(&self) -> Option<Rc<Translator>> {
+        self.config.translator.clone()
+    }
+
+    // Uses the translator "lang set" loaded, see admin::set_language
+    fn apply_language(&mut self) {
+        let translator = crate::admin::ADMIN.lock().unwrap().take_translator();
+        if let Some(translator) = translator {
+            self.config.translator = Some(Rc::new(translator));
+            self.update_suggestions();
+        }
+    }
+
+    fn task_lines(&self) -> Vec<String> {
+        let frames = SpinnerStyle::Dots.frames();
+        let spinner = frames[self.tick % frames.len()];
+        self.tasks
+            .iter()
+            .map(|task| {
+                let mut line = format!("{} {}", spinner, task.name);
+                if let Some((current, total)) = task.progress {
+                    let mut bar = ProgressBar::new(total).with_width(20);
+                    bar.set_position(current);
+                    line.push_str(&format!(" {}", bar.text()));
+                }
+                if !task.message.is_empty() {
+                    line.push_str(&format!(" {}", task.message));
+                }
+                line
+            })
+            .collect()
+    }
+
+    fn render(&mut self) -> io::Result<()> {
+        if self.terminal.is_none() {
+            return Ok(());
+        }
+        self.tick = self.tick.wrapping_add(1);
+        let task_lines = self.task_lines();
+        let config = self.config.clone();
+        // Cursor (column, row) is counted from the start of the prompt
+        let (prompt, input, cursor) = match &self.search {
+            Some(query) => {
+                let found = self.search_match.map(|i| config.history[i].clone()).unwrap_or_default();
+                let cursor_x = visible_length("(reverse-i-search)`") + visible_length(query);
+                (format!("(reverse-i-search)`{}': ", query), found, (cursor_x, 0))
+            }
+            None => {
+                let (col, row) = self.cursor_line_position();
+                let col = visible_length(config.prompt) + col;
+                (config.prompt.to_string(), self.input.clone(), (col, row))
+            }
+        };
+        let output = self.output.clone();
+        let scroll = self.scroll;
+        let suggestions = self.suggestions.clone();
+        let selected_suggestion = self.selected_suggestion;
+        let hint = self.hint.clone();
+        let prompt_len = visible_length(&prompt);
+        // Continuation lines are indented to the end of the prompt
+        let input_lines: Vec<String> = input.split('\n').map(String::from).collect();
+        let total_len = input_lines.iter().map(|l| prompt_len + visible_length(l)).max().unwrap_or(prompt_len);
+        let prompt_style = Style::default().fg(theme_color(&config.theme.prompt_color, Color::Cyan));
+        let input_style = Style::default().fg(theme_color(&config.theme.input_color, Color::White));
+        let hint_style = Style::default().fg(theme_color(&config.theme.hint_color, Color::Gray));
+        let heights = PromptHeights {
+            input: input_lines.len() as u16,
+            suggestions: config.max_suggestions as u16 + 2,
+            hint: 1,
+            tasks: task_lines.len() as u16,
+        };
+
+        let mut areas = PromptAreas::default();
+        let mut suggestion_offset = 0;
+        let mut input_x = 0;
+        self.terminal.as_mut().unwrap().draw(|f| {
+            let chunks = config.layout.split(f.area(), heights);
+            let padding = if config.layout.center_input() && total_len < chunks.input.width as usize {
+                (chunks.input.width as usize - total_len) / 2
+            } else {
+                0
+            };
+
+            // Render output pane, newest lines at the bottom
+            let height = chunks.output.height.saturating_sub(2) as usize;
+            let end = output.len().saturating_sub(scroll);
+            let start = end.saturating_sub(height);
+            let output_lines: Vec<Line> = output[start..end].iter().flat_map(StyledText::to_lines).collect();
+            let title = if scroll > 0 { format!("Output (+{})", scroll) } else { "Output".to_string() };
+            let output_paragraph = Paragraph::new(output_lines)
+                .block(Block::default().borders(Borders::ALL).title(title));
+            f.render_widget(output_paragraph, chunks.output);
+
+            // Render prompt and input
+            let prompt_lines: Vec<Line> = input_lines
+                .iter()
+                .enumerate()
+                .map(|(i, l)| {
+                    let lead = if i == 0 { prompt.clone() } else { " ".repeat(prompt_len) };
+                    Line::from(vec![
+                        Span::raw(" ".repeat(padding)),
+                        Span::styled(lead, prompt_style),
+                        Span::styled(l.clone(), input_style),
+                    ])
+                })
+                .collect();
+            let paragraph = Paragraph::new(prompt_lines)
+                .block(Block::default().borders(Borders::NONE));
+            f.render_widget(paragraph, chunks.input);
+
+            // Render suggestions dropdown
+            let items: Vec<ListItem> = suggestions
+                .iter()
+                .take(config.max_suggestions)
+                .enumerate()
+                .map(|(i, s)| {
+                    let style = if selected_suggestion == Some(i) {
+                        config.theme.selected_style()
+                    } else {
+                        Style::default().fg(theme_color(&config.theme.suggestion_color, Color::White))
+                    };
+                    ListItem::new(s.clone()).style(style)
+                })
+                .collect();
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title("Suggestions"));
+            let mut list_state = ListState::default();
+            list_state.select(selected_suggestion);
+            f.render_stateful_widget(list, chunks.suggestions, &mut list_state);
+            // Scrolled to keep the selection visible if the pane is too small
+            suggestion_offset = list_state.offset();
+
+            // Render hint
+            let hint_paragraph = Paragraph::new(hint.clone())
+                .style(hint_style)
+                .block(Block::default().borders(Borders::NONE));
+            f.render_widget(hint_paragraph, chunks.hint);
+
+            // Render running tasks
+            let task_paragraph = Paragraph::new(task_lines.join("\n"))
+                .style(hint_style);
+            f.render_widget(task_paragraph, chunks.tasks);
+
+            // Set cursor position (adjusted for centering)
+            let cursor_x = chunks.input.x + (padding + cursor.0) as u16;
+            f.set_cursor_position((cursor_x, chunks.input.y + cursor.1 as u16));
+            areas = chunks;
+            input_x = chunks.input.x + (padding + prompt_len) as u16;
+        })?;
+        self.areas = areas;
+        self.suggestion_offset = suggestion_offset;
+        self.input_x = input_x;
+        Ok(())
+    }
+
+    // Line editing, all positions are grapheme indices into the input
+
+    fn input_len(&self) -> usize {
+        self.input.graphemes(true).count()
+    }
+
+    fn byte_index(&self, pos: usize) -> usize {
+        self.input.grapheme_indices(true).nth(pos).map_or(self.input.len(), |(i, _)| i)
+    }
+
+    fn insert_str(&mut self, text: &str) {
+        let at = self.byte_index(self.cursor_pos);
+        self.input.insert_str(at, text);
+        self.cursor_pos += text.graphemes(true).count();
+        self.update_suggestions();
+    }
+
+    // Removes the graphemes in start..end and returns them
+    fn delete_range(&mut self, start: usize, end: usize) -> String {
+        if start >= end {
+            return String::new();
+        }
+        let (from, to) = (self.byte_index(start), self.byte_index(end));
+        let removed: String = self.input.drain(from..to).collect();
+        self.cursor_pos = start;
+        self.update_suggestions();
+        removed
+    }
+
+    fn word_left(&self) -> usize {
+        let graphemes: Vec<&str> = self.input.graphemes(true).collect();
+        let mut pos = self.cursor_pos;
+        while pos > 0 && graphemes[pos - 1].trim().is_empty() {
+            pos -= 1;
+        }
+        while pos > 0 && !graphemes[pos - 1].trim().is_empty() {
+            pos -= 1;
+        }
+        pos
+    }
+
+    fn word_right(&self) -> usize {
+        let graphemes: Vec<&str> = self.input.graphemes(true).collect();
+        let mut pos = self.cursor_pos;
+        while pos < graphemes.len() && graphemes[pos].trim().is_empty() {
+            pos += 1;
+        }
+        while pos < graphemes.len() && !graphemes[pos].trim().is_empty() {
+            pos += 1;
+        }
+        pos
+    }
+
+    fn line_start(&self) -> usize {
+        let graphemes: Vec<&str> = self.input.graphemes(true).collect();
+        graphemes[..self.cursor_pos].iter().rposition(|g| *g == "\n").map_or(0, |i| i + 1)
+    }
+
+    fn line_end(&self) -> usize {
+        let graphemes: Vec<&str> = self.input.graphemes(true).collect();
+        graphemes[self.cursor_pos..].iter().position(|g| *g == "\n").map_or(graphemes.len(), |i| self.cursor_pos + i)
+    }
+
+    // Grapheme index for a (column, row) inside the (multi-line) input, clamped to the line
+    fn position_at(&self, col: usize, row: usize) -> usize {
+        let (mut cur_col, mut cur_row) = (0, 0);
+        for (i, g) in self.input.graphemes(true).enumerate() {
+            if cur_row == row && (cur_col >= col || g == "\n") {
+                return i;
+            }
+            if g == "\n" {
+                cur_row += 1;
+                cur_col = 0;
+            } else {
+                cur_col += 1;
+            }
+        }
+        self.input_len()
+    }
+
+    // (column, row) of the cursor inside the (multi-line) input
+    fn cursor_line_position(&self) -> (usize, usize) {
+        let before = &self.input[..self.byte_index(self.cursor_pos)];
+        let row = before.matches('\n').count();
+        let col = visible_length(before.rsplit('\n').next().unwrap_or(""));
+        (col, row)
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        let mut query = self.search.clone().unwrap_or_default();
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('r'), KeyModifiers::CONTROL) => {
+                // Jump to the next older match
+                if let Some(i) = self.config.search_history(&query, self.search_match) {
+                    self.search_match = Some(i);
+                }
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                query.push(c);
+                self.search_match = self.config.search_history(&query, None);
+                self.search = Some(query);
+            }
+            (KeyCode::Backspace, _) => {
+                query.pop();
+                self.search_match = self.config.search_history(&query, None);
+                self.search = Some(query);
+            }
+            (KeyCode::Esc, _) | (KeyCode::Char('g' | 'c'), KeyModifiers::CONTROL) => {
+                self.search = None;
+                self.search_match = None;
+            }
+            (KeyCode::Enter, _) => {
+                self.accept_search();
+                return self.handle_key(key);
+            }
+            _ => self.accept_search(),
+        }
+        Ok(())
+    }
+
+    fn accept_search(&mut self) {
+        if let Some(i) = self.search_match {
+            self.input = self.config.history[i].clone();
+            self.cursor_pos = self.input_len();
+            self.update_suggestions();
+        }
+        self.search = None;
+        self.search_match = None;
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> io::Result<()> {
+        if self.search.is_some() {
+            return self.handle_search_key(key);
+        }
+        if !matches!(key.code, KeyCode::Tab | KeyCode::BackTab) {
+            self.completion = None;
+        }
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('r'), KeyModifiers::CONTROL) => {
+                self.search = Some(String::new());
+                self.search_match = None;
+            }
+            // Interrupt: cancel running tasks on an empty line, discard the current line otherwise
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) if self.input.is_empty() && !self.tasks.is_empty() => {
+                self.tasks.iter().for_each(CommandTask::cancel);
+                self.print_output("Cancelling running commands");
+            }
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                self.input.clear();
+                self.cursor_pos = 0;
+                self.history_index = None;
+                self.update_suggestions();
+            }
+            // EOF: exit on an empty line, delete forward otherwise
+            (KeyCode::Char('d'), KeyModifiers::CONTROL) => {
+                if self.input.is_empty() {
+                    self.running = false;
+                } else if self.cursor_pos < self.input_len() {
+                    self.delete_range(self.cursor_pos, self.cursor_pos + 1);
+                }
+            }
+            // Shift+Enter (or Alt+Enter where terminals don't report Shift) continues on a new line
+            (KeyCode::Enter, m) if m.intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) => {
+                self.insert_str("\n");
+            }
+            (KeyCode::Enter, _) => {
+                if self.input.trim() == "exit" {
+                    self.running = false;
+                    return Ok(());
+                }
+                if !self.input.is_empty() {
+                    let entry = self.input.clone();
+                    self.config.push_history(&entry);
+                    self.submitted = Some(entry);
+                    self.input.clear();
+                    self.cursor_pos = 0;
+                    self.history_index = None;
+                    self.update_suggestions();
+                }
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                self.insert_str(&c.to_string());
+            }
+            (KeyCode::Backspace, _) if self.cursor_pos > 0 => {
+                self.delete_range(self.cursor_pos - 1, self.cursor_pos);
+            }
+            (KeyCode::Delete, _) if self.cursor_pos < self.input_len() => {
+                self.delete_range(self.cursor_pos, self.cursor_pos + 1);
+            }
+            (KeyCode::Left, KeyModifiers::CONTROL) => {
+                self.cursor_pos = self.word_left();
+            }
+            (KeyCode::Right, KeyModifiers::CONTROL) => {
+                self.cursor_pos = self.word_right();
+            }
+            (KeyCode::Left, _) if self.cursor_pos > 0 => {
+                self.cursor_pos -= 1;
+            }
+            (KeyCode::Right, _) if self.cursor_pos < self.input_len() => {
+                self.cursor_pos += 1;
+            }
+            (KeyCode::PageUp, _) => {
+                self.scroll = (self.scroll + OUTPUT_PAGE).min(self.output.len());
+            }
+            (KeyCode::PageDown, _) => {
+                self.scroll = self.scroll.saturating_sub(OUTPUT_PAGE);
+            }
+            (KeyCode::Home, _) | (KeyCode::Char('a'), KeyModifiers::CONTROL) => {
+                self.cursor_pos = self.line_start();
+            }
+            (KeyCode::End, _) | (KeyCode::Char('e'), KeyModifiers::CONTROL) => {
+                self.cursor_pos = self.line_end();
+            }
+            // Kill to end / start of the line, kill previous word, yank
+            (KeyCode::Char('k'), KeyModifiers::CONTROL) => {
+                let end = self.line_end();
+                self.kill_buffer = self.delete_range(self.cursor_pos, end);
+            }
+            (KeyCode::Char('u'), KeyModifiers::CONTROL) => {
+                let start = self.line_start();
+                self.kill_buffer = self.delete_range(start, self.cursor_pos);
+            }
+            (KeyCode::Char('w'), KeyModifiers::CONTROL) => {
+                let start = self.word_left();
+                self.kill_buffer = self.delete_range(start, self.cursor_pos);
+            }
+            (KeyCode::Char('y'), KeyModifiers::CONTROL) => {
+                let yanked = self.kill_buffer.clone();
+                self.insert_str(&yanked);
+            }
+            (KeyCode::Up, _) => {
+                if !self.suggestions.is_empty() {
+                    self.selected_suggestion = Some(
+                        self.selected_suggestion
+                            .map_or(0, |i| if i == 0 { 0 } else { i - 1 }),
+                    );
+                } else if !self.config.history.is_empty() {
+                    let max_index = self.config.history.len() - 1;
+                    self.history_index = Some(
+                        self.history_index
+                            .map_or(max_index, |i| if i == 0 { 0 } else { i - 1 }),
+                    );
+                    self.input = self.config.history[self.history_index.unwrap()].clone();
+                    self.cursor_pos = self.input_len();
+                    self.update_suggestions();
+                }
+            }
+            (KeyCode::Down, _) => {
+                if !self.suggestions.is_empty() {
+                    self.selected_suggestion = Some(
+                        self.selected_suggestion.map_or(0, |i| {
+                            if i + 1 < self.suggestions.len().min(self.config.max_suggestions) {
+                                i + 1
+                            } else {
+                                i
+                            }
+                        }),
+                    );
+                } else if !self.config.history.is_empty() {
+                    self.history_index = Some(
+                        self.history_index.map_or(0, |i| {
+                            if i + 1 < self.config.history.len() {
+                                i + 1
+                            } else {
+                                i
+                            }
+                        }),
+                    );
+                    self.input = self.config.history[self.history_index.unwrap()].clone();
+                    self.cursor_pos = self.input_len();
+                    self.update_suggestions();
+                }
+            }
+            (KeyCode::Tab, _) => self.complete(true),
+            (KeyCode::BackTab, _) => self.complete(false),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Shell-style completion: the first Tab completes to the longest common
+    // prefix of all suggestions, further presses cycle through them
+    fn complete(&mut self, forward: bool) {
+        if let Some((candidates, idx)) = &mut self.completion {
+            let len = candidates.len();
+            *idx = if forward { (*idx + 1) % len } else { (*idx + len - 1) % len };
+            self.input = candidates[*idx].clone();
+            self.selected_suggestion = Some(*idx);
+            self.cursor_pos = self.input_len();
+            return;
+        }
+        match self.suggestions.len() {
+            0 => {}
+            1 => self.accept_suggestion(0),
+            _ => {
+                let prefix = common_prefix(&self.suggestions);
+                if prefix.len() > self.input.len() && prefix.starts_with(self.input.as_str()) {
+                    self.input = prefix.to_string();
+                    self.cursor_pos = self.input_len();
+                    self.update_suggestions();
+                } else {
+                    let candidates = self.suggestions.clone();
+                    let idx = if forward { 0 } else { candidates.len() - 1 };
+                    self.input = candidates[idx].clone();
+                    self.cursor_pos = self.input_len();
+                    self.selected_suggestion = Some(idx);
+                    self.completion = Some((candidates, idx));
+                }
+            }
+        }
+    }
+
+    fn accept_suggestion(&mut self, idx: usize) {
+        if idx < self.suggestions.len() {
+            // Suggestions are complete input lines
+            self.input = self.suggestions[idx].clone();
+            self.cursor_pos = self.input_len();
+            self.update_suggestions();
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let PromptAreas { output, input, suggestions, .. } = self.areas;
+        let position = Position::new(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                // Suggestion list rows start below the border
+                if suggestions.contains(position) && mouse.row > suggestions.y {
+                    let idx = self.suggestion_offset + (mouse.row - suggestions.y - 1) as usize;
+                    if idx < self.suggestions.len().min(self.config.max_suggestions) {
+                        self.accept_suggestion(idx);
+                    }
+                } else if input.contains(position) && self.search.is_none() {
+                    let col = mouse.column.saturating_sub(self.input_x) as usize;
+                    let row = (mouse.row - input.y) as usize;
+                    self.cursor_pos = self.position_at(col, row);
+                }
+            }
+            MouseEventKind::ScrollUp => {
+                if output.contains(position) {
+                    self.scroll = (self.scroll + MOUSE_SCROLL).min(self.output.len());
+                } else if suggestions.contains(position) {
+                    self.selected_suggestion = self.selected_suggestion.map(|i| i.saturating_sub(1));
+                }
+            }
+            MouseEventKind::ScrollDown => {
+                if output.contains(position) {
+                    self.scroll = self.scroll.saturating_sub(MOUSE_SCROLL);
+                } else if suggestions.contains(position) {
+                    let last = self.suggestions.len().min(self.config.max_suggestions).saturating_sub(1);
+                    self.selected_suggestion = self.selected_suggestion.map(|i| (i + 1).min(last));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Runs a line through the command registry, like pressing Enter in run().
+    // "help" and "macro" are built in, lines are recorded into the macro
+    // being recorded.
+    pub fn execute(&mut self, line: &str) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.first() == Some(&"macro") && self.config.registry.find_command("macro").is_none() {
+            self.run_macro_command(&words[1..]);
+            return;
+        }
+        if let Some((_, lines)) = &mut self.recording {
+            lines.push(line.to_string());
+        }
+        // Chains run in the background, like tasks
+        if CommandRegistry::is_chain(line) {
+            self.tasks.push(self.config.registry.start_chain(&self.config.executor, line));
+            return;
+        }
+        match self.config.registry.run_command(&self.config.executor, line) {
+            Ok(CommandOutcome::Done(result)) => {
+                self.print_result(&result);
+                self.apply_language();
+            }
+            Ok(CommandOutcome::Task(task)) => self.tasks.push(task),
+            // "help [command]" unless a command of that name is registered
+            Err(CommandError::UnknownCommand(_)) if line.split_whitespace().next() == Some("help") => {
+                let command = line.trim_start().trim_start_matches("help");
+                let translator = self.config.translator.as_deref();
+                match self.config.registry.help(&self.config.executor, command, translator) {
+                    Ok(help) => self.print_output(&help),
+                    Err(e) => self.print_command_error(&e),
+                }
+            }
+            // Lines that aren't commands are left to the application
+            Err(CommandError::UnknownCommand(_) | CommandError::NoHandler(_)) => {}
+            Err(e) => self.print_command_error(&e),
+        }
+    }
+
+    // "macro record|stop|play|list|delete|save|load", unless the registry
+    // has a "macro" command of its own
+    fn run_macro_command(&mut self, args: &[&str]) {
+        let result = match args {
+            ["record", name] => match self.recording.replace((name.to_string(), vec![])) {
+                Some((previous, _)) => Err(format!("Already recording '{}', discarded it", previous)),
+                None => Ok(format!("Recording macro '{}', stop with 'macro stop'", name)),
+            },
+            ["stop"] => match (self.recording.take(), self.playback.is_empty()) {
+                (Some((name, lines)), _) => {
+                    let count = lines.len();
+                    self.config.macros.insert(&name, lines);
+                    self.config.save_macros();
+                    Ok(format!("Recorded macro '{}' with {} commands", name, count))
+                }
+                (None, false) => {
+                    self.playback.clear();
+                    Ok("Stopped the macro".to_string())
+                }
+                (None, true) => Err("No macro is recording or playing".to_string()),
+            },
+            ["play", name, delay @ ..] if delay.len() <= 1 => {
+                let delay = match delay.first().map(|d| d.parse::<u64>()) {
+                    None => Ok(0),
+                    Some(Ok(delay)) => Ok(delay),
+                    Some(Err(_)) => Err(format!("Invalid delay '{}', expected milliseconds", delay[0])),
+                };
+                match (self.config.macros.get(name), delay) {
+                    (_, Err(e)) => Err(e),
+                    (None, _) => Err(format!("Macro '{}' does not exist", name)),
+                    (Some(lines), Ok(delay)) => {
+                        self.playback = lines.iter().cloned().collect();
+                        self.playback_delay = Duration::from_millis(delay);
+                        self.next_step = Instant::now();
+                        Ok(format!("Playing macro '{}' ({} commands)", name, self.playback.len()))
+                    }
+                }
+            }
+            ["list"] if self.config.macros.is_empty() => Ok("No macros recorded".to_string()),
+            ["list"] => {
+                let macros = &self.config.macros;
+                let lines: Vec<String> = macros.names().map(|n| format!("{} ({} commands)", n, macros.get(n).unwrap().len())).collect();
+                Ok(lines.join("\n"))
+            }
+            ["delete", name] => match self.config.macros.remove(name) {
+                Some(_) => {
+                    self.config.save_macros();
+                    Ok(format!("Deleted macro '{}'", name))
+                }
+                None => Err(format!("Macro '{}' does not exist", name)),
+            },
+            ["save", path] => match self.config.macros.save(path) {
+                Ok(()) => Ok(format!("Saved {} macros to {}", self.config.macros.len(), path)),
+                Err(e) => Err(format!("Could not write {}: {}", path, e)),
+            },
+            ["load", path] => match Macros::load(path) {
+                Ok(macros) => {
+                    let count = macros.len();
+                    self.config.macros.merge(macros);
+                    self.config.save_macros();
+                    Ok(format!("Loaded {} macros from {}", count, path))
+                }
+                Err(e) => Err(format!("Could not read {}: {}", path, e)),
+            },
+            _ => Err("Usage: macro record <name> | stop | play <name> [delay_ms] | list | delete <name> | save <file> | load <file>".to_string()),
+        };
+        match result {
+            Ok(message) => self.print_result(&message),
+            Err(message) => self.print_error(&message),
+        }
+    }
+
+    // The next line of the playing macro if it's due, echoed like typed input
+    fn next_macro_line(&mut self) -> Option<String> {
+        if self.playback.is_empty() || Instant::now() < self.next_step {
+            return None;
+        }
+        let line = self.playback.pop_front()?;
+        self.next_step = Instant::now() + self.playback_delay;
+        self.print_output(&format!("{}{}", self.config.prompt, line));
+        Some(line)
+    }
+
+    // How long poll_event may wait for input before the next macro line is due
+    fn macro_timeout(&self, timeout: Duration) -> Duration {
+        match self.playback.is_empty() {
+            true => timeout,
+            false => timeout.min(self.next_step.saturating_duration_since(Instant::now())),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    // Enters the alternate screen. Called by poll_event if needed.
+    pub fn start(&mut self) -> io::Result<()> {
+        let Some(terminal) = self.terminal.as_mut().filter(|_| !self.started) else {
+            return Ok(());
+        };
+        install_panic_hook();
+        execute!(
+            terminal.backend_mut(),
+            terminal::EnterAlternateScreen,
+            event::EnableMouseCapture,
+            cursor::EnableBlinking,
+            cursor::Show
+        )?;
+        self.started = true;
+        PROMPT_ACTIVE.with(|active| active.set(true));
+        // Log lines would draw over the prompt, show them in the output pane instead
+        logging::set_capture(true);
+        self.update_suggestions();
+        Ok(())
+    }
+
+    // Leaves the alternate screen and restores the terminal
+    pub fn finish(&mut self) -> io::Result<()> {
+        let Some(terminal) = self.terminal.as_mut() else {
+            return Ok(());
+        };
+        if self.started {
+            execute!(
+                terminal.backend_mut(),
+                event::DisableMouseCapture,
+                terminal::LeaveAlternateScreen,
+                cursor::Show
+            )?;
+            self.started = false;
+            PROMPT_ACTIVE.with(|active| active.set(false));
+            logging::set_capture(false);
+        }
+        terminal::disable_raw_mode()
+    }
+
+    // Renders and handles at most one terminal event, waiting up to `timeout`.
+    // Lets applications run their own loop and interleave input with game logic.
+    pub fn poll_event(&mut self, timeout: Duration) -> io::Result<Option<PromptEvent>> {
+        self.start()?;
+        if !self.running {
+            return Ok(Some(PromptEvent::Exit));
+        }
+        if let Some(line) = self.next_macro_line() {
+            return Ok(Some(PromptEvent::Line(line)));
+        }
+        let timeout = self.macro_timeout(timeout);
+        if self.terminal.is_none() {
+            return self.poll_stdin(timeout);
+        }
+        self.poll_tasks();
+        for line in logging::take_captured() {
+            self.print_output(&line);
+        }
+        self.render()?;
+        if event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(key) => self.handle_key(key)?,
+                Event::Mouse(mouse) => self.handle_mouse(mouse),
+                _ => {}
+            }
+        }
+        if !self.running {
+            return Ok(Some(PromptEvent::Exit));
+        }
+        Ok(self.submitted.take().map(PromptEvent::Line))
+    }
+
+    // poll_event without a terminal: one line from stdin, "exit" or the end of
+    // input exit
+    fn poll_stdin(&mut self, timeout: Duration) -> io::Result<Option<PromptEvent>> {
+        self.poll_tasks();
+        let lines = self.stdin_lines.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                for line in io::stdin().lines().map_while(Result::ok) {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+            receiver
+        });
+        match lines.recv_timeout(timeout) {
+            Ok(line) if line.trim() == "exit" => self.running = false,
+            Ok(line) if !line.trim().is_empty() => {
+                self.config.push_history(&line);
+                return Ok(Some(PromptEvent::Line(line)));
+            }
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => self.running = false,
+        }
+        Ok((!self.running).then_some(PromptEvent::Exit))
+    }
+
+    // Blocks until a line is submitted, None after "exit"
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.poll_event(Duration::from_millis(100))? {
+                Some(PromptEvent::Line(line)) => return Ok(Some(line)),
+                Some(PromptEvent::Exit) => return Ok(None),
+                None => {}
+            }
+        }
+    }
+
+    pub fn run(mut self) -> io::Result<()> {
+        while let Some(line) = self.read_line()? {
+            self.execute(&line);
+        }
+        self.finish()
+    }
+}
+
+// Restores the terminal if the prompt is dropped without finish(), e.g. while
+// unwinding from a panicking command handler
+impl Drop for InteractivePrompt<'_> {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+// Leaves raw mode and the alternate screen, ignoring errors
+fn restore_terminal() {
+    let _ = execute!(io::stdout(), event::DisableMouseCapture, terminal::LeaveAlternateScreen, cursor::Show);
+    let _ = terminal::disable_raw_mode();
+}
+
+thread_local! {
+    // Set while this thread shows a prompt. Panics in task threads leave the terminal alone.
+    static PROMPT_ACTIVE: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+// Restores the terminal before the default hook prints the panic message, so
+// it isn't lost in the alternate screen
+fn install_panic_hook() {
+    static INSTALLED: std::sync::Once = std::sync::Once::new();
+    INSTALLED.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if PROMPT_ACTIVE.with(|active| active.replace(false)) {
+                restore_terminal();
+            }
+            previous(info);
+        }));
+    });
+}
+
+pub type FullscreenTerminal = Terminal<CrosstermBackend<io::Stdout>>;
+
+// Runs a full-screen screen (menus, browsers, ...) in the alternate screen and
+// restores the terminal afterwards, also if `f` panics
+pub fn fullscreen<T>(f: impl FnOnce(&mut FullscreenTerminal) -> io::Result<T>) -> io::Result<T> {
+    if is_headless() {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "stdout is not a terminal"));
+    }
+    terminal::enable_raw_mode()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    execute!(terminal.backend_mut(), terminal::EnterAlternateScreen)?;
+    install_panic_hook();
+    PROMPT_ACTIVE.with(|active| active.set(true));
+    logging::set_capture(true);
+    let result = f(&mut terminal);
+    PROMPT_ACTIVE.with(|active| active.set(false));
+    restore_terminal();
+    // Prints what was logged while the screen was shown
+    logging::set_capture(false);
+    result
+}
+
+// Longest prefix shared by all strings, on char boundaries
+fn common_prefix(strings: &[String]) -> &str {
+    let Some(first) = strings.first() else {
+        return "";
+    };
+    let mut end = first.len();
+    for s in &strings[1..] {
+        end = first[..end]
+            .char_indices()
+            .zip(s.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(end.min(s.len()), |((i, _), _)| i);
+    }
+    &first[..end]
+}
+
+// Main prompt function
+pub fn prompt(config: PromptConfig) -> io::Result<()> {
+    let prompt = InteractivePrompt::new(config)?;
+    prompt.run()
+}
+
+// Simple print with color
+pub fn print_colored(text: &str, color_ref: &ColorRef) -> io::Result<()> {
+    let colored = colored_text(text, color_ref).map_err(io::Error::other)?;
+    print!("{}", colored);
+    io::stdout().flush()
+}
+
+// ---------------
+// SELECTION MENUS
+// ---------------
+
+struct SelectMenu<'a> {
+    items: &'a [&'a str],
+    multi: bool,
+    filter: String,
+    cursor: usize,          // Index into the filtered items
+    checked: HashSet<usize>, // Indices into items
+}
+
+impl SelectMenu<'_> {
+    // Indices of the items containing the filter, case-insensitive
+    fn visible(&self) -> Vec<usize> {
+        let filter = self.filter.to_lowercase();
+        (0..self.items.len())
+            .filter(|&i| self.items[i].to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    // Cancelled right away if headless
+    fn run(&mut self, theme: &ColorTheme) -> io::Result<Option<Vec<usize>>> {
+        if is_headless() {
+            return Ok(None);
+        }
+        fullscreen(|terminal| self.event_loop(terminal, theme))
+    }
+
+    fn event_loop(&mut self, terminal: &mut FullscreenTerminal, theme: &ColorTheme) -> io::Result<Option<Vec<usize>>> {
+        let prompt_style = Style::default().fg(theme_color(&theme.prompt_color, Color::Cyan));
+        let item_style = Style::default().fg(theme_color(&theme.suggestion_color, Color::White));
+        let selected_style = theme.selected_style();
+        let hint_style = Style::default().fg(theme_color(&theme.hint_color, Color::Gray));
+
+        loop {
+            let visible = self.visible();
+            self.cursor = self.cursor.min(visible.len().saturating_sub(1));
+            terminal.draw(|f| {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(1), Constraint::Min(3), Constraint::Length(1)])
+                    .split(f.area());
+                f.render_widget(Paragraph::new(Line::from(vec![
+                    Span::styled("> ", prompt_style),
+                    Span::raw(self.filter.clone()),
+                ])), chunks[0]);
+
+                let items: Vec<ListItem> = visible
+                    .iter()
+                    .map(|&i| {
+                        let text = if self.multi {
+                            let mark = if self.checked.contains(&i) { "[x]" } else { "[ ]" };
+                            format!("{} {}", mark, self.items[i])
+                        } else {
+                            self.items[i].to_string()
+                        };
+                        ListItem::new(text).style(item_style)
+                    })
+                    .collect();
+                let list = List::new(items)
+                    .block(Block::default().borders(Borders::ALL))
+                    .highlight_style(selected_style);
+                let mut state = ListState::default();
+                state.select((!visible.is_empty()).then_some(self.cursor));
+                f.render_stateful_widget(list, chunks[1], &mut state);
+
+                let hint = if self.multi {
+                    "↑/↓ move · space toggle · enter confirm · esc cancel"
+                } else {
+                    "↑/↓ move · enter select · esc cancel"
+                };
+                f.render_widget(Paragraph::new(hint).style(hint_style), chunks[2]);
+                f.set_cursor_position((chunks[0].x + 2 + visible_length(&self.filter) as u16, chunks[0].y));
+            })?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            match (key.code, key.modifiers) {
+                (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => return Ok(None),
+                (KeyCode::Enter, _) => {
+                    if self.multi {
+                        let mut checked: Vec<usize> = self.checked.iter().copied().collect();
+                        checked.sort_unstable();
+                        return Ok(Some(checked));
+                    }
+                    if let Some(&i) = visible.get(self.cursor) {
+                        return Ok(Some(vec![i]));
+                    }
+                }
+                (KeyCode::Up, _) => self.cursor = self.cursor.saturating_sub(1),
+                (KeyCode::Down, _) => self.cursor += 1,
+                (KeyCode::Char(' '), _) if self.multi => {
+                    if let Some(&i) = visible.get(self.cursor) && !self.checked.remove(&i) {
+                        self.checked.insert(i);
+                    }
+                }
+                (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                    self.filter.push(c);
+                    self.cursor = 0;
+                }
+                (KeyCode::Backspace, _) => {
+                    self.filter.pop();
+                    self.cursor = 0;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Lets the user pick one item with the arrow keys, typing filters the list.
+// None if cancelled with Esc.
+pub fn select(items: &[&str], theme: &ColorTheme) -> io::Result<Option<usize>> {
+    let mut menu = SelectMenu { items, multi: false, filter: String::new(), cursor: 0, checked: HashSet::new() };
+    Ok(menu.run(theme)?.and_then(|selected| selected.first().copied()))
+}
+
+// Like select, but Space toggles items and Enter returns all checked ones
+pub fn multi_select(items: &[&str], theme: &ColorTheme) -> io::Result<Option<Vec<usize>>> {
+    let mut menu = SelectMenu { items, multi: true, filter: String::new(), cursor: 0, checked: HashSet::new() };
+    menu.run(theme)
+}
+
+// -------
+// DIALOGS
+// -------
+
+// Reset if colors are disabled
+fn crossterm_color(color_ref: &ColorRef, fallback: CrosstermColor) -> CrosstermColor {
+    if !color::is_enabled() {
+        return CrosstermColor::Reset;
+    }
+    color_ref.resolve().map_or(fallback, |c| CrosstermColor::Rgb { r: c.r, g: c.g, b: c.b })
+}
+
+// Raw mode until dropped, so errors and panics while reading don't leave the
+// terminal in it
+struct RawModeGuard;
+
+impl RawModeGuard {
+    fn enable() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(RawModeGuard)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// Reads one line on the current terminal line. Characters are echoed as
+// `mask` if given. Returns None if cancelled with Esc or Ctrl+C, or right
+// away if headless.
+fn read_dialog_line(
+    label: &str,
+    theme: &ColorTheme,
+    mask: Option<char>,
+    validator: &dyn Fn(&str) -> Result<(), String>,
+) -> io::Result<Option<String>> {
+    if is_headless() {
+        return Ok(None);
+    }
+    let mut stdout = io::stdout();
+    let mut input = String::new();
+    let mut error: Option<String> = None;
+    let _raw_mode = RawModeGuard::enable()?;
+    let result = loop {
+        let echo: String = match mask {
+            Some(mask) => input.chars().map(|_| mask).collect(),
+            None => input.clone(),
+        };
+        queue!(
+            stdout,
+            Print("\r"),
+            Clear(ClearType::CurrentLine),
+            SetForegroundColor(crossterm_color(&theme.prompt_color, CrosstermColor::Cyan)),
+            Print(format!("{} ", label)),
+            SetForegroundColor(crossterm_color(&theme.input_color, CrosstermColor::White)),
+            Print(&echo),
+        )?;
+        if let Some(error) = &error {
+            queue!(
+                stdout,
+                cursor::SavePosition,
+                SetForegroundColor(crossterm_color(&theme.hint_color, CrosstermColor::Grey)),
+                Print(format!("  ✗ {}", error)),
+                cursor::RestorePosition,
+            )?;
+        }
+        queue!(stdout, SetForegroundColor(CrosstermColor::Reset))?;
+        stdout.flush()?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => break None,
+            (KeyCode::Enter, _) => match validator(&input) {
+                Ok(()) => break Some(input),
+                Err(e) => error = Some(e),
+            },
+            (KeyCode::Backspace, _) => {
+                input.pop();
+                error = None;
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                input.push(c);
+                error = None;
+            }
+            _ => {}
+        }
+    };
+    execute!(stdout, Print("\r"), Clear(ClearType::CurrentLine))?;
+    Ok(result)
+}
+
+// "Delete world? [y/N]", true only for y
+pub fn confirm(question: &str, theme: &ColorTheme) -> io::Result<bool> {
+    let answer = read_dialog_line(&format!("{} [y/N]", question), theme, None, &|_| Ok(()))?;
+    Ok(answer.is_some_and(|a| matches!(a.trim().to_lowercase().as_str(), "y" | "yes")))
+}
+
+// Asks until the validator accepts the input, its error is shown next to it
+pub fn input_text(
+    label: &str,
+    theme: &ColorTheme,
+    validator: impl Fn(&str) -> Result<(), String>,
+) -> io::Result<Option<String>> {
+    read_dialog_line(label, theme, None, &validator)
+}
+
+// Like input_text, but echoes '*' instead of the typed characters
+pub fn input_password(label: &str, theme: &ColorTheme) -> io::Result<Option<String>> {
+    read_dialog_line(label, theme, Some('*'), &|_| Ok(()))
+}
+
+// ratatui color of a theme entry, Reset if colors are disabled
+pub fn theme_color(color_ref: &ColorRef, fallback: Color) -> Color {
+    if !color::is_enabled() {
+        return Color::Reset;
+    }
+    color_ref.resolve().map_or(fallback, |c| Color::Rgb(c.r, c.g, c.b))
+}
+
+// Helper trait to resolve ColorRef to ratatui Color
+trait ColorRefExt<'a> {
+    fn resolve(&self) -> Option<crate::color::Color>;
+}
+
+impl<'a> ColorRefExt<'a> for ColorRef<'a> {
+    fn resolve(&self) -> Option<crate::color::Color> {
+        crate::color::resolve_color_ref(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    // (command, capture) of each step, commands trimmed
+    fn steps(input: &str) -> Result<Vec<(&str, Option<&str>)>, CommandError> {
+        Ok(split_chain(input)?.into_iter().map(|s| (s.command.trim(), s.capture)).collect())
+    }
+
+    #[test]
+    fn tokenize_splits_at_whitespace_outside_quotes() {
+        assert_eq!(tokenize("give  coal 10", None).unwrap(), ["give", "coal", "10"]);
+        assert_eq!(tokenize(r#"say "hello world" x"#, None).unwrap(), ["say", "hello world", "x"]);
+        assert_eq!(tokenize(r#"say """#, None).unwrap(), ["say", ""]);
+        assert_eq!(tokenize(r#"say pre"fix x"post"#, None).unwrap(), ["say", "prefix xpost"]);
+    }
+
+    #[test]
+    fn tokenize_escapes() {
+        assert_eq!(tokenize(r#"say \"hi\""#, None).unwrap(), ["say", "\"hi\""]);
+        assert_eq!(tokenize(r"say a\;b \\", None).unwrap(), ["say", "a;b", "\\"]);
+        // Other backslashes are kept, e.g. in paths
+        assert_eq!(tokenize(r"load C:\saves\world", None).unwrap(), ["load", r"C:\saves\world"]);
+    }
+
+    #[test]
+    fn tokenize_rejects_unclosed_quotes() {
+        assert_eq!(tokenize(r#"say "hi "#, None), Err(CommandError::UnclosedQuote(r#"say "hi"#.to_string())));
+    }
+
+    #[test]
+    fn tokenize_substitutes_variables() {
+        let vars = vars(&[("name", "a b"), ("n", "3")]);
+        assert_eq!(tokenize("say {name} {n}", Some(&vars)).unwrap(), ["say", "a b", "3"]);
+        assert_eq!(tokenize(r#"say "x{n}y""#, Some(&vars)).unwrap(), ["say", "x3y"]);
+        assert_eq!(tokenize(r"say \{n}", Some(&vars)).unwrap(), ["say", "{n}"]);
+        // Not a variable reference
+        assert_eq!(tokenize("say {n {Name}", Some(&vars)).unwrap(), ["say", "{n", "{Name}"]);
+        assert_eq!(tokenize("say {n}", None).unwrap(), ["say", "{n}"]);
+    }
+
+    #[test]
+    fn tokenize_rejects_unknown_variables() {
+        let vars = vars(&[("name", "x")]);
+        assert_eq!(tokenize("say {other}", Some(&vars)), Err(CommandError::UnknownVariable("other".to_string())));
+    }
+
+    #[test]
+    fn split_chain_at_semicolons_outside_quotes() {
+        assert_eq!(steps("a; b").unwrap(), [("a", None), ("b", None)]);
+        assert_eq!(steps(r#"say "x; y"; b"#).unwrap(), [(r#"say "x; y""#, None), ("b", None)]);
+        assert_eq!(steps(r"say x\; y").unwrap(), [(r"say x\; y", None)]);
+        assert_eq!(steps("a;; b;").unwrap(), [("a", None), ("b", None)]);
+        assert_eq!(steps("").unwrap(), [("", None)]);
+    }
+
+    #[test]
+    fn split_chain_captures() {
+        assert_eq!(steps("count items -> n; say {n}").unwrap(), [("count items", Some("n")), ("say {n}", None)]);
+        // Only a separate "->" starts a capture
+        assert_eq!(steps("say a->b").unwrap(), [("say a->b", None)]);
+        assert_eq!(steps(r#"say "a -> b""#).unwrap(), [(r#"say "a -> b""#, None)]);
+    }
+
+    #[test]
+    fn split_chain_rejects_invalid_captures() {
+        assert_eq!(steps("a -> Bad").err(), Some(CommandError::InvalidCapture("Bad".to_string())));
+        assert_eq!(steps("a -> ").err(), Some(CommandError::InvalidCapture(String::new())));
+        assert_eq!(steps("-> n").err(), Some(CommandError::InvalidCapture("n".to_string())));
+    }
+}
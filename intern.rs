@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+// --------
+// INTERNER
+// --------
+
+// Global string table. Interned strings are never freed, which is fine for
+// the bounded set of namespaces and names content uses.
+static INTERNER: Lazy<RwLock<Interner>> = Lazy::new(|| RwLock::new(Interner::default()));
+
+#[derive(Default)]
+struct Interner {
+    strings: Vec<&'static str>,
+    symbols: HashMap<&'static str, Symbol>,
+}
+
+// Handle to an interned string. Copying, comparing and hashing only touch
+// the handle, the text is looked up with as_str. Handles depend on the order
+// strings were interned in, so store the text, not the handle.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn intern(s: &str) -> Symbol {
+        if let Some(symbol) = INTERNER.read().unwrap().symbols.get(s) {
+            return *symbol;
+        }
+        let mut interner = INTERNER.write().unwrap();
+        // Another thread may have interned it between the two locks
+        if let Some(symbol) = interner.symbols.get(s) {
+            return *symbol;
+        }
+        let symbol = Symbol(interner.strings.len() as u32);
+        let s: &'static str = Box::leak(s.to_string().into_boxed_str());
+        interner.strings.push(s);
+        interner.symbols.insert(s, symbol);
+        symbol
+    }
+
+    pub fn as_str(self) -> &'static str {
+        INTERNER.read().unwrap().strings[self.0 as usize]
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
@@ -0,0 +1,982 @@
+use crate::error::ParseError;
+use crate::game::{HotbarSelected, ToolBroken};
+use crate::intern::Symbol;
+use crate::localization::{TranslationID, Translator};
+use crate::random::RuzRng;
+use crate::registry::{Enchantment, ID, Item, LootStack, PatternKey, Recipe, RecipeComponent, Registry, StatModifier, Tool};
+use crate::table::{Alignment, Table};
+
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result};
+use std::sync::Arc;
+
+// Share of the max durability added on top when two tools are combined
+pub const REPAIR_BONUS: f32 = 0.05;
+
+// Fixed positions selectable with the number keys 1-9
+pub const HOTBAR_SIZE: usize = 9;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InventoryError {
+    Full(ID),                                     // No free slot left for the item
+    NotEnough { item: ID, owned: u32, needed: u32 },
+    UnknownItem(ID),
+    NoSuchSlot(usize),
+    InvalidLevel { enchantment: ID, level: u32, max: u32 },
+    NotApplicable { enchantment: ID, item: ID },
+    NotSingleItem,                                // Only single items can be enchanted
+    SameSlot,
+    Mismatch(ID, ID),                             // Tools of different kinds can't be combined
+    NotRepairable(ID),
+    NotDamaged(ID),
+    NoSuchEquipmentSlot(EquipmentSlot),
+    NotEquippable { item: ID, slot: EquipmentSlot }, // Not in the slot's tag
+    UnknownEnchantment(ID),
+    InvalidStack(String),                         // Malformed serialized stack
+    Parse(ParseError),
+}
+
+impl Display for InventoryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            InventoryError::Full(item) => write!(f, "No free inventory space for {}", item),
+            InventoryError::NotEnough { item, owned, needed } => write!(f, "Not enough {} ({}/{})", item, owned, needed),
+            InventoryError::UnknownItem(item) => write!(f, "Item with ID {} does not exist", item),
+            InventoryError::NoSuchSlot(index) => write!(f, "No such slot: {}", index),
+            InventoryError::InvalidLevel { enchantment, level, max } => {
+                write!(f, "Level {} of {} is not between 1 and {}", level, enchantment, max)
+            }
+            InventoryError::NotApplicable { enchantment, item } => write!(f, "{} can't be applied to {}", enchantment, item),
+            InventoryError::NotSingleItem => write!(f, "Only single items can be enchanted"),
+            InventoryError::SameSlot => write!(f, "Can't combine a tool with itself"),
+            InventoryError::Mismatch(a, b) => write!(f, "Can't combine {} with {}", a, b),
+            InventoryError::NotRepairable(item) => write!(f, "{} can't be repaired", item),
+            InventoryError::NotDamaged(item) => write!(f, "{} isn't damaged", item),
+            InventoryError::NoSuchEquipmentSlot(slot) => write!(f, "No such equipment slot: {}", slot),
+            InventoryError::NotEquippable { item, slot } => write!(f, "{} can't be equipped as {}", item, slot),
+            InventoryError::UnknownEnchantment(enchantment) => write!(f, "Enchantment with ID {} does not exist", enchantment),
+            InventoryError::InvalidStack(stack) => write!(f, "Invalid item stack: {}", stack),
+            InventoryError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for InventoryError {}
+
+// Remaining uses of a tool, starting at Tool::durability
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Durability {
+    pub current: u32,
+    pub max: u32,
+}
+
+impl Durability {
+    pub fn new(max: u32) -> Self {
+        Durability { current: max, max }
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.current == 0
+    }
+
+    pub fn is_damaged(&self) -> bool {
+        self.current < self.max
+    }
+}
+
+impl Display for Durability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}/{}", self.current, self.max)
+    }
+}
+
+// Changes the damage a tool takes, e.g. an unbreaking enchantment
+pub trait DurabilityModifier {
+    fn modify_damage(&self, damage: u32, rng: &mut RuzRng) -> u32;
+}
+
+// Each point of damage is ignored with a chance of level / (level + 1)
+pub struct Unbreaking(pub u32);
+
+impl DurabilityModifier for Unbreaking {
+    fn modify_damage(&self, damage: u32, rng: &mut RuzRng) -> u32 {
+        let chance = 1.0 / (self.0 + 1) as f32;
+        (0..damage).filter(|_| rng.chance(chance)).count() as u32
+    }
+}
+
+#[derive(Clone)]
+pub struct Slot {
+    pub item: Item,
+    pub count: u32,
+    pub durability: Option<Durability>, // Only set for tools, which never stack
+    pub modifiers: Vec<Arc<dyn DurabilityModifier>>,
+    pub enchantments: Vec<(Enchantment, u32)>, // With level, enchanted slots never stack
+    pub max_stack: Option<u32>, // Overrides Item::stack_size, see max_stack_size
+}
+
+impl Slot {
+    pub fn new(item: Item, count: u32) -> Self {
+        Slot { item, count, durability: None, modifiers: vec![], enchantments: vec![], max_stack: None }
+    }
+
+    // A single undamaged tool, `item` is the Item registered under the tool's ID
+    pub fn tool(item: Item, tool: &Tool) -> Self {
+        Slot { durability: Some(Durability::new(tool.durability)), ..Slot::new(item, 1) }
+    }
+
+    pub fn with_modifier(mut self, modifier: impl DurabilityModifier + 'static) -> Self {
+        self.modifiers.push(Arc::new(modifier));
+        self
+    }
+
+    // Overrides the item's stack size for this stack only, e.g. 1 for a
+    // named item that shouldn't merge with others
+    pub fn with_max_stack(mut self, max_stack: u32) -> Self {
+        self.max_stack = Some(max_stack.max(1));
+        self
+    }
+
+    // 1 for tools and enchanted items, otherwise the override or the item's
+    // stack size
+    pub fn max_stack_size(&self) -> u32 {
+        if !self.is_plain() {
+            return 1;
+        }
+        self.max_stack.unwrap_or(self.item.stack_size).max(1)
+    }
+
+    pub fn is_stackable(&self) -> bool {
+        self.max_stack_size() > 1
+    }
+
+    // Same item and stack size, neither unstackable
+    pub fn stacks_with(&self, other: &Slot) -> bool {
+        self.item.id == other.item.id && self.max_stack == other.max_stack && self.is_stackable() && other.is_stackable()
+    }
+
+    // Room left before the stack is full
+    pub fn space(&self) -> u32 {
+        self.max_stack_size().saturating_sub(self.count)
+    }
+
+    // No durability or enchantments
+    fn is_plain(&self) -> bool {
+        self.durability.is_none() && self.enchantments.is_empty()
+    }
+
+    // Adds or replaces an enchantment. The item (or the tool registered under
+    // its ID) needs one of the enchantment's applicable tags.
+    pub fn enchant(&mut self, enchantment: &Enchantment, level: u32, registry: &Registry) -> std::result::Result<(), InventoryError> {
+        if level == 0 || level > enchantment.max_level {
+            return Err(InventoryError::InvalidLevel { enchantment: enchantment.id, level, max: enchantment.max_level });
+        }
+        let tool_tags = registry.tools.get(&self.item.id).map_or(&[][..], |t| &t.tags[..]);
+        if !enchantment.is_applicable_to(&self.item.tags) && !enchantment.is_applicable_to(tool_tags) {
+            return Err(InventoryError::NotApplicable { enchantment: enchantment.id, item: self.item.id });
+        }
+        if self.count > 1 {
+            return Err(InventoryError::NotSingleItem);
+        }
+        self.enchantments.retain(|(e, _)| e.id != enchantment.id);
+        self.enchantments.push((enchantment.clone(), level));
+        Ok(())
+    }
+
+    pub fn enchantment_level(&self, id: &ID) -> u32 {
+        self.enchantments.iter().find(|(e, _)| &e.id == id).map_or(0, |(_, level)| *level)
+    }
+
+    // Translated item name ("namespace:item.name") followed by the colored
+    // enchantment names, e.g. "Pickaxe (Efficiency II, Unbreaking III)"
+    pub fn display_name(&self, translator: &Translator) -> String {
+        let name = translator.translate(&TranslationID::from_id(&self.item.id, "item"), None);
+        if self.enchantments.is_empty() {
+            return name;
+        }
+        let enchantments: Vec<String> = self.enchantments.iter().map(|(e, level)| e.display_name(*level, translator)).collect();
+        format!("{} ({})", name, enchantments.join(", "))
+    }
+
+    // Sum of a modifier's value times the level over all enchantments
+    fn stat(&self, value: impl Fn(&StatModifier) -> Option<f32>) -> f32 {
+        self.enchantments
+            .iter()
+            .flat_map(|(e, level)| e.modifiers.iter().filter_map(&value).map(move |v| v * *level as f32))
+            .sum()
+    }
+
+    // Factor for Tool::speed, 1.0 without ToolSpeed enchantments
+    pub fn speed_multiplier(&self) -> f32 {
+        1.0 + self.stat(|m| match m {
+            StatModifier::ToolSpeed(v) => Some(*v),
+            _ => None,
+        })
+    }
+
+    // Number of extra loot rolls: the whole part of the LootLuck sum plus one
+    // more with the remaining fraction as chance
+    pub fn extra_loot_rolls(&self, rng: &mut RuzRng) -> u32 {
+        let luck = self.stat(|m| match m {
+            StatModifier::LootLuck(v) => Some(*v),
+            _ => None,
+        });
+        let luck = luck.max(0.0);
+        luck.trunc() as u32 + rng.chance(luck.fract()) as u32
+    }
+
+    // Applies `amount` damage after the modifiers and DurabilityUse
+    // enchantments. Returns true if the tool broke, slots without durability
+    // never do.
+    pub fn damage(&mut self, amount: u32, rng: &mut RuzRng) -> bool {
+        if self.durability.is_none() {
+            return false;
+        }
+        let mut amount = self.modifiers.iter().fold(amount, |amount, m| m.modify_damage(amount, rng));
+        let durability_use = self.stat(|m| match m {
+            StatModifier::DurabilityUse(v) => Some(*v),
+            _ => None,
+        });
+        if durability_use > 0.0 {
+            let chance = 1.0 / (1.0 + durability_use);
+            amount = (0..amount).filter(|_| rng.chance(chance)).count() as u32;
+        }
+        let Some(durability) = self.durability.as_mut() else {
+            return false;
+        };
+        durability.current = durability.current.saturating_sub(amount);
+        durability.is_broken()
+    }
+}
+
+// ---------
+// EQUIPMENT
+// ---------
+
+// One of the armor slots or a custom one, e.g. EquipmentSlot::custom("ring")
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EquipmentSlot(&'static str);
+
+impl EquipmentSlot {
+    pub const HEAD: EquipmentSlot = EquipmentSlot("head");
+    pub const CHEST: EquipmentSlot = EquipmentSlot("chest");
+    pub const LEGS: EquipmentSlot = EquipmentSlot("legs");
+    pub const FEET: EquipmentSlot = EquipmentSlot("feet");
+
+    pub const ARMOR: [EquipmentSlot; 4] = [Self::HEAD, Self::CHEST, Self::LEGS, Self::FEET];
+
+    pub fn custom(name: &str) -> EquipmentSlot {
+        EquipmentSlot(Symbol::intern(name).as_str())
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl Display for EquipmentSlot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Clone)]
+pub struct Equipment {
+    pub slot: EquipmentSlot,
+    pub tag: Option<ID>, // Only items in the tag fit, any item if None
+    pub stack: Option<Slot>,
+}
+
+impl Equipment {
+    pub fn accepts(&self, item: &ID, registry: &Registry) -> bool {
+        self.tag.is_none_or(|tag| PatternKey::Tag(tag).matches(item, registry))
+    }
+}
+
+// ---------
+// INVENTORY
+// ---------
+
+// The hotbar has fixed positions, so using up a stack never moves another
+// into the selection. The other stacks are in `slots` without gaps. Slot
+// indices count the hotbar positions first, index HOTBAR_SIZE is slots[0].
+// max_slots includes the hotbar. Equipped stacks and the offhand are kept
+// apart and don't count for crafting.
+#[derive(Clone)]
+pub struct Inventory {
+    pub owner_money: Option<u32>,
+    pub hotbar: [Option<Slot>; HOTBAR_SIZE],
+    pub slots: Vec<Slot>,
+    pub max_slots: usize,
+    pub selected: usize, // Hotbar index of the active stack
+    pub offhand: Option<Slot>,
+    pub equipment: Vec<Equipment>, // In the order of with_equipment_slot
+}
+
+impl Inventory {
+    pub fn new(owner_money: Option<u32>) -> Self {
+        Self {
+            owner_money,
+            hotbar: Default::default(),
+            slots: Vec::new(),
+            max_slots: 32,
+            selected: 0,
+            offhand: None,
+            equipment: vec![],
+        }
+    }
+
+    // Adds (or replaces the tag of) an equipment slot, e.g.
+    // with_equipment_slot(EquipmentSlot::HEAD, Some(id!("game:helmets")))
+    pub fn with_equipment_slot(mut self, slot: EquipmentSlot, tag: Option<ID>) -> Self {
+        match self.equipment.iter_mut().find(|e| e.slot == slot) {
+            Some(equipment) => equipment.tag = tag,
+            None => self.equipment.push(Equipment { slot, tag, stack: None }),
+        }
+        self
+    }
+
+    // Fills up existing stacks first. Without enough space as much as fits is
+    // added and Full is returned.
+    pub fn add_item(&mut self, item: Item, quantity: u32) -> std::result::Result<(), InventoryError> {
+        self.add_slot(Slot::new(item, quantity))
+    }
+
+    // Adds a whole slot like add_item, tools keep their durability, modifiers
+    // and enchantments and the stack its max_stack
+    pub fn add_slot(&mut self, slot: Slot) -> std::result::Result<(), InventoryError> {
+        match self.insert(slot) {
+            Some(rest) => Err(InventoryError::Full(rest.item.id)),
+            None => Ok(()),
+        }
+    }
+
+    // Adds rolled loot. Tools get one slot per item, a "durability" component
+    // sets their remaining durability and enchantment IDs as components
+    // enchant them with the component's value as level. "max_stack" (a size)
+    // and "unstackable" override the item's stack size.
+    pub fn add_loot(&mut self, stack: &LootStack, registry: &Registry) -> std::result::Result<(), InventoryError> {
+        let Some(item) = registry.items.get(&stack.item) else {
+            return Err(InventoryError::UnknownItem(stack.item));
+        };
+        let max_stack = match (stack.components.get("max_stack"), stack.components.contains_key("unstackable")) {
+            (_, true) => Some(1),
+            (Some(max_stack), false) => max_stack.parse::<u32>().ok(),
+            (None, false) => None,
+        };
+        let plain = !registry.tools.contains_key(&stack.item)
+            && !stack.components.keys().any(|k| ID::parse(k).is_ok_and(|id| registry.enchantments.contains_key(&id)));
+        if plain {
+            let slot = Slot::new(item.clone(), stack.count);
+            return self.add_slot(match max_stack {
+                Some(max_stack) => slot.with_max_stack(max_stack),
+                None => slot,
+            });
+        }
+        for _ in 0..stack.count {
+            let mut slot = match registry.tools.get(&stack.item) {
+                Some(tool) => Slot::tool(item.clone(), tool),
+                None => Slot::new(item.clone(), 1),
+            };
+            for (key, value) in &stack.components {
+                if key == "durability"
+                    && let (Some(durability), Ok(current)) = (slot.durability.as_mut(), value.parse::<u32>())
+                {
+                    durability.current = current.min(durability.max);
+                } else if let Some(enchantment) = ID::parse(key).ok().and_then(|id| registry.enchantments.get(&id))
+                    && let Ok(level) = value.parse::<u32>()
+                    && let Err(e) = slot.enchant(enchantment, level, registry)
+                {
+                    log::warn!("{}", e);
+                }
+            }
+            self.add_slot(slot)?;
+        }
+        Ok(())
+    }
+
+    // Removes as many as there are, NotEnough if that's less than `quantity`
+    pub fn remove_item(&mut self, item: &Item, quantity: u32) -> std::result::Result<(), InventoryError> {
+        let mut removed = 0;
+
+        for slot in self.stacks_mut() {
+            if slot.item.id == item.id {
+                let can_remove = (quantity - removed).min(slot.count);
+                slot.count -= can_remove;
+                removed += can_remove;
+            }
+        }
+
+        self.retain_stacks(|s| s.count > 0);
+
+        if removed < quantity {
+            return Err(InventoryError::NotEnough { item: item.id, owned: removed, needed: quantity });
+        }
+        Ok(())
+    }
+
+    pub fn total_items_of(&self, item: &Item) -> u32 {
+        self.stacks()
+            .filter(|s| s.item.id == item.id)
+            .map(|s| s.count)
+            .sum()
+    }
+
+    pub fn total_items(&self) -> u32 {
+        self.stacks().map(|s| s.count).sum()
+    }
+
+    pub fn has_item(&self, item: &Item, quantity: u32) -> bool {
+        self.total_items_of(item) >= quantity
+    }
+
+    // Slots
+
+    // Filled hotbar positions, then `slots`
+    pub fn stacks(&self) -> impl Iterator<Item = &Slot> {
+        self.hotbar.iter().flatten().chain(&self.slots)
+    }
+
+    pub fn stacks_mut(&mut self) -> impl Iterator<Item = &mut Slot> {
+        self.hotbar.iter_mut().flatten().chain(&mut self.slots)
+    }
+
+    pub fn stack_count(&self) -> usize {
+        self.stacks().count()
+    }
+
+    // The stack at a slot index, None for empty hotbar positions
+    pub fn slot(&self, index: usize) -> Option<&Slot> {
+        match index.checked_sub(HOTBAR_SIZE) {
+            None => self.hotbar[index].as_ref(),
+            Some(i) => self.slots.get(i),
+        }
+    }
+
+    pub fn slot_mut(&mut self, index: usize) -> Option<&mut Slot> {
+        match index.checked_sub(HOTBAR_SIZE) {
+            None => self.hotbar[index].as_mut(),
+            Some(i) => self.slots.get_mut(i),
+        }
+    }
+
+    // Hotbar positions within max_slots, all of them unless the inventory is
+    // smaller
+    fn hotbar_size(&self) -> usize {
+        self.max_slots.min(HOTBAR_SIZE)
+    }
+
+    // A usable hotbar position (empty or not) or a stack of `slots`
+    fn is_slot(&self, index: usize) -> bool {
+        match index.checked_sub(HOTBAR_SIZE) {
+            None => index < self.hotbar_size(),
+            Some(i) => i < self.slots.len(),
+        }
+    }
+
+    // Removes the stack at `index`. Later stacks of `slots` move up, hotbar
+    // positions stay empty.
+    fn take_slot(&mut self, index: usize) -> Option<Slot> {
+        match index.checked_sub(HOTBAR_SIZE) {
+            None => self.hotbar[index].take(),
+            Some(i) => (i < self.slots.len()).then(|| self.slots.remove(i)),
+        }
+    }
+
+    // Puts a stack back where take_slot took it from
+    fn restore_slot(&mut self, index: usize, slot: Slot) {
+        match index.checked_sub(HOTBAR_SIZE) {
+            None => self.hotbar[index] = Some(slot),
+            Some(i) => self.slots.insert(i, slot),
+        }
+    }
+
+    fn retain_stacks(&mut self, mut keep: impl FnMut(&Slot) -> bool) {
+        for position in &mut self.hotbar {
+            if position.as_ref().is_some_and(|s| !keep(s)) {
+                *position = None;
+            }
+        }
+        self.slots.retain(keep);
+    }
+
+    // Transfer
+
+    // Adds as much of the stack as fits, filling up matching stacks first,
+    // then empty hotbar positions and then `slots`, and returns the rest
+    // instead of failing like add_slot. Unstackable items take one slot each.
+    pub fn insert(&mut self, mut stack: Slot) -> Option<Slot> {
+        if stack.is_stackable() {
+            for slot in self.stacks_mut() {
+                if slot.stacks_with(&stack) {
+                    let add = stack.count.min(slot.space());
+                    slot.count += add;
+                    stack.count -= add;
+                }
+            }
+        }
+        let max_stack = stack.max_stack_size();
+        let (hotbar_size, slots_size) = (self.hotbar_size(), self.max_slots.saturating_sub(HOTBAR_SIZE));
+        while stack.count > 0 {
+            let add = Slot { count: stack.count.min(max_stack), ..stack.clone() };
+            let count = add.count;
+            match self.hotbar[..hotbar_size].iter_mut().find(|p| p.is_none()) {
+                Some(position) => *position = Some(add),
+                None if self.slots.len() < slots_size => self.slots.push(add),
+                None => break,
+            }
+            stack.count -= count;
+        }
+        (stack.count > 0).then_some(stack)
+    }
+
+    // Moves up to `amount` of the stack at `index` into `other`, e.g. from
+    // the player's inventory into a chest. What doesn't fit stays in the
+    // slot and its count is returned.
+    pub fn transfer_to(&mut self, other: &mut Inventory, index: usize, amount: u32) -> std::result::Result<u32, InventoryError> {
+        let slot = self.slot_mut(index).ok_or(InventoryError::NoSuchSlot(index))?;
+        let amount = amount.min(slot.count);
+        if amount == 0 {
+            return Ok(0);
+        }
+        let whole = amount == slot.count;
+        let moving = if whole {
+            self.take_slot(index).ok_or(InventoryError::NoSuchSlot(index))?
+        } else {
+            slot.count -= amount;
+            Slot { count: amount, ..slot.clone() }
+        };
+        let Some(overflow) = other.insert(moving) else {
+            return Ok(0);
+        };
+        let left = overflow.count;
+        if whole {
+            self.restore_slot(index, overflow);
+        } else if let Some(slot) = self.slot_mut(index) {
+            slot.count += left;
+        }
+        Ok(left)
+    }
+
+    // Shift-click: moves the whole stack at `index` into `other`, returns
+    // the count that didn't fit
+    pub fn quick_move(&mut self, other: &mut Inventory, index: usize) -> std::result::Result<u32, InventoryError> {
+        let count = self.slot(index).ok_or(InventoryError::NoSuchSlot(index))?.count;
+        self.transfer_to(other, index, count)
+    }
+
+    // Quick-moves every stack of the item, e.g. to empty a machine's output.
+    // Returns the count left behind.
+    pub fn quick_move_all(&mut self, other: &mut Inventory, id: &ID) -> u32 {
+        let mut left = 0;
+        let mut index = 0;
+        while index < HOTBAR_SIZE + self.slots.len() {
+            if self.slot(index).is_none_or(|s| &s.item.id != id) {
+                index += 1;
+                continue;
+            }
+            let len = self.slots.len();
+            let overflow = self.quick_move(other, index).unwrap_or(0);
+            left += overflow;
+            // A moved stack of `slots` leaves its index to the next one
+            if overflow > 0 || index < HOTBAR_SIZE || self.slots.len() == len {
+                index += 1;
+            }
+        }
+        left
+    }
+
+    // Hotbar
+
+    pub fn hotbar(&self) -> &[Option<Slot>] {
+        &self.hotbar[..self.hotbar_size()]
+    }
+
+    // The active stack, None if the selected hotbar position is empty
+    pub fn selected_slot(&self) -> Option<&Slot> {
+        self.hotbar.get(self.selected)?.as_ref()
+    }
+
+    pub fn selected_slot_mut(&mut self) -> Option<&mut Slot> {
+        self.hotbar.get_mut(self.selected)?.as_mut()
+    }
+
+    // Selects hotbar slot `index` (0-based). The event is None if the
+    // selection didn't change.
+    pub fn select(&mut self, index: usize) -> std::result::Result<Option<HotbarSelected>, InventoryError> {
+        if index >= HOTBAR_SIZE {
+            return Err(InventoryError::NoSuchSlot(index));
+        }
+        if index == self.selected {
+            return Ok(None);
+        }
+        let previous = std::mem::replace(&mut self.selected, index);
+        Ok(Some(HotbarSelected { previous, selected: index, item: self.selected_slot().map(|s| s.item.id) }))
+    }
+
+    // Selects by number key, 1 is the first hotbar slot
+    pub fn select_key(&mut self, key: u32) -> std::result::Result<Option<HotbarSelected>, InventoryError> {
+        match (key as usize).checked_sub(1) {
+            Some(index) => self.select(index),
+            None => Err(InventoryError::NoSuchSlot(0)),
+        }
+    }
+
+    // Moves the selection by `delta` slots, wrapping around, e.g. for the
+    // mouse wheel
+    pub fn scroll_selection(&mut self, delta: i32) -> Option<HotbarSelected> {
+        let index = (self.selected as i32 + delta).rem_euclid(HOTBAR_SIZE as i32) as usize;
+        self.select(index).ok().flatten()
+    }
+
+    // Hotbar positions may be empty, swapping one with a stack of `slots`
+    // moves the stack there
+    pub fn swap_slots(&mut self, a: usize, b: usize) -> std::result::Result<(), InventoryError> {
+        for index in [a, b] {
+            if !self.is_slot(index) {
+                return Err(InventoryError::NoSuchSlot(index));
+            }
+        }
+        let (low, high) = (a.min(b), a.max(b));
+        match (low < HOTBAR_SIZE, high < HOTBAR_SIZE) {
+            (true, true) => self.hotbar.swap(low, high),
+            (false, false) => self.slots.swap(low - HOTBAR_SIZE, high - HOTBAR_SIZE),
+            _ => match self.hotbar[low].take() {
+                Some(stack) => self.hotbar[low] = Some(std::mem::replace(&mut self.slots[high - HOTBAR_SIZE], stack)),
+                None => self.hotbar[low] = Some(self.slots.remove(high - HOTBAR_SIZE)),
+            },
+        }
+        Ok(())
+    }
+
+    // Swaps the selected hotbar position with the offhand, either may be empty
+    pub fn swap_hands(&mut self) -> std::result::Result<(), InventoryError> {
+        let position = self.hotbar.get_mut(self.selected).ok_or(InventoryError::NoSuchSlot(self.selected))?;
+        std::mem::swap(position, &mut self.offhand);
+        Ok(())
+    }
+
+    // Equipment
+
+    pub fn equipment_slot(&self, slot: EquipmentSlot) -> Option<&Equipment> {
+        self.equipment.iter().find(|e| e.slot == slot)
+    }
+
+    pub fn equipped(&self, slot: EquipmentSlot) -> Option<&Slot> {
+        self.equipment_slot(slot)?.stack.as_ref()
+    }
+
+    // Equipped stacks with their slots, in slot order
+    pub fn equipped_slots(&self) -> impl Iterator<Item = (EquipmentSlot, &Slot)> {
+        self.equipment.iter().filter_map(|e| Some((e.slot, e.stack.as_ref()?)))
+    }
+
+    // Moves the stack at `index` into the equipment slot. A stack that was
+    // equipped before takes its place.
+    pub fn equip(&mut self, index: usize, slot: EquipmentSlot, registry: &Registry) -> std::result::Result<(), InventoryError> {
+        let item = self.slot(index).ok_or(InventoryError::NoSuchSlot(index))?.item.id;
+        let equipment = self.equipment.iter().position(|e| e.slot == slot).ok_or(InventoryError::NoSuchEquipmentSlot(slot))?;
+        if !self.equipment[equipment].accepts(&item, registry) {
+            return Err(InventoryError::NotEquippable { item, slot });
+        }
+        let stack = self.take_slot(index).ok_or(InventoryError::NoSuchSlot(index))?;
+        if let Some(previous) = self.equipment[equipment].stack.replace(stack) {
+            self.restore_slot(index, previous);
+        }
+        Ok(())
+    }
+
+    // Moves the equipped stack back into the inventory, Full if there's no
+    // space for it. Ok without a stack to remove.
+    pub fn unequip(&mut self, slot: EquipmentSlot) -> std::result::Result<(), InventoryError> {
+        let equipment = self.equipment.iter_mut().find(|e| e.slot == slot).ok_or(InventoryError::NoSuchEquipmentSlot(slot))?;
+        let Some(stack) = equipment.stack.take() else {
+            return Ok(());
+        };
+        if let Err(e) = self.add_slot(stack.clone()) {
+            self.equipment.iter_mut().find(|e| e.slot == slot).unwrap().stack = Some(stack);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // Durability
+
+    // Damages the tool in slot `index`, a broken tool is removed
+    pub fn damage_tool(&mut self, index: usize, amount: u32, rng: &mut RuzRng) -> Option<ToolBroken> {
+        if !self.slot_mut(index)?.damage(amount, rng) {
+            return None;
+        }
+        Some(ToolBroken { tool: self.take_slot(index)?.item.id })
+    }
+
+    // Removes tools with no durability left, e.g. after World::break_block
+    pub fn remove_broken(&mut self) -> Vec<ToolBroken> {
+        let mut broken = vec![];
+        self.retain_stacks(|slot| {
+            let keep = !slot.durability.is_some_and(|d| d.is_broken());
+            if !keep {
+                broken.push(ToolBroken { tool: slot.item.id });
+            }
+            keep
+        });
+        broken
+    }
+
+    // Combines two damaged tools of the same kind into the first slot: their
+    // remaining durability plus REPAIR_BONUS of the max, capped at the max.
+    // The second slot is removed.
+    pub fn repair(&mut self, first: usize, second: usize) -> std::result::Result<(), InventoryError> {
+        if first == second {
+            return Err(InventoryError::SameSlot);
+        }
+        let (a, b) = match (self.slot(first), self.slot(second)) {
+            (Some(a), Some(b)) => (a, b),
+            (None, _) => return Err(InventoryError::NoSuchSlot(first)),
+            _ => return Err(InventoryError::NoSuchSlot(second)),
+        };
+        if a.item.id != b.item.id {
+            return Err(InventoryError::Mismatch(a.item.id, b.item.id));
+        }
+        let (Some(da), Some(db)) = (a.durability, b.durability) else {
+            return Err(InventoryError::NotRepairable(a.item.id));
+        };
+        if !da.is_damaged() && !db.is_damaged() {
+            return Err(InventoryError::NotDamaged(a.item.id));
+        }
+        let bonus = (da.max as f32 * REPAIR_BONUS) as u32;
+        let current = (da.current + db.current + bonus).min(da.max);
+        if let Some(slot) = self.slot_mut(first) {
+            slot.durability = Some(Durability { current, max: da.max });
+        }
+        self.take_slot(second);
+        Ok(())
+    }
+
+    // Crafting
+
+    pub fn count_of(&self, id: &ID) -> u32 {
+        self.stacks().filter(|s| &s.item.id == id).map(|s| s.count).sum()
+    }
+
+    // (ingredient, needed, owned) for every ingredient of the recipe
+    pub fn ingredient_counts(&self, recipe: &Recipe) -> Vec<(ID, u32, u32)> {
+        recipe
+            .ingredients
+            .iter()
+            .map(|c| (c.id, c.count, self.count_of(&c.id)))
+            .collect()
+    }
+
+    pub fn can_craft(&self, recipe: &Recipe) -> bool {
+        self.ingredient_counts(recipe).iter().all(|(_, needed, owned)| owned >= needed)
+    }
+
+    // Removes the ingredients and adds the rolled results, which are
+    // returned. The inventory stays unchanged if ingredients are missing, a
+    // result is not a registered item or there's no space for the results.
+    pub fn craft(
+        &mut self,
+        recipe: &Recipe,
+        registry: &Registry,
+        rng: &mut RuzRng,
+    ) -> std::result::Result<Vec<RecipeComponent>, InventoryError> {
+        if let Some((item, needed, owned)) = self.ingredient_counts(recipe).into_iter().find(|(_, n, o)| o < n) {
+            return Err(InventoryError::NotEnough { item, owned, needed });
+        }
+        let mut crafted = self.clone();
+        for component in &recipe.ingredients {
+            let Some(slot) = crafted.stacks().find(|s| s.item.id == component.id) else {
+                continue;
+            };
+            let item = slot.item.clone();
+            crafted.remove_item(&item, component.count)?;
+        }
+        let results = recipe.roll_results(rng);
+        for component in &results {
+            let item = registry.items.get(&component.id).ok_or(InventoryError::UnknownItem(component.id))?;
+            crafted.add_item(item.clone(), component.count)?;
+        }
+        *self = crafted;
+        Ok(results)
+    }
+}
+
+// Persistence
+
+impl Inventory {
+    // "inventory <max slots> <money or none>" followed by one
+    // "hotbar <position> <stack>" line per filled hotbar position and one
+    // "slot <stack>" line per other slot, where a stack is
+    // "<item> <count> [durability <current>/<max>] [enchant <id> <level>]... [max_stack <size>]".
+    // Then "selected <index>", "offhand <stack>" and one
+    // "equipment <slot> <tag or any> [<stack>]" line per equipment slot.
+    // Durability modifiers aren't saved.
+    pub fn serialize(&self) -> String {
+        let money = self.owner_money.map_or("none".to_string(), |m| m.to_string());
+        let mut output = format!("inventory {} {}\n", self.max_slots, money);
+        for (position, slot) in self.hotbar.iter().enumerate() {
+            if let Some(slot) = slot {
+                output += &format!("hotbar {} {}\n", position, serialize_stack(slot));
+            }
+        }
+        for slot in &self.slots {
+            output += &format!("slot {}\n", serialize_stack(slot));
+        }
+        if self.selected > 0 {
+            output += &format!("selected {}\n", self.selected);
+        }
+        if let Some(offhand) = &self.offhand {
+            output += &format!("offhand {}\n", serialize_stack(offhand));
+        }
+        for equipment in &self.equipment {
+            let tag = equipment.tag.map_or("any".to_string(), |t| t.to_string());
+            output += &format!("equipment {} {}", equipment.slot, tag);
+            if let Some(stack) = &equipment.stack {
+                output += &format!(" {}", serialize_stack(stack));
+            }
+            output += "\n";
+        }
+        output
+    }
+
+    // Items and enchantments are looked up in `registry`
+    pub fn deserialize(content: &str, registry: &Registry) -> std::result::Result<Inventory, InventoryError> {
+        let mut lines = content.lines().enumerate();
+        let first = lines.next().map_or("", |(_, l)| l);
+        let header: Vec<&str> = first.split_whitespace().collect();
+        let header_error = || InventoryError::Parse(ParseError::InvalidLine { line: 1, text: first.to_string() });
+        let mut inventory = match header[..] {
+            ["inventory", max_slots, money] => {
+                let max_slots = max_slots.parse::<usize>().map_err(|_| header_error())?;
+                let money = match money {
+                    "none" => None,
+                    m => Some(m.parse::<u32>().map_err(|_| header_error())?),
+                };
+                Inventory { owner_money: money, max_slots, ..Inventory::new(None) }
+            }
+            _ => return Err(InventoryError::Parse(ParseError::MissingHeader("inventory <max slots> <money>"))),
+        };
+        for (i, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = || InventoryError::Parse(ParseError::InvalidLine { line: i + 1, text: line.to_string() });
+            let mut parts = line.split_whitespace();
+            match parts.next() {
+                Some("hotbar") => {
+                    let position = parts.next().and_then(|p| p.parse::<usize>().ok()).filter(|&p| p < HOTBAR_SIZE);
+                    let position = position.ok_or_else(error)?;
+                    inventory.hotbar[position] = Some(deserialize_stack(&mut parts, registry, error)?);
+                }
+                Some("slot") => inventory.slots.push(deserialize_stack(&mut parts, registry, error)?),
+                Some("offhand") => inventory.offhand = Some(deserialize_stack(&mut parts, registry, error)?),
+                Some("selected") => {
+                    let selected = parts.next().and_then(|s| s.parse::<usize>().ok()).filter(|&s| s < HOTBAR_SIZE);
+                    inventory.selected = selected.ok_or_else(error)?;
+                }
+                Some("equipment") => {
+                    let slot = EquipmentSlot::custom(parts.next().ok_or_else(error)?);
+                    let tag = match parts.next().ok_or_else(error)? {
+                        "any" => None,
+                        t => Some(ID::parse(t).map_err(|_| error())?),
+                    };
+                    let mut parts = parts.peekable();
+                    let stack = match parts.peek() {
+                        Some(_) => Some(deserialize_stack(&mut parts, registry, error)?),
+                        None => None,
+                    };
+                    inventory = inventory.with_equipment_slot(slot, tag);
+                    inventory.equipment.last_mut().filter(|e| e.slot == slot).ok_or_else(error)?.stack = stack;
+                }
+                _ => return Err(error()),
+            }
+        }
+        Ok(inventory)
+    }
+}
+
+// Also used for slots in sync deltas
+pub(crate) fn serialize_stack(slot: &Slot) -> String {
+    let mut output = format!("{} {}", slot.item.id, slot.count);
+    if let Some(durability) = slot.durability {
+        output += &format!(" durability {}", durability);
+    }
+    for (enchantment, level) in &slot.enchantments {
+        output += &format!(" enchant {} {}", enchantment.id, level);
+    }
+    if let Some(max_stack) = slot.max_stack {
+        output += &format!(" max_stack {}", max_stack);
+    }
+    output
+}
+
+pub(crate) fn deserialize_stack<'a>(
+    parts: &mut impl Iterator<Item = &'a str>,
+    registry: &Registry,
+    error: impl Fn() -> InventoryError,
+) -> std::result::Result<Slot, InventoryError> {
+    let id = parts.next().and_then(|p| ID::parse(p).ok()).ok_or_else(&error)?;
+    let item = registry.items.get(&id).ok_or(InventoryError::UnknownItem(id))?;
+    let count = parts.next().and_then(|c| c.parse::<u32>().ok()).ok_or_else(&error)?;
+    let mut slot = Slot::new(item.clone(), count);
+    while let Some(key) = parts.next() {
+        match key {
+            "durability" => {
+                let (current, max) = parts.next().and_then(|d| d.split_once('/')).ok_or_else(&error)?;
+                let current = current.parse::<u32>().map_err(|_| error())?;
+                let max = max.parse::<u32>().map_err(|_| error())?;
+                slot.durability = Some(Durability { current, max });
+            }
+            "enchant" => {
+                let id = parts.next().and_then(|p| ID::parse(p).ok()).ok_or_else(&error)?;
+                let enchantment = registry.enchantments.get(&id).ok_or(InventoryError::UnknownEnchantment(id))?;
+                let level = parts.next().and_then(|l| l.parse::<u32>().ok()).ok_or_else(&error)?;
+                slot.enchantments.push((enchantment.clone(), level));
+            }
+            "max_stack" => {
+                let max_stack = parts.next().and_then(|m| m.parse::<u32>().ok()).ok_or_else(&error)?;
+                slot = slot.with_max_stack(max_stack);
+            }
+            _ => return Err(error()),
+        }
+    }
+    Ok(slot)
+}
+
+impl Display for Inventory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut slots: Vec<Slot> = self.stacks().cloned().collect();
+        slots.sort_by_key(|s| s.item.id.to_string().to_lowercase());
+
+        let columns = match slots.len() {
+            0..=8 => 1,
+            9..=26 => 2,
+            _ => 3,
+        };
+
+        let mut table = Table::new();
+        for _ in 0..columns {
+            table = table
+                .with_sized_column("Item", Alignment::Left, 21)
+                .with_sized_column("Amount", Alignment::Right, 7);
+        }
+
+        if slots.is_empty() {
+            table.add_row(Vec::<String>::new());
+        }
+        for chunk in slots.chunks(columns) {
+            table.add_row(chunk.iter().flat_map(|s| {
+                let amount = s.durability.map_or(format!("{}x", s.count), |d| d.to_string());
+                [s.item.id.to_string(), amount]
+            }));
+        }
+
+        table.add_footer("Total Items", &format!("{}/{}", self.total_items(), self.max_slots as u32 * 64));
+        table.add_footer("Stacks", &format!("{}/{}", self.stack_count(), self.max_slots));
+        table.add_footer("Selected", &self.selected_slot().map_or("-".into(), |s| s.item.id.to_string()));
+        table.add_footer("Money", &self.owner_money.map_or("N/A".into(), |v| v.to_string()));
+
+        write!(f, "{table}")
+    }
+}
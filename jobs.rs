@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::interface::{Command, CommandArg, TaskReporter};
+
+pub static JOBS: Lazy<Mutex<JobRegistry>> = Lazy::new(|| Mutex::new(JobRegistry::new("checkpoints")));
+
+// ----------
+// CHECKPOINT
+// ----------
+
+// Progress of a job, saved after every step so an interrupted run can resume
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    pub job: String,
+    pub position: u64,
+    pub state: String, // Job specific data, e.g. the last exported chunk
+}
+
+// Stores one checkpoint file per job in a directory
+#[derive(Clone, Debug)]
+pub struct CheckpointStore {
+    dir: PathBuf,
+}
+
+impl CheckpointStore {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        CheckpointStore { dir: dir.into() }
+    }
+
+    fn path(&self, job: &str) -> PathBuf {
+        self.dir.join(format!("{}.checkpoint", job))
+    }
+
+    pub fn save(&self, checkpoint: &Checkpoint) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first so a crash never leaves a half written checkpoint
+        let tmp = self.path(&checkpoint.job).with_extension("tmp");
+        fs::write(&tmp, format!("{}\n{}", checkpoint.position, checkpoint.state))?;
+        fs::rename(tmp, self.path(&checkpoint.job))
+    }
+
+    pub fn load(&self, job: &str) -> io::Result<Option<Checkpoint>> {
+        let content = match fs::read_to_string(self.path(job)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let (position, state) = content.split_once('\n').unwrap_or((&content, ""));
+        let position = position
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("invalid checkpoint for job '{}'", job)))?;
+        Ok(Some(Checkpoint { job: job.to_string(), position, state: state.to_string() }))
+    }
+
+    pub fn clear(&self, job: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(job)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+// ----
+// JOBS
+// ----
+
+// A long-running job split into steps, e.g. world export or datapack import
+pub trait Job {
+    fn total(&self) -> u64;
+
+    // Runs the step at `position` and returns the next position. `state` is
+    // persisted together with the position.
+    fn step(&mut self, position: u64, state: &mut String) -> Result<u64, String>;
+}
+
+pub struct JobRegistry {
+    store: CheckpointStore,
+    jobs: HashMap<String, fn() -> Box<dyn Job>>,
+}
+
+impl JobRegistry {
+    pub fn new<P: Into<PathBuf>>(checkpoint_dir: P) -> Self {
+        JobRegistry {
+            store: CheckpointStore::new(checkpoint_dir),
+            jobs: HashMap::new(),
+        }
+    }
+
+    pub fn set_store(&mut self, store: CheckpointStore) {
+        self.store = store;
+    }
+
+    pub fn register(&mut self, name: &str, factory: fn() -> Box<dyn Job>) {
+        if self.jobs.contains_key(name) {
+            panic!("Job {} already exists", name);
+        }
+        self.jobs.insert(name.to_string(), factory);
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.jobs.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    pub fn checkpoint(&self, name: &str) -> io::Result<Option<Checkpoint>> {
+        self.store.load(name)
+    }
+}
+
+// Runs a registered job, continuing from its checkpoint if one exists. The
+// checkpoint is removed once the job has finished.
+pub fn run_job(name: &str, reporter: Option<&TaskReporter>) -> Result<String, String> {
+    let (factory, store) = {
+        let jobs = JOBS.lock().unwrap();
+        let factory = *jobs.jobs.get(name).ok_or(format!("Job {} does not exist", name))?;
+        (factory, jobs.store.clone())
+    };
+
+    let mut job = factory();
+    let mut checkpoint = store
+        .load(name)
+        .map_err(|e| e.to_string())?
+        .unwrap_or(Checkpoint { job: name.to_string(), position: 0, state: String::new() });
+    let resumed_at = checkpoint.position;
+    let total = job.total();
+
+    while checkpoint.position < total {
+        // Stopping between steps keeps the checkpoint for the next run
+        if reporter.is_some_and(|r| r.is_cancelled()) {
+            return Err(format!("Job {} cancelled at {}/{}", name, checkpoint.position, total));
+        }
+        let next = job.step(checkpoint.position, &mut checkpoint.state)?;
+        if next <= checkpoint.position {
+            return Err(format!("Job {} did not advance past {}", name, checkpoint.position));
+        }
+        checkpoint.position = next;
+        store.save(&checkpoint).map_err(|e| e.to_string())?;
+        if let Some(reporter) = reporter {
+            reporter.progress(checkpoint.position.min(total), total);
+        }
+    }
+    store.clear(name).map_err(|e| e.to_string())?;
+
+    if resumed_at > 0 {
+        Ok(format!("Job {} finished (resumed at {}/{})", name, resumed_at, total))
+    } else {
+        Ok(format!("Job {} finished", name))
+    }
+}
+
+fn resume_handler(args: HashMap<String, String>, reporter: TaskReporter) -> Result<String, String> {
+    run_job(&args["job"], Some(&reporter))
+}
+
+// "resume <job>" runs a job in the background, continuing from its checkpoint
+pub fn resume_command() -> Command {
+    Command::new("resume")
+        .with_args(vec![CommandArg {
+            name: "job".to_string(),
+            arg_type: "string".to_string(),
+            range: None,
+            optional: false,
+            default: None,
+            help: None,
+        }])
+        .with_task_handler(resume_handler)
+}
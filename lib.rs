@@ -0,0 +1,71 @@
+// Toolkit for terminal games: colored text, a content registry with IDs, tags,
+// loot and recipes, inventories, quests, dialogues, localization and, with
+// the `tui` feature, an interactive command prompt and full-screen menus.
+//
+// Features:
+//   tui       - interface (command prompt, menus), tui (screens, dialogue
+//               player), input (game controls), scene (scene stack) and
+//               jobs
+//   serde     - Serialize/Deserialize for conditions and RNG state
+//   parallel  - RegistryBuilder parses content on all cores (rayon)
+//   scripting - Rhai scripts for commands, loot conditions and events
+//   net       - remote console serving the command registry over TCP and
+//               host/client sync of world, entities and inventories
+//               (implies tui)
+
+#[cfg(feature = "tui")]
+pub mod admin;
+pub mod behavior;
+pub mod color;
+pub mod combat;
+pub mod conditions;
+pub mod datapack;
+pub mod dialogue;
+pub mod docs;
+pub mod ecs;
+pub mod error;
+pub mod format;
+pub mod game;
+pub mod hud;
+#[cfg(feature = "tui")]
+pub mod input;
+pub mod intern;
+#[cfg(feature = "tui")]
+pub mod interface;
+pub mod inventory;
+#[cfg(feature = "tui")]
+pub mod jobs;
+pub mod localization;
+pub mod logging;
+pub mod minimap;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod notify;
+pub mod particles;
+pub mod pathfinding;
+pub mod pool;
+pub mod profiler;
+pub mod quests;
+pub mod random;
+pub mod recipes;
+pub mod register;
+pub mod registry;
+pub mod savegame;
+#[cfg(feature = "tui")]
+pub mod scene;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod sprite;
+pub mod stats;
+pub mod structure;
+#[cfg(feature = "net")]
+pub mod sync;
+pub mod table;
+pub mod theme;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod ui;
+pub mod world;
+pub mod worldgen;
+
+pub use error::RuztexError;
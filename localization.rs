@@ -1,6 +1,12 @@
-use std::{collections::HashMap, fs, path::Path, borrow::Cow};
+use std::{cell::RefCell, collections::{HashMap, HashSet, VecDeque}, fs, io, path::{Path, PathBuf}, borrow::Cow};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
 use regex::Regex;
-use crate::registries::ID;
+use crate::format;
+use crate::registry::ID;
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct TranslationID {
@@ -23,30 +29,88 @@ impl TranslationID {
         // TID: "namespace:category.name"
         let binding = id.clone().to_string();
         let parts: Vec<&str> = binding.splitn(2, ':').collect();
-        return Self {
+        Self {
             namespace: parts[0].to_string(),
             category: c.to_string(),
             name: parts[1].to_string(),
-        };
+        }
+    }
+
+    // Keys for command translations. `path` is the command path joined with '_',
+    // e.g. "give_all" for the subcommand "give all".
+
+    // "namespace:command.path" - description shown in help output
+    pub fn command(namespace: &str, path: &str) -> Self {
+        Self::new(namespace, "command", path)
+    }
+
+    // "namespace:command_arg.path__arg" - display name of an argument
+    pub fn command_arg(namespace: &str, path: &str, arg: &str) -> Self {
+        Self::new(namespace, "command_arg", &format!("{}__{}", path, arg))
+    }
+
+    // "namespace:command_error.key" - error message, e.g. "missing_argument"
+    pub fn command_error(namespace: &str, key: &str) -> Self {
+        Self::new(namespace, "command_error", key)
     }
+
+    // Whether `value` is a "namespace:category.name" key as lang files use
+    // them (see Translator::is_valid_identifier), usable in constants
+    pub const fn is_valid_literal(value: &str) -> bool {
+        const fn part(bytes: &[u8], start: usize, end: usize, max: usize, underscore: bool) -> bool {
+            if start == end || end - start > max {
+                return false;
+            }
+            let mut i = start;
+            while i < end {
+                if !(bytes[i].is_ascii_lowercase() || (underscore && bytes[i] == b'_')) {
+                    return false;
+                }
+                i += 1;
+            }
+            true
+        }
+        let bytes = value.as_bytes();
+        let mut colon = 0;
+        while colon < bytes.len() && bytes[colon] != b':' {
+            colon += 1;
+        }
+        let mut dot = colon;
+        while dot < bytes.len() && bytes[dot] != b'.' {
+            dot += 1;
+        }
+        dot < bytes.len()
+            && part(bytes, 0, colon, 16, false)
+            && part(bytes, colon + 1, dot, 16, true)
+            && part(bytes, dot + 1, bytes.len(), 64, true)
+    }
+
+    // "namespace:category.name"
+    pub fn parse(value: &str) -> Result<Self, LocalizationError> {
+        let invalid = || LocalizationError::InvalidTranslationId(value.to_string());
+        let (namespace, rest) = value.split_once(':').ok_or_else(invalid)?;
+        let (category, name) = rest.split_once('.').ok_or_else(invalid)?;
+        Ok(Self::new(namespace, category, name))
+    }
+}
+
+// TranslationID from a "namespace:category.name" literal, checked at compile
+// time, e.g. tid!("ruztex:item.coal")
+#[macro_export]
+macro_rules! tid {
+    ($id:literal) => {{
+        const _: () = assert!(
+            $crate::localization::TranslationID::is_valid_literal($id),
+            concat!("invalid translation ID literal \"", $id, "\", expected \"namespace:category.name\""),
+        );
+        $crate::localization::TranslationID::from($id)
+    }};
 }
 
 impl From<&str> for TranslationID {
-    /// Format: "namespace:category.name"
+    /// Format: "namespace:category.name", panics on other formats
     fn from(value: &str) -> Self {
-        let parts: Vec<&str> = value.splitn(2, ':').collect();
-        if parts.len() == 2 {
-            let namespace = parts[0].to_string();
-            let category_name: Vec<&str> = parts[1].splitn(2, '.').collect();
-            if category_name.len() == 2 {
-                return Self {
-                    namespace,
-                    category: category_name[0].to_string(),
-                    name: category_name[1].to_string(),
-                };
-            }
-        }
-        panic!("Invalid TranslationID format: '{}'. Expected format: 'namespace:category.name'", value);
+        Self::parse(value).unwrap_or_else(|e| panic!("{}", e))
     }
 }
 
@@ -56,6 +120,43 @@ impl From<String> for TranslationID {
     }
 }
 
+#[derive(Debug)]
+pub enum LocalizationError {
+    Io(PathBuf, io::Error),
+    Yaml(serde_yaml::Error),
+    InvalidTranslationId(String),
+    DuplicateLanguage(String),
+}
+
+impl Display for LocalizationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LocalizationError::Io(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            LocalizationError::Yaml(e) => write!(f, "invalid translations: {}", e),
+            LocalizationError::InvalidTranslationId(id) => {
+                write!(f, "Invalid TranslationID format: '{}'. Expected format: 'namespace:category.name'", id)
+            }
+            LocalizationError::DuplicateLanguage(code) => write!(f, "Language with code '{}' already exists", code),
+        }
+    }
+}
+
+impl Error for LocalizationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LocalizationError::Io(_, e) => Some(e),
+            LocalizationError::Yaml(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for LocalizationError {
+    fn from(e: serde_yaml::Error) -> Self {
+        LocalizationError::Yaml(e)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Language {
     pub name: String,
@@ -81,32 +182,172 @@ impl LanguageList {
         re.is_match(code)
     }
 
-    pub fn add(&mut self, name: &str, code: &str) {
+    pub fn add(&mut self, name: &str, code: &str) -> Result<(), LocalizationError> {
         // Überprüfen, ob die Sprache bereits existiert
         if self.languages.iter().any(|lang| lang.code == code) {
-            eprintln!("⚠ Language with code '{}' already exists!", code);
-            return;
+            return Err(LocalizationError::DuplicateLanguage(code.to_string()));
         }
 
         self.languages.push(Language {
             name: name.to_string(),
             code: code.to_string(),
         });
+        Ok(())
     }
 
     pub fn get(&self, code: &str) -> Option<&Language> {
         if !Self::is_valid_code(code) {
-            eprintln!("⚠ Language code '{}' is not valid! Expected format: <xx_XX> (2 lowercase + '_' + 2 uppercase letters)", code);
+            log::warn!("Language code '{}' is not valid! Expected format: <xx_XX> (2 lowercase + '_' + 2 uppercase letters)", code);
             return None;
         }
         self.languages.iter().find(|lang| lang.code == code)
     }
 }
 
+impl Default for LanguageList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Translations a mod ships for its commands and content, one YAML document
+// (same format as the lang files) per language code
+#[derive(Clone, Debug)]
+pub struct LocalizationBundle {
+    pub namespace: String,
+    pub languages: HashMap<String, String>,
+}
+
+impl LocalizationBundle {
+    pub fn new(namespace: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            languages: HashMap::new(),
+        }
+    }
+
+    pub fn with_language(mut self, code: &str, yaml: &str) -> Self {
+        self.languages.insert(code.to_string(), yaml.to_string());
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64, // Category had to be (re-)loaded from the file
+    pub evictions: u64,
+}
+
+// Categories of a language file that are loaded on first use and evicted
+// (least recently used first) once they exceed the memory budget
+#[derive(Debug)]
+struct CategoryCache {
+    path: PathBuf,
+    index: HashMap<(String, String), Vec<Range<usize>>>, // Byte ranges of each category's entries in the file
+    budget: usize,                                       // Bytes of keys + translations
+    used: usize,
+    categories: HashMap<(String, String), (HashMap<TranslationID, String>, usize)>,
+    lru: VecDeque<(String, String)>, // Least recently used first
+    stats: CacheStats,
+    removed: HashSet<String>,         // Namespaces not loaded from the file anymore
+}
+
+impl CategoryCache {
+    fn get(&mut self, id: &TranslationID) -> Option<String> {
+        if self.removed.contains(&id.namespace) {
+            return None;
+        }
+        let key = (id.namespace.clone(), id.category.clone());
+        if self.categories.contains_key(&key) {
+            self.stats.hits += 1;
+            self.lru.retain(|k| k != &key);
+        } else {
+            self.stats.misses += 1;
+            self.load(&key);
+        }
+        self.lru.push_back(key.clone());
+        self.categories.get(&key)?.0.get(id).cloned()
+    }
+
+    // Reads and parses only the entries of the category, see index_categories
+    fn read_category(&self, key: &(String, String)) -> Result<HashMap<TranslationID, String>, String> {
+        let Some(ranges) = self.index.get(key) else {
+            return Ok(HashMap::new());
+        };
+        let mut file = File::open(&self.path).map_err(|e| e.to_string())?;
+        let mut content = Vec::new();
+        for range in ranges {
+            let mut entry = vec![0; range.len()];
+            file.seek(SeekFrom::Start(range.start as u64)).and_then(|_| file.read_exact(&mut entry)).map_err(|e| e.to_string())?;
+            content.extend(entry);
+            if content.last() != Some(&b'\n') {
+                content.push(b'\n');
+            }
+        }
+        let content = String::from_utf8(content).map_err(|e| e.to_string())?;
+        Translator::parse(&content).map_err(|e| e.to_string())
+    }
+
+    fn load(&mut self, key: &(String, String)) {
+        let translations: HashMap<TranslationID, String> = match self.read_category(key) {
+            Ok(all) => all.into_iter().filter(|(id, _)| id.namespace == key.0 && id.category == key.1).collect(),
+            Err(e) => {
+                log::warn!("Could not load translations from {}: {}", self.path.display(), e);
+                HashMap::new()
+            }
+        };
+        let size = translations
+            .iter()
+            .map(|(id, t)| id.namespace.len() + id.category.len() + id.name.len() + t.len())
+            .sum();
+        self.used += size;
+        self.categories.insert(key.clone(), (translations, size));
+
+        // The category that was just loaded is not in the LRU list yet, so it is always kept
+        while self.used > self.budget && !self.lru.is_empty() {
+            let oldest = self.lru.pop_front().unwrap();
+            if let Some((_, size)) = self.categories.remove(&oldest) {
+                self.used -= size;
+                self.stats.evictions += 1;
+            }
+        }
+    }
+}
+
+// Byte ranges of the entries of each category in a flat language file. An
+// entry is a top-level "namespace:category.name: ..." line with the indented
+// lines below it, e.g. of a block scalar.
+fn index_categories(content: &str) -> HashMap<(String, String), Vec<Range<usize>>> {
+    let mut index: HashMap<(String, String), Vec<Range<usize>>> = HashMap::new();
+    let mut current: Option<((String, String), usize)> = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        // Blank, comment and indented lines belong to the current entry
+        if line.starts_with([' ', '\t', '#', '\r', '\n']) {
+            continue;
+        }
+        if let Some((key, from)) = current.take() {
+            index.entry(key).or_default().push(from..start);
+        }
+        let key = line.split_once(": ").map_or(line.trim_end().trim_end_matches(':'), |(key, _)| key);
+        if let Ok(id) = TranslationID::parse(key.trim_matches(['"', '\''])) {
+            current = Some(((id.namespace, id.category), start));
+        }
+    }
+    if let Some((key, from)) = current {
+        index.entry(key).or_default().push(from..offset);
+    }
+    index
+}
+
 #[derive(Debug)]
 pub struct Translator {
     pub language: Language,
     pub translations: HashMap<TranslationID, String>,
+    cache: Option<RefCell<CategoryCache>>,
 }
 
 impl Translator {
@@ -116,10 +357,82 @@ impl Translator {
         re.is_match(identifier)
     }
 
-    pub fn load<P: AsRef<Path>>(language: Language, path: P) -> Result<Self, Box<dyn std::error::Error>> {
-        let content = fs::read_to_string(path)?;
+    pub fn load<P: AsRef<Path>>(language: Language, path: P) -> Result<Self, LocalizationError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| LocalizationError::Io(path.to_path_buf(), e))?;
+        let mut translations = Self::embedded(&language.code);
+        translations.extend(Self::parse(&content)?);
+
+        Ok(Self { language, translations, cache: None })
+    }
+
+    // Loads categories of the file on first use instead of all at once, keeping at
+    // most `budget` bytes of translations in memory. Only the embedded translations
+    // are held in `translations`. The file is scanned once here for where each
+    // category's entries are, a miss then only reads and parses those.
+    pub fn load_lazy<P: AsRef<Path>>(language: Language, path: P, budget: usize) -> Self {
+        let translations = Self::embedded(&language.code);
+        let path = path.as_ref();
+        let index = match fs::read_to_string(path) {
+            Ok(content) => index_categories(&content),
+            Err(e) => {
+                log::warn!("Could not load translations from {}: {}", path.display(), e);
+                HashMap::new()
+            }
+        };
+        let cache = CategoryCache {
+            path: path.to_path_buf(),
+            index,
+            budget,
+            used: 0,
+            categories: HashMap::new(),
+            lru: VecDeque::new(),
+            stats: CacheStats::default(),
+            removed: HashSet::new(),
+        };
+        Self { language, translations, cache: Some(RefCell::new(cache)) }
+    }
+
+    // Hit/miss statistics of a lazily loading translator
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|c| c.borrow().stats)
+    }
+
+    fn lookup(&self, id: &TranslationID) -> Option<String> {
+        if let Some(cache) = &self.cache
+            && let Some(translation) = cache.borrow_mut().get(id)
+        {
+            return Some(translation);
+        }
+        self.translations.get(id).cloned()
+    }
+
+    // Like load, but falls back to the embedded translations if the file is missing or broken
+    pub fn load_or_default<P: AsRef<Path>>(language: Language, path: P) -> Self {
+        let path = path.as_ref();
+        match Self::load(language.clone(), path) {
+            Ok(translator) => translator,
+            Err(e) => {
+                log::warn!("Could not load language file {}: {} - using built-in translations", path.display(), e);
+                let translations = Self::embedded(&language.code);
+                Self { language, translations, cache: None }
+            }
+        }
+    }
+
+    // Translations for the crate's own namespace, compiled into the binary
+    pub fn embedded(code: &str) -> HashMap<TranslationID, String> {
+        let content = match code {
+            "en_US" => include_str!("lang/embedded/en_US.yaml"),
+            "de_DE" => include_str!("lang/embedded/de_DE.yaml"),
+            _ => return HashMap::new(),
+        };
+        Self::parse(content).expect("embedded translations must be valid")
+    }
+
+    fn parse(content: &str) -> Result<HashMap<TranslationID, String>, LocalizationError> {
         // Kompakte flache Map: key = "namespace.category:name"
-        let raw_yaml: HashMap<String, String> = serde_yaml::from_str(&content)?;
+        let raw_yaml: HashMap<String, String> = serde_yaml::from_str(content)?;
 
         let mut translations = HashMap::new();
 
@@ -129,11 +442,60 @@ impl Translator {
             } else {
                 // Ungültiges Format, überspringen oder Fehler?
                 // Hier überspringen:
-                eprintln!("Ungültiger Key in Übersetzungen: {}", key);
+                log::warn!("Invalid translation key: {}", key);
             }
         }
 
-        Ok(Self { language, translations })
+        Ok(translations)
+    }
+
+    // Adds translations contributed by a mod. Keys outside the mod's namespace are
+    // skipped so mods can't overwrite each other's texts.
+    pub fn merge(&mut self, namespace: &str, translations: HashMap<TranslationID, String>) {
+        if let Some(cache) = &self.cache {
+            cache.borrow_mut().removed.remove(namespace);
+        }
+        for (id, translation) in translations {
+            if id.namespace != namespace {
+                log::warn!("Skipping translation {}:{}.{} from namespace '{}'", id.namespace, id.category, id.name, namespace);
+                continue;
+            }
+            self.translations.insert(id, translation);
+        }
+    }
+
+    // Drops the translations of an unloaded mod, returns how many were removed.
+    // A lazy translator also stops loading them from its file until the
+    // namespace is merged again.
+    pub fn remove_namespace(&mut self, namespace: &str) -> usize {
+        let before = self.translations.len();
+        self.translations.retain(|id, _| id.namespace != namespace);
+        if let Some(cache) = &self.cache {
+            let mut cache = cache.borrow_mut();
+            cache.removed.insert(namespace.to_string());
+            let keys: Vec<(String, String)> = cache.categories.keys().filter(|(ns, _)| ns == namespace).cloned().collect();
+            for key in keys {
+                if let Some((_, size)) = cache.categories.remove(&key) {
+                    cache.used -= size;
+                }
+                cache.lru.retain(|k| k != &key);
+            }
+        }
+        before - self.translations.len()
+    }
+
+    // Merges the bundle's translations for the active language, falling back to
+    // en_US if the mod doesn't ship that language
+    pub fn load_bundle(&mut self, bundle: &LocalizationBundle) -> Result<(), LocalizationError> {
+        let content = bundle
+            .languages
+            .get(&self.language.code)
+            .or_else(|| bundle.languages.get("en_US"));
+        if let Some(content) = content {
+            let translations = Self::parse(content)?;
+            self.merge(&bundle.namespace, translations);
+        }
+        Ok(())
     }
 
     pub fn set_language(&mut self, language: Language) {
@@ -141,26 +503,94 @@ impl Translator {
     }
 
     pub fn translate<'a>(&self, id: &TranslationID, vars: Option<&HashMap<&str, Cow<'a, str>>>) -> String {
-        if let Some(translation) = self.translations.get(id) {
-            if let Some(vars) = vars {
-                let re = Regex::new(r"%(\{([a-z][a-zA-Z0-9_]*)\}|[a-zA-Z0-9])").unwrap();
-                re.replace_all(translation, |caps: &regex::Captures| {
-                    let key = if let Some(m) = caps.get(2) {
-                        m.as_str()
-                    } else {
-                        caps.get(1).unwrap().as_str()
-                    };
-
-                    match vars.get(key) {
-                        Some(val) => Cow::Borrowed(val.as_ref()),
-                        None => Cow::Owned(caps.get(0).unwrap().as_str().to_owned()),
-                    }
-                }).into_owned()
-            } else {
-                translation.clone()
-            }
+        self.try_translate(id, vars).unwrap_or_else(|| format!("{}:{}.{}", id.namespace, id.category, id.name))
+    }
+
+    // Like translate, but None instead of the key if there is no translation.
+    // Typed placeholders like "%{count:number}" format their variable for the
+    // language, see format::format_value.
+    pub fn try_translate<'a>(&self, id: &TranslationID, vars: Option<&HashMap<&str, Cow<'a, str>>>) -> Option<String> {
+        let translation = self.lookup(id)?;
+        let translated = if let Some(vars) = vars {
+            let re = Regex::new(r"%(\{([a-z][a-zA-Z0-9_]*)(?::([a-z]+))?\}|[a-zA-Z0-9])").unwrap();
+            re.replace_all(&translation, |caps: &regex::Captures| {
+                let key = if let Some(m) = caps.get(2) {
+                    m.as_str()
+                } else {
+                    caps.get(1).unwrap().as_str()
+                };
+
+                match (vars.get(key), caps.get(3)) {
+                    (Some(val), Some(kind)) => match format::format_value(kind.as_str(), val, &self.language.code) {
+                        Some(formatted) => Cow::Owned(formatted),
+                        None => Cow::Borrowed(val.as_ref()),
+                    },
+                    (Some(val), None) => Cow::Borrowed(val.as_ref()),
+                    (None, _) => Cow::Owned(caps.get(0).unwrap().as_str().to_owned()),
+                }
+            }).into_owned()
         } else {
-            format!("{}:{}.{}", id.namespace, id.category, id.name)
+            translation
+        };
+        Some(translated)
+    }
+}
+// ------------------
+// TRANSLATION MEMORY
+// ------------------
+
+#[derive(Clone, Debug)]
+pub struct TranslationSuggestion {
+    pub id: TranslationID,
+    pub source: String,      // Similar existing source string
+    pub translation: String, // Its translation in the target language
+    pub similarity: f32,     // 0.0 - 1.0
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j + 1] + 1).min(curr[j] + 1).min(prev[j] + cost);
         }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+pub fn similarity(a: &str, b: &str) -> f32 {
+    let a = a.to_lowercase();
+    let b = b.to_lowercase();
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 1.0;
     }
-}
\ No newline at end of file
+    1.0 - levenshtein(&a, &b) as f32 / max_len as f32
+}
+
+/// Suggests translations for `text` by looking up similar source strings in `source`
+/// and returning what they were translated to in `target`, best match first.
+pub fn suggest(source: &Translator, target: &Translator, text: &str, min_similarity: f32) -> Vec<TranslationSuggestion> {
+    let mut suggestions: Vec<TranslationSuggestion> = source
+        .translations
+        .iter()
+        .filter_map(|(id, src)| {
+            let translation = target.translations.get(id)?;
+            let similarity = similarity(text, src);
+            (similarity >= min_similarity).then(|| TranslationSuggestion {
+                id: id.clone(),
+                source: src.clone(),
+                translation: translation.clone(),
+                similarity,
+            })
+        })
+        .collect();
+
+    suggestions.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    suggestions
+}
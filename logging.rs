@@ -0,0 +1,159 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+
+use crate::color::{self, ColorRef, colored_text};
+use crate::theme::current_theme;
+
+// -------
+// LOGGING
+// -------
+
+// Logger behind the `log` macros (log::warn!, log::info!, ...), set up with
+// init. Lines look like
+//
+//   12:04:31 WARN  ruztex::utils: No free inventory space for ruztex:coal!
+//
+// with the level colored on the console. While an InteractivePrompt is on
+// screen, console lines are captured and shown in its output pane instead.
+static LOGGER: Lazy<Logger> = Lazy::new(|| Logger {
+    config: RwLock::new(LogConfig::new()),
+    file: Mutex::new(None),
+    capturing: AtomicBool::new(false),
+    captured: Mutex::new(vec![]),
+});
+
+#[derive(Clone, Debug)]
+pub struct LogConfig {
+    pub level: LevelFilter,
+    pub colored: bool,         // Colored console output (if color::is_enabled), files are always plain
+    pub file: Option<PathBuf>, // Appended to in addition to the console
+}
+
+impl LogConfig {
+    pub fn new() -> Self {
+        LogConfig { level: LevelFilter::Info, colored: true, file: None }
+    }
+
+    pub fn with_level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn with_colors(mut self, colored: bool) -> Self {
+        self.colored = colored;
+        self
+    }
+
+    pub fn with_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.file = Some(path.as_ref().to_path_buf());
+        self
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Installs the logger, or reconfigures it if it's already installed
+pub fn init(config: LogConfig) -> io::Result<()> {
+    let file = match &config.file {
+        Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+        None => None,
+    };
+    log::set_max_level(config.level);
+    *LOGGER.file.lock().unwrap() = file;
+    *LOGGER.config.write().unwrap() = config;
+    // Fails if already installed, which is fine
+    let _ = log::set_logger(&*LOGGER);
+    Ok(())
+}
+
+// While capturing, console lines are kept for take_captured instead of
+// being written to stderr, e.g. so they don't draw over a full-screen UI
+pub fn set_capture(capture: bool) {
+    LOGGER.capturing.store(capture, Ordering::SeqCst);
+    if !capture {
+        for line in take_captured() {
+            eprintln!("{}", line);
+        }
+    }
+}
+
+// Console lines logged since the last call while capturing, oldest first
+pub fn take_captured() -> Vec<String> {
+    std::mem::take(&mut *LOGGER.captured.lock().unwrap())
+}
+
+struct Logger {
+    config: RwLock<LogConfig>,
+    file: Mutex<Option<File>>,
+    capturing: AtomicBool,
+    captured: Mutex<Vec<String>>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.config.read().unwrap().level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let colored = self.config.read().unwrap().colored && color::is_enabled();
+        let time = timestamp();
+        let level = format!("{:<5}", record.level());
+        let message = format!("{}: {}", record.target(), record.args());
+
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            // A broken log file shouldn't take the game down
+            let _ = writeln!(file, "{} {} {}", time, level, message);
+        }
+
+        let line = if colored {
+            let level = colored_text(&level, &level_color(record.level())).unwrap_or(level);
+            let time = colored_text(&time, &current_theme().hint_color).unwrap_or(time);
+            format!("{} {} {}", time, level, message)
+        } else {
+            format!("{} {} {}", time, level, message)
+        };
+        if self.capturing.load(Ordering::SeqCst) {
+            self.captured.lock().unwrap().push(line);
+        } else {
+            eprintln!("{}", line);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+// From the current theme
+fn level_color(level: Level) -> ColorRef<'static> {
+    let theme = current_theme();
+    match level {
+        Level::Error => theme.error_color,
+        Level::Warn => theme.warning_color,
+        Level::Info => theme.success_color,
+        Level::Debug => theme.prompt_color,
+        Level::Trace => theme.hint_color,
+    }
+}
+
+// "HH:MM:SS" in UTC
+fn timestamp() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    format!("{:02}:{:02}:{:02}", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60)
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use crate::color::{AmbientTint, Color, ColorRef, Style, StyledText, TintOverlays, resolve_color_ref};
+use crate::ecs::{Entities, EntityId, Position};
+use crate::registry::{ID, Registry, RegistryKey};
+use crate::world::{BlockPos, BlockState, World};
+
+// Top-down view of a world region, one character per block. Rows go down
+// with increasing y. Rendered to StyledText for printing with ANSI colors,
+// or drawn directly as a ratatui widget with the `tui` feature:
+//
+//   let glyphs = GlyphTable::new()
+//       .with_block(id!("game:stone"), '#', ColorRef::Named("default", "gray"))
+//       .with_tag(id!("game:logs"), 'T', ColorRef::Named("default", "green"));
+//   let map = Minimap::new(&world, &registry, &glyphs)
+//       .following(&entities, player)
+//       .with_tint(AmbientTint::new().with_light(AmbientTint::daylight(hour)).with_weather(Weather::Rain))
+//       .with_biome_color(id!("game:swamp"), Color::from_hex("#4a5d23"), 0.3);
+//   println!("{}", map.render(40, 15));
+
+// ------
+// GLYPHS
+// ------
+
+#[derive(Clone, Debug)]
+pub struct Glyph {
+    pub symbol: char,
+    pub color: Option<ColorRef<'static>>, // None keeps the terminal's default
+}
+
+impl Glyph {
+    pub fn new(symbol: char, color: ColorRef<'_>) -> Self {
+        Glyph { symbol, color: Some(color.into_owned()) }
+    }
+
+    pub fn plain(symbol: char) -> Self {
+        Glyph { symbol, color: None }
+    }
+
+    fn style(&self) -> Style {
+        match self.color.as_ref().and_then(resolve_color_ref) {
+            Some(c) => Style::new().fg(c),
+            None => Style::new(),
+        }
+    }
+}
+
+// Glyphs for blocks and entity kinds. A block's own ID is looked up first,
+// then its tags in the order they were added. Blocks without a glyph are
+// drawn as `unknown`, positions without a block as `empty`.
+#[derive(Clone, Debug)]
+pub struct GlyphTable {
+    blocks: HashMap<ID, Glyph>,
+    tags: Vec<(ID, Glyph)>,
+    entities: HashMap<ID, Glyph>,
+    unknown: Glyph,
+    empty: Glyph,
+}
+
+impl GlyphTable {
+    pub fn new() -> Self {
+        GlyphTable {
+            blocks: HashMap::new(),
+            tags: vec![],
+            entities: HashMap::new(),
+            unknown: Glyph::plain('?'),
+            empty: Glyph::plain(' '),
+        }
+    }
+
+    pub fn with_block(mut self, block: ID, symbol: char, color: ColorRef<'_>) -> Self {
+        self.blocks.insert(block, Glyph::new(symbol, color));
+        self
+    }
+
+    pub fn with_tag(mut self, tag: ID, symbol: char, color: ColorRef<'_>) -> Self {
+        self.tags.retain(|(t, _)| *t != tag);
+        self.tags.push((tag, Glyph::new(symbol, color)));
+        self
+    }
+
+    // Entities of this kind (their registered entity ID)
+    pub fn with_entity(mut self, kind: ID, symbol: char, color: ColorRef<'_>) -> Self {
+        self.entities.insert(kind, Glyph::new(symbol, color));
+        self
+    }
+
+    pub fn with_unknown(mut self, glyph: Glyph) -> Self {
+        self.unknown = glyph;
+        self
+    }
+
+    pub fn with_empty(mut self, glyph: Glyph) -> Self {
+        self.empty = glyph;
+        self
+    }
+
+    pub fn block_glyph(&self, state: &BlockState, registry: &Registry) -> &Glyph {
+        if let Some(glyph) = self.blocks.get(&state.block) {
+            return glyph;
+        }
+        let own_tags = registry.blocks.get(&state.block).map_or(&[][..], |b| b.tags());
+        self.tags
+            .iter()
+            .find(|(tag, _)| {
+                own_tags.contains(tag) || registry.tags.get(tag).is_some_and(|t| t.contains(RegistryKey::BLOCK, &state.block))
+            })
+            .map_or(&self.unknown, |(_, glyph)| glyph)
+    }
+
+    pub fn entity_glyph(&self, kind: &ID) -> Option<&Glyph> {
+        self.entities.get(kind)
+    }
+}
+
+impl Default for GlyphTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// -------
+// MINIMAP
+// -------
+
+// Block colors go through an AmbientTint, untinted by default. Markers
+// keep their colors so they stay visible at night.
+pub struct Minimap<'a> {
+    world: &'a World,
+    registry: &'a Registry,
+    glyphs: &'a GlyphTable,
+    center: BlockPos, // Its z is the layer shown
+    markers: Vec<(BlockPos, Glyph)>,
+    tint: AmbientTint,
+    biome_colors: HashMap<ID, (Color, f64)>, // Palette color and strength by World::biome
+}
+
+impl<'a> Minimap<'a> {
+    pub fn new(world: &'a World, registry: &'a Registry, glyphs: &'a GlyphTable) -> Self {
+        Minimap {
+            world,
+            registry,
+            glyphs,
+            center: BlockPos::new(0, 0),
+            markers: vec![],
+            tint: AmbientTint::new(),
+            biome_colors: HashMap::new(),
+        }
+    }
+
+    // Light level, weather and overlays of the tint apply to every block.
+    // Its biome color is used for columns without a with_biome_color.
+    pub fn with_tint(mut self, tint: AmbientTint) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    // Tints the columns of the biome towards `color` with `strength` (0.0 - 1.0)
+    pub fn with_biome_color(mut self, biome: ID, color: Color, strength: f64) -> Self {
+        self.biome_colors.insert(biome, (color, strength));
+        self
+    }
+
+    // Switches the biome, light and weather stages of the tint on or off
+    pub fn with_overlays(mut self, overlays: TintOverlays) -> Self {
+        self.tint.overlays = overlays;
+        self
+    }
+
+    pub fn centered_on(mut self, center: BlockPos) -> Self {
+        self.center = center;
+        self
+    }
+
+    // Centers the viewport on the entity's Position and marks every entity
+    // with a glyph for its kind. Stays put if the entity has no Position.
+    pub fn following(mut self, entities: &Entities, entity: EntityId) -> Self {
+        if let Some(pos) = entities.get::<Position>(entity) {
+            self.center = BlockPos::new_3d(pos.x, pos.y, self.center.z);
+        }
+        self.with_entities(entities)
+    }
+
+    // Marks every entity with a Position and a glyph for its kind
+    pub fn with_entities(mut self, entities: &Entities) -> Self {
+        for (entity, pos) in entities.query::<Position>() {
+            if let Some(glyph) = entities.kind(entity).and_then(|kind| self.glyphs.entity_glyph(kind)) {
+                self.markers.push((BlockPos::new_3d(pos.x, pos.y, self.center.z), glyph.clone()));
+            }
+        }
+        self
+    }
+
+    // Drawn over the block at `pos`, later markers over earlier ones
+    pub fn with_marker(mut self, pos: BlockPos, glyph: Glyph) -> Self {
+        self.markers.push((pos, glyph));
+        self
+    }
+
+    // World position of the top left cell of a width x height view
+    pub fn origin(&self, width: u16, height: u16) -> BlockPos {
+        self.center.offset(-(width as i32 / 2), -(height as i32 / 2), 0)
+    }
+
+    // Symbol and style per cell, row by row
+    fn cells(&self, width: u16, height: u16) -> Vec<Vec<(char, Style)>> {
+        let origin = self.origin(width, height);
+        let mut rows: Vec<Vec<(char, Style)>> = (0..height as i32)
+            .map(|dy| {
+                (0..width as i32)
+                    .map(|dx| {
+                        let pos = origin.offset(dx, dy, 0);
+                        let glyph = match self.world.get(pos) {
+                            Some(state) => self.glyphs.block_glyph(state, self.registry),
+                            None => &self.glyphs.empty,
+                        };
+                        (glyph.symbol, self.tinted(glyph.style(), pos))
+                    })
+                    .collect()
+            })
+            .collect();
+        for (pos, glyph) in &self.markers {
+            let (dx, dy) = (pos.x - origin.x, pos.y - origin.y);
+            if pos.z == origin.z && (0..width as i32).contains(&dx) && (0..height as i32).contains(&dy) {
+                rows[dy as usize][dx as usize] = (glyph.symbol, glyph.style());
+            }
+        }
+        rows
+    }
+
+    fn tinted(&self, mut style: Style, pos: BlockPos) -> Style {
+        let Some(fg) = style.fg else {
+            return style;
+        };
+        let biome = self.world.biome(pos).and_then(|b| self.biome_colors.get(&b));
+        style.fg = Some(match biome {
+            Some(&biome) => AmbientTint { biome: Some(biome), ..self.tint.clone() }.apply(fg),
+            None => self.tint.apply(fg),
+        });
+        style
+    }
+
+    // width x height cells around the center, one line per row
+    pub fn render(&self, width: u16, height: u16) -> StyledText {
+        let mut text = StyledText::new();
+        let mut symbol = [0; 4];
+        for (i, row) in self.cells(width, height).into_iter().enumerate() {
+            if i > 0 {
+                text.push("\n", Style::new());
+            }
+            for (c, style) in row {
+                text.push(c.encode_utf8(&mut symbol), style);
+            }
+        }
+        text
+    }
+}
+
+// Fills the area, centered like render
+#[cfg(feature = "tui")]
+impl ratatui::widgets::Widget for &Minimap<'_> {
+    fn render(self, area: ratatui::layout::Rect, buf: &mut ratatui::buffer::Buffer) {
+        let colored = crate::color::is_enabled();
+        for (dy, row) in self.cells(area.width, area.height).into_iter().enumerate() {
+            for (dx, (c, style)) in row.into_iter().enumerate() {
+                let cell = &mut buf[(area.x + dx as u16, area.y + dy as u16)];
+                cell.set_char(c);
+                if colored {
+                    cell.set_style(style);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::color::{ColorRef, StyledText, colored_text};
+use crate::interface::{CommandError, CommandOutcome, CommandRegistry, Executor};
+use crate::theme::{ColorTheme, current_theme};
+
+// Remote administration: a line-based TCP server running the commands of a
+// CommandRegistry with the permissions of the client's executor. Clients
+// start as the anonymous executor, if there is one, and log in with a
+// token. Every line a client sends is one command, answered with
+//
+//   progress <current> <total> <message>   Any number, while a task runs
+//   ok <n>                                 Then n lines of output
+//   error <n>                              Then n lines of the error
+//
+// Output lines are colored like in the prompt (results and errors in the
+// theme's colors) unless the client sends "color off". Built in are
+// "login <token>", "color on|off", "help [command]" and "quit".
+//
+// Clients are disconnected after sending a line longer than MAX_LINE,
+// being idle for IDLE_TIMEOUT or MAX_FAILED_LOGINS invalid tokens; every
+// invalid token also delays the answer. Clients beyond the limit (see
+// with_max_clients) are refused with an error.
+//
+//   let server = RemoteConsole::new(commands)
+//       .with_token("s3cret", Executor::console())
+//       .with_anonymous(Executor::player("guest", 0))
+//       .start("127.0.0.1:25575")?;
+//   // $ nc localhost 25575
+//   // login s3cret
+//   // ok 1
+//   // Logged in as console
+//   server.stop();
+
+// How often the accept loop checks for stop and tasks for progress
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+// Longest command line in bytes, without the line break
+pub const MAX_LINE: usize = 4096;
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+pub const MAX_FAILED_LOGINS: u32 = 3;
+// Before answering an invalid token, against guessing
+const LOGIN_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_CLIENTS: usize = 8;
+
+#[derive(Clone)]
+pub struct RemoteConsole {
+    registry: Arc<CommandRegistry>,
+    tokens: HashMap<String, Executor>,
+    anonymous: Option<Executor>, // Before logging in, None requires a login
+    theme: ColorTheme,
+    max_clients: usize,
+}
+
+// State of one connection
+struct Session {
+    executor: Option<Executor>,
+    color: bool,
+    failed_logins: u32,
+}
+
+impl RemoteConsole {
+    pub fn new(registry: CommandRegistry) -> Self {
+        RemoteConsole {
+            registry: Arc::new(registry),
+            tokens: HashMap::new(),
+            anonymous: None,
+            theme: current_theme(),
+            max_clients: DEFAULT_MAX_CLIENTS,
+        }
+    }
+
+    // Clients sending "login <token>" run commands as `executor`
+    pub fn with_token(mut self, token: &str, executor: Executor) -> Self {
+        self.tokens.insert(token.to_string(), executor);
+        self
+    }
+
+    pub fn with_anonymous(mut self, executor: Executor) -> Self {
+        self.anonymous = Some(executor);
+        self
+    }
+
+    pub fn with_theme(mut self, theme: ColorTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    // Clients connected at the same time, 8 by default
+    pub fn with_max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = max_clients;
+        self
+    }
+
+    // Listens on `address` in the background, one thread per client
+    pub fn start(self, address: impl ToSocketAddrs) -> io::Result<RemoteConsoleHandle> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+        log::info!("Remote console listening on {}", address);
+        let stop = Arc::new(AtomicBool::new(false));
+        let clients: Arc<Mutex<Vec<TcpStream>>> = Arc::new(Mutex::new(vec![]));
+        let console = Arc::new(self);
+        let thread = {
+            let stop = stop.clone();
+            let clients = clients.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((mut stream, peer)) => {
+                            if clients.lock().unwrap().len() >= console.max_clients {
+                                log::warn!("Remote console refused {}, too many clients", peer);
+                                let _ = write!(stream, "error 1\nToo many clients\n");
+                                continue;
+                            }
+                            let accepted = stream
+                                .set_nonblocking(false)
+                                .and_then(|_| stream.set_read_timeout(Some(IDLE_TIMEOUT)))
+                                .and_then(|_| stream.try_clone());
+                            match accepted {
+                                Ok(clone) => clients.lock().unwrap().push(clone),
+                                Err(e) => {
+                                    log::warn!("Could not accept remote console client {}: {}", peer, e);
+                                    continue;
+                                }
+                            }
+                            let console = console.clone();
+                            let clients = clients.clone();
+                            thread::spawn(move || {
+                                console.serve(stream, peer);
+                                clients.lock().unwrap().retain(|c| c.peer_addr().ok() != Some(peer));
+                            });
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                        Err(e) => log::warn!("Remote console accept failed: {}", e),
+                    }
+                }
+            })
+        };
+        Ok(RemoteConsoleHandle { address, stop, clients, thread: Some(thread) })
+    }
+
+    fn serve(&self, stream: TcpStream, peer: SocketAddr) {
+        log::info!("Remote console client {} connected", peer);
+        let mut session = Session { executor: self.anonymous.clone(), color: true, failed_logins: 0 };
+        let mut out = match stream.try_clone() {
+            Ok(out) => out,
+            Err(e) => {
+                log::warn!("Remote console client {}: {}", peer, e);
+                return;
+            }
+        };
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            // One byte more than allowed, to tell long lines from the last one
+            match (&mut reader).take(MAX_LINE as u64 + 1).read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) if line.trim_end_matches(['\r', '\n']).len() > MAX_LINE => {
+                    log::warn!("Remote console client {} sent a line longer than {} bytes", peer, MAX_LINE);
+                    let _ = self.answer(&session, Err("Line too long".to_string()), &mut out);
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    log::info!("Remote console client {} timed out", peer);
+                    let _ = self.answer(&session, Err("Idle for too long".to_string()), &mut out);
+                    break;
+                }
+                Err(_) => break,
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            match self.respond(&mut session, line.trim(), &mut out) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => {
+                    log::warn!("Remote console client {}: {}", peer, e);
+                    break;
+                }
+            }
+        }
+        log::info!("Remote console client {} disconnected", peer);
+    }
+
+    // Runs one line and writes the answer, false after "quit"
+    fn respond(&self, session: &mut Session, line: &str, out: &mut impl Write) -> io::Result<bool> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words.as_slice() {
+            ["quit"] => {
+                self.answer(session, Ok("Bye".to_string()), out)?;
+                return Ok(false);
+            }
+            ["login", token] => match self.tokens.get(*token) {
+                Some(executor) => {
+                    log::info!("Remote console login as {}", executor.name);
+                    session.executor = Some(executor.clone());
+                    Ok(format!("Logged in as {}", executor.name))
+                }
+                None => {
+                    log::warn!("Remote console login with an invalid token");
+                    session.failed_logins += 1;
+                    thread::sleep(LOGIN_DELAY);
+                    if session.failed_logins >= MAX_FAILED_LOGINS {
+                        self.answer(session, Err("Too many invalid tokens".to_string()), out)?;
+                        return Ok(false);
+                    }
+                    Err("Invalid token".to_string())
+                }
+            },
+            ["color", setting @ ("on" | "off")] => {
+                session.color = *setting == "on";
+                Ok(format!("Colors {}", setting))
+            }
+            _ => match &session.executor {
+                Some(executor) => self.run(executor, line, out)?,
+                None => Err("Not logged in, use login <token>".to_string()),
+            },
+        };
+        self.answer(session, result, out)?;
+        Ok(true)
+    }
+
+    // Runs a command, reporting the progress of tasks until they finish
+    fn run(&self, executor: &Executor, line: &str, out: &mut impl Write) -> io::Result<Result<String, String>> {
+        let outcome = match CommandRegistry::is_chain(line) {
+            true => Ok(CommandOutcome::Task(self.registry.start_chain(executor, line))),
+            false => self.registry.run_command(executor, line),
+        };
+        let outcome = match outcome {
+            Ok(outcome) => outcome,
+            // "help [command]" unless a command of that name is registered
+            Err(CommandError::UnknownCommand(_)) if line.split_whitespace().next() == Some("help") => {
+                let command = line.trim_start().trim_start_matches("help");
+                return Ok(self.registry.help(executor, command, None).map_err(|e| e.to_string()));
+            }
+            Err(e) => return Ok(Err(e.to_string())),
+        };
+        let mut task = match outcome {
+            CommandOutcome::Done(result) => return Ok(Ok(result)),
+            CommandOutcome::Task(task) => task,
+        };
+        let mut reported = (None, String::new());
+        while !task.poll() {
+            if reported != (task.progress, task.message.clone()) {
+                reported = (task.progress, task.message.clone());
+                let (current, total) = task.progress.unwrap_or_default();
+                let line = format!("progress {} {} {}", current, total, task.message);
+                writeln!(out, "{}", line.trim_end())?;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        Ok(task.result.take().unwrap_or(Ok(String::new())).map_err(|e| e.to_string()))
+    }
+
+    fn answer(&self, session: &Session, result: Result<String, String>, out: &mut impl Write) -> io::Result<()> {
+        let (status, text, color) = match &result {
+            Ok(text) => ("ok", text, &self.theme.success_color),
+            Err(text) => ("error", text, &self.theme.error_color),
+        };
+        let lines: Vec<String> = text.lines().map(|line| paint(line, color, session.color)).collect();
+        writeln!(out, "{} {}", status, lines.len())?;
+        for line in lines {
+            writeln!(out, "{}", line)?;
+        }
+        out.flush()
+    }
+}
+
+// Colored with `color` unless the line has colors of its own, or without any colors
+fn paint(line: &str, color: &ColorRef, enabled: bool) -> String {
+    match enabled {
+        true if line.contains('\x1b') => line.to_string(),
+        true => colored_text(line, color).unwrap_or_else(|_| line.to_string()),
+        false => StyledText::from_ansi(line).plain_text(),
+    }
+}
+
+// A running RemoteConsole, stopped when dropped
+pub struct RemoteConsoleHandle {
+    address: SocketAddr,
+    stop: Arc<AtomicBool>,
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RemoteConsoleHandle {
+    // The bound address, e.g. to find the port after binding to port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn clients(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    // Stops accepting clients and disconnects the connected ones
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for client in self.clients.lock().unwrap().drain(..) {
+            let _ = client.shutdown(Shutdown::Both);
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+            log::info!("Remote console on {} stopped", self.address);
+        }
+    }
+}
+
+impl Drop for RemoteConsoleHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, IsTerminal, Write};
+
+use crate::game::{EventBus, ItemCrafted, SubscriptionId};
+use crate::quests::QuestCompleted;
+use crate::theme::current_theme;
+
+// Built-in notification events. Games can use their own names too.
+pub const QUEST_COMPLETED: &str = "quest_completed";
+pub const CRAFTING_FINISHED: &str = "crafting_finished";
+
+// -----
+// SINKS
+// -----
+
+// How a notification reaches the player
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotifySink {
+    Bell,    // Terminal bell (BEL)
+    Desktop, // Desktop notification through OSC 9, supported by e.g. iTerm2, kitty and Windows Terminal
+    Silent,
+}
+
+impl NotifySink {
+    // "bell", "desktop" or "silent", as in theme files
+    pub fn parse(value: &str) -> Option<NotifySink> {
+        match value.trim() {
+            "bell" => Some(NotifySink::Bell),
+            "desktop" => Some(NotifySink::Desktop),
+            "silent" => Some(NotifySink::Silent),
+            _ => None,
+        }
+    }
+
+    // Escape sequence that triggers the notification
+    fn sequence(&self, message: &str) -> String {
+        match self {
+            NotifySink::Bell => "\x07".to_string(),
+            // Control characters would end the sequence early
+            NotifySink::Desktop => format!("\x1b]9;{}\x07", message.replace(|c: char| c.is_control(), " ")),
+            NotifySink::Silent => String::new(),
+        }
+    }
+}
+
+impl Display for NotifySink {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NotifySink::Bell => write!(f, "bell"),
+            NotifySink::Desktop => write!(f, "desktop"),
+            NotifySink::Silent => write!(f, "silent"),
+        }
+    }
+}
+
+// -------------
+// NOTIFICATIONS
+// -------------
+
+// Sink per notification event, part of a ColorTheme. Events without an
+// entry are silent.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Notifications {
+    sinks: BTreeMap<String, NotifySink>,
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, event: &str, sink: NotifySink) -> Self {
+        self.set(event, sink);
+        self
+    }
+
+    pub fn set(&mut self, event: &str, sink: NotifySink) {
+        self.sinks.insert(event.to_string(), sink);
+    }
+
+    pub fn sink(&self, event: &str) -> NotifySink {
+        self.sinks.get(event).copied().unwrap_or(NotifySink::Silent)
+    }
+
+    // (event, sink) sorted by event
+    pub fn iter(&self) -> impl Iterator<Item = (&str, NotifySink)> {
+        self.sinks.iter().map(|(event, sink)| (event.as_str(), *sink))
+    }
+
+    // Writes the notification for `event` to `out`
+    pub fn notify_to(&self, out: &mut impl Write, event: &str, message: &str) -> io::Result<()> {
+        let sequence = self.sink(event).sequence(message);
+        if sequence.is_empty() {
+            return Ok(());
+        }
+        out.write_all(sequence.as_bytes())?;
+        out.flush()
+    }
+}
+
+// Notifies the player of `event` as configured by the current theme. Does
+// nothing if stdout isn't a terminal.
+pub fn notify(event: &str, message: &str) {
+    let mut stdout = io::stdout();
+    if !stdout.is_terminal() {
+        return;
+    }
+    if let Err(e) = current_theme().notifications.notify_to(&mut stdout, event, message) {
+        log::warn!("Could not send notification {}: {}", event, e);
+    }
+}
+
+// Notifies on QuestCompleted and ItemCrafted events of the bus
+pub fn attach(events: &mut EventBus) -> Vec<SubscriptionId> {
+    vec![
+        events.subscribe(|event: &QuestCompleted, _| notify(QUEST_COMPLETED, &format!("Quest completed: {}", event.quest))),
+        events.subscribe(|event: &ItemCrafted, _| notify(CRAFTING_FINISHED, &format!("Crafted {}", event.recipe))),
+    ]
+}
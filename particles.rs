@@ -0,0 +1,263 @@
+use std::time::Duration;
+
+use crate::color::{Color, ColorRef, interpolate_multi_color, resolve_color_ref};
+use crate::game::TickContext;
+use crate::random::RuzRng;
+
+// Short-lived colored glyphs drawn over a scene, e.g. sparkles when crafting
+// or damage numbers above an entity. Positions are in screen cells relative
+// to the area the particles are drawn into, velocities in cells per second.
+//
+//   let mut particles = ParticleSystem::new(rng.fork("particles"));
+//   particles.emit(&ParticleSpec::sparkles(), 12.0, 5.0);
+//   particles.emit_text(&ParticleSpec::damage(), "-7", 30.0, 8.0);
+//   game.run(|ctx| particles.update(ctx), |_| { /* frame.render_widget(&particles, area) */ });
+
+// ----
+// SPEC
+// ----
+
+// How particles of one kind look and move. Lifetime and velocities are picked
+// between the given bounds for every particle.
+#[derive(Clone, Debug)]
+pub struct ParticleSpec {
+    frames: Vec<String>, // Text over the particle's life
+    lifetime: (Duration, Duration),
+    velocity_x: (f32, f32),
+    velocity_y: (f32, f32),         // Positive is down
+    gravity: f32,                   // Added to the y velocity per second
+    colors: Vec<ColorRef<'static>>, // Gradient over the particle's life
+    count: u32,                     // Particles per emit
+}
+
+impl ParticleSpec {
+    pub fn new(frames: &[&str]) -> Self {
+        ParticleSpec {
+            frames: frames.iter().map(|f| f.to_string()).collect(),
+            lifetime: (Duration::from_secs(1), Duration::from_secs(1)),
+            velocity_x: (0.0, 0.0),
+            velocity_y: (0.0, 0.0),
+            gravity: 0.0,
+            colors: vec![],
+            count: 1,
+        }
+    }
+
+    // Stars bursting outwards and fading from yellow to dark gray
+    pub fn sparkles() -> Self {
+        ParticleSpec::new(&["*", "+", "·"])
+            .with_lifetime(Duration::from_millis(400), Duration::from_millis(900))
+            .with_velocity((-6.0, 6.0), (-4.0, 1.0))
+            .with_gravity(6.0)
+            .with_colors(&[
+                ColorRef::Named("default", "yellow"),
+                ColorRef::Named("default", "white"),
+                ColorRef::Named("default", "dark_gray"),
+            ])
+            .with_count(8)
+    }
+
+    // Text rising and fading from red to dark red, see ParticleSystem::emit_text
+    pub fn damage() -> Self {
+        ParticleSpec::new(&[])
+            .with_lifetime(Duration::from_millis(800), Duration::from_millis(800))
+            .with_velocity((-0.5, 0.5), (-3.0, -2.0))
+            .with_colors(&[ColorRef::Named("default", "red"), ColorRef::Named("default", "dark_red")])
+    }
+
+    pub fn with_lifetime(mut self, min: Duration, max: Duration) -> Self {
+        self.lifetime = (min, max.max(min));
+        self
+    }
+
+    pub fn with_velocity(mut self, x: (f32, f32), y: (f32, f32)) -> Self {
+        self.velocity_x = x;
+        self.velocity_y = y;
+        self
+    }
+
+    pub fn with_gravity(mut self, gravity: f32) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    pub fn with_colors(mut self, colors: &[ColorRef]) -> Self {
+        self.colors = colors.iter().map(|c| c.clone().into_owned()).collect();
+        self
+    }
+
+    pub fn with_count(mut self, count: u32) -> Self {
+        self.count = count;
+        self
+    }
+}
+
+// --------
+// PARTICLE
+// --------
+
+#[derive(Clone, Debug)]
+pub struct Particle {
+    pub x: f32,
+    pub y: f32,
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    pub age: Duration,
+    pub lifetime: Duration,
+    gravity: f32,
+    frames: Vec<String>,
+    colors: Vec<Color>,
+}
+
+impl Particle {
+    // 0.0 when emitted, 1.0 when it disappears
+    pub fn progress(&self) -> f32 {
+        if self.lifetime.is_zero() {
+            return 1.0;
+        }
+        (self.age.as_secs_f32() / self.lifetime.as_secs_f32()).min(1.0)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.age < self.lifetime
+    }
+
+    pub fn text(&self) -> &str {
+        let index = ((self.progress() * self.frames.len() as f32) as usize).min(self.frames.len().saturating_sub(1));
+        self.frames.get(index).map_or("", String::as_str)
+    }
+
+    // None keeps the terminal's default
+    pub fn color(&self) -> Option<Color> {
+        match self.colors.len() {
+            0 => None,
+            1 => Some(self.colors[0]),
+            _ => Some(interpolate_multi_color(&self.colors, self.progress() as f64)),
+        }
+    }
+
+    // Cell of the first character, the text is centered on x
+    pub fn cell(&self) -> (i32, i32) {
+        let width = self.text().chars().count() as f32;
+        ((self.x - width / 2.0 + 0.5).floor() as i32, self.y.floor() as i32)
+    }
+}
+
+// ------
+// SYSTEM
+// ------
+
+const DEFAULT_MAX_PARTICLES: usize = 512;
+
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    rng: RuzRng,
+    max_particles: usize, // The oldest particles make room for new ones
+}
+
+impl ParticleSystem {
+    pub fn new(rng: RuzRng) -> Self {
+        ParticleSystem { particles: vec![], rng, max_particles: DEFAULT_MAX_PARTICLES }
+    }
+
+    pub fn with_max_particles(mut self, max_particles: usize) -> Self {
+        self.max_particles = max_particles;
+        self
+    }
+
+    fn between(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * self.rng.next_f32()
+    }
+
+    fn spawn(&mut self, spec: &ParticleSpec, frames: Vec<String>, x: f32, y: f32) {
+        // Unresolvable colors are left out of the gradient
+        let colors: Vec<Color> = spec.colors.iter().filter_map(resolve_color_ref).collect();
+        for _ in 0..spec.count {
+            let (min, max) = spec.lifetime;
+            let lifetime = min + (max - min).mul_f32(self.rng.next_f32());
+            let particle = Particle {
+                x,
+                y,
+                velocity_x: self.between(spec.velocity_x),
+                velocity_y: self.between(spec.velocity_y),
+                age: Duration::ZERO,
+                lifetime,
+                gravity: spec.gravity,
+                frames: frames.clone(),
+                colors: colors.clone(),
+            };
+            self.particles.push(particle);
+        }
+        let excess = self.particles.len().saturating_sub(self.max_particles);
+        self.particles.drain(..excess);
+    }
+
+    // Emits spec's count of particles at (x, y)
+    pub fn emit(&mut self, spec: &ParticleSpec, x: f32, y: f32) {
+        self.spawn(spec, spec.frames.clone(), x, y);
+    }
+
+    // Like emit, showing `text` instead of the spec's frames, e.g. damage numbers
+    pub fn emit_text(&mut self, spec: &ParticleSpec, text: &str, x: f32, y: f32) {
+        self.spawn(spec, vec![text.to_string()], x, y);
+    }
+
+    // Moves and ages every particle, removing those past their lifetime
+    pub fn tick(&mut self, delta: Duration) {
+        let seconds = delta.as_secs_f32();
+        for particle in &mut self.particles {
+            particle.velocity_y += particle.gravity * seconds;
+            particle.x += particle.velocity_x * seconds;
+            particle.y += particle.velocity_y * seconds;
+            particle.age += delta;
+        }
+        self.particles.retain(Particle::is_alive);
+    }
+
+    // Ticks by the game loop's fixed tick duration
+    pub fn update(&mut self, ctx: &TickContext) {
+        self.tick(ctx.delta);
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+}
+
+// Draws the particles over what's already in the area, oldest first.
+// Characters outside the area are cut off.
+#[cfg(feature = "tui")]
+impl ratatui::widgets::Widget for &ParticleSystem {
+    fn render(self, area: ratatui::layout::Rect, buf: &mut ratatui::buffer::Buffer) {
+        let colored = crate::color::is_enabled();
+        for particle in &self.particles {
+            let (x, y) = particle.cell();
+            if y < 0 || y >= area.height as i32 {
+                continue;
+            }
+            for (i, symbol) in particle.text().chars().enumerate() {
+                let x = x + i as i32;
+                if x < 0 || x >= area.width as i32 {
+                    continue;
+                }
+                let cell = &mut buf[(area.x + x as u16, area.y + y as u16)];
+                cell.set_char(symbol);
+                if let Some(c) = particle.color().filter(|_| colored) {
+                    cell.set_fg(ratatui::style::Color::Rgb(c.r, c.g, c.b));
+                }
+            }
+        }
+    }
+}
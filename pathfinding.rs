@@ -0,0 +1,304 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::ecs::{Entities, EntityId, Position};
+use crate::registry::{ID, PatternKey, Registry};
+use crate::world::{BlockPos, World};
+
+// A* over the world grid between neighboring positions (4 in 2-D worlds, 6
+// in 3-D). Positions are walkable if empty or if their block is in the
+// walkable tag; top-down maps whose floors are blocks can turn off empty
+// positions. Entering a position costs 1, or the cost of the first cost tag
+// its block is in.
+//
+//   let pathfinder = Pathfinder::new().with_walkable(id!("game:passable")).with_cost(id!("game:mud"), 3.0);
+//   let path = pathfinder.find_path(&world, from, to, &registry);
+//   entities.insert(zombie, Navigator::new(goal));
+//   entities.navigate(&world, &pathfinder, &registry); // One step each, once per tick
+//   entities.block_changed(pos); // After breaking or placing, paths through it are repaired
+
+// ----
+// PATH
+// ----
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    pub positions: Vec<BlockPos>, // From the start to the goal, both included
+    pub cost: f32,
+}
+
+impl Path {
+    pub fn start(&self) -> BlockPos {
+        self.positions[0]
+    }
+
+    pub fn goal(&self) -> BlockPos {
+        self.positions[self.positions.len() - 1]
+    }
+
+    // Steps, 0 if the start is the goal
+    pub fn len(&self) -> usize {
+        self.positions.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, pos: BlockPos) -> bool {
+        self.positions.contains(&pos)
+    }
+}
+
+// ----------
+// PATHFINDER
+// ----------
+
+#[derive(Clone, Debug)]
+pub struct Pathfinder {
+    pub walkable: Option<ID>, // Tag of blocks that can be walked through
+    pub empty_walkable: bool, // Whether empty positions can be walked through, true by default
+    pub costs: Vec<(ID, f32)>,
+    pub max_nodes: usize, // Positions explored before giving up, bounds searches in open space
+}
+
+impl Default for Pathfinder {
+    fn default() -> Self {
+        Pathfinder { walkable: None, empty_walkable: true, costs: vec![], max_nodes: 10_000 }
+    }
+}
+
+impl Pathfinder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_walkable(mut self, tag: ID) -> Self {
+        self.walkable = Some(tag);
+        self
+    }
+
+    pub fn with_empty_walkable(mut self, empty_walkable: bool) -> Self {
+        self.empty_walkable = empty_walkable;
+        self
+    }
+
+    // Cost of entering blocks in the tag, at least 0.1
+    pub fn with_cost(mut self, tag: ID, cost: f32) -> Self {
+        self.costs.push((tag, cost.max(0.1)));
+        self
+    }
+
+    pub fn with_max_nodes(mut self, max_nodes: usize) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    // Cost of entering `pos`, None if it isn't walkable
+    pub fn cost(&self, world: &World, pos: BlockPos, registry: &Registry) -> Option<f32> {
+        let Some(state) = world.get(pos) else {
+            return self.empty_walkable.then_some(1.0);
+        };
+        let in_tag = |tag: ID| PatternKey::Tag(tag).matches(&state.block, registry);
+        if !self.walkable.is_some_and(in_tag) {
+            return None;
+        }
+        Some(self.costs.iter().find(|(tag, _)| in_tag(*tag)).map_or(1.0, |(_, cost)| *cost))
+    }
+
+    pub fn is_walkable(&self, world: &World, pos: BlockPos, registry: &Registry) -> bool {
+        self.cost(world, pos, registry).is_some()
+    }
+
+    // Cheapest path from `from` to `to`. The start doesn't have to be
+    // walkable (an entity stands there), the goal does. None if there is no
+    // path within max_nodes explored positions.
+    pub fn find_path(&self, world: &World, from: BlockPos, to: BlockPos, registry: &Registry) -> Option<Path> {
+        if from == to {
+            return Some(Path { positions: vec![from], cost: 0.0 });
+        }
+        if !self.is_walkable(world, to, registry) {
+            return None;
+        }
+        // Never overestimates, so the first path to reach the goal is the cheapest
+        let min_cost = self.costs.iter().map(|(_, cost)| *cost).fold(1.0, f32::min);
+        let estimate = |pos: BlockPos| {
+            let distance = (pos.x - to.x).abs() + (pos.y - to.y).abs() + (pos.z - to.z).abs();
+            distance as f32 * min_cost
+        };
+
+        let mut open = BinaryHeap::new();
+        let mut visited: HashMap<BlockPos, (f32, Option<BlockPos>)> = HashMap::new(); // Cost so far and previous position
+        open.push(Node { estimate: estimate(from), cost: 0.0, pos: from });
+        visited.insert(from, (0.0, None));
+        let mut explored = 0;
+        while let Some(Node { cost, pos, .. }) = open.pop() {
+            if pos == to {
+                let mut positions = vec![to];
+                while let Some(previous) = visited[positions.last().unwrap()].1 {
+                    positions.push(previous);
+                }
+                positions.reverse();
+                return Some(Path { positions, cost });
+            }
+            if cost > visited[&pos].0 {
+                continue; // Reached more cheaply since it was queued
+            }
+            explored += 1;
+            if explored > self.max_nodes {
+                break;
+            }
+            for next in world.neighbor_positions(pos) {
+                let Some(step) = self.cost(world, next, registry) else {
+                    continue;
+                };
+                let cost = cost + step;
+                if visited.get(&next).is_none_or(|(known, _)| cost < *known) {
+                    visited.insert(next, (cost, Some(pos)));
+                    open.push(Node { estimate: cost + estimate(next), cost, pos: next });
+                }
+            }
+        }
+        None
+    }
+}
+
+// Open position of the search, the heap pops the lowest estimate first
+struct Node {
+    estimate: f32,
+    cost: f32,
+    pos: BlockPos,
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate).then_with(|| other.pos.cmp(&self.pos))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Node {}
+
+// ----------
+// NAVIGATION
+// ----------
+
+// Component of entities walking to a goal. The path is computed on the
+// first update and only partly recomputed after blocks change: from the
+// step before the first changed position on it. Changes off the path (e.g.
+// a shortcut opening up) are ignored. Unreachable goals are tried again
+// once a block changed or the entity moved.
+#[derive(Clone, Debug)]
+pub struct Navigator {
+    goal: BlockPos,
+    path: Vec<BlockPos>,           // Remaining steps, the next one first
+    planned: bool,                 // Whether `path` leads to the goal
+    changed: Vec<BlockPos>,        // Since the last update
+    unreachable: Option<BlockPos>, // Where the last search failed
+}
+
+impl Navigator {
+    pub fn new(goal: BlockPos) -> Self {
+        Navigator { goal, path: vec![], planned: false, changed: vec![], unreachable: None }
+    }
+
+    pub fn goal(&self) -> BlockPos {
+        self.goal
+    }
+
+    // Drops the current path
+    pub fn set_goal(&mut self, goal: BlockPos) {
+        *self = Navigator::new(goal);
+    }
+
+    // Remaining steps, empty until the first update or if the goal is unreachable
+    pub fn path(&self) -> &[BlockPos] {
+        &self.path
+    }
+
+    pub fn block_changed(&mut self, pos: BlockPos) {
+        self.changed.push(pos);
+    }
+
+    // Plans or repairs the path for an entity at `position`, false if the
+    // goal can't be reached from there
+    pub fn update(&mut self, position: BlockPos, world: &World, pathfinder: &Pathfinder, registry: &Registry) -> bool {
+        if position == self.goal {
+            self.path.clear();
+            self.planned = true;
+            self.changed.clear();
+            return true;
+        }
+        if self.unreachable == Some(position) && self.changed.is_empty() {
+            return false;
+        }
+        let broken = self.path.iter().position(|pos| self.changed.contains(pos));
+        self.changed.clear();
+        let on_track = self.planned && self.path.first().is_some_and(|next| world.neighbor_positions(position).contains(next));
+        let kept = match broken {
+            _ if !on_track => 0,
+            Some(index) => index,
+            None => return true,
+        };
+        let start = if kept == 0 { position } else { self.path[kept - 1] };
+        self.path.truncate(kept);
+        match pathfinder.find_path(world, start, self.goal, registry) {
+            Some(path) => {
+                self.path.extend(path.positions.into_iter().skip(1));
+                self.planned = true;
+                self.unreachable = None;
+            }
+            None => {
+                self.path.clear();
+                self.planned = false;
+                self.unreachable = Some(position);
+            }
+        }
+        self.planned
+    }
+
+    // Updates, then takes the next step from `position`. None at the goal or
+    // if it can't be reached.
+    pub fn next_step(&mut self, position: BlockPos, world: &World, pathfinder: &Pathfinder, registry: &Registry) -> Option<BlockPos> {
+        if !self.update(position, world, pathfinder, registry) || self.path.is_empty() {
+            return None;
+        }
+        Some(self.path.remove(0))
+    }
+}
+
+impl Entities {
+    // Moves every entity with a Position and a Navigator one step toward its
+    // goal (in 2-D worlds). Returns the entities that arrived this step.
+    pub fn navigate(&mut self, world: &World, pathfinder: &Pathfinder, registry: &Registry) -> Vec<EntityId> {
+        let mut arrived = vec![];
+        let walkers: Vec<(EntityId, Position)> = self.query2::<Position, Navigator>().map(|(e, p, _)| (e, *p)).collect();
+        for (entity, position) in walkers {
+            let navigator = self.get_mut::<Navigator>(entity).unwrap();
+            let Some(next) = navigator.next_step(BlockPos::new(position.x, position.y), world, pathfinder, registry) else {
+                continue;
+            };
+            if next == navigator.goal() {
+                arrived.push(entity);
+            }
+            self.insert(entity, Position { x: next.x, y: next.y });
+        }
+        arrived
+    }
+
+    // Tells every Navigator about a broken or placed block
+    pub fn block_changed(&mut self, pos: BlockPos) {
+        self.for_each_mut::<Navigator>(|_, navigator| navigator.block_changed(pos));
+    }
+}
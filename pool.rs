@@ -0,0 +1,140 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+
+use crate::random::WeightedList;
+
+// Values that can be cleared and handed out again instead of reallocating
+pub trait Reusable: Default {
+    fn reset(&mut self);
+}
+
+impl Reusable for String {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Reusable for Vec<T> {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+impl<K, V> Reusable for HashMap<K, V> {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+impl<T> Reusable for WeightedList<T> {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PoolStats {
+    pub acquired: u64, // Values handed out
+    pub reused: u64,   // ... of which came from the pool instead of a new allocation
+}
+
+impl PoolStats {
+    pub fn reuse_rate(&self) -> f64 {
+        if self.acquired == 0 {
+            0.0
+        } else {
+            self.reused as f64 / self.acquired as f64
+        }
+    }
+}
+
+// Single-threaded pool of reusable values, e.g. one per tick loop or per prompt
+pub struct Pool<T: Reusable> {
+    free: RefCell<Vec<T>>,
+    max_free: usize,
+    stats: Cell<PoolStats>,
+}
+
+impl<T: Reusable> Pool<T> {
+    pub fn new(max_free: usize) -> Self {
+        Pool {
+            free: RefCell::new(Vec::new()),
+            max_free,
+            stats: Cell::new(PoolStats::default()),
+        }
+    }
+
+    // Hands out a cleared value; it goes back to the pool when the guard is dropped
+    pub fn get(&self) -> Pooled<'_, T> {
+        let mut stats = self.stats.get();
+        stats.acquired += 1;
+        let value = match self.free.borrow_mut().pop() {
+            Some(value) => {
+                stats.reused += 1;
+                value
+            }
+            None => T::default(),
+        };
+        self.stats.set(stats);
+        Pooled { value: Some(value), pool: self }
+    }
+
+    fn put(&self, mut value: T) {
+        let mut free = self.free.borrow_mut();
+        if free.len() < self.max_free {
+            value.reset();
+            free.push(value);
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        self.stats.get()
+    }
+
+    // Drops all pooled values, e.g. at the end of a tick after a load spike
+    pub fn clear(&self) {
+        self.free.borrow_mut().clear();
+    }
+}
+
+pub struct Pooled<'p, T: Reusable> {
+    value: Option<T>,
+    pool: &'p Pool<T>,
+}
+
+impl<T: Reusable> Pooled<'_, T> {
+    // Keeps the value instead of returning it to the pool
+    pub fn detach(mut self) -> T {
+        self.value.take().unwrap()
+    }
+}
+
+impl<T: Reusable> Deref for Pooled<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value.as_ref().unwrap()
+    }
+}
+
+impl<T: Reusable> DerefMut for Pooled<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value.as_mut().unwrap()
+    }
+}
+
+impl<T: Reusable> Drop for Pooled<'_, T> {
+    fn drop(&mut self) {
+        if let Some(value) = self.value.take() {
+            self.pool.put(value);
+        }
+    }
+}
+
+thread_local! {
+    // Scratch strings for hot paths like suggestion generation and rendering
+    pub static STRINGS: Pool<String> = Pool::new(32);
+    // Candidate entries (by index) of LootTable::roll_with
+    pub static LOOT_ENTRIES: Pool<WeightedList<usize>> = Pool::new(8);
+}
@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::color::{Color, Style, StyledText};
+
+// Time spent per system (world, AI, rendering, ...) with rolling averages
+// over the last samples. SceneManager records update, render and command
+// times into SceneContext::profiler and draws the overlay, toggled with F3
+// or the console's "profile" command; games add their own systems:
+//
+//   let start = Instant::now();
+//   behaviors.tick(&mut entities, &mut ai_context);
+//   context.profiler.record(profiler::AI, start.elapsed());
+//   let generated = context.profiler.time(profiler::WORLD, || world.generate_chunk(chunk, &registry));
+//   println!("{}", context.profiler.dump());
+
+pub const WORLD: &str = "world";
+pub const AI: &str = "ai";
+pub const UPDATE: &str = "update"; // Whole ticks, including world and AI
+pub const RENDER: &str = "render";
+pub const COMMANDS: &str = "commands";
+
+// Rolling timings of one system
+#[derive(Clone, Debug)]
+pub struct Timings {
+    pub name: String,
+    samples: VecDeque<Duration>, // The newest last
+}
+
+impl Timings {
+    fn new(name: &str) -> Self {
+        Timings { name: name.to_string(), samples: VecDeque::new() }
+    }
+
+    pub fn average(&self) -> Duration {
+        match self.samples.len() {
+            0 => Duration::ZERO,
+            len => self.samples.iter().sum::<Duration>() / len as u32,
+        }
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples.iter().max().copied().unwrap_or_default()
+    }
+
+    pub fn last(&self) -> Duration {
+        self.samples.back().copied().unwrap_or_default()
+    }
+
+    pub fn samples(&self) -> usize {
+        self.samples.len()
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[derive(Clone, Debug)]
+pub struct Profiler {
+    systems: Vec<Timings>, // In the order they were first recorded
+    window: usize,         // Samples averaged per system, 60 by default
+    budget: Option<Duration>,
+    pub visible: bool, // Whether SceneManager draws the overlay
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler { systems: vec![], window: 60, budget: None, visible: false }
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    // Time per tick, e.g. GameLoop::tick_duration. Adds each system's share
+    // of it to the overlay and dump.
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.set_budget(budget);
+        self
+    }
+
+    pub fn set_budget(&mut self, budget: Duration) {
+        self.budget = Some(budget);
+    }
+
+    pub fn budget(&self) -> Option<Duration> {
+        self.budget
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn record(&mut self, system: &str, duration: Duration) {
+        let index = match self.systems.iter().position(|t| t.name == system) {
+            Some(index) => index,
+            None => {
+                self.systems.push(Timings::new(system));
+                self.systems.len() - 1
+            }
+        };
+        let samples = &mut self.systems[index].samples;
+        samples.push_back(duration);
+        while samples.len() > self.window {
+            samples.pop_front();
+        }
+    }
+
+    // Runs `f` and records how long it took
+    pub fn time<R>(&mut self, system: &str, f: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = f();
+        self.record(system, start.elapsed());
+        result
+    }
+
+    pub fn get(&self, system: &str) -> Option<&Timings> {
+        self.systems.iter().find(|t| t.name == system)
+    }
+
+    pub fn systems(&self) -> &[Timings] {
+        &self.systems
+    }
+
+    // Forgets all samples
+    pub fn reset(&mut self) {
+        self.systems.clear();
+    }
+
+    // Average of the system as a share of the budget, e.g. 0.5 for half a tick
+    pub fn share(&self, timings: &Timings) -> Option<f64> {
+        self.budget.filter(|b| !b.is_zero()).map(|b| timings.average().as_secs_f64() / b.as_secs_f64())
+    }
+
+    // One line per system, slowest average first:
+    //   system     avg      max     last  budget
+    //   render  3.10ms   5.02ms   2.98ms     62%
+    pub fn dump(&self) -> String {
+        if self.systems.is_empty() {
+            return "No samples recorded".to_string();
+        }
+        let width = self.systems.iter().map(|t| t.name.len()).max().unwrap_or(0).max("system".len());
+        let mut lines = vec![format!("{:<width$} {:>9} {:>9} {:>9} {:>7}", "system", "avg", "max", "last", "budget")];
+        let mut systems: Vec<&Timings> = self.systems.iter().collect();
+        systems.sort_by_key(|t| std::cmp::Reverse(t.average()));
+        for timings in systems {
+            let share = self.share(timings).map_or("-".to_string(), |s| format!("{:.0}%", s * 100.0));
+            lines.push(format!(
+                "{:<width$} {:>9} {:>9} {:>9} {:>7}",
+                timings.name,
+                millis(timings.average()),
+                millis(timings.max()),
+                millis(timings.last()),
+                share
+            ));
+        }
+        lines.join("\n")
+    }
+
+    // "name avg" per system in recording order, systems taking more than
+    // half the budget in yellow, more than all of it in red
+    pub fn render(&self) -> StyledText {
+        let width = self.systems.iter().map(|t| t.name.len()).max().unwrap_or(0);
+        let mut text = StyledText::new();
+        for (i, timings) in self.systems.iter().enumerate() {
+            if i > 0 {
+                text.push("\n", Style::new());
+            }
+            let style = match self.share(timings) {
+                Some(share) if share > 1.0 => Style::new().fg(Color::from_hex("#d9372b")),
+                Some(share) if share > 0.5 => Style::new().fg(Color::from_hex("#e8c33a")),
+                _ => Style::new(),
+            };
+            text.push(&format!("{:<width$} {:>8}", timings.name, millis(timings.average())), style);
+        }
+        text
+    }
+}
+
+// Clears the area and draws the lines from its top left
+#[cfg(feature = "tui")]
+impl ratatui::widgets::Widget for &Profiler {
+    fn render(self, area: ratatui::layout::Rect, buf: &mut ratatui::buffer::Buffer) {
+        ratatui::widgets::Widget::render(ratatui::widgets::Clear, area, buf);
+        ratatui::widgets::Widget::render(ratatui::text::Text::from(self.render()), area, buf);
+    }
+}
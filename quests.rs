@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::conditions::ConditionContext;
+use crate::error::ParseError;
+use crate::game::{BlockBroken, ItemCrafted};
+use crate::random::RuzRng;
+use crate::registry::{Criterion, ID, LootStack, Quest, Registry, RegistryKey};
+
+// ------
+// EVENTS
+// ------
+
+#[derive(Clone, Debug)]
+pub struct QuestCompleted {
+    pub player: String,
+    pub quest: ID,
+    pub rewards: Vec<LootStack>, // Rolled from the quest's reward loot table
+}
+
+// ---------
+// QUEST LOG
+// ---------
+
+// Quest progress of one player profile. Criteria only count while a quest is
+// available, i.e. all its prerequisites are completed and, for quests with
+// Quest::manual_start, it was started.
+#[derive(Clone, Debug)]
+pub struct QuestLog {
+    pub player: String,
+    progress: HashMap<ID, Vec<u32>>, // Per quest, one count per criterion
+    completed: Vec<ID>,              // In completion order
+    started: Vec<ID>,                // Quests with manual_start, in start order
+}
+
+impl QuestLog {
+    pub fn new(player: &str) -> Self {
+        QuestLog { player: player.to_string(), progress: HashMap::new(), completed: vec![], started: vec![] }
+    }
+
+    pub fn is_completed(&self, quest: &ID) -> bool {
+        self.completed.contains(quest)
+    }
+
+    pub fn completed(&self) -> &[ID] {
+        &self.completed
+    }
+
+    pub fn is_started(&self, quest: &ID) -> bool {
+        self.started.contains(quest)
+    }
+
+    // Makes a quest with manual_start available once its prerequisites are
+    // completed. False if it was already started or completed.
+    pub fn start(&mut self, quest: &ID) -> bool {
+        if self.is_started(quest) || self.is_completed(quest) {
+            return false;
+        }
+        self.started.push(*quest);
+        true
+    }
+
+    pub fn is_available(&self, quest: &Quest) -> bool {
+        !self.is_completed(&quest.id)
+            && (!quest.manual_start || self.is_started(&quest.id))
+            && quest.prerequisites.iter().all(|p| self.is_completed(p))
+    }
+
+    // Available quests, sorted by ID
+    pub fn available<'a>(&self, registry: &'a Registry) -> Vec<&'a Quest> {
+        let mut quests: Vec<&Quest> = registry.quests.values().filter(|q| self.is_available(q)).collect();
+        quests.sort_by_key(|q| q.id.to_string());
+        quests
+    }
+
+    // (criterion, counted so far) for every criterion of the quest
+    pub fn progress<'a>(&self, quest: &'a Quest) -> Vec<(&'a Criterion, u32)> {
+        let counts = self.progress.get(&quest.id);
+        quest
+            .criteria
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let count = if self.is_completed(&quest.id) { c.count() } else { counts.and_then(|p| p.get(i)).copied().unwrap_or(0) };
+                (c, count)
+            })
+            .collect()
+    }
+
+    // The context is tested against the quests' conditions
+
+    pub fn item_collected(&mut self, item: &ID, count: u32, context: &ConditionContext, registry: &Registry, rng: &mut RuzRng) -> Vec<QuestCompleted> {
+        self.advance(registry, context, rng, count, |c| matches!(c, Criterion::Collect { item: i, .. } if i == item))
+    }
+
+    pub fn block_broken(&mut self, event: &BlockBroken, context: &ConditionContext, registry: &Registry, rng: &mut RuzRng) -> Vec<QuestCompleted> {
+        let block = &event.block;
+        let has_tag = |tag: &ID| {
+            registry.blocks.get(block).is_some_and(|b| b.tags.contains(tag))
+                || registry.tags.get(tag).is_some_and(|t| t.contains(RegistryKey::BLOCK, block))
+        };
+        self.advance(registry, context, rng, 1, |c| matches!(c, Criterion::BreakBlocks { tag, .. } if has_tag(tag)))
+    }
+
+    pub fn item_crafted(&mut self, event: &ItemCrafted, context: &ConditionContext, registry: &Registry, rng: &mut RuzRng) -> Vec<QuestCompleted> {
+        self.advance(registry, context, rng, 1, |c| matches!(c, Criterion::Craft { recipe, .. } if recipe == &event.recipe))
+    }
+
+    // Adds `amount` to the matching criteria of available quests whose
+    // condition holds and completes the quests whose criteria are all met
+    fn advance(
+        &mut self,
+        registry: &Registry,
+        context: &ConditionContext,
+        rng: &mut RuzRng,
+        amount: u32,
+        matches: impl Fn(&Criterion) -> bool,
+    ) -> Vec<QuestCompleted> {
+        let mut completed = vec![];
+        for quest in self.available(registry) {
+            if !quest.criteria.iter().any(&matches) || quest.condition.as_ref().is_some_and(|c| !c.test(context, rng)) {
+                continue;
+            }
+            let counts = self.progress.entry(quest.id).or_insert_with(|| vec![0; quest.criteria.len()]);
+            counts.resize(quest.criteria.len(), 0);
+            for (criterion, count) in quest.criteria.iter().zip(counts.iter_mut()) {
+                if matches(criterion) {
+                    *count = (*count + amount).min(criterion.count());
+                }
+            }
+            if quest.criteria.iter().zip(counts.iter()).all(|(c, count)| *count >= c.count()) {
+                completed.push(quest);
+            }
+        }
+        completed.into_iter().map(|quest| self.complete(quest, registry, rng)).collect()
+    }
+
+    fn complete(&mut self, quest: &Quest, registry: &Registry, rng: &mut RuzRng) -> QuestCompleted {
+        self.progress.remove(&quest.id);
+        self.started.retain(|q| q != &quest.id);
+        self.completed.push(quest.id);
+        let rewards = match quest.reward.as_ref().and_then(|id| registry.loot_tables.get(id)) {
+            Some(loot_table) => loot_table.roll(rng),
+            None => vec![],
+        };
+        QuestCompleted { player: self.player.clone(), quest: quest.id, rewards }
+    }
+
+    // "quests <player>" followed by "completed <quest>" lines in completion
+    // order, "started <quest>" lines in start order and
+    // "progress <quest> <count>..." lines sorted by quest
+    pub fn serialize(&self) -> String {
+        let mut output = format!("quests {}\n", self.player);
+        for quest in &self.completed {
+            output += &format!("completed {}\n", quest);
+        }
+        for quest in &self.started {
+            output += &format!("started {}\n", quest);
+        }
+        let mut progress: Vec<(&ID, &Vec<u32>)> = self.progress.iter().collect();
+        progress.sort_by_key(|(id, _)| id.to_string());
+        for (quest, counts) in progress {
+            let counts: Vec<String> = counts.iter().map(u32::to_string).collect();
+            output += &format!("progress {} {}\n", quest, counts.join(" "));
+        }
+        output
+    }
+
+    pub fn deserialize(content: &str) -> Result<QuestLog, ParseError> {
+        let mut lines = content.lines().enumerate();
+        let mut log = match lines.next().and_then(|(_, l)| l.trim().strip_prefix("quests ")) {
+            Some(player) => QuestLog::new(player),
+            None => return Err(ParseError::MissingHeader("quests <player>")),
+        };
+        for (i, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = || ParseError::InvalidLine { line: i + 1, text: line.to_string() };
+            let mut parts = line.split_whitespace();
+            let kind = parts.next();
+            let quest = parts.next().and_then(|p| ID::parse(p).ok()).ok_or_else(error)?;
+            match kind {
+                Some("completed") => log.completed.push(quest),
+                Some("started") => log.started.push(quest),
+                Some("progress") => {
+                    let counts = parts.map(|p| p.parse::<u32>().map_err(|_| error())).collect::<Result<Vec<u32>, ParseError>>()?;
+                    log.progress.insert(quest, counts);
+                }
+                _ => return Err(error()),
+            }
+        }
+        Ok(log)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.serialize())
+    }
+
+    pub fn load(path: &Path) -> io::Result<QuestLog> {
+        let content = fs::read_to_string(path)?;
+        QuestLog::deserialize(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
@@ -0,0 +1,170 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::registry::fnv1a;
+
+// Small seedable random number generator (SplitMix64) for loot rolls, world
+// generation and other game logic. The same seed always gives the same
+// sequence. Not suitable for cryptography.
+//
+// Each system should use its own fork, so e.g. extra loot rolls don't change
+// what the world generator produces. The state serializes as a single u64.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuzRng {
+    state: u64,
+}
+
+impl RuzRng {
+    pub fn new(seed: u64) -> Self {
+        RuzRng { state: seed }
+    }
+
+    // Seeded from the current time
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+        RuzRng::new(nanos)
+    }
+
+    // Current state, RuzRng::new(state) continues the sequence from here
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    // Independent generator for a system, e.g. "loot" or "worldgen". Advances
+    // this generator once, so later forks with the same name differ.
+    pub fn fork(&mut self, system: &str) -> RuzRng {
+        let seed = self.next_u64();
+        RuzRng::new(fnv1a(seed, system.bytes()))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // Uniform in [min, max]
+    pub fn range(&mut self, min: u32, max: u32) -> u32 {
+        if min >= max {
+            return min;
+        }
+        // In u64, so 0..=u32::MAX doesn't overflow
+        let span = (max - min) as u64 + 1;
+        min + (self.next_u64() % span) as u32
+    }
+
+    // True with the given probability (0.0 - 1.0)
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for RuzRng {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.state)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for RuzRng {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u64::deserialize(deserializer).map(RuzRng::new)
+    }
+}
+
+// -------------
+// WEIGHTED LIST
+// -------------
+
+// Values with weights, e.g. for spawn tables or dialogue choices. sample()
+// picks a value with probability weight / total weight, using a binary
+// search over the prefix sums of the weights.
+#[derive(Clone, Debug)]
+pub struct WeightedList<T> {
+    values: Vec<T>,
+    cumulative: Vec<u64>, // Sum of the weights up to and including each value
+}
+
+impl<T> WeightedList<T> {
+    pub fn new() -> Self {
+        WeightedList { values: vec![], cumulative: vec![] }
+    }
+
+    // Values with weight 0 are kept but never sampled
+    pub fn push(&mut self, value: T, weight: u32) {
+        self.cumulative.push(self.total_weight() + weight as u64);
+        self.values.push(value);
+    }
+
+    pub fn with(mut self, value: T, weight: u32) -> Self {
+        self.push(value, weight);
+        self
+    }
+
+    pub fn total_weight(&self) -> u64 {
+        self.cumulative.last().copied().unwrap_or(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.cumulative.clear();
+    }
+
+    pub fn weight(&self, index: usize) -> Option<u32> {
+        let previous = if index == 0 { 0 } else { *self.cumulative.get(index - 1)? };
+        Some((self.cumulative.get(index)? - previous) as u32)
+    }
+
+    // (value, weight) pairs in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&T, u32)> {
+        self.values.iter().enumerate().map(|(i, v)| (v, self.weight(i).unwrap_or(0)))
+    }
+
+    // None if the list is empty or all weights are 0
+    pub fn sample_index(&self, rng: &mut RuzRng) -> Option<usize> {
+        let total = self.total_weight();
+        if total == 0 {
+            return None;
+        }
+        let pick = rng.next_u64() % total;
+        Some(self.cumulative.partition_point(|&sum| sum <= pick))
+    }
+
+    pub fn sample(&self, rng: &mut RuzRng) -> Option<&T> {
+        self.sample_index(rng).map(|i| &self.values[i])
+    }
+}
+
+impl<T> Default for WeightedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FromIterator<(T, u32)> for WeightedList<T> {
+    fn from_iter<I: IntoIterator<Item = (T, u32)>>(iter: I) -> Self {
+        let mut list = WeightedList::new();
+        for (value, weight) in iter {
+            list.push(value, weight);
+        }
+        list
+    }
+}
@@ -0,0 +1,271 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::conditions::ConditionContext;
+use crate::error::ParseError;
+use crate::random::RuzRng;
+use crate::registry::{ID, PatternKey, Recipe, RecipeComponent, RecipeShape, Registry, RegistryKey};
+
+// ------
+// EVENTS
+// ------
+
+#[derive(Clone, Debug)]
+pub struct RecipeUnlocked {
+    pub player: String,
+    pub recipe: ID,
+}
+
+// -----------
+// RECIPE BOOK
+// -----------
+
+// Recipes a player profile has discovered. Recipes without an unlock
+// condition are always known, the others stay unlocked once their condition
+// held, even if it doesn't anymore (e.g. the ingredient was used up).
+#[derive(Clone, Debug)]
+pub struct RecipeBook {
+    pub player: String,
+    unlocked: Vec<ID>, // In discovery order
+}
+
+impl RecipeBook {
+    pub fn new(player: &str) -> Self {
+        RecipeBook { player: player.to_string(), unlocked: vec![] }
+    }
+
+    pub fn is_unlocked(&self, recipe: &Recipe) -> bool {
+        recipe.unlock_condition.is_none() || self.unlocked.contains(&recipe.id)
+    }
+
+    // Recipes discovered so far, without the ones that need no unlocking
+    pub fn unlocked(&self) -> &[ID] {
+        &self.unlocked
+    }
+
+    // Unlocks a recipe regardless of its condition, e.g. from a recipe item.
+    // False if it was already unlocked.
+    pub fn unlock(&mut self, recipe: &ID) -> bool {
+        if self.unlocked.contains(recipe) {
+            return false;
+        }
+        self.unlocked.push(*recipe);
+        true
+    }
+
+    // Tests the unlock conditions of all locked recipes against `context`
+    // and unlocks the ones that hold, in ID order
+    pub fn discover(&mut self, context: &ConditionContext, registry: &Registry, rng: &mut RuzRng) -> Vec<RecipeUnlocked> {
+        let mut locked: Vec<&Recipe> = registry.recipes.values().filter(|r| !self.is_unlocked(r)).collect();
+        locked.sort_by_key(|r| r.id.to_string());
+        let discovered: Vec<ID> = locked.into_iter().filter(|r| r.is_unlocked(context, rng)).map(|r| r.id).collect();
+        discovered
+            .into_iter()
+            .map(|recipe| {
+                self.unlocked.push(recipe);
+                RecipeUnlocked { player: self.player.clone(), recipe }
+            })
+            .collect()
+    }
+
+    // "recipes <player>" followed by one "unlocked <recipe>" line per
+    // discovered recipe, in discovery order
+    pub fn serialize(&self) -> String {
+        let mut output = format!("recipes {}\n", self.player);
+        for recipe in &self.unlocked {
+            output += &format!("unlocked {}\n", recipe);
+        }
+        output
+    }
+
+    pub fn deserialize(content: &str) -> Result<RecipeBook, ParseError> {
+        let mut lines = content.lines().enumerate();
+        let mut book = match lines.next().and_then(|(_, l)| l.trim().strip_prefix("recipes ")) {
+            Some(player) => RecipeBook::new(player),
+            None => return Err(ParseError::MissingHeader("recipes <player>")),
+        };
+        for (i, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = || ParseError::InvalidLine { line: i + 1, text: line.to_string() };
+            match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                ["unlocked", recipe] => {
+                    book.unlock(&ID::parse(recipe).map_err(|_| error())?);
+                }
+                _ => return Err(error()),
+            }
+        }
+        Ok(book)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.serialize())
+    }
+
+    pub fn load(path: &Path) -> io::Result<RecipeBook> {
+        let content = fs::read_to_string(path)?;
+        RecipeBook::deserialize(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+// -------------
+// CRAFTING GRID
+// -------------
+
+// Cells a player placed ingredients into, e.g. a 3x3 crafting table. Each
+// cell holds a stack, crafting uses up one of every occupied cell.
+//
+//   let mut grid = CraftingGrid::new(3, 3);
+//   grid.set(0, 0, Some(RecipeComponent::new(id!("game:planks"), 4)));
+//   grid.set(0, 1, Some(RecipeComponent::new(id!("game:planks"), 4)));
+//   let sticks = grid.craft(&registry, &mut rng);
+#[derive(Clone, Debug, PartialEq)]
+pub struct CraftingGrid {
+    width: usize,
+    height: usize,
+    cells: Vec<Option<RecipeComponent>>, // Row by row
+}
+
+impl CraftingGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        CraftingGrid { width, height, cells: vec![None; width * height] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&RecipeComponent> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells[y * self.width + x].as_ref()
+    }
+
+    // Empty stacks clear the cell, cells outside the grid are ignored
+    pub fn set(&mut self, x: usize, y: usize, stack: Option<RecipeComponent>) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = stack.filter(|s| s.count > 0);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(None);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(Option::is_none)
+    }
+
+    // Item IDs of the smallest rectangle around the occupied cells
+    fn trimmed(&self) -> Vec<Vec<Option<ID>>> {
+        let occupied = |x: usize, y: usize| self.get(x, y).is_some();
+        let rows: Vec<usize> = (0..self.height).filter(|&y| (0..self.width).any(|x| occupied(x, y))).collect();
+        let columns: Vec<usize> = (0..self.width).filter(|&x| (0..self.height).any(|y| occupied(x, y))).collect();
+        let (Some(&top), Some(&bottom), Some(&left), Some(&right)) = (rows.first(), rows.last(), columns.first(), columns.last()) else {
+            return vec![];
+        };
+        (top..=bottom).map(|y| (left..=right).map(|x| self.get(x, y).map(|s| s.id)).collect()).collect()
+    }
+
+    // The first recipe in ID order the grid's contents match
+    pub fn find_recipe<'a>(&self, registry: &'a Registry) -> Option<&'a Recipe> {
+        let mut recipes: Vec<&Recipe> = registry.recipes.values().collect();
+        recipes.sort_by_key(|r| r.id.to_string());
+        recipes.into_iter().find(|r| r.matches_grid(self, registry))
+    }
+
+    // Uses up one of every occupied cell and returns the rolled results of
+    // the matching recipe, None if no recipe matches
+    pub fn craft(&mut self, registry: &Registry, rng: &mut RuzRng) -> Option<Vec<RecipeComponent>> {
+        let results = self.find_recipe(registry)?.roll_results(rng);
+        for cell in &mut self.cells {
+            if let Some(stack) = cell {
+                stack.count -= 1;
+                if stack.count == 0 {
+                    *cell = None;
+                }
+            }
+        }
+        Some(results)
+    }
+}
+
+// --------
+// MATCHING
+// --------
+
+impl PatternKey {
+    // Whether an item or block with the ID fits the key, tags are looked up
+    // in the tag's entries and the item's or block's own tags
+    pub fn matches(&self, id: &ID, registry: &Registry) -> bool {
+        match self {
+            PatternKey::Item(item) => item == id,
+            PatternKey::Tag(tag) => {
+                let own_tags = match registry.items.get(id) {
+                    Some(item) => item.tags(),
+                    None => registry.blocks.get(id).map_or(&[][..], |b| b.tags()),
+                };
+                own_tags.contains(tag)
+                    || registry.tags.get(tag).is_some_and(|t| t.contains(RegistryKey::ITEM, id) || t.contains(RegistryKey::BLOCK, id))
+            }
+        }
+    }
+}
+
+impl RecipeShape {
+    // Whether the grid holds exactly this pattern somewhere, with nothing
+    // else around it. Mirrored shapes also match the other way round.
+    pub fn matches(&self, grid: &CraftingGrid, registry: &Registry) -> bool {
+        let cells = grid.trimmed();
+        self.matches_cells(&cells, registry, false) || (self.mirrored && self.matches_cells(&cells, registry, true))
+    }
+
+    fn matches_cells(&self, cells: &[Vec<Option<ID>>], registry: &Registry, mirror: bool) -> bool {
+        // Empty rows and columns around the pattern don't count either
+        let occupied = |x: usize, y: usize| self.key_at(x, y).is_some();
+        let rows: Vec<usize> = (0..self.height()).filter(|&y| (0..self.width()).any(|x| occupied(x, y))).collect();
+        let columns: Vec<usize> = (0..self.width()).filter(|&x| (0..self.height()).any(|y| occupied(x, y))).collect();
+        let (Some(&top), Some(&bottom), Some(&left), Some(&right)) = (rows.first(), rows.last(), columns.first(), columns.last()) else {
+            return false;
+        };
+        let (width, height) = (right - left + 1, bottom - top + 1);
+        if cells.len() != height || cells.iter().any(|row| row.len() != width) {
+            return false;
+        }
+        cells.iter().enumerate().all(|(y, row)| {
+            row.iter().enumerate().all(|(x, cell)| {
+                let x = if mirror { right - x } else { left + x };
+                match (self.key_at(x, top + y), cell) {
+                    (None, None) => true,
+                    (Some(key), Some(id)) => key.matches(id, registry),
+                    _ => false,
+                }
+            })
+        })
+    }
+}
+
+impl Recipe {
+    // Shaped recipes match by their pattern, shapeless ones if the grid holds
+    // exactly the ingredients, one per cell, in any arrangement
+    pub fn matches_grid(&self, grid: &CraftingGrid, registry: &Registry) -> bool {
+        if let Some(shape) = &self.shape {
+            return shape.matches(grid, registry);
+        }
+        let mut remaining: Vec<RecipeComponent> = self.ingredients.clone();
+        for stack in grid.cells.iter().flatten() {
+            match remaining.iter_mut().find(|c| c.id == stack.id && c.count > 0) {
+                Some(component) => component.count -= 1,
+                None => return false,
+            }
+        }
+        !remaining.is_empty() && remaining.iter().all(|c| c.count == 0)
+    }
+}
@@ -1,16 +1,15 @@
-use crate::registries::{ID, Item, Block, Tag, REGISTRY, RegistrableEntity};
+use crate::register;
+use crate::registry::with_registry_mut;
 
 pub fn register() {
     // Initialize the registry
-    let mut registry = REGISTRY.lock().unwrap();
-
-    registry.register(RegistrableEntity::Tag(Tag::new(ID::new("ruz", "fuel"))));
-
-    registry.register(RegistrableEntity::Item(Item::new(
-        ID::new("ruztex", "coal"), vec![ID::new("ruz", "fuel")], 64,
-    )));
-
-    registry.register(RegistrableEntity::Block(Block::new(
-        ID::new("ruztex", "coal"), vec![ID::new("ruz", "fuel")], 5.0,
-    )));
-}
\ No newline at end of file
+    with_registry_mut(|registry| {
+        register! {
+            items { "ruztex:coal" { tags: ["ruz:fuel"], stack_size: 64 } }
+            blocks { "ruztex:coal" { tags: ["ruz:fuel"], hardness: 5.0 } }
+            tags { "ruz:fuel" }
+        }
+        .run(registry)
+        .unwrap_or_else(|e| panic!("Invalid built-in content: {}", e));
+    });
+}
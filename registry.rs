@@ -0,0 +1,2952 @@
+use std::cell::Cell;
+#[allow(unused_imports)]
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Debug, Display, Formatter, Result};
+use std::hash::Hash;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+
+use crate::color::{Color, ColorRef, colored_text};
+use crate::conditions::{Condition, ConditionContext};
+use crate::ecs::{Entities, EntityId};
+use crate::intern::Symbol;
+use crate::localization::{TranslationID, Translator};
+use crate::pool::LOOT_ENTRIES;
+use crate::random::RuzRng;
+use crate::savegame::PlayerProfile;
+use crate::stats::AttributeModifier;
+use crate::world::BlockState;
+
+// Reads vastly outnumber writes once content is loaded. Prefer
+// with_registry and with_registry_mut over locking it directly.
+pub static REGISTRY: Lazy<RwLock<Registry>> = Lazy::new(|| RwLock::new(Registry::new()));
+
+// --
+// ID
+// --
+
+pub(crate) const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+// 64-bit FNV-1a of `bytes`, continuing from `hash` (FNV_OFFSET for a new
+// one). Behind every hash that is stored or sent: ID::stable_hash,
+// Registry::namespace_hashes and RuzRng::fork.
+pub(crate) fn fnv1a(hash: u64, bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(hash, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// Copyable: namespace and name are interned, so cloning or comparing IDs
+// doesn't touch the strings
+#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+pub struct ID {
+    namespace: Symbol,
+    name: Symbol,
+}
+
+impl ID {
+    // Panics if the ID doesn't pass the current IdPolicy, see try_new
+    pub fn new(namespace: &str, name: &str) -> Self {
+        Self::try_new(namespace, name).unwrap_or_else(|e| panic!("Invalid ID '{}:{}': {}", namespace, name, e))
+    }
+
+    pub fn try_new(namespace: &str, name: &str) -> std::result::Result<Self, IdError> {
+        ID::policy().validate(namespace, name)?;
+        Ok(Self { namespace: Symbol::intern(namespace), name: Symbol::intern(name) })
+    }
+
+    // "namespace:name"
+    pub fn parse(value: &str) -> std::result::Result<Self, IdError> {
+        let (namespace, name) = value.split_once(':').ok_or_else(|| IdError::MissingSeparator(value.to_string()))?;
+        Self::try_new(namespace, name)
+    }
+
+    // For id!, which checked the literal at compile time. Skips the policy.
+    #[doc(hidden)]
+    pub fn from_literal(value: &'static str) -> Self {
+        let (namespace, name) = value.split_once(':').expect("id! checks for the separator");
+        Self { namespace: Symbol::intern(namespace), name: Symbol::intern(name) }
+    }
+
+    // Rules new IDs are validated with, IdPolicy::STRICT unless changed
+    pub fn policy() -> IdPolicy {
+        *ID_POLICY.read().unwrap()
+    }
+
+    // Set before registering content, IDs created earlier aren't checked again
+    pub fn set_policy(policy: IdPolicy) {
+        *ID_POLICY.write().unwrap() = policy;
+    }
+
+    pub fn namespace(&self) -> &'static str {
+        self.namespace.as_str()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name.as_str()
+    }
+
+    /// 64-bit FNV-1a hash of "namespace:name".
+    ///
+    /// Unlike `Hash`, this value is part of the content API: it never changes between
+    /// versions, so it can be stored in saves and sent over the network.
+    pub fn stable_hash(&self) -> u64 {
+        fnv1a(FNV_OFFSET, self.namespace().bytes().chain(std::iter::once(b':')).chain(self.name().bytes()))
+    }
+
+    pub fn is_valid_identifier(s: &str, n: Option<(u32, u32)>, allow_underscore: bool) -> bool {
+        if let Some((min, max)) = n {
+            if min > max {
+                panic!("Invalid length range: min cannot be greater than max");
+            }
+
+            let valid_chars = if allow_underscore {
+                s.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+            } else {
+                s.chars().all(|c| c.is_ascii_lowercase())
+            };
+
+            !s.is_empty() && valid_chars && (min..=max).contains(&(s.len() as u32))
+        } else {
+            let valid_chars = if allow_underscore {
+                s.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+            } else {
+                s.chars().all(|c| c.is_ascii_lowercase())
+            };
+
+            !s.is_empty() && valid_chars
+        }
+    }
+}
+
+// ID from a "namespace:name" literal, checked against IdPolicy::STRICT at
+// compile time, so typos fail the build instead of panicking at runtime.
+// Interned once per call site, e.g. id!("ruztex:coal").
+#[macro_export]
+macro_rules! id {
+    ($id:literal) => {{
+        const _: () = assert!(
+            $crate::registry::IdPolicy::STRICT.is_valid_literal($id),
+            concat!("invalid ID literal \"", $id, "\", expected \"namespace:name\" in lowercase letters"),
+        );
+        static ID: ::std::sync::OnceLock<$crate::registry::ID> = ::std::sync::OnceLock::new();
+        *ID.get_or_init(|| $crate::registry::ID::from_literal($id))
+    }};
+}
+
+impl From<&str> for ID {
+    fn from(value: &str) -> Self {
+        ID::parse(value).unwrap_or_else(|e| panic!("Invalid ID '{}': {}", value, e))
+    }
+}
+
+impl Display for ID {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}:{}", self.namespace, self.name)
+    }
+}
+
+impl Debug for ID {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("ID").field("namespace", &self.namespace).field("name", &self.name).finish()
+    }
+}
+
+static ID_POLICY: RwLock<IdPolicy> = RwLock::new(IdPolicy::STRICT);
+
+// Which namespaces and names are valid IDs. Namespaces are lowercase
+// letters (and digits if allowed), names additionally allow '_' and, with
+// allow_paths, '/' between segments like "ores/iron".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdPolicy {
+    pub max_len: usize, // Per namespace and name
+    pub allow_digits: bool,
+    pub allow_paths: bool,
+}
+
+impl IdPolicy {
+    pub const STRICT: IdPolicy = IdPolicy { max_len: 16, allow_digits: false, allow_paths: false };
+    pub const RELAXED: IdPolicy = IdPolicy { max_len: 64, allow_digits: true, allow_paths: true };
+
+    pub fn validate(&self, namespace: &str, name: &str) -> std::result::Result<(), IdError> {
+        self.check("namespace", namespace, false)?;
+        self.check("name", name, true)?;
+        if self.allow_paths && name.split('/').any(str::is_empty) {
+            return Err(IdError::EmptySegment(name.to_string()));
+        }
+        Ok(())
+    }
+
+    // validate for a whole "namespace:name", usable in constants (see id!)
+    pub const fn is_valid_literal(&self, value: &str) -> bool {
+        let bytes = value.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() && bytes[i] != b':' {
+            i += 1;
+        }
+        i < bytes.len() && self.is_valid_part(bytes, 0, i, false) && self.is_valid_part(bytes, i + 1, bytes.len(), true)
+    }
+
+    const fn is_valid_part(&self, bytes: &[u8], start: usize, end: usize, is_name: bool) -> bool {
+        if start == end || end - start > self.max_len {
+            return false;
+        }
+        let mut i = start;
+        while i < end {
+            let c = bytes[i];
+            let allowed = c.is_ascii_lowercase()
+                || (self.allow_digits && c.is_ascii_digit())
+                || (is_name && (c == b'_' || (self.allow_paths && c == b'/')));
+            let empty_segment = c == b'/' && (i == start || i + 1 == end || bytes[i + 1] == b'/');
+            if !allowed || empty_segment {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    fn check(&self, part: &'static str, value: &str, is_name: bool) -> std::result::Result<(), IdError> {
+        if value.is_empty() {
+            return Err(IdError::Empty(part));
+        }
+        if value.len() > self.max_len {
+            return Err(IdError::TooLong { part, len: value.len(), max: self.max_len });
+        }
+        let allowed = |c: char| {
+            c.is_ascii_lowercase()
+                || (self.allow_digits && c.is_ascii_digit())
+                || (is_name && (c == '_' || (self.allow_paths && c == '/')))
+        };
+        match value.chars().find(|c| !allowed(*c)) {
+            Some(c) => Err(IdError::InvalidChar { part, c }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for IdPolicy {
+    fn default() -> Self {
+        IdPolicy::STRICT
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IdError {
+    MissingSeparator(String), // No ':' between namespace and name
+    Empty(&'static str),      // "namespace" or "name"
+    TooLong { part: &'static str, len: usize, max: usize },
+    InvalidChar { part: &'static str, c: char },
+    EmptySegment(String), // Path name with a leading, trailing or double '/'
+}
+
+impl Display for IdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            IdError::MissingSeparator(value) => write!(f, "'{}' is not in the form 'namespace:name'", value),
+            IdError::Empty(part) => write!(f, "{} is empty", part),
+            IdError::TooLong { part, len, max } => write!(f, "{} is {} characters long, at most {} are allowed", part, len, max),
+            IdError::InvalidChar { part, c } => write!(f, "{} contains the invalid character '{}'", part, c),
+            IdError::EmptySegment(name) => write!(f, "name '{}' has an empty path segment", name),
+        }
+    }
+}
+
+impl std::error::Error for IdError {}
+
+// -----
+// ITEMS
+// -----
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    pub id: ID,
+    pub tags: Vec<ID>,
+    pub stack_size: u32,
+}
+
+impl Item {
+    pub fn new(id: ID, tags: Vec<ID>, stack_size: u32) -> Self {
+        Item { id, tags, stack_size }
+    }
+
+    pub fn tags(&self) -> &[ID] {
+        &self.tags
+    }
+
+    pub fn stack_size(&self) -> u32 {
+        self.stack_size
+    }
+}
+
+impl Registrable for Item {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+// ----------
+// LOOTTABLES
+// ----------
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LootEntry {
+    pub items: Vec<ID>,  // Items, die gedroppt werden können
+    pub weight: u32,     // Gewicht für Zufallsauswahl, default 1
+    pub min: u32,        // Min Anzahl Items
+    pub max: u32,        // Max Anzahl Items
+    pub chance: f32,     // Drop Chance (0.0 - 1.0)
+    pub condition: Option<Condition>, // Entry is skipped unless it holds
+    pub functions: Vec<LootFunction>, // Applied in order to the rolled stack
+}
+
+impl LootEntry {
+    pub fn new(items: Vec<ID>, min: u32, max: u32, chance: f32, weight: Option<u32>) -> Self {
+        if items.is_empty() {
+            panic!("LootEntry must have at least one item");
+        }
+        if min > max {
+            panic!("min cannot be greater than max");
+        }
+        if !(0.0..=1.0).contains(&chance) {
+            panic!("chance must be between 0.0 and 1.0");
+        }
+        LootEntry {
+            items,
+            weight: weight.unwrap_or(1),
+            min,
+            max,
+            chance,
+            condition: None,
+            functions: vec![],
+        }
+    }
+
+    pub fn with_condition(mut self, condition: Condition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    pub fn with_function(mut self, function: LootFunction) -> Self {
+        self.functions.push(function);
+        self
+    }
+}
+
+// Rolled drop. Components are extra data for the resulting slot, e.g.
+// "durability" or an enchantment ID with its level, see Inventory::add_loot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LootStack {
+    pub item: ID,
+    pub count: u32,
+    pub components: BTreeMap<String, String>,
+}
+
+impl LootStack {
+    pub fn new(item: ID, count: u32) -> Self {
+        LootStack { item, count, components: BTreeMap::new() }
+    }
+}
+
+// Recipes in a tag with this name (in any namespace) are furnace recipes
+pub const SMELTING_TAG: &str = "smelting";
+
+// Changes a rolled stack depending on the breaking context
+#[derive(Clone, Debug, PartialEq)]
+pub enum LootFunction {
+    SetCount { min: u32, max: u32 },
+    LootingBonus { enchantment: ID, per_level: u32 }, // Adds 0..=per_level * level of the held slot's enchantment
+    SetComponents(BTreeMap<String, String>),
+    FurnaceSmelt, // Replaces the item with the result of its SMELTING_TAG recipe
+}
+
+impl LootFunction {
+    pub fn apply(&self, stack: &mut LootStack, context: &ConditionContext, rng: &mut RuzRng) {
+        match self {
+            LootFunction::SetCount { min, max } => stack.count = rng.range(*min, *max),
+            LootFunction::LootingBonus { enchantment, per_level } => {
+                let level = context.held.map_or(0, |s| s.enchantment_level(enchantment));
+                stack.count = stack.count.saturating_add(rng.range(0, per_level.saturating_mul(level)));
+            }
+            LootFunction::SetComponents(components) => {
+                stack.components.extend(components.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+            LootFunction::FurnaceSmelt => {
+                let Some(registry) = context.registry else {
+                    return;
+                };
+                let smelting = registry.recipes.values().find(|recipe| {
+                    matches!(&recipe.ingredients[..], [ingredient] if ingredient.id == stack.item)
+                        && recipe.results.len() == 1
+                        && registry.tags.values().any(|tag| {
+                            tag.id.name() == SMELTING_TAG && tag.contains(RegistryKey::RECIPE, &recipe.id)
+                        })
+                });
+                if let Some(recipe) = smelting {
+                    stack.item = recipe.results[0].id;
+                    stack.count *= recipe.results[0].count;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LootTable {
+    pub id: ID,
+    pub entries: Vec<LootEntry>,
+    pub functions: Vec<LootFunction>, // Applied to every stack after the entry's own
+}
+
+impl LootTable {
+    pub fn new(id: ID, entries: Vec<LootEntry>) -> Self {
+        if entries.is_empty() {
+            panic!("LootTable must have at least one entry");
+        }
+        LootTable { id, entries, functions: vec![] }
+    }
+
+    pub fn with_function(mut self, function: LootFunction) -> Self {
+        self.functions.push(function);
+        self
+    }
+
+    // Picks one entry by weight, then drops `min..=max` of one of its items
+    // with the entry's chance. Empty if nothing dropped.
+    pub fn roll(&self, rng: &mut RuzRng) -> Vec<LootStack> {
+        self.roll_with(&ConditionContext::new(), rng)
+    }
+
+    // Like roll, but only entries whose condition holds in `context` can be
+    // picked, and the loot functions see the context. Nothing drops if the
+    // context's loot filter doesn't allow the table.
+    pub fn roll_with(&self, context: &ConditionContext, rng: &mut RuzRng) -> Vec<LootStack> {
+        if context.loot_filter.is_some_and(|filter| !filter.allows(&self.id, context)) {
+            return vec![];
+        }
+        let entry = LOOT_ENTRIES.with(|pool| {
+            let mut entries = pool.get();
+            for (i, entry) in self.entries.iter().enumerate() {
+                if entry.condition.as_ref().is_none_or(|c| c.test(context, rng)) {
+                    entries.push(i, entry.weight);
+                }
+            }
+            entries.sample(rng).map(|&i| &self.entries[i])
+        });
+        let Some(entry) = entry else {
+            return vec![];
+        };
+        if !rng.chance(entry.chance) {
+            return vec![];
+        }
+        let item = &entry.items[rng.range(0, entry.items.len() as u32 - 1) as usize];
+        let mut stack = LootStack::new(*item, rng.range(entry.min, entry.max));
+        for function in entry.functions.iter().chain(&self.functions) {
+            function.apply(&mut stack, context, rng);
+        }
+        if stack.count == 0 {
+            return vec![];
+        }
+        vec![stack]
+    }
+}
+
+impl Registrable for LootTable {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+// ------
+// BLOCKS
+// ------
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub id: ID,
+    pub tags: Vec<ID>,
+    pub hardness: f32,
+    pub loot_table: Option<LootTable>,
+}
+
+impl Block {
+    pub fn new(id: ID, tags: Vec<ID>, hardness: f32) -> Self {
+        Block { id, tags, hardness, loot_table: None }
+    }
+
+    pub fn hardness(&self) -> f32 {
+        self.hardness
+    }
+
+    pub fn loot_table(&self) -> Option<&LootTable> {
+        self.loot_table.as_ref()
+    }
+
+    pub fn tags(&self) -> &[ID] {
+        &self.tags
+    }
+}
+
+impl Registrable for Block {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Block {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}:{}", self.id.namespace, self.id.name)
+    }
+}
+
+// ----
+// TAGS
+// ----
+
+// Kind of entity a tag entry refers to: one of the built-in registries or a
+// custom one, e.g. RegistryKey::custom("examplemod:spell"). Built-in keys are
+// named like RegistrableEntity::type_name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RegistryKey(&'static str);
+
+impl RegistryKey {
+    pub const ITEM: RegistryKey = RegistryKey("Item");
+    pub const BLOCK: RegistryKey = RegistryKey("Block");
+    pub const TAG: RegistryKey = RegistryKey("Tag");
+    pub const TOOL: RegistryKey = RegistryKey("Tool");
+    pub const RECIPE: RegistryKey = RegistryKey("Recipe");
+    pub const LOOT_TABLE: RegistryKey = RegistryKey("LootTable");
+    pub const ENTITY: RegistryKey = RegistryKey("Entity");
+    pub const ENCHANTMENT: RegistryKey = RegistryKey("Enchantment");
+    pub const QUEST: RegistryKey = RegistryKey("Quest");
+    pub const EFFECT: RegistryKey = RegistryKey("Effect");
+    pub const DIALOGUE: RegistryKey = RegistryKey("Dialogue");
+    pub const SPRITE: RegistryKey = RegistryKey("Sprite");
+    pub const BIOME: RegistryKey = RegistryKey("Biome");
+    pub const STRUCTURE: RegistryKey = RegistryKey("Structure");
+
+    pub const BUILTIN: [RegistryKey; 14] = [
+        Self::ITEM,
+        Self::BLOCK,
+        Self::TAG,
+        Self::TOOL,
+        Self::RECIPE,
+        Self::LOOT_TABLE,
+        Self::ENTITY,
+        Self::ENCHANTMENT,
+        Self::QUEST,
+        Self::EFFECT,
+        Self::DIALOGUE,
+        Self::SPRITE,
+        Self::BIOME,
+        Self::STRUCTURE,
+    ];
+
+    // Key of a registry the game doesn't know, namespaced to avoid clashes
+    pub fn custom(name: &str) -> RegistryKey {
+        RegistryKey(Symbol::intern(name).as_str())
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0
+    }
+
+    pub fn is_builtin(&self) -> bool {
+        Self::BUILTIN.contains(self)
+    }
+}
+
+impl Display for RegistryKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tag {
+    pub id: ID,
+    pub entries: HashSet<(RegistryKey, ID)>, // e.g. (RegistryKey::ITEM, ID)
+}
+
+impl Tag {
+    pub fn new(id: ID) -> Self {
+        Tag {
+            id,
+            entries: HashSet::new(),
+        }
+    }
+
+    pub fn add(&mut self, key: RegistryKey, entity_id: &ID) {
+        self.entries.insert((key, *entity_id));
+    }
+
+    pub fn contains(&self, key: RegistryKey, entity_id: &ID) -> bool {
+        self.entries.contains(&(key, *entity_id))
+    }
+
+    // Entries sorted by kind, then ID
+    pub fn iter(&self) -> impl Iterator<Item = (RegistryKey, ID)> {
+        let mut entries: Vec<(RegistryKey, ID)> = self.entries.iter().copied().collect();
+        entries.sort_by_key(|(key, id)| (*key, id.to_string()));
+        entries.into_iter()
+    }
+
+    // IDs of the entries of one kind, sorted
+    pub fn of_kind(&self, key: RegistryKey) -> Vec<ID> {
+        self.iter().filter(|(k, _)| *k == key).map(|(_, id)| id).collect()
+    }
+
+    pub fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Registrable for Tag {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}:#{}", self.id.namespace, self.id.name)
+    }
+}
+
+// -----
+// TOOLS
+// -----
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tool {
+    pub id: ID,
+    pub tags: Vec<ID>,
+    pub durability: u32, // Max durability, slots track the remaining one
+    pub level: u32,
+    pub speed: f32,
+    pub modifiers: Vec<AttributeModifier>, // Apply while the tool is held, see Stats::set_equipment
+}
+
+impl Tool {
+    pub fn new(id: ID, tags: Vec<ID>, durability: u32, level: u32, speed: f32) -> Self {
+        Tool { id, tags, durability, level, speed, modifiers: vec![] }
+    }
+
+    pub fn with_modifier(mut self, modifier: AttributeModifier) -> Self {
+        self.modifiers.push(modifier);
+        self
+    }
+
+    pub fn durability(&self) -> u32 {
+        self.durability
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn tags(&self) -> &[ID] {
+        &self.tags
+    }
+}
+
+impl Registrable for Tool {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+// -------
+// RECIPES
+// -------
+
+// Ingredients only use id and count. Results can vary: between count and
+// max are produced with the chance, e.g. 10% for 1-2 extra slag.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecipeComponent {
+    pub id: ID, // ID of the item or block
+    pub count: u32, // Number of items or blocks needed, the minimum for results
+    pub max: u32,   // Maximum number of results, at least count
+    pub chance: f32, // Chance for results (0.0 - 1.0)
+}
+
+impl RecipeComponent {
+    pub fn new(id: ID, count: u32) -> Self {
+        RecipeComponent { id, count, max: count, chance: 1.0 }
+    }
+
+    pub fn with_range(mut self, min: u32, max: u32) -> std::result::Result<Self, RegistryError> {
+        if min > max {
+            return Err(RegistryError::InvalidRange { min, max });
+        }
+        self.count = min;
+        self.max = max;
+        Ok(self)
+    }
+
+    pub fn with_chance(mut self, chance: f32) -> std::result::Result<Self, RegistryError> {
+        if !(0.0..=1.0).contains(&chance) {
+            return Err(RegistryError::InvalidChance(chance));
+        }
+        self.chance = chance;
+        Ok(self)
+    }
+
+    // Raises count and max together, e.g. for every pattern cell of an
+    // ingredient
+    pub fn add_count(&mut self, count: u32) {
+        self.count += count;
+        self.max += count;
+    }
+
+    // Always the same count
+    pub fn is_fixed(&self) -> bool {
+        self.chance >= 1.0 && self.count == self.max
+    }
+
+    // Number produced, 0 if the chance failed
+    pub fn roll(&self, rng: &mut RuzRng) -> u32 {
+        if self.is_fixed() {
+            return self.count;
+        }
+        if !rng.chance(self.chance) {
+            return 0;
+        }
+        rng.range(self.count, self.max)
+    }
+}
+
+// "2x id", "1-3x id", "1x id (10%)"
+impl Display for RecipeComponent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if self.count == self.max {
+            write!(f, "{}x {}", self.count, self.id)?;
+        } else {
+            write!(f, "{}-{}x {}", self.count, self.max, self.id)?;
+        }
+        if self.chance < 1.0 {
+            write!(f, " ({}%)", (self.chance * 100.0).round())?;
+        }
+        Ok(())
+    }
+}
+
+// What a pattern character of a shaped recipe stands for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PatternKey {
+    Item(ID), // The item or block itself
+    Tag(ID),  // Any item or block with the tag
+}
+
+impl PatternKey {
+    pub fn id(&self) -> &ID {
+        match self {
+            PatternKey::Item(id) | PatternKey::Tag(id) => id,
+        }
+    }
+}
+
+// Arrangement of a shaped recipe's ingredients on a crafting grid, one
+// character per cell and ' ' for empty cells, e.g. ["##", "##"] for a
+// 2x2 square. The pattern can be placed anywhere on a larger grid.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecipeShape {
+    pub pattern: Vec<String>, // Rows, all the same length
+    pub keys: BTreeMap<char, PatternKey>,
+    pub mirrored: bool, // Also matches mirrored left to right
+}
+
+impl RecipeShape {
+    // Shorter rows are padded with empty cells
+    pub fn new(pattern: &[&str]) -> Self {
+        let width = pattern.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        let pattern = pattern.iter().map(|row| format!("{:<width$}", row, width = width)).collect();
+        RecipeShape { pattern, keys: BTreeMap::new(), mirrored: false }
+    }
+
+    pub fn with_key(mut self, key: char, item: ID) -> Self {
+        self.keys.insert(key, PatternKey::Item(item));
+        self
+    }
+
+    pub fn with_tag_key(mut self, key: char, tag: ID) -> Self {
+        self.keys.insert(key, PatternKey::Tag(tag));
+        self
+    }
+
+    pub fn with_mirrored(mut self, mirrored: bool) -> Self {
+        self.mirrored = mirrored;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.pattern.first().map_or(0, |row| row.chars().count())
+    }
+
+    pub fn height(&self) -> usize {
+        self.pattern.len()
+    }
+
+    // Key of the cell, None for empty cells
+    pub fn key_at(&self, x: usize, y: usize) -> Option<&PatternKey> {
+        let key = self.pattern.get(y)?.chars().nth(x)?;
+        self.keys.get(&key)
+    }
+
+    // How often each key's item or tag is needed, in pattern order
+    fn counts(&self) -> Vec<RecipeComponent> {
+        let mut counts: Vec<RecipeComponent> = vec![];
+        for key in self.pattern.iter().flat_map(|row| row.chars()).filter_map(|c| self.keys.get(&c)) {
+            match counts.iter_mut().find(|c| &c.id == key.id()) {
+                Some(component) => component.add_count(1),
+                None => counts.push(RecipeComponent::new(*key.id(), 1)),
+            }
+        }
+        counts
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recipe {
+    pub id: ID,
+    pub ingredients: Vec<RecipeComponent>, // IDs of items or blocks
+    pub results: Vec<RecipeComponent>,     // ID of the resulting item or block
+    pub unlock_condition: Option<Condition>, // Always unlocked without one
+    pub shape: Option<RecipeShape>,        // None for shapeless recipes
+}
+
+impl Recipe {
+    pub fn new(id: ID, ingredients: Vec<RecipeComponent>, results: Vec<RecipeComponent>) -> Self {
+        Recipe { id, ingredients, results, unlock_condition: None, shape: None }
+    }
+
+    // The ingredients are the pattern's keys, counted per cell. Panics if the
+    // pattern is empty or uses a character without a key.
+    pub fn shaped(id: ID, shape: RecipeShape, results: Vec<RecipeComponent>) -> Self {
+        if shape.pattern.iter().all(|row| row.trim().is_empty()) {
+            panic!("Shaped recipe {} has an empty pattern", id);
+        }
+        if let Some(c) = shape.pattern.iter().flat_map(|row| row.chars()).find(|c| *c != ' ' && !shape.keys.contains_key(c)) {
+            panic!("Shaped recipe {} uses '{}' without a key", id, c);
+        }
+        Recipe { id, ingredients: shape.counts(), results, unlock_condition: None, shape: Some(shape) }
+    }
+
+    pub fn with_unlock_condition(mut self, condition: Condition) -> Self {
+        self.unlock_condition = Some(condition);
+        self
+    }
+
+    pub fn is_unlocked(&self, context: &ConditionContext, rng: &mut RuzRng) -> bool {
+        self.unlock_condition.as_ref().is_none_or(|c| c.test(context, rng))
+    }
+
+    pub fn ingredients(&self) -> &[RecipeComponent] {
+        &self.ingredients
+    }
+
+    pub fn results(&self) -> &[RecipeComponent] {
+        &self.results
+    }
+
+    // One roll of every result with a fixed count, results that weren't
+    // produced are left out
+    pub fn roll_results(&self, rng: &mut RuzRng) -> Vec<RecipeComponent> {
+        self.results
+            .iter()
+            .filter_map(|component| {
+                let count = component.roll(rng);
+                (count > 0).then(|| RecipeComponent::new(component.id, count))
+            })
+            .collect()
+    }
+
+    pub fn is_shaped(&self) -> bool {
+        self.shape.is_some()
+    }
+
+    // Tags used as pattern keys
+    fn references(&self) -> Vec<(RegistryKey, &ID)> {
+        let keys = self.shape.iter().flat_map(|shape| shape.keys.values());
+        keys.filter_map(|key| match key {
+            PatternKey::Tag(tag) => Some((RegistryKey::TAG, tag)),
+            PatternKey::Item(_) => None,
+        })
+        .collect()
+    }
+}
+
+impl Registrable for Recipe {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Recipe {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}:{}", self.id.namespace, self.id.name)
+    }
+}
+
+// --------
+// ENTITIES
+// --------
+
+// Kind of entity that can be spawned, e.g. "ruztex:zombie"
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entity {
+    pub id: ID,
+    pub tags: Vec<ID>,
+    pub max_health: u32,
+    pub behavior: Option<String>, // Spawned with this Ai behavior, see behavior::Behaviors
+}
+
+impl Entity {
+    pub fn new(id: ID, tags: Vec<ID>, max_health: u32) -> Self {
+        Entity { id, tags, max_health, behavior: None }
+    }
+
+    pub fn with_behavior(mut self, behavior: &str) -> Self {
+        self.behavior = Some(behavior.to_string());
+        self
+    }
+
+    pub fn max_health(&self) -> u32 {
+        self.max_health
+    }
+}
+
+impl Registrable for Entity {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Entity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+// ------------
+// ENCHANTMENTS
+// ------------
+
+// Effect of an enchantment, values are per level
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatModifier {
+    ToolSpeed(f32),     // Added to the tool's speed multiplier (1.0)
+    LootLuck(f32),      // Chance of an extra loot roll, above 1.0 guarantees one
+    DurabilityUse(f32), // Damage is only taken with a chance of 1 / (1 + value)
+    Attribute(AttributeModifier), // Applies while the enchanted slot is held
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Enchantment {
+    pub id: ID,
+    pub applicable_tags: Vec<ID>, // Items with one of these tags can be enchanted, any item if empty
+    pub max_level: u32,
+    pub modifiers: Vec<StatModifier>,
+    pub color: Color, // Color of the name in item descriptions
+}
+
+impl Enchantment {
+    pub fn new(id: ID, applicable_tags: Vec<ID>, max_level: u32, modifiers: Vec<StatModifier>) -> Self {
+        if max_level == 0 {
+            panic!("max_level must be at least 1");
+        }
+        Enchantment { id, applicable_tags, max_level, modifiers, color: Color::from_hex("#b48ead") }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn max_level(&self) -> u32 {
+        self.max_level
+    }
+
+    pub fn is_applicable_to(&self, tags: &[ID]) -> bool {
+        self.applicable_tags.is_empty() || self.applicable_tags.iter().any(|t| tags.contains(t))
+    }
+
+    // Translated name ("namespace:enchantment.name") with the level in roman
+    // numerals, e.g. "Unbreaking III", in the enchantment's color
+    pub fn display_name(&self, level: u32, translator: &Translator) -> String {
+        let name = translator.translate(&TranslationID::from_id(&self.id, "enchantment"), None);
+        let text = if self.max_level == 1 { name } else { format!("{} {}", name, roman_numeral(level)) };
+        colored_text(&text, &ColorRef::Direct(self.color)).unwrap_or(text)
+    }
+}
+
+impl Registrable for Enchantment {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Enchantment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+pub fn roman_numeral(mut value: u32) -> String {
+    const NUMERALS: [(u32, &str); 13] = [
+        (1000, "M"), (900, "CM"), (500, "D"), (400, "CD"), (100, "C"), (90, "XC"),
+        (50, "L"), (40, "XL"), (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I"),
+    ];
+    let mut result = String::new();
+    for (amount, numeral) in NUMERALS {
+        while value >= amount {
+            result += numeral;
+            value -= amount;
+        }
+    }
+    result
+}
+
+// -------
+// EFFECTS
+// -------
+
+// Called every `interval` ticks for each entity with the effect, e.g. to heal
+// a bit per tick. The amplifier starts at 0.
+pub type EffectTick = fn(entity: EntityId, entities: &mut Entities, amplifier: u32);
+
+// Status effect, e.g. from potions or food. Entities carry active effects in
+// their ecs::EffectMap.
+#[derive(Clone, Debug)]
+pub struct Effect {
+    pub id: ID,
+    pub duration: u32,      // Default duration in ticks
+    pub max_amplifier: u32,
+    pub interval: u32,      // Ticks between on_tick calls
+    pub on_tick: Option<EffectTick>,
+    pub color: Color,       // Color of the name in the HUD
+    pub modifiers: Vec<AttributeModifier>, // Per amplifier level, see Stats::set_effects
+}
+
+impl Effect {
+    pub fn new(id: ID, duration: u32, max_amplifier: u32) -> Self {
+        Effect { id, duration, max_amplifier, interval: 1, on_tick: None, color: Color::from_hex("#88c0d0"), modifiers: vec![] }
+    }
+
+    pub fn with_modifier(mut self, modifier: AttributeModifier) -> Self {
+        self.modifiers.push(modifier);
+        self
+    }
+
+    pub fn with_tick(mut self, interval: u32, on_tick: EffectTick) -> Self {
+        self.interval = interval.max(1);
+        self.on_tick = Some(on_tick);
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    // Translated name ("namespace:effect.name") with the amplifier as roman
+    // numeral from II on, e.g. "Regeneration II", in the effect's color
+    pub fn display_name(&self, amplifier: u32, translator: &Translator) -> String {
+        let name = translator.translate(&TranslationID::from_id(&self.id, "effect"), None);
+        let text = if amplifier == 0 { name } else { format!("{} {}", name, roman_numeral(amplifier + 1)) };
+        colored_text(&text, &ColorRef::Direct(self.color)).unwrap_or(text)
+    }
+}
+
+impl Registrable for Effect {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Effect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+// Function pointers can't be compared reliably, so effects only differ in
+// on_tick if one has a tick function and the other doesn't
+impl PartialEq for Effect {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.duration == other.duration
+            && self.max_amplifier == other.max_amplifier
+            && self.interval == other.interval
+            && self.on_tick.is_some() == other.on_tick.is_some()
+            && self.color == other.color
+            && self.modifiers == other.modifiers
+    }
+}
+
+// ------
+// QUESTS
+// ------
+
+// Condition of a quest, counted from the moment the quest becomes available
+#[derive(Clone, Debug, PartialEq)]
+pub enum Criterion {
+    Collect { item: ID, count: u32 },
+    BreakBlocks { tag: ID, count: u32 },
+    Craft { recipe: ID, count: u32 },
+}
+
+impl Criterion {
+    pub fn count(&self) -> u32 {
+        match self {
+            Criterion::Collect { count, .. } | Criterion::BreakBlocks { count, .. } | Criterion::Craft { count, .. } => *count,
+        }
+    }
+}
+
+impl Display for Criterion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Criterion::Collect { item, count } => write!(f, "collect {}x {}", count, item),
+            Criterion::BreakBlocks { tag, count } => write!(f, "break {}x #{}", count, tag),
+            Criterion::Craft { recipe, count } => write!(f, "craft {}x {}", count, recipe),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quest {
+    pub id: ID,
+    pub criteria: Vec<Criterion>,   // All of them complete the quest
+    pub prerequisites: Vec<ID>,     // Quests to complete first
+    pub reward: Option<ID>,         // Loot table rolled on completion
+    pub condition: Option<Condition>, // Criteria only count while it holds
+    pub manual_start: bool,         // Only available once started, e.g. by a dialogue
+}
+
+impl Quest {
+    pub fn new(id: ID, criteria: Vec<Criterion>) -> Self {
+        if criteria.is_empty() {
+            panic!("Quest must have at least one criterion");
+        }
+        Quest { id, criteria, prerequisites: vec![], reward: None, condition: None, manual_start: false }
+    }
+
+    pub fn with_prerequisite(mut self, quest: ID) -> Self {
+        self.prerequisites.push(quest);
+        self
+    }
+
+    pub fn with_reward(mut self, loot_table: ID) -> Self {
+        self.reward = Some(loot_table);
+        self
+    }
+
+    pub fn with_condition(mut self, condition: Condition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    // The quest stays unavailable until QuestLog::start is called for it
+    pub fn with_manual_start(mut self) -> Self {
+        self.manual_start = true;
+        self
+    }
+
+    pub fn criteria(&self) -> &[Criterion] {
+        &self.criteria
+    }
+}
+
+impl Registrable for Quest {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Quest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+// ---------
+// DIALOGUES
+// ---------
+
+// What choosing a dialogue choice does, see dialogue::apply
+#[derive(Clone, Debug, PartialEq)]
+pub enum Consequence {
+    GiveItem { item: ID, count: u32 },
+    StartQuest(ID), // See Quest::with_manual_start
+    SetFlag(String),
+    ClearFlag(String),
+}
+
+impl Display for Consequence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Consequence::GiveItem { item, count } => write!(f, "give {}x {}", count, item),
+            Consequence::StartQuest(quest) => write!(f, "start quest {}", quest),
+            Consequence::SetFlag(flag) => write!(f, "set flag {}", flag),
+            Consequence::ClearFlag(flag) => write!(f, "clear flag {}", flag),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DialogueChoice {
+    pub text: TranslationID,
+    pub next: Option<String>,         // Node to continue with, None ends the dialogue
+    pub condition: Option<Condition>, // The choice is only offered while it holds
+    pub consequences: Vec<Consequence>, // Applied in order when chosen
+}
+
+impl DialogueChoice {
+    pub fn new(text: TranslationID, next: Option<&str>) -> Self {
+        DialogueChoice { text, next: next.map(str::to_string), condition: None, consequences: vec![] }
+    }
+
+    pub fn with_condition(mut self, condition: Condition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    pub fn with_consequence(mut self, consequence: Consequence) -> Self {
+        self.consequences.push(consequence);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DialogueNode {
+    pub name: String, // Unique within the dialogue, target of DialogueChoice::next
+    pub speaker: Option<TranslationID>,
+    pub text: TranslationID,
+    pub choices: Vec<DialogueChoice>, // None end the dialogue after this node
+}
+
+impl DialogueNode {
+    pub fn new(name: &str, text: TranslationID) -> Self {
+        DialogueNode { name: name.to_string(), speaker: None, text, choices: vec![] }
+    }
+
+    pub fn with_speaker(mut self, speaker: TranslationID) -> Self {
+        self.speaker = Some(speaker);
+        self
+    }
+
+    pub fn with_choice(mut self, choice: DialogueChoice) -> Self {
+        self.choices.push(choice);
+        self
+    }
+}
+
+// Conversation tree starting at the first node
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dialogue {
+    pub id: ID,
+    pub nodes: Vec<DialogueNode>,
+}
+
+impl Dialogue {
+    pub fn new(id: ID, nodes: Vec<DialogueNode>) -> Self {
+        if nodes.is_empty() {
+            panic!("Dialogue must have at least one node");
+        }
+        for (i, node) in nodes.iter().enumerate() {
+            if nodes[..i].iter().any(|n| n.name == node.name) {
+                panic!("Dialogue {} has more than one node named '{}'", id, node.name);
+            }
+            for next in node.choices.iter().filter_map(|c| c.next.as_ref()) {
+                if !nodes.iter().any(|n| &n.name == next) {
+                    panic!("Dialogue {} node '{}' leads to missing node '{}'", id, node.name, next);
+                }
+            }
+        }
+        Dialogue { id, nodes }
+    }
+
+    pub fn start(&self) -> &DialogueNode {
+        &self.nodes[0]
+    }
+
+    pub fn node(&self, name: &str) -> Option<&DialogueNode> {
+        self.nodes.iter().find(|n| n.name == name)
+    }
+
+    // Items and quests the consequences refer to
+    fn references(&self) -> Vec<(RegistryKey, &ID)> {
+        let consequences = self.nodes.iter().flat_map(|n| &n.choices).flat_map(|c| &c.consequences);
+        consequences
+            .filter_map(|c| match c {
+                Consequence::GiveItem { item, .. } => Some((RegistryKey::ITEM, item)),
+                Consequence::StartQuest(quest) => Some((RegistryKey::QUEST, quest)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Registrable for Dialogue {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Dialogue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+// ------
+// BIOMES
+// ------
+
+// Region of the world with its own surface and features. World generation
+// gives each column the biome closest to its temperature noise (see
+// worldgen::NoiseTerrain) and records it in the World; loot tables and
+// quests can check it with the in_biome condition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Biome {
+    pub id: ID,
+    pub tags: Vec<ID>,
+    pub temperature: f32,        // 0.0 (cold) - 1.0 (hot)
+    pub surface: ID,             // Top block of each column
+    pub layers: Vec<(ID, u32)>,  // Blocks below the surface with their thickness
+    pub features: Vec<ID>,       // Generators added with NoiseTerrain::with_feature, e.g. ore veins
+}
+
+impl Biome {
+    pub fn new(id: ID, surface: ID, temperature: f32) -> Self {
+        Biome { id, tags: vec![], temperature: temperature.clamp(0.0, 1.0), surface, layers: vec![], features: vec![] }
+    }
+
+    pub fn with_tags(mut self, tags: Vec<ID>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn with_layer(mut self, block: ID, thickness: u32) -> Self {
+        self.layers.push((block, thickness));
+        self
+    }
+
+    pub fn with_feature(mut self, feature: ID) -> Self {
+        self.features.push(feature);
+        self
+    }
+}
+
+impl Registrable for Biome {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Biome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+// ----------
+// STRUCTURES
+// ----------
+
+// Block pattern placed into the world as a whole, e.g. a hut or a ruin.
+// Layers go up along z (2-D worlds only use the first), rows along y and
+// columns along x from the placement position. Characters are palette keys,
+// except ' ' (keeps the world's block) and '.' (clears it). See the
+// structure module for schematic files and worldgen::StructureGenerator for
+// placement by biome and rarity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Structure {
+    pub id: ID,
+    pub tags: Vec<ID>,
+    pub layers: Vec<Vec<String>>, // Bottom to top, rows of each layer from the top
+    pub palette: BTreeMap<char, BlockState>,
+    pub biomes: Vec<ID>, // Biomes or biome tags it generates in, anywhere if empty
+    pub rarity: u32,     // Generated in about one of `rarity` chunks, never if 0
+}
+
+impl Structure {
+    pub const KEEP: char = ' ';
+    pub const AIR: char = '.';
+
+    pub fn new(id: ID) -> Self {
+        Structure { id, tags: vec![], layers: vec![], palette: BTreeMap::new(), biomes: vec![], rarity: 0 }
+    }
+
+    pub fn with_tags(mut self, tags: Vec<ID>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    // The next layer up, one string per row
+    pub fn with_layer(mut self, rows: &[&str]) -> Self {
+        self.layers.push(rows.iter().map(|row| row.to_string()).collect());
+        self
+    }
+
+    pub fn with_key(mut self, key: char, block: BlockState) -> Self {
+        self.palette.insert(key, block);
+        self
+    }
+
+    pub fn with_biome(mut self, biome: ID) -> Self {
+        self.biomes.push(biome);
+        self
+    }
+
+    pub fn with_rarity(mut self, rarity: u32) -> Self {
+        self.rarity = rarity;
+        self
+    }
+
+    // Size along x, y and z; shorter rows and layers count as ' '
+    pub fn width(&self) -> usize {
+        self.layers.iter().flatten().map(|row| row.chars().count()).max().unwrap_or(0)
+    }
+
+    pub fn length(&self) -> usize {
+        self.layers.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.layers.len()
+    }
+
+    // ' ' outside the pattern
+    pub fn key_at(&self, x: usize, y: usize, z: usize) -> char {
+        self.layers.get(z).and_then(|layer| layer.get(y)).and_then(|row| row.chars().nth(x)).unwrap_or(Self::KEEP)
+    }
+
+    // Every position [x, y, z] the structure changes, with its block or None
+    // for air. Keys missing from the palette are left out, see
+    // Structure::validate.
+    pub fn blocks(&self) -> Vec<([usize; 3], Option<&BlockState>)> {
+        let mut blocks = vec![];
+        for (z, layer) in self.layers.iter().enumerate() {
+            for (y, row) in layer.iter().enumerate() {
+                for (x, key) in row.chars().enumerate() {
+                    match key {
+                        Self::KEEP => {}
+                        Self::AIR => blocks.push(([x, y, z], None)),
+                        _ => blocks.extend(self.palette.get(&key).map(|block| ([x, y, z], Some(block)))),
+                    }
+                }
+            }
+        }
+        blocks
+    }
+
+    // Tags and the blocks of the palette
+    fn references(&self) -> Vec<(RegistryKey, &ID)> {
+        let blocks = self.palette.values().map(|state| (RegistryKey::BLOCK, &state.block));
+        self.tags.iter().map(|t| (RegistryKey::TAG, t)).chain(blocks).collect()
+    }
+}
+
+impl Registrable for Structure {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Structure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+// -------
+// SPRITES
+// -------
+
+// One character of a sprite, None colors use the terminal's default
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpriteCell {
+    pub symbol: char,
+    pub color: Option<Color>,
+}
+
+// ASCII/Unicode art, e.g. the look of the item, block or entity with the
+// same ID. Empty cells are transparent. See the sprite module for loading,
+// composing and drawing sprites.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sprite {
+    pub id: ID,
+    width: usize,
+    height: usize,
+    cells: Vec<Option<SpriteCell>>, // Row by row
+}
+
+impl Sprite {
+    // Uncolored art, one row per line. Spaces are empty, shorter lines are
+    // padded with empty cells.
+    pub fn new(id: ID, art: &str) -> Self {
+        let rows: Vec<Vec<char>> = art.lines().map(|line| line.chars().collect()).collect();
+        let mut sprite = Sprite::blank(id, rows.iter().map(Vec::len).max().unwrap_or(0), rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &symbol) in row.iter().enumerate() {
+                if symbol != ' ' {
+                    sprite.set(x, y, Some(SpriteCell { symbol, color: None }));
+                }
+            }
+        }
+        sprite
+    }
+
+    pub fn blank(id: ID, width: usize, height: usize) -> Self {
+        Sprite { id, width, height, cells: vec![None; width * height] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // None for empty cells and positions outside the sprite
+    pub fn get(&self, x: usize, y: usize) -> Option<SpriteCell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells[y * self.width + x]
+    }
+
+    // Positions outside the sprite are ignored
+    pub fn set(&mut self, x: usize, y: usize, cell: Option<SpriteCell>) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = cell;
+        }
+    }
+}
+
+impl Registrable for Sprite {
+    fn id(&self) -> &ID {
+        &self.id
+    }
+}
+
+impl Display for Sprite {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+// --------
+// REGISTRY
+// --------
+
+pub trait Registrable {
+    fn id(&self) -> &ID;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegistrableEntity {
+    Item(Item),
+    Block(Block),
+    Tag(Tag),
+    Tool(Tool),
+    Recipe(Recipe),
+    LootTable(LootTable),
+    Entity(Entity),
+    Enchantment(Enchantment),
+    Quest(Quest),
+    Effect(Effect),
+    Dialogue(Dialogue),
+    Sprite(Sprite),
+    Biome(Biome),
+    Structure(Structure),
+}
+
+impl RegistrableEntity {
+    pub fn id(&self) -> &ID {
+        match self {
+            RegistrableEntity::Item(item) => &item.id,
+            RegistrableEntity::Block(block) => &block.id,
+            RegistrableEntity::Tag(tag) => &tag.id,
+            RegistrableEntity::Tool(tool) => &tool.id,
+            RegistrableEntity::Recipe(recipe) => &recipe.id,
+            RegistrableEntity::LootTable(loot_table) => &loot_table.id,
+            RegistrableEntity::Entity(entity) => &entity.id,
+            RegistrableEntity::Enchantment(enchantment) => &enchantment.id,
+            RegistrableEntity::Quest(quest) => &quest.id,
+            RegistrableEntity::Effect(effect) => &effect.id,
+            RegistrableEntity::Dialogue(dialogue) => &dialogue.id,
+            RegistrableEntity::Sprite(sprite) => &sprite.id,
+            RegistrableEntity::Biome(biome) => &biome.id,
+            RegistrableEntity::Structure(structure) => &structure.id,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            RegistrableEntity::Item(_) => "Item",
+            RegistrableEntity::Block(_) => "Block",
+            RegistrableEntity::Tag(_) => "Tag",
+            RegistrableEntity::Tool(_) => "Tool",
+            RegistrableEntity::Recipe(_) => "Recipe",
+            RegistrableEntity::LootTable(_) => "LootTable",
+            RegistrableEntity::Entity(_) => "Entity",
+            RegistrableEntity::Enchantment(_) => "Enchantment",
+            RegistrableEntity::Quest(_) => "Quest",
+            RegistrableEntity::Effect(_) => "Effect",
+            RegistrableEntity::Dialogue(_) => "Dialogue",
+            RegistrableEntity::Sprite(_) => "Sprite",
+            RegistrableEntity::Biome(_) => "Biome",
+            RegistrableEntity::Structure(_) => "Structure",
+        }
+    }
+
+    pub fn key(&self) -> RegistryKey {
+        RegistryKey(self.type_name())
+    }
+
+    pub fn phase(&self) -> Phase {
+        match self {
+            RegistrableEntity::Tag(_) => Phase::Tags,
+            RegistrableEntity::Item(_)
+            | RegistrableEntity::Block(_)
+            | RegistrableEntity::Tool(_)
+            | RegistrableEntity::Entity(_)
+            | RegistrableEntity::Enchantment(_)
+            | RegistrableEntity::Effect(_)
+            | RegistrableEntity::Sprite(_)
+            | RegistrableEntity::Biome(_) => Phase::Content,
+            RegistrableEntity::Structure(_) => Phase::Structures,
+            RegistrableEntity::Recipe(_) => Phase::Recipes,
+            RegistrableEntity::LootTable(_) => Phase::LootTables,
+            RegistrableEntity::Quest(_) => Phase::Quests,
+            RegistrableEntity::Dialogue(_) => Phase::Dialogues,
+        }
+    }
+
+    // Tags, quests, items and blocks that must be registered before this entity
+    fn references(&self) -> Vec<(RegistryKey, &ID)> {
+        fn tags(tags: &[ID]) -> Vec<(RegistryKey, &ID)> {
+            tags.iter().map(|t| (RegistryKey::TAG, t)).collect()
+        }
+        match self {
+            RegistrableEntity::Item(item) => tags(&item.tags),
+            RegistrableEntity::Block(block) => tags(&block.tags),
+            RegistrableEntity::Entity(entity) => tags(&entity.tags),
+            RegistrableEntity::Biome(biome) => tags(&biome.tags),
+            RegistrableEntity::Structure(structure) => structure.references(),
+            RegistrableEntity::Enchantment(enchantment) => tags(&enchantment.applicable_tags),
+            RegistrableEntity::Quest(quest) => quest.prerequisites.iter().map(|p| (RegistryKey::QUEST, p)).collect(),
+            RegistrableEntity::Recipe(recipe) => recipe.references(),
+            RegistrableEntity::Dialogue(dialogue) => dialogue.references(),
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegistryError {
+    InvalidId(IdError),
+    AlreadyExists(RegistryKey, ID),
+    NotFound(RegistryKey, ID),
+    MissingReference { key: RegistryKey, id: ID, missing: (RegistryKey, ID) },
+    UnresolvedPrerequisites(Vec<ID>),  // Quests whose prerequisites form a cycle
+    Conflicts(Vec<(RegistryKey, ID)>), // See ConflictPolicy::Fail
+    InvalidRange { min: u32, max: u32 },
+    InvalidChance(f32),                // Not between 0.0 and 1.0
+    Multiple(Vec<RegistryError>),
+}
+
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            RegistryError::InvalidId(e) => write!(f, "invalid ID: {}", e),
+            RegistryError::AlreadyExists(key, id) => write!(f, "{} {} already exists", key, id),
+            RegistryError::NotFound(key, id) => write!(f, "{} {} does not exist", key, id),
+            RegistryError::MissingReference { key, id, missing } => {
+                write!(f, "{} {} references missing {} {}", key, id, missing.0, missing.1)
+            }
+            RegistryError::UnresolvedPrerequisites(quests) => {
+                let quests: Vec<String> = quests.iter().map(ID::to_string).collect();
+                write!(f, "Quest prerequisites can't be resolved: {}", quests.join(", "))
+            }
+            RegistryError::Conflicts(entities) => {
+                let entities: Vec<String> = entities.iter().map(|(key, id)| format!("{} {}", key, id)).collect();
+                write!(f, "Conflicting entities: {}", entities.join(", "))
+            }
+            RegistryError::InvalidRange { min, max } => write!(f, "Minimum {} is greater than maximum {}", min, max),
+            RegistryError::InvalidChance(chance) => write!(f, "Chance {} is not between 0.0 and 1.0", chance),
+            RegistryError::Multiple(errors) => {
+                let errors: Vec<String> = errors.iter().map(RegistryError::to_string).collect();
+                write!(f, "{}", errors.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+impl From<IdError> for RegistryError {
+    fn from(e: IdError) -> Self {
+        RegistryError::InvalidId(e)
+    }
+}
+
+// ---------
+// CHANGELOG
+// ---------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Register,
+    Remove,
+    Override,
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ChangeKind::Register => write!(f, "register"),
+            ChangeKind::Remove => write!(f, "remove"),
+            ChangeKind::Override => write!(f, "override"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RegistryEvent {
+    pub kind: ChangeKind,
+    pub entity_type: &'static str, // e.g. "Item", "LootTable"
+    pub id: ID,
+    pub source: String,            // Namespace that made the change
+    pub timestamp: SystemTime,
+}
+
+impl Display for RegistryEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let millis = self.timestamp.duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+        write!(f, "{} {} {} {} by {}", millis, self.kind, self.entity_type, self.id, self.source)
+    }
+}
+
+// Registered content at one point in time, see Registry::snapshot
+#[derive(Clone, Debug)]
+pub struct RegistrySnapshot {
+    items: HashMap<ID, Item>,
+    blocks: HashMap<ID, Block>,
+    tags: HashMap<ID, Tag>,
+    tools: HashMap<ID, Tool>,
+    recipes: HashMap<ID, Recipe>,
+    loot_tables: HashMap<ID, LootTable>,
+    entities: HashMap<ID, Entity>,
+    enchantments: HashMap<ID, Enchantment>,
+    quests: HashMap<ID, Quest>,
+    effects: HashMap<ID, Effect>,
+    dialogues: HashMap<ID, Dialogue>,
+    sprites: HashMap<ID, Sprite>,
+    biomes: HashMap<ID, Biome>,
+    structures: HashMap<ID, Structure>,
+}
+
+// --------
+// PIPELINE
+// --------
+
+// Registration phases in the order RegistrationPipeline runs them
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Phase {
+    Tags,
+    Content, // Items, blocks, tools, entities, enchantments, effects, sprites and biomes
+    Structures, // After the blocks they are built from
+    Recipes,
+    LootTables,
+    Quests, // After their prerequisites
+    Dialogues, // After the quests and items they refer to
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Phase::Tags => write!(f, "tags"),
+            Phase::Content => write!(f, "content"),
+            Phase::Structures => write!(f, "structures"),
+            Phase::Recipes => write!(f, "recipes"),
+            Phase::LootTables => write!(f, "loot tables"),
+            Phase::Quests => write!(f, "quests"),
+            Phase::Dialogues => write!(f, "dialogues"),
+        }
+    }
+}
+
+// Collects content in any order and registers it phase by phase, so e.g. an
+// item can be added before the tag it's in. References are checked against
+// the registry and the collected content before anything is registered.
+#[derive(Clone, Debug, Default)]
+pub struct RegistrationPipeline {
+    pending: Vec<RegistrableEntity>,
+}
+
+impl RegistrationPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, entity: RegistrableEntity) {
+        self.pending.push(entity);
+    }
+
+    pub fn with(mut self, entity: RegistrableEntity) -> Self {
+        self.add(entity);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    // Registers everything, returns how many entities were registered. On
+    // duplicate IDs, unresolved references or prerequisite cycles the
+    // registry stays unchanged and all problems are reported at once.
+    pub fn run(self, registry: &mut Registry) -> std::result::Result<usize, RegistryError> {
+        let mut errors = vec![];
+        let mut seen: HashSet<(RegistryKey, &ID)> = HashSet::new();
+        for entity in &self.pending {
+            let key = (entity.key(), entity.id());
+            if registry.find(entity).is_some() || !seen.insert(key) {
+                errors.push(RegistryError::AlreadyExists(key.0, *key.1));
+            }
+        }
+        for entity in &self.pending {
+            for (key, id) in entity.references() {
+                if !registry.contains(key, id) && !seen.contains(&(key, id)) {
+                    let missing = (key, *id);
+                    errors.push(RegistryError::MissingReference { key: entity.key(), id: *entity.id(), missing });
+                }
+            }
+        }
+
+        let mut pending = self.pending;
+        pending.sort_by_key(|e| e.phase()); // Stable, keeps the order within a phase
+        let mut ordered: Vec<RegistrableEntity> = vec![];
+        let mut quests: Vec<RegistrableEntity> = vec![];
+        let mut dialogues: Vec<RegistrableEntity> = vec![];
+        for entity in pending {
+            match entity {
+                RegistrableEntity::Quest(_) => quests.push(entity),
+                RegistrableEntity::Dialogue(_) => dialogues.push(entity),
+                _ => ordered.push(entity),
+            }
+        }
+        while !quests.is_empty() {
+            let ready = quests.iter().position(|quest| {
+                quest.references().iter().all(|(_, p)| {
+                    registry.quests.contains_key(*p) || ordered.iter().any(|e| e.key() == RegistryKey::QUEST && e.id() == *p)
+                })
+            });
+            match ready {
+                Some(index) => ordered.push(quests.remove(index)),
+                None => {
+                    if errors.is_empty() {
+                        errors.push(RegistryError::UnresolvedPrerequisites(quests.iter().map(|q| *q.id()).collect()));
+                    }
+                    break;
+                }
+            }
+        }
+        ordered.extend(dialogues);
+        match errors.len() {
+            0 => {}
+            1 => return Err(errors.remove(0)),
+            _ => return Err(RegistryError::Multiple(errors)),
+        }
+
+        let count = ordered.len();
+        for entity in ordered {
+            registry.register(entity)?;
+        }
+        Ok(count)
+    }
+}
+
+// Builds a RegistrationPipeline from content written out declaratively:
+//
+//   register! {
+//       tags { "ruz:fuel" }
+//       items { "ruztex:coal" { tags: ["ruz:fuel"], stack_size: 64 } }
+//       blocks { "ruztex:coal" { tags: ["ruz:fuel"], hardness: 5.0 } }
+//   }
+//   .run(&mut registry)?;
+//
+// Sections (tags, items, blocks, tools, entities) can come in any order, the
+// pipeline sorts out the registration order. IDs are checked like id!
+// literals. Fields are the entity's public fields, omitted ones keep their
+// default: stack_size 64, hardness 1.0, durability 100, level 0, speed 1.0,
+// max_health 20. A list of string literals becomes a list of IDs; values of
+// more than one token (e.g. negative numbers) go in parentheses.
+#[macro_export]
+macro_rules! register {
+    ($($section:ident { $($id:literal $({ $($field:ident: $value:tt),* $(,)? })?)* })*) => {{
+        let mut pipeline = $crate::registry::RegistrationPipeline::new();
+        $($(
+            pipeline.add($crate::__register_entity!($section $id { $($($field: $value),*)? }));
+        )*)*
+        pipeline
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __register_entity {
+    (@build $variant:ident $init:expr, $($field:ident: $value:tt),*) => {{
+        #[allow(unused_mut)]
+        let mut entity = $init;
+        $($crate::__register_field!(entity.$field = $value);)*
+        $crate::registry::RegistrableEntity::$variant(entity)
+    }};
+    (tags $id:literal { $($field:ident: $value:tt),* }) => {
+        $crate::__register_entity!(@build Tag $crate::registry::Tag::new($crate::id!($id)), $($field: $value),*)
+    };
+    (items $id:literal { $($field:ident: $value:tt),* }) => {
+        $crate::__register_entity!(@build Item $crate::registry::Item::new($crate::id!($id), vec![], 64), $($field: $value),*)
+    };
+    (blocks $id:literal { $($field:ident: $value:tt),* }) => {
+        $crate::__register_entity!(@build Block $crate::registry::Block::new($crate::id!($id), vec![], 1.0), $($field: $value),*)
+    };
+    (tools $id:literal { $($field:ident: $value:tt),* }) => {
+        $crate::__register_entity!(@build Tool $crate::registry::Tool::new($crate::id!($id), vec![], 100, 0, 1.0), $($field: $value),*)
+    };
+    (entities $id:literal { $($field:ident: $value:tt),* }) => {
+        $crate::__register_entity!(@build Entity $crate::registry::Entity::new($crate::id!($id), vec![], 20), $($field: $value),*)
+    };
+    ($section:ident $id:literal { $($rest:tt)* }) => {
+        compile_error!(concat!("unknown register! section '", stringify!($section), "'"))
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __register_field {
+    ($entity:ident . $field:ident = [$($id:literal),* $(,)?]) => {
+        $entity.$field = vec![$($crate::id!($id)),*]
+    };
+    ($entity:ident . $field:ident = $value:tt) => {
+        $entity.$field = $value
+    };
+}
+
+// -----------------
+// BULK REGISTRATION
+// -----------------
+
+// Builds a large batch of content (e.g. all entries of a datapack) without
+// holding the registry lock, then registers it in one go. With the
+// `parallel` feature, sources are parsed on rayon's thread pool.
+#[derive(Clone, Debug, Default)]
+pub struct RegistryBuilder {
+    pipeline: RegistrationPipeline,
+    errors: Vec<RegistryError>, // Of sources that failed to parse, in source order
+}
+
+impl RegistryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, entity: RegistrableEntity) {
+        self.pipeline.add(entity);
+    }
+
+    // Turns every source into an entity, e.g. datapack lines into items.
+    // Parse errors are reported by commit.
+    pub fn parse<S, F>(&mut self, sources: &[S], parse: F)
+    where
+        S: Sync,
+        F: Fn(&S) -> std::result::Result<RegistrableEntity, RegistryError> + Sync,
+    {
+        #[cfg(feature = "parallel")]
+        let results: Vec<_> = {
+            use rayon::prelude::*;
+            sources.par_iter().map(&parse).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let results: Vec<_> = sources.iter().map(&parse).collect();
+
+        for result in results {
+            match result {
+                Ok(entity) => self.pipeline.add(entity),
+                Err(e) => self.errors.push(e),
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pipeline.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pipeline.is_empty()
+    }
+
+    // Write-locks `registry` once and registers the whole batch like
+    // RegistrationPipeline::run. Nothing is registered if a source failed to
+    // parse or the batch doesn't validate.
+    pub fn commit(mut self, registry: &RwLock<Registry>) -> std::result::Result<usize, RegistryError> {
+        match self.errors.len() {
+            0 => {}
+            1 => return Err(self.errors.remove(0)),
+            _ => return Err(RegistryError::Multiple(self.errors)),
+        }
+        self.pipeline.run(&mut registry.write().unwrap())
+    }
+}
+
+// ------
+// SEARCH
+// ------
+
+// Registry entry matching a search, see Registry::search
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHit {
+    pub key: RegistryKey,
+    pub id: ID,
+    pub score: u32,      // Higher is better
+    pub matched: String, // The ID, name or display name that matched best
+}
+
+// Searchable texts of every registered entity: its ID, its name and the
+// display names added with Registry::index_display_names. Kept up to date by
+// the registry when entities are registered or removed.
+#[derive(Clone, Debug, Default)]
+pub struct SearchIndex {
+    entries: HashMap<(RegistryKey, ID), Vec<String>>, // Lowercase, ID first
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, key: RegistryKey, id: &ID) {
+        self.entries.entry((key, *id)).or_insert_with(|| vec![id.to_string(), id.name().to_string()]);
+    }
+
+    pub fn remove(&mut self, key: RegistryKey, id: &ID) {
+        self.entries.remove(&(key, *id));
+    }
+
+    // Adds a display name, e.g. a translation, if the entry is indexed
+    pub fn add_name(&mut self, key: RegistryKey, id: &ID, name: &str) {
+        if let Some(terms) = self.entries.get_mut(&(key, *id)) {
+            let name = name.to_lowercase();
+            if !terms.contains(&name) {
+                terms.push(name);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // At most `limit` hits, best first, ties sorted by ID
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return vec![];
+        }
+        let mut hits: Vec<SearchHit> = self
+            .entries
+            .iter()
+            .filter_map(|((key, id), terms)| {
+                let (score, matched) = terms.iter().map(|t| (score(&query, t), t)).max_by_key(|(score, _)| *score)?;
+                (score > 0).then(|| SearchHit { key: *key, id: *id, score, matched: matched.clone() })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.id.to_string().cmp(&b.id.to_string())).then(a.key.cmp(&b.key)));
+        hits.truncate(limit);
+        hits
+    }
+}
+
+// How well `term` matches `query` (both lowercase): exact > prefix >
+// substring > all characters in order > similar spelling, 0 for no match.
+// Shorter terms rank higher within a kind of match.
+fn score(query: &str, term: &str) -> u32 {
+    let extra = term.chars().count().saturating_sub(query.chars().count()).min(99) as u32;
+    if term == query {
+        return 1000;
+    }
+    if term.starts_with(query) {
+        return 800 - extra;
+    }
+    if let Some(pos) = term.find(query) {
+        return 600 - (pos as u32).min(99) - extra.min(99) / 2;
+    }
+    let mut chars = term.chars();
+    if query.chars().all(|q| chars.any(|c| c == q)) {
+        return 400 - extra;
+    }
+    let similarity = crate::localization::similarity(query, term);
+    if similarity >= 0.6 {
+        return (similarity * 200.0) as u32;
+    }
+    0
+}
+
+// --------------
+// DIFF AND MERGE
+// --------------
+
+// How Registry::merge resolves an entity registered in both registries
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    KeepExisting, // The merged registry's version is dropped
+    Override,     // Replaced like override_entity, tag entries are combined
+    Fail,         // Nothing is merged
+}
+
+// (entity type, ID) pairs, in registration order
+#[derive(Clone, Debug, Default)]
+pub struct RegistryDiff {
+    pub added: Vec<(&'static str, ID)>,
+    pub changed: Vec<(&'static str, ID)>,
+    pub removed: Vec<(&'static str, ID)>,
+}
+
+impl RegistryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+// One line per entity: "+ Item ruztex:apple", "~ ..." (changed), "- ..." (removed)
+impl Display for RegistryDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for (prefix, entries) in [("+", &self.added), ("~", &self.changed), ("-", &self.removed)] {
+            for (typ, id) in entries {
+                writeln!(f, "{} {} {}", prefix, typ, id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MergeReport {
+    pub added: Vec<(&'static str, ID)>,
+    pub overridden: Vec<(&'static str, ID)>,
+    pub kept: Vec<(&'static str, ID)>, // Conflicts resolved with KeepExisting
+}
+
+// One line per entity: "+ ..." (added), "! ..." (overridden), "= ..." (kept)
+impl Display for MergeReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for (prefix, entries) in [("+", &self.added), ("!", &self.overridden), ("=", &self.kept)] {
+            for (typ, id) in entries {
+                writeln!(f, "{} {} {}", prefix, typ, id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Registry {
+    pub items: HashMap<ID, Item>,
+    pub blocks: HashMap<ID, Block>,
+    pub tags: HashMap<ID, Tag>,
+    pub tools: HashMap<ID, Tool>,
+    pub recipes: HashMap<ID, Recipe>,
+    pub loot_tables: HashMap<ID, LootTable>,
+    pub entities: HashMap<ID, Entity>,
+    pub enchantments: HashMap<ID, Enchantment>,
+    pub quests: HashMap<ID, Quest>,
+    pub effects: HashMap<ID, Effect>,
+    pub dialogues: HashMap<ID, Dialogue>,
+    pub sprites: HashMap<ID, Sprite>,
+    pub biomes: HashMap<ID, Biome>,
+    pub structures: HashMap<ID, Structure>,
+    pub changelog: Vec<RegistryEvent>, // Append-only, see log()
+    source: Option<String>,
+    search_index: SearchIndex,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry {
+            items: HashMap::new(),
+            blocks: HashMap::new(),
+            tags: HashMap::new(),
+            tools: HashMap::new(),
+            recipes: HashMap::new(),
+            loot_tables: HashMap::new(),
+            entities: HashMap::new(),
+            enchantments: HashMap::new(),
+            quests: HashMap::new(),
+            effects: HashMap::new(),
+            dialogues: HashMap::new(),
+            sprites: HashMap::new(),
+            biomes: HashMap::new(),
+            structures: HashMap::new(),
+            changelog: Vec::new(),
+            source: None,
+            search_index: SearchIndex::new(),
+        }
+    }
+
+    // Namespace recorded as source of the following changes (e.g. the mod that is
+    // currently registering its content). Without one, the entity's namespace is used.
+    pub fn set_source(&mut self, namespace: Option<&str>) {
+        self.source = namespace.map(str::to_string);
+    }
+
+    fn log(&mut self, kind: ChangeKind, entity: &RegistrableEntity) {
+        let source = self.source.clone().unwrap_or_else(|| entity.id().namespace().to_string());
+        self.changelog.push(RegistryEvent {
+            kind,
+            entity_type: entity.type_name(),
+            id: *entity.id(),
+            source,
+            timestamp: SystemTime::now(),
+        });
+    }
+
+    // All changes made to the entity with this ID, oldest first
+    pub fn history_of<'a>(&'a self, id: &'a ID) -> impl Iterator<Item = &'a RegistryEvent> {
+        self.changelog.iter().filter(move |e| &e.id == id)
+    }
+
+    // All changes made by a namespace, oldest first
+    pub fn changes_by<'a>(&'a self, source: &'a str) -> impl Iterator<Item = &'a RegistryEvent> {
+        self.changelog.iter().filter(move |e| e.source == source)
+    }
+
+    // Recipes the player can craft from, sorted by ID: the ones without an
+    // unlock condition and the ones in the profile's recipe book
+    pub fn unlocked_recipes(&self, profile: &PlayerProfile) -> Vec<&Recipe> {
+        let mut recipes: Vec<&Recipe> = self.recipes.values().filter(|r| profile.recipes.is_unlocked(r)).collect();
+        recipes.sort_by_key(|r| r.id.to_string());
+        recipes
+    }
+
+    // Hash of the registered IDs per namespace, e.g. to detect that a save
+    // was made with different content. Like ID::stable_hash, it's stable
+    // between versions.
+    pub fn namespace_hashes(&self) -> BTreeMap<String, u64> {
+        let mut entries: Vec<(String, String)> = vec![];
+        let mut add = |typ: &str, id: &ID| entries.push((id.namespace().to_string(), format!("{} {}", typ, id)));
+        self.items.keys().for_each(|id| add("Item", id));
+        self.blocks.keys().for_each(|id| add("Block", id));
+        self.tags.keys().for_each(|id| add("Tag", id));
+        self.tools.keys().for_each(|id| add("Tool", id));
+        self.recipes.keys().for_each(|id| add("Recipe", id));
+        self.loot_tables.keys().for_each(|id| add("LootTable", id));
+        self.entities.keys().for_each(|id| add("Entity", id));
+        self.enchantments.keys().for_each(|id| add("Enchantment", id));
+        self.quests.keys().for_each(|id| add("Quest", id));
+        self.effects.keys().for_each(|id| add("Effect", id));
+        self.dialogues.keys().for_each(|id| add("Dialogue", id));
+        self.sprites.keys().for_each(|id| add("Sprite", id));
+        self.biomes.keys().for_each(|id| add("Biome", id));
+        self.structures.keys().for_each(|id| add("Structure", id));
+        entries.sort();
+
+        let mut hashes = BTreeMap::new();
+        for (namespace, entry) in entries {
+            let hash = hashes.entry(namespace).or_insert(FNV_OFFSET);
+            *hash = fnv1a(*hash, entry.bytes().chain(std::iter::once(b'\n')));
+        }
+        hashes
+    }
+
+    // One event per line, e.g. for bug reports
+    pub fn export_changelog(&self) -> String {
+        self.changelog.iter().map(|e| format!("{}\n", e)).collect()
+    }
+
+    // Fails if the ID is taken or a tag or prerequisite quest it refers to
+    // isn't registered yet; the registry is unchanged then
+    pub fn register(&mut self, entity: RegistrableEntity) -> std::result::Result<(), RegistryError> {
+        if self.find(&entity).is_some() {
+            return Err(RegistryError::AlreadyExists(entity.key(), *entity.id()));
+        }
+        self.check_references(&entity)?;
+        self.log(ChangeKind::Register, &entity);
+        self.insert(entity);
+        Ok(())
+    }
+
+    // Replaces an already registered entity with the same ID (or registers it)
+    pub fn override_entity(&mut self, entity: RegistrableEntity) -> std::result::Result<(), RegistryError> {
+        self.check_references(&entity)?;
+        self.log(ChangeKind::Override, &entity);
+        let id = *entity.id();
+        match &entity {
+            RegistrableEntity::Quest(quest) => {
+                // Keep other quests' prerequisites pointing at the replaced one
+                self.quests.remove(&id);
+                self.insert(RegistrableEntity::Quest(quest.clone()));
+                return Ok(());
+            }
+            RegistrableEntity::Tag(tag) => {
+                // Keep the entries of the replaced tag
+                let mut tag = tag.clone();
+                if let Some(old) = self.tags.get(&id) {
+                    tag.entries.extend(old.entries.iter().cloned());
+                }
+                self.tags.insert(id, tag);
+                return Ok(());
+            }
+            _ => {}
+        }
+        if let Some(existing) = self.find(&entity) {
+            self.delete(&existing);
+        }
+        self.insert(entity);
+        Ok(())
+    }
+
+    fn check_references(&self, entity: &RegistrableEntity) -> std::result::Result<(), RegistryError> {
+        match entity.references().into_iter().find(|(key, id)| !self.contains(*key, id)) {
+            Some((key, id)) => Err(RegistryError::MissingReference { key: entity.key(), id: *entity.id(), missing: (key, *id) }),
+            None => Ok(()),
+        }
+    }
+
+    // Whether `biome` is `id`, or `id` is a tag the registered biome is in
+    pub fn biome_matches(&self, biome: &ID, id: &ID) -> bool {
+        biome == id
+            || self.biomes.get(biome).is_some_and(|b| b.tags.contains(id))
+            || self.tags.get(id).is_some_and(|t| t.contains(RegistryKey::BIOME, biome))
+    }
+
+    // Whether an entity of the built-in registry `key` is registered under `id`
+    pub fn contains(&self, key: RegistryKey, id: &ID) -> bool {
+        match key {
+            RegistryKey::ITEM => self.items.contains_key(id),
+            RegistryKey::BLOCK => self.blocks.contains_key(id),
+            RegistryKey::TAG => self.tags.contains_key(id),
+            RegistryKey::TOOL => self.tools.contains_key(id),
+            RegistryKey::RECIPE => self.recipes.contains_key(id),
+            RegistryKey::LOOT_TABLE => self.loot_tables.contains_key(id),
+            RegistryKey::ENTITY => self.entities.contains_key(id),
+            RegistryKey::ENCHANTMENT => self.enchantments.contains_key(id),
+            RegistryKey::QUEST => self.quests.contains_key(id),
+            RegistryKey::EFFECT => self.effects.contains_key(id),
+            RegistryKey::DIALOGUE => self.dialogues.contains_key(id),
+            RegistryKey::SPRITE => self.sprites.contains_key(id),
+            RegistryKey::BIOME => self.biomes.contains_key(id),
+            RegistryKey::STRUCTURE => self.structures.contains_key(id),
+            _ => false,
+        }
+    }
+
+    // The entity of the built-in registry `key` registered under `id`
+    pub fn entity(&self, key: RegistryKey, id: &ID) -> Option<RegistrableEntity> {
+        match key {
+            RegistryKey::ITEM => self.items.get(id).cloned().map(RegistrableEntity::Item),
+            RegistryKey::BLOCK => self.blocks.get(id).cloned().map(RegistrableEntity::Block),
+            RegistryKey::TAG => self.tags.get(id).cloned().map(RegistrableEntity::Tag),
+            RegistryKey::TOOL => self.tools.get(id).cloned().map(RegistrableEntity::Tool),
+            RegistryKey::RECIPE => self.recipes.get(id).cloned().map(RegistrableEntity::Recipe),
+            RegistryKey::LOOT_TABLE => self.loot_tables.get(id).cloned().map(RegistrableEntity::LootTable),
+            RegistryKey::ENTITY => self.entities.get(id).cloned().map(RegistrableEntity::Entity),
+            RegistryKey::ENCHANTMENT => self.enchantments.get(id).cloned().map(RegistrableEntity::Enchantment),
+            RegistryKey::QUEST => self.quests.get(id).cloned().map(RegistrableEntity::Quest),
+            RegistryKey::EFFECT => self.effects.get(id).cloned().map(RegistrableEntity::Effect),
+            RegistryKey::DIALOGUE => self.dialogues.get(id).cloned().map(RegistrableEntity::Dialogue),
+            RegistryKey::SPRITE => self.sprites.get(id).cloned().map(RegistrableEntity::Sprite),
+            RegistryKey::BIOME => self.biomes.get(id).cloned().map(RegistrableEntity::Biome),
+            RegistryKey::STRUCTURE => self.structures.get(id).cloned().map(RegistrableEntity::Structure),
+            _ => None,
+        }
+    }
+
+    // IDs registered in the built-in registry `key`, sorted
+    pub fn ids(&self, key: RegistryKey) -> Vec<ID> {
+        fn sorted<T>(map: &HashMap<ID, T>) -> Vec<ID> {
+            let mut ids: Vec<ID> = map.keys().copied().collect();
+            ids.sort_by_key(|id| id.to_string());
+            ids
+        }
+        match key {
+            RegistryKey::ITEM => sorted(&self.items),
+            RegistryKey::BLOCK => sorted(&self.blocks),
+            RegistryKey::TAG => sorted(&self.tags),
+            RegistryKey::TOOL => sorted(&self.tools),
+            RegistryKey::RECIPE => sorted(&self.recipes),
+            RegistryKey::LOOT_TABLE => sorted(&self.loot_tables),
+            RegistryKey::ENTITY => sorted(&self.entities),
+            RegistryKey::ENCHANTMENT => sorted(&self.enchantments),
+            RegistryKey::QUEST => sorted(&self.quests),
+            RegistryKey::EFFECT => sorted(&self.effects),
+            RegistryKey::DIALOGUE => sorted(&self.dialogues),
+            RegistryKey::SPRITE => sorted(&self.sprites),
+            RegistryKey::BIOME => sorted(&self.biomes),
+            RegistryKey::STRUCTURE => sorted(&self.structures),
+            _ => vec![],
+        }
+    }
+
+
+    // The registered entity of the same type with the same ID
+    fn find(&self, entity: &RegistrableEntity) -> Option<RegistrableEntity> {
+        let id = entity.id();
+        match entity {
+            RegistrableEntity::Item(_) => self.items.get(id).cloned().map(RegistrableEntity::Item),
+            RegistrableEntity::Block(_) => self.blocks.get(id).cloned().map(RegistrableEntity::Block),
+            RegistrableEntity::Tag(_) => self.tags.get(id).cloned().map(RegistrableEntity::Tag),
+            RegistrableEntity::Tool(_) => self.tools.get(id).cloned().map(RegistrableEntity::Tool),
+            RegistrableEntity::Recipe(_) => self.recipes.get(id).cloned().map(RegistrableEntity::Recipe),
+            RegistrableEntity::LootTable(_) => self.loot_tables.get(id).cloned().map(RegistrableEntity::LootTable),
+            RegistrableEntity::Entity(_) => self.entities.get(id).cloned().map(RegistrableEntity::Entity),
+            RegistrableEntity::Enchantment(_) => self.enchantments.get(id).cloned().map(RegistrableEntity::Enchantment),
+            RegistrableEntity::Quest(_) => self.quests.get(id).cloned().map(RegistrableEntity::Quest),
+            RegistrableEntity::Effect(_) => self.effects.get(id).cloned().map(RegistrableEntity::Effect),
+            RegistrableEntity::Dialogue(_) => self.dialogues.get(id).cloned().map(RegistrableEntity::Dialogue),
+            RegistrableEntity::Sprite(_) => self.sprites.get(id).cloned().map(RegistrableEntity::Sprite),
+            RegistrableEntity::Biome(_) => self.biomes.get(id).cloned().map(RegistrableEntity::Biome),
+            RegistrableEntity::Structure(_) => self.structures.get(id).cloned().map(RegistrableEntity::Structure),
+        }
+    }
+
+    // Every registered entity in an order they can be registered in: tags
+    // and loot tables before what refers to them, quests after their
+    // prerequisites, dialogues last. Sorted by ID within each type.
+    fn all_entities(&self) -> Vec<RegistrableEntity> {
+        fn sorted<T: Clone>(map: &HashMap<ID, T>, wrap: fn(T) -> RegistrableEntity) -> Vec<RegistrableEntity> {
+            let mut entries: Vec<(&ID, &T)> = map.iter().collect();
+            entries.sort_by_key(|(id, _)| id.to_string());
+            entries.into_iter().map(|(_, value)| wrap(value.clone())).collect()
+        }
+        let mut entities = sorted(&self.tags, RegistrableEntity::Tag);
+        entities.extend(sorted(&self.loot_tables, RegistrableEntity::LootTable));
+        entities.extend(sorted(&self.items, RegistrableEntity::Item));
+        entities.extend(sorted(&self.blocks, RegistrableEntity::Block));
+        entities.extend(sorted(&self.tools, RegistrableEntity::Tool));
+        entities.extend(sorted(&self.recipes, RegistrableEntity::Recipe));
+        entities.extend(sorted(&self.entities, RegistrableEntity::Entity));
+        entities.extend(sorted(&self.enchantments, RegistrableEntity::Enchantment));
+        entities.extend(sorted(&self.effects, RegistrableEntity::Effect));
+        entities.extend(sorted(&self.sprites, RegistrableEntity::Sprite));
+        entities.extend(sorted(&self.biomes, RegistrableEntity::Biome));
+        entities.extend(sorted(&self.structures, RegistrableEntity::Structure));
+
+        let mut quests: Vec<&Quest> = self.quests.values().collect();
+        quests.sort_by_key(|q| q.id.to_string());
+        let mut ordered: Vec<ID> = vec![];
+        while ordered.len() < quests.len() {
+            let before = ordered.len();
+            for quest in &quests {
+                let ready = quest.prerequisites.iter().all(|p| ordered.contains(p) || !self.quests.contains_key(p));
+                if !ordered.contains(&quest.id) && ready {
+                    ordered.push(quest.id);
+                }
+            }
+            if ordered.len() == before {
+                break; // Prerequisite cycle, can't be registered anyway
+            }
+        }
+        entities.extend(ordered.iter().map(|id| RegistrableEntity::Quest(self.quests[id].clone())));
+        entities.extend(sorted(&self.dialogues, RegistrableEntity::Dialogue));
+        entities
+    }
+
+    // Search
+
+    // Registered entities matching `query` by ID, name or display name, best
+    // first, e.g. search("coa", 10) finds ruztex:coal
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        self.search_index.search(query, limit)
+    }
+
+    // Makes the translated names of all registered entities searchable, e.g.
+    // "Block of Coal" for examplemod:block.coal
+    pub fn index_display_names(&mut self, translator: &Translator) {
+        for (key, id) in self.search_index.entries.keys().copied().collect::<Vec<_>>() {
+            let translation_id = TranslationID::from_id(&id, &key.name().to_lowercase());
+            let name = translator.translate(&translation_id, None);
+            // Untranslated keys come back as "namespace:category.name"
+            if name != format!("{}:{}.{}", translation_id.namespace, translation_id.category, translation_id.name) {
+                self.search_index.add_name(key, &id, &name);
+            }
+        }
+    }
+
+    // Snapshots
+
+    // Copy of the registered content, e.g. before enabling a mod. The
+    // changelog isn't part of it, it keeps growing across restores.
+    pub fn snapshot(&self) -> RegistrySnapshot {
+        RegistrySnapshot {
+            items: self.items.clone(),
+            blocks: self.blocks.clone(),
+            tags: self.tags.clone(),
+            tools: self.tools.clone(),
+            recipes: self.recipes.clone(),
+            loot_tables: self.loot_tables.clone(),
+            entities: self.entities.clone(),
+            enchantments: self.enchantments.clone(),
+            quests: self.quests.clone(),
+            effects: self.effects.clone(),
+            dialogues: self.dialogues.clone(),
+            sprites: self.sprites.clone(),
+            biomes: self.biomes.clone(),
+            structures: self.structures.clone(),
+        }
+    }
+
+    // Replaces the registered content with the snapshot's
+    pub fn restore(&mut self, snapshot: RegistrySnapshot) {
+        self.items = snapshot.items;
+        self.blocks = snapshot.blocks;
+        self.tags = snapshot.tags;
+        self.tools = snapshot.tools;
+        self.recipes = snapshot.recipes;
+        self.loot_tables = snapshot.loot_tables;
+        self.entities = snapshot.entities;
+        self.enchantments = snapshot.enchantments;
+        self.quests = snapshot.quests;
+        self.effects = snapshot.effects;
+        self.dialogues = snapshot.dialogues;
+        self.sprites = snapshot.sprites;
+        self.biomes = snapshot.biomes;
+        self.structures = snapshot.structures;
+
+        // Display names of entities that still exist are kept
+        let mut index = SearchIndex::new();
+        for entity in self.all_entities() {
+            let entry = (entity.key(), *entity.id());
+            match self.search_index.entries.remove(&entry) {
+                Some(terms) => {
+                    index.entries.insert(entry, terms);
+                }
+                None => index.insert(entry.0, &entry.1),
+            }
+        }
+        self.search_index = index;
+    }
+
+    // Unloads a mod: removes every entity of the namespace, the tag and loot
+    // entries of other namespaces pointing at them, the recipes using them
+    // and the namespace's translations from `translator`. Returns the removed
+    // entities in removal order.
+    pub fn remove_namespace(&mut self, namespace: &str, translator: &mut Translator) -> Vec<(&'static str, ID)> {
+        let translations = translator.remove_namespace(namespace);
+        log::info!("Removed {} translations of namespace '{}'", translations, namespace);
+        let mut removed = vec![];
+        // Reverse registration order, so e.g. quests go before their prerequisites
+        let entities: Vec<RegistrableEntity> =
+            self.all_entities().into_iter().rev().filter(|e| e.id().namespace() == namespace).collect();
+        for entity in entities {
+            removed.push((entity.type_name(), *entity.id()));
+            self.unregister(&entity);
+        }
+
+        for tag in self.tags.values_mut() {
+            tag.entries.retain(|(_, id)| id.namespace() != namespace);
+        }
+        for loot_table in self.loot_tables.values_mut() {
+            for entry in &mut loot_table.entries {
+                entry.items.retain(|id| id.namespace() != namespace);
+            }
+        }
+        for block in self.blocks.values_mut() {
+            if let Some(loot_table) = &mut block.loot_table {
+                for entry in &mut loot_table.entries {
+                    entry.items.retain(|id| id.namespace() != namespace);
+                }
+            }
+        }
+
+        let mut recipes: Vec<Recipe> = self
+            .recipes
+            .values()
+            .filter(|r| r.ingredients.iter().chain(&r.results).any(|c| c.id.namespace() == namespace))
+            .cloned()
+            .collect();
+        recipes.sort_by_key(|r| r.id.to_string());
+        for recipe in recipes {
+            removed.push(("Recipe", recipe.id));
+            self.unregister(&RegistrableEntity::Recipe(recipe));
+        }
+        removed
+    }
+
+    // Diff and merge
+
+    // What changes from this registry to `other`
+    pub fn diff(&self, other: &Registry) -> RegistryDiff {
+        let mut diff = RegistryDiff::default();
+        for entity in other.all_entities() {
+            match self.find(&entity) {
+                None => diff.added.push((entity.type_name(), *entity.id())),
+                Some(existing) if existing != entity => diff.changed.push((entity.type_name(), *entity.id())),
+                Some(_) => {}
+            }
+        }
+        for entity in self.all_entities() {
+            if other.find(&entity).is_none() {
+                diff.removed.push((entity.type_name(), *entity.id()));
+            }
+        }
+        diff
+    }
+
+    // Registers the entities of `other` that are missing here. Entities
+    // registered in both but different are resolved by `policy`; with
+    // ConflictPolicy::Fail nothing is merged if there is such a conflict.
+    // All or nothing: if an entity fails to register, the registry is
+    // restored to before the merge.
+    pub fn merge(&mut self, other: &Registry, policy: ConflictPolicy) -> std::result::Result<MergeReport, RegistryError> {
+        let snapshot = self.snapshot();
+        self.merge_entities(other, policy).inspect_err(|_| self.restore(snapshot))
+    }
+
+    fn merge_entities(&mut self, other: &Registry, policy: ConflictPolicy) -> std::result::Result<MergeReport, RegistryError> {
+        let entities = other.all_entities();
+        if policy == ConflictPolicy::Fail {
+            let conflicts: Vec<(RegistryKey, ID)> = entities
+                .iter()
+                .filter(|e| self.find(e).is_some_and(|existing| &existing != *e))
+                .map(|e| (e.key(), *e.id()))
+                .collect();
+            if !conflicts.is_empty() {
+                return Err(RegistryError::Conflicts(conflicts));
+            }
+        }
+
+        let mut report = MergeReport::default();
+        for entity in entities {
+            let entry = (entity.type_name(), *entity.id());
+            match self.find(&entity) {
+                None => {
+                    self.register(entity)?;
+                    report.added.push(entry);
+                }
+                Some(existing) if existing == entity => {}
+                Some(_) if policy == ConflictPolicy::Override => {
+                    self.override_entity(entity)?;
+                    report.overridden.push(entry);
+                }
+                Some(_) => report.kept.push(entry),
+            }
+        }
+        Ok(report)
+    }
+
+    // Callers check for duplicate IDs and missing references first
+    fn insert(&mut self, entity: RegistrableEntity) {
+        self.search_index.insert(entity.key(), entity.id());
+        match entity {
+            RegistrableEntity::Item(item) => {
+                self.items.insert(item.id, item.clone());
+
+                for tag_id in &item.tags {
+                    if let Some(tag) = self.tags.get_mut(tag_id) {
+                        tag.add(RegistryKey::ITEM, &item.id);
+                    }
+                }
+            },
+            RegistrableEntity::Block(mut block) => {
+                if let Some(loot_table) = self.loot_tables.get(&block.id) {
+                    block.loot_table = Some(loot_table.clone());
+                } else {
+                    block.loot_table = None;
+                }
+
+                self.blocks.insert(block.id, block.clone());
+
+                for tag_id in &block.tags {
+                    if let Some(tag) = self.tags.get_mut(tag_id) {
+                        tag.add(RegistryKey::BLOCK, &block.id);
+                    }
+                }
+            },
+            RegistrableEntity::Tag(tag) => {
+                self.tags.insert(tag.id, tag.clone());
+            },
+            RegistrableEntity::Tool(tool) => {
+                self.tools.insert(tool.id, tool.clone());
+                // Tools don't have tags, so we don't need to do anything here
+            },
+            RegistrableEntity::Recipe(recipe) => {
+                self.recipes.insert(recipe.id, recipe.clone());
+                // Recipes don't have tags, so we don't need to do anything here
+            },
+            RegistrableEntity::LootTable(loot_table) => {
+                // Blocks registered earlier pick up their loot table
+                if let Some(block) = self.blocks.get_mut(&loot_table.id) {
+                    block.loot_table = Some(loot_table.clone());
+                }
+                self.loot_tables.insert(loot_table.id, loot_table.clone());
+            },
+            RegistrableEntity::Entity(entity) => {
+                for tag_id in &entity.tags {
+                    if let Some(tag) = self.tags.get_mut(tag_id) {
+                        tag.add(RegistryKey::ENTITY, &entity.id);
+                    }
+                }
+                self.entities.insert(entity.id, entity);
+            },
+            RegistrableEntity::Enchantment(enchantment) => {
+                self.enchantments.insert(enchantment.id, enchantment);
+            },
+            RegistrableEntity::Quest(quest) => {
+                // Prerequisites are registered first (see check_references), so the graph can't have cycles
+                self.quests.insert(quest.id, quest);
+            },
+            RegistrableEntity::Effect(effect) => {
+                self.effects.insert(effect.id, effect);
+            },
+            RegistrableEntity::Dialogue(dialogue) => {
+                self.dialogues.insert(dialogue.id, dialogue);
+            },
+            RegistrableEntity::Sprite(sprite) => {
+                self.sprites.insert(sprite.id, sprite);
+            },
+            RegistrableEntity::Biome(biome) => {
+                for tag_id in &biome.tags {
+                    if let Some(tag) = self.tags.get_mut(tag_id) {
+                        tag.add(RegistryKey::BIOME, &biome.id);
+                    }
+                }
+                self.biomes.insert(biome.id, biome);
+            },
+            RegistrableEntity::Structure(structure) => {
+                for tag_id in &structure.tags {
+                    if let Some(tag) = self.tags.get_mut(tag_id) {
+                        tag.add(RegistryKey::STRUCTURE, &structure.id);
+                    }
+                }
+                self.structures.insert(structure.id, structure);
+            },
+        }
+    }
+
+    pub fn remove(&mut self, entity: &RegistrableEntity) -> std::result::Result<(), RegistryError> {
+        if self.find(entity).is_none() {
+            return Err(RegistryError::NotFound(entity.key(), *entity.id()));
+        }
+        self.unregister(entity);
+        Ok(())
+    }
+
+    fn unregister(&mut self, entity: &RegistrableEntity) {
+        self.log(ChangeKind::Remove, entity);
+        self.delete(entity);
+    }
+
+    fn delete(&mut self, entity: &RegistrableEntity) {
+        match entity {
+            RegistrableEntity::Item(item) => {
+                self.items.remove(&item.id);
+                for tag_id in &item.tags {
+                    if let Some(tag) = self.tags.get_mut(tag_id) {
+                        tag.entries.remove(&(RegistryKey::ITEM, item.id));
+                    }
+                }
+            },
+            RegistrableEntity::Block(block) => {
+                self.blocks.remove(&block.id);
+                for tag_id in &block.tags {
+                    if let Some(tag) = self.tags.get_mut(tag_id) {
+                        tag.entries.remove(&(RegistryKey::BLOCK, block.id));
+                    }
+                }
+            },
+            RegistrableEntity::Tag(tag) => {
+                // remove the tag from all items and blocks
+                for item in self.items.values_mut() {
+                    if let Some(pos) = item.tags.iter().position(|t| t == &tag.id) {
+                        item.tags.remove(pos);
+                    }
+                }
+                for block in self.blocks.values_mut() {
+                    if let Some(pos) = block.tags.iter().position(|t| t == &tag.id) {
+                        block.tags.remove(pos);
+                    }
+                }
+
+                for tool in self.tools.values_mut() {
+                    if let Some(pos) = tool.tags.iter().position(|t| t == &tag.id) {
+                        tool.tags.remove(pos);
+                    }
+                }
+
+                for entity in self.entities.values_mut() {
+                    if let Some(pos) = entity.tags.iter().position(|t| t == &tag.id) {
+                        entity.tags.remove(pos);
+                    }
+                }
+
+                for enchantment in self.enchantments.values_mut() {
+                    enchantment.applicable_tags.retain(|t| t != &tag.id);
+                }
+
+                for recipe in self.recipes.values_mut() {
+                    if let Some(pos) = recipe.ingredients.iter().position(|c| c.id == tag.id) {
+                        recipe.ingredients.remove(pos);
+                    }
+
+                    if let Some(pos) = recipe.results.iter().position(|c| c.id == tag.id) {
+                        recipe.results.remove(pos);
+                    }
+                }
+                // finally remove the tag itself
+                self.tags.remove(&tag.id);
+            },
+            RegistrableEntity::Tool(tool) => {
+                self.tools.remove(&tool.id);
+            },
+            RegistrableEntity::Recipe(recipe) => {
+                self.recipes.remove(&recipe.id);
+            },
+            RegistrableEntity::LootTable(loot_table) => {
+                self.loot_tables.remove(&loot_table.id);
+                if let Some(block) = self.blocks.get_mut(&loot_table.id) {
+                    block.loot_table = None;
+                }
+            },
+            RegistrableEntity::Entity(entity) => {
+                self.entities.remove(&entity.id);
+                for tag_id in &entity.tags {
+                    if let Some(tag) = self.tags.get_mut(tag_id) {
+                        tag.entries.remove(&(RegistryKey::ENTITY, entity.id));
+                    }
+                }
+            },
+            RegistrableEntity::Enchantment(enchantment) => {
+                self.enchantments.remove(&enchantment.id);
+            },
+            RegistrableEntity::Quest(quest) => {
+                self.quests.remove(&quest.id);
+                for other in self.quests.values_mut() {
+                    other.prerequisites.retain(|p| p != &quest.id);
+                }
+            },
+            RegistrableEntity::Effect(effect) => {
+                self.effects.remove(&effect.id);
+            },
+            RegistrableEntity::Dialogue(dialogue) => {
+                self.dialogues.remove(&dialogue.id);
+            },
+            RegistrableEntity::Sprite(sprite) => {
+                self.sprites.remove(&sprite.id);
+            },
+            RegistrableEntity::Biome(biome) => {
+                self.biomes.remove(&biome.id);
+            },
+            RegistrableEntity::Structure(structure) => {
+                self.structures.remove(&structure.id);
+            },
+        }
+        self.search_index.remove(entity.key(), entity.id());
+        // Entries added to tags directly, e.g. loot tables or other tags
+        let entry = (entity.key(), *entity.id());
+        for tag in self.tags.values_mut() {
+            tag.entries.remove(&entry);
+        }
+    }
+
+    // return the entity by its ID
+    pub fn get(&self, entity: RegistrableEntity, id: &ID) -> Option<&dyn Registrable> {
+        match entity {
+            RegistrableEntity::Item(_) => self.items.get(id).map(|item| item as &dyn Registrable),
+            RegistrableEntity::Block(_) => self.blocks.get(id).map(|block| block as &dyn Registrable),
+            RegistrableEntity::Tag(_) => self.tags.get(id).map(|tag| tag as &dyn Registrable),
+            RegistrableEntity::Tool(_) => self.tools.get(id).map(|tool| tool as &dyn Registrable),
+            RegistrableEntity::Recipe(_) => self.recipes.get(id).map(|recipe| recipe as &dyn Registrable),
+            RegistrableEntity::Entity(_) => self.entities.get(id).map(|entity| entity as &dyn Registrable),
+            RegistrableEntity::Enchantment(_) => self.enchantments.get(id).map(|enchantment| enchantment as &dyn Registrable),
+            RegistrableEntity::Quest(_) => self.quests.get(id).map(|quest| quest as &dyn Registrable),
+            RegistrableEntity::Effect(_) => self.effects.get(id).map(|effect| effect as &dyn Registrable),
+            RegistrableEntity::Dialogue(_) => self.dialogues.get(id).map(|dialogue| dialogue as &dyn Registrable),
+            RegistrableEntity::Sprite(_) => self.sprites.get(id).map(|sprite| sprite as &dyn Registrable),
+            RegistrableEntity::Biome(_) => self.biomes.get(id).map(|biome| biome as &dyn Registrable),
+            RegistrableEntity::Structure(_) => self.structures.get(id).map(|structure| structure as &dyn Registrable),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+thread_local! {
+    // How this thread currently holds REGISTRY through the helpers below
+    static ACCESS: Cell<Access> = const { Cell::new(Access::None) };
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Access {
+    None,
+    Read(*const Registry), // Behind the outermost read guard
+    Write,
+}
+
+// Restores the previous access when the closure returns or panics
+struct AccessGuard(Access);
+
+impl Drop for AccessGuard {
+    fn drop(&mut self) {
+        ACCESS.with(|a| a.set(self.0));
+    }
+}
+
+// Runs `f` with shared access to REGISTRY. Can be nested, e.g. a command
+// handler reading the registry while the caller of run_command reads it too;
+// nested calls reuse the outer read guard, as std's RwLock can deadlock on
+// recursive reads with a writer waiting. Panics instead of deadlocking when
+// called inside with_registry_mut.
+pub fn with_registry<R>(f: impl FnOnce(&Registry) -> R) -> R {
+    match ACCESS.with(|a| a.get()) {
+        // SAFETY: the outer call holds the read guard until its closure,
+        // which this call is part of, returns
+        Access::Read(registry) => f(unsafe { &*registry }),
+        Access::Write => panic!("with_registry called inside with_registry_mut, pass the registry down instead"),
+        Access::None => {
+            let registry = REGISTRY.read().unwrap_or_else(|e| e.into_inner());
+            ACCESS.with(|a| a.set(Access::Read(&*registry)));
+            let _guard = AccessGuard(Access::None);
+            f(&registry)
+        }
+    }
+}
+
+// Runs `f` with exclusive access to REGISTRY. Panics instead of deadlocking
+// when this thread already accesses it through with_registry or
+// with_registry_mut.
+pub fn with_registry_mut<R>(f: impl FnOnce(&mut Registry) -> R) -> R {
+    let previous = ACCESS.with(|a| a.get());
+    if previous != Access::None {
+        panic!("with_registry_mut called while the registry is already in use on this thread");
+    }
+    let mut registry = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    ACCESS.with(|a| a.set(Access::Write));
+    let _guard = AccessGuard(previous);
+    f(&mut registry)
+}
\ No newline at end of file
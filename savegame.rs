@@ -0,0 +1,320 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::conditions::ConditionContext;
+use crate::inventory::{HOTBAR_SIZE, Inventory};
+use crate::quests::QuestLog;
+use crate::random::RuzRng;
+use crate::recipes::{RecipeBook, RecipeUnlocked};
+use crate::registry::Registry;
+use crate::world::World;
+
+// Version written into new saves. Older saves are upgraded by Migrations.
+pub const SAVE_VERSION: u32 = 3;
+
+const META_FILE: &str = "save.txt";
+const INVENTORY_FILE: &str = "inventory.txt";
+const QUESTS_FILE: &str = "quests.txt";
+const RECIPES_FILE: &str = "recipes.txt";
+const WORLD_FILE: &str = "world.txt";
+
+// -------
+// PROFILE
+// -------
+
+// A player with their inventory (the wallet is Inventory::owner_money),
+// quest progress, discovered recipes and story flags
+#[derive(Clone)]
+pub struct PlayerProfile {
+    pub name: String,
+    pub inventory: Inventory,
+    pub quests: QuestLog,
+    pub recipes: RecipeBook,
+    pub flags: BTreeSet<String>, // e.g. set by dialogues, tested by Condition::FlagSet
+}
+
+impl PlayerProfile {
+    pub fn new(name: &str) -> Self {
+        PlayerProfile {
+            name: name.to_string(),
+            inventory: Inventory::new(Some(0)),
+            quests: QuestLog::new(name),
+            recipes: RecipeBook::new(name),
+            flags: BTreeSet::new(),
+        }
+    }
+
+    pub fn money(&self) -> u32 {
+        self.inventory.owner_money.unwrap_or(0)
+    }
+
+    // Unlocks the recipes whose condition holds for this profile, e.g. after
+    // picking up an item or completing a quest
+    pub fn discover_recipes(&mut self, registry: &Registry, rng: &mut RuzRng) -> Vec<RecipeUnlocked> {
+        let mut book = self.recipes.clone();
+        let unlocked = book.discover(&ConditionContext::new().with_registry(registry).with_player(self), registry, rng);
+        self.recipes = book;
+        unlocked
+    }
+}
+
+// ----------
+// MIGRATIONS
+// ----------
+
+// Contents of the save files by file name, as read from disk
+pub type SaveFiles = HashMap<String, String>;
+
+// Upgrades the files of a save from one version to the next
+pub type Migration = fn(&mut SaveFiles) -> Result<(), String>;
+
+// Migration steps by the version they upgrade from. Starts with the steps
+// for the game's own save format changes, with_step adds or replaces steps.
+pub struct Migrations {
+    steps: BTreeMap<u32, Migration>,
+}
+
+impl Migrations {
+    pub fn new() -> Self {
+        Migrations { steps: BTreeMap::new() }.with_step(1, add_recipe_book).with_step(2, fixed_hotbar)
+    }
+
+    // `migration` upgrades saves of version `from` to `from + 1`
+    pub fn with_step(mut self, from: u32, migration: Migration) -> Self {
+        self.steps.insert(from, migration);
+        self
+    }
+
+    fn apply(&self, files: &mut SaveFiles, mut version: u32) -> Result<(), String> {
+        while version < SAVE_VERSION {
+            let migration = self.steps.get(&version).ok_or_else(|| format!("no migration from save version {}", version))?;
+            migration(files).map_err(|e| format!("migration from save version {} failed: {}", version, e))?;
+            version += 1;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Migrations {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Version 2 added recipes.txt, older profiles start with an empty recipe book
+fn add_recipe_book(files: &mut SaveFiles) -> Result<(), String> {
+    let player = files[META_FILE]
+        .lines()
+        .find_map(|l| l.strip_prefix("player "))
+        .ok_or("missing 'player <name>' entry")?;
+    let book = RecipeBook::new(player).serialize();
+    files.insert(RECIPES_FILE.to_string(), book);
+    Ok(())
+}
+
+// Version 3 gave the hotbar fixed positions, the first slots of older
+// inventories were the hotbar
+fn fixed_hotbar(files: &mut SaveFiles) -> Result<(), String> {
+    let inventory = files.get_mut(INVENTORY_FILE).ok_or("missing inventory.txt")?;
+    let mut position = 0;
+    let mut migrated = String::new();
+    for line in inventory.lines() {
+        match line.strip_prefix("slot ") {
+            Some(stack) if position < HOTBAR_SIZE => {
+                migrated += &format!("hotbar {} {}\n", position, stack);
+                position += 1;
+            }
+            _ => migrated += &format!("{}\n", line),
+        }
+    }
+    *inventory = migrated;
+    Ok(())
+}
+
+// --------
+// SAVEGAME
+// --------
+
+// Everything needed to continue a game, stored as a directory of text files:
+//
+//   save.txt           "savegame <version>", "generation <n>", "player <name>",
+//                      "rng <state>", one "registry <namespace> <hash>" line
+//                      per namespace and one "flag <name>" line per player flag
+//   inventory.<n>.txt  Inventory::serialize
+//   quests.<n>.txt     QuestLog::serialize
+//   recipes.<n>.txt    RecipeBook::serialize
+//   world.<n>.txt      World::serialize
+//
+// Every save writes the other files under the next generation, so save.txt
+// always names a complete set. Saves without a generation use the names
+// without <n>.
+pub struct SaveGame {
+    pub player: PlayerProfile,
+    pub world: World,
+    pub rng: RuzRng, // Forked per system, saved so loading continues the same sequence
+    pub registry_hashes: BTreeMap<String, u64>, // Registry::namespace_hashes when saved
+}
+
+impl SaveGame {
+    pub fn new(player: PlayerProfile, world: World, rng: RuzRng, registry: &Registry) -> Self {
+        SaveGame { player, world, rng, registry_hashes: registry.namespace_hashes() }
+    }
+
+    // Namespaces whose content differs from when the game was saved,
+    // including added and removed ones
+    pub fn registry_mismatches(&self, registry: &Registry) -> Vec<String> {
+        let current = registry.namespace_hashes();
+        let mut namespaces: Vec<&String> = self.registry_hashes.keys().chain(current.keys()).collect();
+        namespaces.sort();
+        namespaces.dedup();
+        namespaces.into_iter().filter(|ns| self.registry_hashes.get(*ns) != current.get(*ns)).cloned().collect()
+    }
+
+    // Writes the files of the next generation, then replaces save.txt
+    // (temporary file, then rename) to switch to them, and removes the
+    // previous generation. Everything is synced to disk before the switch, so
+    // an interrupted save leaves the previous one complete.
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let previous = match fs::read_to_string(dir.join(META_FILE)) {
+            Ok(meta) => Some(generation(&meta)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let next = previous.flatten().map_or(1, |g| g + 1);
+        write_synced(&dir.join(file_name(INVENTORY_FILE, Some(next))), &self.player.inventory.serialize())?;
+        write_synced(&dir.join(file_name(QUESTS_FILE, Some(next))), &self.player.quests.serialize())?;
+        write_synced(&dir.join(file_name(RECIPES_FILE, Some(next))), &self.player.recipes.serialize())?;
+        write_synced(&dir.join(file_name(WORLD_FILE, Some(next))), &self.world.serialize())?;
+
+        let mut meta = format!(
+            "savegame {}\ngeneration {}\nplayer {}\nrng {:016x}\n",
+            SAVE_VERSION,
+            next,
+            self.player.name,
+            self.rng.state()
+        );
+        for (namespace, hash) in &self.registry_hashes {
+            meta += &format!("registry {} {:016x}\n", namespace, hash);
+        }
+        for flag in &self.player.flags {
+            meta += &format!("flag {}\n", flag);
+        }
+        let tmp = dir.join(META_FILE).with_extension("tmp");
+        write_synced(&tmp, &meta)?;
+        fs::rename(&tmp, dir.join(META_FILE))?;
+        sync_dir(dir)?;
+
+        if let Some(previous) = previous {
+            for name in [INVENTORY_FILE, QUESTS_FILE, RECIPES_FILE, WORLD_FILE] {
+                let _ = fs::remove_file(dir.join(file_name(name, previous)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load(dir: &Path, registry: &Registry) -> io::Result<SaveGame> {
+        SaveGame::load_with(dir, registry, &Migrations::new())
+    }
+
+    // Loads a save, upgrading older versions with `migrations` first. Items
+    // and enchantments are looked up in `registry`, see registry_mismatches
+    // for content changes since saving.
+    pub fn load_with(dir: &Path, registry: &Registry, migrations: &Migrations) -> io::Result<SaveGame> {
+        let mut files = SaveFiles::new();
+        let meta = fs::read_to_string(dir.join(META_FILE))?;
+        let generation = generation(&meta);
+        files.insert(META_FILE.to_string(), meta);
+        for name in [INVENTORY_FILE, QUESTS_FILE, WORLD_FILE] {
+            files.insert(name.to_string(), fs::read_to_string(dir.join(file_name(name, generation)))?);
+        }
+        // Only in saves since version 2, older ones get it from the migration
+        if let Ok(recipes) = fs::read_to_string(dir.join(file_name(RECIPES_FILE, generation))) {
+            files.insert(RECIPES_FILE.to_string(), recipes);
+        }
+        SaveGame::from_files(files, registry, migrations).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn from_files(mut files: SaveFiles, registry: &Registry, migrations: &Migrations) -> Result<SaveGame, String> {
+        let version = files[META_FILE]
+            .lines()
+            .next()
+            .and_then(|l| l.strip_prefix("savegame "))
+            .and_then(|v| v.trim().parse::<u32>().ok())
+            .ok_or("missing 'savegame <version>' header")?;
+        if version > SAVE_VERSION {
+            return Err(format!("save version {} is newer than the supported {}", version, SAVE_VERSION));
+        }
+        migrations.apply(&mut files, version)?;
+
+        let mut name = None;
+        let mut rng = None;
+        let mut registry_hashes = BTreeMap::new();
+        let mut flags = BTreeSet::new();
+        for line in files[META_FILE].lines().skip(1) {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts[..] {
+                ["player", ..] => name = line.strip_prefix("player ").map(str::to_string),
+                ["generation", _] => {}
+                ["rng", state] => {
+                    let state = u64::from_str_radix(state, 16).map_err(|_| format!("invalid rng state: {}", line))?;
+                    rng = Some(RuzRng::new(state));
+                }
+                ["registry", namespace, hash] => {
+                    let hash = u64::from_str_radix(hash, 16).map_err(|_| format!("invalid registry hash: {}", line))?;
+                    registry_hashes.insert(namespace.to_string(), hash);
+                }
+                ["flag", ..] => {
+                    flags.extend(line.strip_prefix("flag ").map(str::to_string));
+                }
+                [] => {}
+                _ => return Err(format!("invalid save entry: {}", line)),
+            }
+        }
+        let name = name.ok_or("missing 'player <name>' entry")?;
+        let rng = rng.ok_or("missing 'rng <state>' entry")?;
+        let invalid = |file: &str, e: &dyn Display| format!("{}: {}", file, e);
+        let player = PlayerProfile {
+            inventory: Inventory::deserialize(&files[INVENTORY_FILE], registry).map_err(|e| invalid(INVENTORY_FILE, &e))?,
+            quests: QuestLog::deserialize(&files[QUESTS_FILE]).map_err(|e| invalid(QUESTS_FILE, &e))?,
+            recipes: RecipeBook::deserialize(files.get(RECIPES_FILE).ok_or("missing recipes.txt")?)
+                .map_err(|e| invalid(RECIPES_FILE, &e))?,
+            name,
+            flags,
+        };
+        let world = World::deserialize(&files[WORLD_FILE]).map_err(|e| invalid(WORLD_FILE, &e))?;
+        Ok(SaveGame { player, world, rng, registry_hashes })
+    }
+}
+
+// The "generation <n>" of a save.txt, None for saves from before generations
+fn generation(meta: &str) -> Option<u32> {
+    meta.lines().find_map(|l| l.strip_prefix("generation ")?.trim().parse().ok())
+}
+
+// "world.txt" as "world.<generation>.txt"
+fn file_name(name: &str, generation: Option<u32>) -> String {
+    match (generation, name.rsplit_once('.')) {
+        (Some(generation), Some((stem, extension))) => format!("{}.{}.{}", stem, generation, extension),
+        _ => name.to_string(),
+    }
+}
+
+fn write_synced(path: &Path, content: &str) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()
+}
+
+// Makes a rename in `dir` durable. Directories can't be opened for that on
+// every platform, elsewhere the rename has to do.
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
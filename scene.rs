@@ -0,0 +1,622 @@
+use std::io;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::{
+    Frame,
+    Terminal,
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+};
+
+use crate::game::{GameLoop, TickContext};
+use crate::input::{InputMap, InputState, ScriptedInput};
+use crate::interface::{ColorTheme, CommandError, CommandOutcome, CommandRegistry, CommandTask, Executor, fullscreen, theme_color};
+use crate::localization::{TranslationID, Translator};
+use crate::profiler::{self, Profiler};
+use crate::random::RuzRng;
+use crate::savegame::PlayerProfile;
+use crate::theme::current_theme;
+
+// Full-screen apps as a stack of scenes, e.g. main menu → world view →
+// inventory. Only the top scene gets events and updates; it and the overlays
+// on top of it are drawn.
+//
+//   let context = SceneContext::new(PlayerProfile::new("Steve"), ());
+//   let menu = MenuScene::new("My Game")
+//       .with_entry("Play", |_| Transition::Push(Box::new(WorldView::new())))
+//       .with_entry("Quit", |_| Transition::Quit);
+//   SceneManager::new(context).run(menu)?;
+//
+// Without a terminal, e.g. in tests or on a dedicated server, scenes run with
+// scripted input and draw to a NullRenderer (or a ratatui TestBackend):
+//
+//   let input = ScriptedInput::new().with_tap(KeyChord::key(KeyCode::Enter)).with_idle(100);
+//   manager.run_headless(menu, &mut NullRenderer, input)?;
+
+// ------
+// SCENES
+// ------
+
+// What the scene stack does after a scene handled an event or update
+pub enum Transition<D = ()> {
+    None,
+    Push(Box<dyn Scene<D>>),
+    Pop,
+    Replace(Box<dyn Scene<D>>), // Pops the current scene, then pushes this one
+    Quit,                       // Pops every scene and ends SceneManager::run
+}
+
+pub trait Scene<D = ()> {
+    // For logging transitions
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    fn handle_event(&mut self, event: &Event, context: &mut SceneContext<D>) -> Transition<D>;
+
+    // Runs every tick while the scene is on top
+    fn update(&mut self, _tick: &mut TickContext, _context: &mut SceneContext<D>) -> Transition<D> {
+        Transition::None
+    }
+
+    fn render(&self, frame: &mut Frame, context: &SceneContext<D>);
+
+    // When the scene is pushed and when it's popped. Not called when another
+    // scene is pushed on top of it.
+    fn on_enter(&mut self, _context: &mut SceneContext<D>) {}
+    fn on_exit(&mut self, _context: &mut SceneContext<D>) {}
+
+    // Overlays are drawn on top of the scene below them, e.g. a console over
+    // the world view
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+// -------
+// CONTEXT
+// -------
+
+// State shared by all scenes. Game specific state goes into `data`.
+pub struct SceneContext<D = ()> {
+    pub player: PlayerProfile,
+    pub rng: RuzRng,
+    pub theme: ColorTheme,
+    pub translator: Option<Rc<Translator>>,
+    pub commands: CommandRegistry,
+    pub executor: Executor,
+    pub input: InputState, // Updated with the events of each frame
+    pub profiler: Profiler, // Budget set to the tick duration by SceneManager::run
+    pub data: D,
+}
+
+impl<D> SceneContext<D> {
+    pub fn new(player: PlayerProfile, data: D) -> Self {
+        SceneContext {
+            player,
+            rng: RuzRng::from_time(),
+            theme: current_theme(),
+            translator: None,
+            commands: CommandRegistry::new(),
+            executor: Executor::console(),
+            input: InputState::new(InputMap::wasd()),
+            profiler: Profiler::new(),
+            data,
+        }
+    }
+
+    pub fn with_rng(mut self, rng: RuzRng) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: ColorTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_translator(mut self, translator: Rc<Translator>) -> Self {
+        self.translator = Some(translator);
+        self
+    }
+
+    pub fn with_commands(mut self, commands: CommandRegistry, executor: Executor) -> Self {
+        self.commands = commands;
+        self.executor = executor;
+        self
+    }
+
+    pub fn with_input_map(mut self, map: InputMap) -> Self {
+        self.input = InputState::new(map);
+        self
+    }
+
+    // The translation, or `text` itself if there is no translator or it isn't a translation ID
+    pub fn translate(&self, text: &str) -> String {
+        let id = TranslationID::parse(text).ok();
+        self.translator
+            .as_ref()
+            .zip(id)
+            .and_then(|(translator, id)| translator.try_translate(&id, None))
+            .unwrap_or_else(|| text.to_string())
+    }
+}
+
+// ---------
+// RENDERERS
+// ---------
+
+// Where SceneManager::run_headless draws frames. Every ratatui Terminal is
+// one, e.g. Terminal<TestBackend> to check what was drawn.
+pub trait Renderer {
+    fn draw(&mut self, render: &mut dyn FnMut(&mut Frame)) -> io::Result<()>;
+}
+
+impl<B: Backend> Renderer for Terminal<B> {
+    fn draw(&mut self, render: &mut dyn FnMut(&mut Frame)) -> io::Result<()> {
+        Terminal::draw(self, |frame| render(frame)).map(|_| ())
+    }
+}
+
+// Draws nothing, scenes aren't rendered at all
+pub struct NullRenderer;
+
+impl Renderer for NullRenderer {
+    fn draw(&mut self, _render: &mut dyn FnMut(&mut Frame)) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// -------
+// MANAGER
+// -------
+
+// Runs the scene stack in a full-screen terminal. Updates run at the tick
+// rate of `game` (20 per second by default), events are handled and the
+// screen is drawn in between. F3 toggles the profiler overlay. Scenes get
+// key presses and repeats, releases only update SceneContext::input.
+pub struct SceneManager<D = ()> {
+    pub context: SceneContext<D>,
+    pub game: GameLoop,
+    stack: Vec<Box<dyn Scene<D>>>,
+}
+
+impl<D> SceneManager<D> {
+    pub fn new(context: SceneContext<D>) -> Self {
+        SceneManager { context, game: GameLoop::new(20), stack: vec![] }
+    }
+
+    pub fn with_tick_rate(mut self, tick_rate: u32) -> Self {
+        self.game = self.game.with_tick_rate(tick_rate);
+        self
+    }
+
+    pub fn push(&mut self, mut scene: Box<dyn Scene<D>>) {
+        log::debug!("Entering scene {}", scene.name());
+        scene.on_enter(&mut self.context);
+        self.stack.push(scene);
+    }
+
+    pub fn pop(&mut self) -> Option<Box<dyn Scene<D>>> {
+        let mut scene = self.stack.pop()?;
+        log::debug!("Leaving scene {}", scene.name());
+        scene.on_exit(&mut self.context);
+        Some(scene)
+    }
+
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    // Name of the top scene
+    pub fn current(&self) -> Option<&str> {
+        self.stack.last().map(|scene| scene.name())
+    }
+
+    pub fn apply(&mut self, transition: Transition<D>) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(scene) => self.push(scene),
+            Transition::Pop => {
+                self.pop();
+            }
+            Transition::Replace(scene) => {
+                self.pop();
+                self.push(scene);
+            }
+            Transition::Quit => {
+                while self.pop().is_some() {}
+            }
+        }
+    }
+
+    // Pushes `scene` and runs until the stack is empty. Ctrl+C quits.
+    pub fn run(&mut self, scene: impl Scene<D> + 'static) -> io::Result<()> {
+        self.push(Box::new(scene));
+        self.game.set_running(true);
+        self.context.profiler.set_budget(self.game.tick_duration());
+        let result = fullscreen(|terminal| {
+            let tick_duration = self.game.tick_duration();
+            let mut previous = Instant::now();
+            let mut lag = Duration::ZERO;
+            while !self.stack.is_empty() {
+                self.draw(terminal)?;
+
+                // Events until the next tick is due
+                let mut events = vec![];
+                let deadline = Instant::now() + tick_duration.saturating_sub(lag);
+                while event::poll(deadline.saturating_duration_since(Instant::now()))? {
+                    events.push(event::read()?);
+                }
+                self.handle_events(&events);
+
+                let now = Instant::now();
+                lag += now - previous;
+                previous = now;
+                while lag >= tick_duration && !self.stack.is_empty() {
+                    self.tick();
+                    lag -= tick_duration;
+                }
+            }
+            Ok(())
+        });
+        // An error leaves the stack as it is, pop what's left so every scene sees on_exit
+        self.apply(Transition::Quit);
+        self.game.set_running(false);
+        result
+    }
+
+    // Runs like run, but without a terminal and as fast as possible: each
+    // frame is drawn with `renderer`, gets the next events of `input` and runs
+    // one tick. Stops once the stack is empty or the input ran out; the
+    // scenes left are kept for inspecting them.
+    pub fn run_headless(&mut self, scene: impl Scene<D> + 'static, renderer: &mut impl Renderer, mut input: ScriptedInput) -> io::Result<()> {
+        self.push(Box::new(scene));
+        self.game.set_running(true);
+        self.context.profiler.set_budget(self.game.tick_duration());
+        while !self.stack.is_empty() {
+            let Some(events) = input.next_frame() else {
+                break;
+            };
+            self.draw(renderer)?;
+            self.handle_events(&events);
+            if !self.stack.is_empty() {
+                self.tick();
+            }
+        }
+        self.game.set_running(false);
+        Ok(())
+    }
+
+    fn draw(&mut self, renderer: &mut impl Renderer) -> io::Result<()> {
+        let start = Instant::now();
+        renderer.draw(&mut |f| self.render(f))?;
+        self.context.profiler.record(profiler::RENDER, start.elapsed());
+        Ok(())
+    }
+
+    fn handle_events(&mut self, events: &[Event]) {
+        self.context.input.step(events.iter().cloned());
+        for event in events {
+            match event {
+                Event::Key(KeyEvent { kind: KeyEventKind::Release, .. }) => continue,
+                Event::Key(KeyEvent { code: KeyCode::Char('c'), modifiers: KeyModifiers::CONTROL, .. }) => self.apply(Transition::Quit),
+                Event::Key(KeyEvent { code: KeyCode::F(3), .. }) => {
+                    self.context.profiler.toggle();
+                    continue;
+                }
+                _ => {}
+            }
+            let Some(scene) = self.stack.last_mut() else {
+                break;
+            };
+            let transition = scene.handle_event(event, &mut self.context);
+            self.apply(transition);
+        }
+    }
+
+    // Updates the top scene once
+    fn tick(&mut self) {
+        let SceneManager { context, game, stack } = self;
+        let Some(scene) = stack.last_mut() else {
+            return;
+        };
+        let mut transition = Transition::None;
+        let start = Instant::now();
+        game.step(&mut |tick| transition = scene.update(tick, context));
+        context.profiler.record(profiler::UPDATE, start.elapsed());
+        // TickContext::stop
+        if !game.is_running() {
+            game.set_running(true);
+            transition = Transition::Quit;
+        }
+        self.apply(transition);
+    }
+
+    // The top scene and the overlays above the scene below them, then the
+    // profiler in the top right corner
+    fn render(&self, frame: &mut Frame) {
+        let base = self.stack.iter().rposition(|scene| !scene.is_overlay()).unwrap_or(0);
+        for scene in &self.stack[base..] {
+            scene.render(frame, &self.context);
+        }
+        let profiler = &self.context.profiler;
+        if profiler.visible {
+            let text = profiler.render().plain_text();
+            let area = frame.area();
+            let width = (text.lines().map(|l| l.chars().count()).max().unwrap_or(0) as u16).min(area.width);
+            let height = (text.lines().count() as u16).min(area.height);
+            frame.render_widget(profiler, Rect::new(area.right() - width, area.y, width, height));
+        }
+    }
+}
+
+// ----
+// MENU
+// ----
+
+type MenuAction<D> = Box<dyn FnMut(&mut SceneContext<D>) -> Transition<D>>;
+
+// List of entries, each starting a transition, e.g. the main menu. Labels
+// can be translation IDs.
+//
+// Keys: ↑/↓ move, Enter choose, Esc back
+pub struct MenuScene<D = ()> {
+    title: String,
+    entries: Vec<(String, MenuAction<D>)>,
+    cursor: usize,
+}
+
+impl<D> MenuScene<D> {
+    pub fn new(title: &str) -> Self {
+        MenuScene { title: title.to_string(), entries: vec![], cursor: 0 }
+    }
+
+    pub fn with_entry(mut self, label: &str, action: impl FnMut(&mut SceneContext<D>) -> Transition<D> + 'static) -> Self {
+        self.entries.push((label.to_string(), Box::new(action)));
+        self
+    }
+}
+
+impl<D> Scene<D> for MenuScene<D> {
+    fn name(&self) -> &str {
+        &self.title
+    }
+
+    fn handle_event(&mut self, event: &Event, context: &mut SceneContext<D>) -> Transition<D> {
+        let Event::Key(key) = event else {
+            return Transition::None;
+        };
+        match key.code {
+            KeyCode::Esc => return Transition::Pop,
+            KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Down => self.cursor = (self.cursor + 1).min(self.entries.len().saturating_sub(1)),
+            KeyCode::Enter => {
+                if let Some((_, action)) = self.entries.get_mut(self.cursor) {
+                    return action(context);
+                }
+            }
+            _ => {}
+        }
+        Transition::None
+    }
+
+    fn render(&self, frame: &mut Frame, context: &SceneContext<D>) {
+        let theme = &context.theme;
+        let item_style = Style::default().fg(theme_color(&theme.suggestion_color, Color::White));
+        let hint_style = Style::default().fg(theme_color(&theme.hint_color, Color::Gray));
+
+        let width = self.entries.iter().map(|(label, _)| context.translate(label).chars().count()).max().unwrap_or(0);
+        let width = (width.max(self.title.chars().count()) as u16 + 8).min(frame.area().width);
+        let height = (self.entries.len() as u16 + 2).min(frame.area().height);
+        let area = frame.area();
+        let menu_area = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+
+        let items: Vec<ListItem> =
+            self.entries.iter().map(|(label, _)| ListItem::new(context.translate(label)).style(item_style)).collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(context.translate(&self.title)))
+            .highlight_style(theme.selected_style());
+        let mut state = ListState::default();
+        state.select((!self.entries.is_empty()).then_some(self.cursor));
+        frame.render_stateful_widget(list, menu_area, &mut state);
+
+        let hint_area = Rect::new(area.x, area.bottom().saturating_sub(1), area.width, 1.min(area.height));
+        frame.render_widget(Paragraph::new("↑/↓ move · enter choose · esc back").style(hint_style), hint_area);
+    }
+}
+
+// -------
+// CONSOLE
+// -------
+
+const CONSOLE_HISTORY: usize = 200;
+
+// Command line over the lower part of the screen, running the context's
+// commands as its executor. Tasks run in the background and print their
+// result when done. Besides "help", "profile dump|show|hide|reset" is built
+// in unless a command of that name is registered.
+//
+// Keys: Enter run, Esc close
+pub struct ConsoleScene {
+    input: String,
+    output: Vec<String>,
+    tasks: Vec<CommandTask>,
+}
+
+impl ConsoleScene {
+    pub fn new() -> Self {
+        ConsoleScene { input: String::new(), output: vec![], tasks: vec![] }
+    }
+
+    fn print(&mut self, text: &str) {
+        self.output.extend(text.lines().map(str::to_string));
+        let excess = self.output.len().saturating_sub(CONSOLE_HISTORY);
+        self.output.drain(..excess);
+    }
+
+    fn execute<D>(&mut self, context: &mut SceneContext<D>) {
+        let line = std::mem::take(&mut self.input);
+        if line.trim().is_empty() {
+            return;
+        }
+        self.print(&format!("> {}", line));
+        let start = Instant::now();
+        // Chains run in the background, like tasks
+        let result = match CommandRegistry::is_chain(&line) {
+            true => Ok(CommandOutcome::Task(context.commands.start_chain(&context.executor, &line))),
+            false => context.commands.run_command(&context.executor, &line),
+        };
+        context.profiler.record(profiler::COMMANDS, start.elapsed());
+        let result = match result {
+            Ok(CommandOutcome::Done(result)) => Ok(result),
+            Ok(CommandOutcome::Task(task)) => {
+                self.tasks.push(task);
+                return;
+            }
+            // "help [command]" unless a command of that name is registered
+            Err(CommandError::UnknownCommand(_)) if line.split_whitespace().next() == Some("help") => {
+                let command = line.trim_start().trim_start_matches("help");
+                context.commands.help(&context.executor, command, context.translator.as_deref())
+            }
+            Err(CommandError::UnknownCommand(_)) if line.split_whitespace().next() == Some("profile") => {
+                Ok(profile(&mut context.profiler, line.split_whitespace().nth(1)))
+            }
+            Err(e) => Err(e),
+        };
+        self.print_result(result, context);
+    }
+
+    // Also uses the translator "lang set" loaded, see admin::set_language
+    fn print_result<D>(&mut self, result: Result<String, CommandError>, context: &mut SceneContext<D>) {
+        if let Some(translator) = crate::admin::ADMIN.lock().unwrap().take_translator() {
+            context.translator = Some(Rc::new(translator));
+        }
+        match result {
+            Ok(output) => self.print(&output),
+            Err(e) => {
+                let message = match &context.translator {
+                    Some(translator) => e.localized(translator),
+                    None => e.to_string(),
+                };
+                self.print(&message);
+            }
+        }
+    }
+}
+
+// The built-in "profile" console command
+fn profile(profiler: &mut Profiler, action: Option<&str>) -> String {
+    match action {
+        Some("dump") | None => profiler.dump(),
+        Some("show") => {
+            profiler.visible = true;
+            "Profiler overlay shown".to_string()
+        }
+        Some("hide") => {
+            profiler.visible = false;
+            "Profiler overlay hidden".to_string()
+        }
+        Some("reset") => {
+            profiler.reset();
+            "Profiler samples cleared".to_string()
+        }
+        Some(other) => format!("Unknown profile action '{}', expected dump, show, hide or reset", other),
+    }
+}
+
+impl Default for ConsoleScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D> Scene<D> for ConsoleScene {
+    fn name(&self) -> &str {
+        "console"
+    }
+
+    fn handle_event(&mut self, event: &Event, context: &mut SceneContext<D>) -> Transition<D> {
+        let Event::Key(key) = event else {
+            return Transition::None;
+        };
+        match key.code {
+            KeyCode::Esc => return Transition::Pop,
+            KeyCode::Enter => self.execute(context),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => self.input.push(c),
+            _ => {}
+        }
+        Transition::None
+    }
+
+    fn update(&mut self, _tick: &mut TickContext, context: &mut SceneContext<D>) -> Transition<D> {
+        let mut finished = vec![];
+        self.tasks.retain_mut(|task| {
+            let done = task.poll();
+            if done {
+                finished.extend(task.result.take());
+            }
+            !done
+        });
+        for result in finished {
+            self.print_result(result, context);
+        }
+        Transition::None
+    }
+
+    fn render(&self, frame: &mut Frame, context: &SceneContext<D>) {
+        let theme = &context.theme;
+        let output_style = Style::default().fg(theme_color(&theme.suggestion_color, Color::White));
+        let input_style = Style::default().fg(theme_color(&theme.input_color, Color::White));
+        let prompt_style = Style::default().fg(theme_color(&theme.prompt_color, Color::Cyan));
+
+        let area = frame.area();
+        let height = (area.height / 3).max(4).min(area.height);
+        let console_area = Rect::new(area.x, area.bottom() - height, area.width, height);
+        frame.render_widget(Clear, console_area);
+        let block = Block::default().borders(Borders::TOP).title("Console");
+        let inner = block.inner(console_area);
+        frame.render_widget(block, console_area);
+
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(inner);
+        let mut lines: Vec<Line> = self
+            .output
+            .iter()
+            .skip(self.output.len().saturating_sub(parts[0].height as usize))
+            .map(|line| Line::styled(line.as_str(), output_style))
+            .collect();
+        for task in &self.tasks {
+            let progress = task.progress.map_or(String::new(), |(current, total)| format!(" {}/{}", current, total));
+            lines.push(Line::styled(format!("[{}{}] {}", task.name, progress, task.message), prompt_style));
+        }
+        let skip = lines.len().saturating_sub(parts[0].height as usize);
+        frame.render_widget(Paragraph::new(lines.split_off(skip)), parts[0]);
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled("> ", prompt_style),
+                Span::styled(self.input.as_str(), input_style),
+            ])),
+            parts[1],
+        );
+        frame.set_cursor_position((parts[1].x + 2 + self.input.chars().count() as u16, parts[1].y));
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
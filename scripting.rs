@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use rhai::{AST, Array, Dynamic, Engine, FnPtr, FuncArgs, Map, Scope};
+
+use crate::conditions::{ConditionContext, LootFilter};
+use crate::datapack::DatapackImport;
+use crate::game::{BlockBroken, EventBus, ItemCrafted};
+#[cfg(feature = "tui")]
+use crate::interface::{Command, CommandArg, CommandError, CommandRegistry};
+use crate::random::RuzRng;
+use crate::registry::{ID, LootStack, LootTable, RegistryEvent};
+
+// Operations a single script call may run before it's aborted
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+// -------
+// SCRIPTS
+// -------
+
+// Rhai scripts shipped by datapacks, so content can be added without
+// recompiling. Top-level code runs once on load and can register hooks:
+//
+//   register_command("heal", "Heals the player", ["player"], |args| `Healed ${args.player}`);
+//   register_loot_condition("ruztex:stone", |ctx| ctx.tool != "");
+//
+// The arguments of a command are optional strings and can be left out.
+// Loot conditions see "table", "block", "tool", "held", "biome" and "x",
+// "y", "z" of the ConditionContext, empty if missing. The host is a
+// LootFilter, see ConditionContext::with_loot_filter and
+// World::break_block_filtered.
+//
+// Scripts react to game and registry events by defining functions:
+//
+//   fn on_block_broken(block, x, y) { ... }      // x and y are () without a position
+//   fn on_item_crafted(recipe, results) { ... }  // results: [[item, count], ...]
+//   fn on_registry_change(kind, type, id) { ... }
+pub struct ScriptHost {
+    engine: Engine,
+    scripts: Vec<Script>,
+    commands: Vec<ScriptCommand>,
+    loot_conditions: Vec<(ID, usize, FnPtr)>, // (loot table, script, condition)
+    pending: Arc<Mutex<Registrations>>,       // Filled by the script being loaded
+}
+
+struct Script {
+    namespace: String,
+    ast: AST,
+}
+
+#[derive(Default)]
+struct Registrations {
+    commands: Vec<(String, String, Vec<String>, FnPtr)>,
+    loot_conditions: Vec<(String, FnPtr)>,
+}
+
+// A command registered by a script, run with ScriptHost::run_command
+pub struct ScriptCommand {
+    pub namespace: String,
+    pub name: String,
+    pub description: String,
+    pub args: Vec<String>,
+    script: usize,
+    handler: FnPtr,
+}
+
+impl ScriptHost {
+    pub fn new() -> Self {
+        let pending = Arc::new(Mutex::new(Registrations::default()));
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let registrations = pending.clone();
+        engine.register_fn("register_command", move |name: &str, description: &str, handler: FnPtr| {
+            registrations.lock().unwrap().commands.push((name.to_string(), description.to_string(), vec![], handler));
+        });
+        let registrations = pending.clone();
+        engine.register_fn("register_command", move |name: &str, description: &str, args: Array, handler: FnPtr| {
+            let args = args.into_iter().map(|arg| arg.to_string()).collect();
+            registrations.lock().unwrap().commands.push((name.to_string(), description.to_string(), args, handler));
+        });
+        let registrations = pending.clone();
+        engine.register_fn("register_loot_condition", move |table: &str, condition: FnPtr| {
+            registrations.lock().unwrap().loot_conditions.push((table.to_string(), condition));
+        });
+
+        ScriptHost { engine, scripts: vec![], commands: vec![], loot_conditions: vec![], pending }
+    }
+
+    // Compiles and runs a script, keeping the hooks it registered. Nothing
+    // is kept if a hook is invalid.
+    pub fn load(&mut self, namespace: &str, source: &str) -> Result<(), String> {
+        let ast = self.engine.compile(source).map_err(|e| format!("{}: {}", namespace, e))?;
+        *self.pending.lock().unwrap() = Registrations::default();
+        let result = self.engine.run_ast_with_scope(&mut Scope::new(), &ast);
+        let registrations = std::mem::take(&mut *self.pending.lock().unwrap());
+        result.map_err(|e| format!("{}: {}", namespace, e))?;
+
+        let index = self.scripts.len();
+        let mut loot_conditions = vec![];
+        for (table, condition) in registrations.loot_conditions {
+            let table = ID::parse(&table).map_err(|e| format!("{}: invalid loot table ID '{}': {}", namespace, table, e))?;
+            loot_conditions.push((table, index, condition));
+        }
+        for (name, ..) in &registrations.commands {
+            if name.is_empty() || name.contains(':') || name.contains(char::is_whitespace) {
+                return Err(format!("{}: invalid command name '{}'", namespace, name));
+            }
+            if self.commands.iter().any(|c| c.namespace == namespace && c.name == *name) {
+                return Err(format!("{}: command '{}' is already registered", namespace, name));
+            }
+        }
+
+        self.loot_conditions.extend(loot_conditions);
+        for (name, description, args, handler) in registrations.commands {
+            self.commands.push(ScriptCommand {
+                namespace: namespace.to_string(),
+                name,
+                description,
+                args,
+                script: index,
+                handler,
+            });
+        }
+        self.scripts.push(Script { namespace: namespace.to_string(), ast });
+        Ok(())
+    }
+
+    pub fn load_file(&mut self, namespace: &str, path: &Path) -> Result<(), String> {
+        let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.load(namespace, &source)
+    }
+
+    // Loads every .rhai file of a datapack directory, in file name order
+    pub fn load_dir(&mut self, namespace: &str, dir: &Path) -> Result<usize, String> {
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .map_err(|e| format!("{}: {}", dir.display(), e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+            .collect();
+        paths.sort();
+        for path in &paths {
+            self.load_file(namespace, path)?;
+        }
+        Ok(paths.len())
+    }
+
+    // Loads the scripts import_datapack found, in the namespace of their
+    // directory. Stops at the first script that fails.
+    pub fn load_datapack(&mut self, import: &DatapackImport) -> Result<usize, String> {
+        for (namespace, path) in &import.scripts {
+            self.load_file(namespace, path)?;
+        }
+        Ok(import.scripts.len())
+    }
+
+    // Commands
+
+    pub fn commands(&self) -> &[ScriptCommand] {
+        &self.commands
+    }
+
+    // Runs a script command, "name" or "namespace:name", with its arguments
+    // as a map, returns the handler's result as text
+    pub fn run_command(&self, name: &str, args: &HashMap<String, String>) -> Result<String, String> {
+        let matches = |c: &&ScriptCommand| match name.split_once(':') {
+            Some((namespace, name)) => c.namespace == namespace && c.name == name,
+            None => c.name == name,
+        };
+        let command = self
+            .commands
+            .iter()
+            .find(matches)
+            .ok_or_else(|| format!("Unknown script command: {}", name))?;
+        let args: Map = args.iter().map(|(k, v)| (k.into(), Dynamic::from(v.clone()))).collect();
+        let script = &self.scripts[command.script];
+        let result: Dynamic = command
+            .handler
+            .call(&self.engine, &script.ast, (args,))
+            .map_err(|e| format!("{}: {}", script.namespace, e))?;
+        Ok(if result.is_unit() { String::new() } else { result.to_string() })
+    }
+
+    // Loot conditions
+
+    // True if every condition scripts registered for the loot table returns
+    // true for `context`, e.g. {"block": "ruztex:stone", "tool": "ruztex:pickaxe"}.
+    // Failing conditions count as false.
+    pub fn loot_allowed(&self, table: &ID, context: &HashMap<String, String>) -> bool {
+        let context: Map = context.iter().map(|(k, v)| (k.into(), Dynamic::from(v.clone()))).collect();
+        self.conditions_hold(table, context)
+    }
+
+    fn conditions_hold(&self, table: &ID, context: Map) -> bool {
+        self.loot_conditions.iter().filter(|(id, _, _)| id == table).all(|(_, script, condition)| {
+            let script = &self.scripts[*script];
+            match condition.call::<bool>(&self.engine, &script.ast, (context.clone(),)) {
+                Ok(allowed) => allowed,
+                Err(e) => {
+                    log::warn!("Loot condition of {} for {} failed: {}", script.namespace, table, e);
+                    false
+                }
+            }
+        })
+    }
+
+    // LootTable::roll_with with the scripted conditions as the loot filter
+    pub fn roll_loot(&self, table: &LootTable, context: &ConditionContext, rng: &mut RuzRng) -> Vec<LootStack> {
+        table.roll_with(&context.with_loot_filter(self), rng)
+    }
+
+    // Events
+
+    // Calls `function` in every script that defines it with that many
+    // parameters. Errors are reported and don't stop the other scripts.
+    fn call_event(&self, function: &str, args: impl FuncArgs + Clone) {
+        let arity = {
+            let mut values = vec![];
+            args.clone().parse(&mut values);
+            values.len()
+        };
+        for script in &self.scripts {
+            let defined = script.ast.iter_functions().any(|f| f.name == function && f.params.len() == arity);
+            if !defined {
+                continue;
+            }
+            if let Err(e) = self.engine.call_fn::<Dynamic>(&mut Scope::new(), &script.ast, function, args.clone()) {
+                log::warn!("{} in {} failed: {}", function, script.namespace, e);
+            }
+        }
+    }
+
+    pub fn block_broken(&self, event: &BlockBroken) {
+        let (x, y) = match event.position {
+            Some((x, y)) => (Dynamic::from(x as i64), Dynamic::from(y as i64)),
+            None => (Dynamic::UNIT, Dynamic::UNIT),
+        };
+        self.call_event("on_block_broken", (event.block.to_string(), x, y));
+    }
+
+    pub fn item_crafted(&self, event: &ItemCrafted) {
+        let results: Array = event
+            .results
+            .iter()
+            .map(|(id, count)| Dynamic::from(vec![Dynamic::from(id.to_string()), Dynamic::from(*count as i64)]))
+            .collect();
+        self.call_event("on_item_crafted", (event.recipe.to_string(), results));
+    }
+
+    pub fn registry_changed(&self, event: &RegistryEvent) {
+        self.call_event("on_registry_change", (event.kind.to_string(), event.entity_type.to_string(), event.id.to_string()));
+    }
+
+    // Forwards BlockBroken and ItemCrafted events of the bus to the scripts
+    pub fn attach(host: &Arc<Mutex<ScriptHost>>, events: &mut EventBus) {
+        let scripts = host.clone();
+        events.subscribe(move |event: &BlockBroken, _| scripts.lock().unwrap().block_broken(event));
+        let scripts = host.clone();
+        events.subscribe(move |event: &ItemCrafted, _| scripts.lock().unwrap().item_crafted(event));
+    }
+
+    // Registers every script command in the command registry, under the
+    // namespace of its script. Commands loaded later need another call.
+    #[cfg(feature = "tui")]
+    pub fn register_commands(host: &Arc<Mutex<ScriptHost>>, commands: &mut CommandRegistry) -> Result<usize, CommandError> {
+        let scripts = host.lock().unwrap();
+        for command in &scripts.commands {
+            let args = command
+                .args
+                .iter()
+                .map(|name| CommandArg {
+                    name: name.clone(),
+                    arg_type: "string".to_string(),
+                    range: None,
+                    optional: true,
+                    default: None,
+                    help: None,
+                })
+                .collect();
+            let name = format!("{}:{}", command.namespace, command.name);
+            let host = host.clone();
+            commands.register_command(
+                Command::new(&command.name)
+                    .with_namespace(&command.namespace)
+                    .with_args(args)
+                    .with_shared_handler(move |args| host.lock().unwrap().run_command(&name, &args)),
+            )?;
+        }
+        Ok(scripts.commands.len())
+    }
+}
+
+// Context of a loot condition, see the comment of ScriptHost
+fn loot_context(table: &ID, context: &ConditionContext) -> Map {
+    let id = |id: Option<&ID>| id.map(ID::to_string).unwrap_or_default();
+    let coordinate = |c: Option<i32>| c.map(|c| c.to_string()).unwrap_or_default();
+    [
+        ("table", table.to_string()),
+        ("block", id(context.block.map(|b| &b.id))),
+        ("tool", id(context.tool.map(|t| &t.id))),
+        ("held", id(context.held.map(|s| &s.item.id))),
+        ("biome", id(context.biome.as_ref())),
+        ("x", coordinate(context.position.map(|p| p.x))),
+        ("y", coordinate(context.position.map(|p| p.y))),
+        ("z", coordinate(context.position.map(|p| p.z))),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.into(), Dynamic::from(value)))
+    .collect()
+}
+
+impl LootFilter for ScriptHost {
+    fn allows(&self, table: &ID, context: &ConditionContext) -> bool {
+        self.conditions_hold(table, loot_context(table, context))
+    }
+}
+
+impl Default for ScriptHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::color::{Color, ColorError, ColorRef, GradientDirection, Style, StyledText, interpolate_multi_color, resolve_color_ref};
+use crate::registry::{ID, IdError, Sprite, SpriteCell};
+
+// Sprite files have an [art] section and optionally a [colors] section of
+// the same shape, whose characters are keys of the [palette] section.
+// Spaces in the art are empty, spaces and '.' in the colors keep the
+// terminal's default color. Lines starting with '#' are comments, except
+// inside [art] and [colors].
+//
+//   [art]
+//    /\
+//   /__\
+//   [colors]
+//    rr
+//   rggr
+//   [palette]
+//   r = #c0392b
+//   g = default::gray
+
+pub const SPRITE_EXTENSION: &str = "sprite";
+
+// ------
+// ERRORS
+// ------
+
+#[derive(Debug)]
+pub enum SpriteError {
+    Io(PathBuf, io::Error),
+    InvalidId(PathBuf, IdError),
+    MissingArt,
+    InvalidLine { line: usize, text: String }, // Line number starting at 1
+    InvalidColor { line: usize, value: String },
+    UnknownColorKey { line: usize, key: char },
+}
+
+impl Display for SpriteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SpriteError::Io(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            SpriteError::InvalidId(path, e) => write!(f, "no sprite ID for {}: {}", path.display(), e),
+            SpriteError::MissingArt => write!(f, "sprite has no [art] section"),
+            SpriteError::InvalidLine { line, text } => write!(f, "invalid line {}: {}", line, text),
+            SpriteError::InvalidColor { line, value } => write!(f, "invalid color '{}' on line {}", value, line),
+            SpriteError::UnknownColorKey { line, key } => write!(f, "color key '{}' on line {} is not in the palette", key, line),
+        }
+    }
+}
+
+impl Error for SpriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SpriteError::Io(_, e) => Some(e),
+            SpriteError::InvalidId(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// -------
+// LOADING
+// -------
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    None,
+    Art,
+    Colors,
+    Palette,
+}
+
+impl Sprite {
+    pub fn parse(id: ID, content: &str) -> Result<Sprite, SpriteError> {
+        let mut section = Section::None;
+        let mut art: Vec<&str> = vec![];
+        let mut colors: Vec<(usize, &str)> = vec![];
+        let mut palette: HashMap<char, Color> = HashMap::new();
+        for (i, line) in content.lines().enumerate() {
+            let number = i + 1;
+            let next = match line.trim_end() {
+                "[art]" => Some(Section::Art),
+                "[colors]" => Some(Section::Colors),
+                "[palette]" => Some(Section::Palette),
+                _ => None,
+            };
+            if let Some(next) = next {
+                section = next;
+                continue;
+            }
+            match section {
+                Section::Art => art.push(line),
+                Section::Colors => colors.push((number, line)),
+                Section::None | Section::Palette if line.trim().is_empty() || line.trim_start().starts_with('#') => {}
+                Section::Palette => {
+                    let invalid = || SpriteError::InvalidLine { line: number, text: line.to_string() };
+                    let (key, value) = line.split_once('=').ok_or_else(invalid)?;
+                    let mut key_chars = key.trim().chars();
+                    let (Some(key), None) = (key_chars.next(), key_chars.next()) else {
+                        return Err(invalid());
+                    };
+                    let color = ColorRef::parse(value)
+                        .as_ref()
+                        .and_then(resolve_color_ref)
+                        .ok_or_else(|| SpriteError::InvalidColor { line: number, value: value.trim().to_string() })?;
+                    palette.insert(key, color);
+                }
+                Section::None => return Err(SpriteError::InvalidLine { line: number, text: line.to_string() }),
+            }
+        }
+        // Blank lines before the next section aren't part of the art
+        while art.last().is_some_and(|l| l.trim().is_empty()) {
+            art.pop();
+        }
+        if art.is_empty() {
+            return Err(SpriteError::MissingArt);
+        }
+
+        let mut sprite = Sprite::new(id, &art.join("\n"));
+        for (y, (number, line)) in colors.into_iter().enumerate() {
+            for (x, key) in line.chars().enumerate() {
+                if key == ' ' || key == '.' {
+                    continue;
+                }
+                let color = *palette.get(&key).ok_or(SpriteError::UnknownColorKey { line: number, key })?;
+                if let Some(cell) = sprite.get(x, y) {
+                    sprite.set(x, y, Some(SpriteCell { color: Some(color), ..cell }));
+                }
+            }
+        }
+        Ok(sprite)
+    }
+
+    pub fn load<P: AsRef<Path>>(id: ID, path: P) -> Result<Sprite, SpriteError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| SpriteError::Io(path.to_path_buf(), e))?;
+        Sprite::parse(id, &content)
+    }
+
+    // Every .sprite file in `dir`, named `namespace:<file stem>`, sorted by ID
+    pub fn load_dir<P: AsRef<Path>>(namespace: &str, dir: P) -> Result<Vec<Sprite>, SpriteError> {
+        let dir = dir.as_ref();
+        let entries = fs::read_dir(dir).map_err(|e| SpriteError::Io(dir.to_path_buf(), e))?;
+        let mut sprites = vec![];
+        for entry in entries {
+            let path = entry.map_err(|e| SpriteError::Io(dir.to_path_buf(), e))?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SPRITE_EXTENSION) {
+                continue;
+            }
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+            let id = ID::try_new(namespace, stem).map_err(|e| SpriteError::InvalidId(path.clone(), e))?;
+            sprites.push(Sprite::load(id, &path)?);
+        }
+        sprites.sort_by_key(|s| s.id.to_string());
+        Ok(sprites)
+    }
+}
+
+// -----------
+// COMPOSITION
+// -----------
+
+// Characters that point the other way when mirrored
+const MIRRORED_HORIZONTAL: [(char, char); 6] = [('/', '\\'), ('(', ')'), ('[', ']'), ('{', '}'), ('<', '>'), ('▌', '▐')];
+const MIRRORED_VERTICAL: [(char, char); 4] = [('/', '\\'), ('^', 'v'), ('▀', '▄'), ('\'', ',')];
+
+fn mirror(symbol: char, pairs: &[(char, char)]) -> char {
+    pairs
+        .iter()
+        .find_map(|&(a, b)| {
+            if symbol == a {
+                Some(b)
+            } else if symbol == b {
+                Some(a)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(symbol)
+}
+
+impl Sprite {
+    // `top` drawn over this sprite with its top left corner at (x, y). Empty
+    // cells of `top` let this sprite show through, parts outside are cut off.
+    pub fn overlay(&self, top: &Sprite, x: i32, y: i32) -> Sprite {
+        let mut result = self.clone();
+        for ty in 0..top.height() {
+            for tx in 0..top.width() {
+                let (rx, ry) = (x + tx as i32, y + ty as i32);
+                if let (Some(cell), Ok(rx), Ok(ry)) = (top.get(tx, ty), usize::try_from(rx), usize::try_from(ry)) {
+                    result.set(rx, ry, Some(cell));
+                }
+            }
+        }
+        result
+    }
+
+    // Mirrored left to right, e.g. an entity facing the other way
+    pub fn flipped_horizontal(&self) -> Sprite {
+        let mut result = Sprite::blank(self.id, self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let cell = self.get(x, y).map(|c| SpriteCell { symbol: mirror(c.symbol, &MIRRORED_HORIZONTAL), ..c });
+                result.set(self.width() - 1 - x, y, cell);
+            }
+        }
+        result
+    }
+
+    // Mirrored top to bottom
+    pub fn flipped_vertical(&self) -> Sprite {
+        let mut result = Sprite::blank(self.id, self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let cell = self.get(x, y).map(|c| SpriteCell { symbol: mirror(c.symbol, &MIRRORED_VERTICAL), ..c });
+                result.set(x, self.height() - 1 - y, cell);
+            }
+        }
+        result
+    }
+
+    // Blends the colors towards a gradient across the sprite, `strength` 0.0
+    // keeps them, 1.0 replaces them. Uncolored cells take the gradient color.
+    // One color tints the whole sprite evenly.
+    pub fn tinted(&self, gradient: &[ColorRef], direction: GradientDirection, strength: f64) -> Result<Sprite, ColorError> {
+        let colors = gradient
+            .iter()
+            .map(|c| resolve_color_ref(c).ok_or_else(|| ColorError::Unresolved(c.to_string())))
+            .collect::<Result<Vec<Color>, ColorError>>()?;
+        if colors.is_empty() {
+            return Err(ColorError::TooFewColors);
+        }
+        let mut result = self.clone();
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let Some(cell) = self.get(x, y) else {
+                    continue;
+                };
+                let (position, length) = match direction {
+                    GradientDirection::Horizontal => (x, self.width()),
+                    GradientDirection::Vertical => (y, self.height()),
+                };
+                let tint = interpolate_multi_color(&colors, position as f64 / (length.max(2) - 1) as f64);
+                let color = cell.color.map_or(tint, |c| c.mix(tint, strength));
+                result.set(x, y, Some(SpriteCell { color: Some(color), ..cell }));
+            }
+        }
+        Ok(result)
+    }
+}
+
+// -------
+// DRAWING
+// -------
+
+impl Sprite {
+    // One line per row, empty cells as spaces. Printable with ANSI colors or
+    // convertible to ratatui Text.
+    pub fn render(&self) -> StyledText {
+        let mut text = StyledText::new();
+        let mut symbol = [0; 4];
+        for y in 0..self.height() {
+            if y > 0 {
+                text.push("\n", Style::new());
+            }
+            for x in 0..self.width() {
+                match self.get(x, y) {
+                    Some(SpriteCell { symbol: c, color }) => {
+                        let style = color.map_or(Style::new(), |color| Style::new().fg(color));
+                        text.push(c.encode_utf8(&mut symbol), style);
+                    }
+                    None => text.push(" ", Style::new()),
+                }
+            }
+        }
+        text
+    }
+}
@@ -0,0 +1,325 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use crate::ecs::{EffectMap, Entities, EntityId, Health};
+use crate::intern::Symbol;
+use crate::inventory::Slot;
+use crate::random::RuzRng;
+use crate::registry::{ID, Registry, StatModifier, Tool};
+
+// Attributes of entities (max health, mining speed, luck, ...) computed from
+// a base value and modifiers of tools, enchantments and effects. Values are
+// cached until a base or modifier changes.
+//
+//   let mut stats = Stats::new().with_base(Attribute::MAX_HEALTH, 30.0);
+//   stats.set_equipment(Some(&held), &registry);
+//   stats.set_effects(&effect_map, &registry);
+//   let speed = stats.get(Attribute::MINING_SPEED);
+//
+// Entities spawned from a registered kind get Stats with their max health,
+// Entities::update_stats keeps effects and Health in sync. World::break_block
+// and the combat module read them.
+
+// ----------
+// ATTRIBUTES
+// ----------
+
+// One of the built-in attributes or a custom one, e.g.
+// Attribute::custom("examplemod:mana")
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Attribute(&'static str);
+
+impl Attribute {
+    pub const MAX_HEALTH: Attribute = Attribute("max_health");
+    pub const MINING_SPEED: Attribute = Attribute("mining_speed"); // Factor on the time to break blocks, 1.0 by hand
+    pub const LUCK: Attribute = Attribute("luck"); // Extra loot rolls, the fraction is a chance for one more
+    pub const ATTACK_DAMAGE: Attribute = Attribute("attack_damage");
+    pub const ARMOR: Attribute = Attribute("armor"); // Each point blocks 4% of damage, up to 80%
+    pub const HUNGER: Attribute = Attribute("hunger"); // Food level, games lower the base over time, see hud::StatBar
+    pub const STAMINA: Attribute = Attribute("stamina");
+
+    pub const BUILTIN: [Attribute; 7] =
+        [Self::MAX_HEALTH, Self::MINING_SPEED, Self::LUCK, Self::ATTACK_DAMAGE, Self::ARMOR, Self::HUNGER, Self::STAMINA];
+
+    pub fn custom(name: &str) -> Attribute {
+        Attribute(Symbol::intern(name).as_str())
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0
+    }
+
+    // Base value unless Stats sets another one
+    pub fn default_base(&self) -> f32 {
+        match *self {
+            Attribute::MAX_HEALTH | Attribute::HUNGER | Attribute::STAMINA => 20.0,
+            Attribute::MINING_SPEED | Attribute::ATTACK_DAMAGE => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// How modifiers stack:
+//   (base + all Add) * (1 + all MultiplyBase) * (1 + each MultiplyTotal)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Add,
+    MultiplyBase,
+    MultiplyTotal,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttributeModifier {
+    pub attribute: Attribute,
+    pub operation: Operation,
+    pub amount: f32,
+}
+
+impl AttributeModifier {
+    pub fn new(attribute: Attribute, operation: Operation, amount: f32) -> Self {
+        AttributeModifier { attribute, operation, amount }
+    }
+
+    pub fn add(attribute: Attribute, amount: f32) -> Self {
+        Self::new(attribute, Operation::Add, amount)
+    }
+
+    pub fn multiply_base(attribute: Attribute, amount: f32) -> Self {
+        Self::new(attribute, Operation::MultiplyBase, amount)
+    }
+
+    pub fn multiply_total(attribute: Attribute, amount: f32) -> Self {
+        Self::new(attribute, Operation::MultiplyTotal, amount)
+    }
+
+    // Amount times `factor`, e.g. per enchantment level
+    pub fn scaled(self, factor: f32) -> Self {
+        AttributeModifier { amount: self.amount * factor, ..self }
+    }
+}
+
+// "+2 attack_damage", "+50% mining_speed" (multiply base), "x1.5 luck" (multiply total)
+impl Display for AttributeModifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.operation {
+            Operation::Add => write!(f, "{:+} {}", self.amount, self.attribute),
+            Operation::MultiplyBase => write!(f, "{:+}% {}", self.amount * 100.0, self.attribute),
+            Operation::MultiplyTotal => write!(f, "x{} {}", 1.0 + self.amount, self.attribute),
+        }
+    }
+}
+
+// -------
+// SOURCES
+// -------
+
+// Where a modifier comes from, so it can be replaced when e.g. the held tool
+// changes or an effect runs out
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ModifierSource {
+    Tool(ID),
+    Enchantment(ID),
+    Effect(ID),
+    Custom(String),
+}
+
+impl ModifierSource {
+    fn is_equipment(&self) -> bool {
+        matches!(self, ModifierSource::Tool(_) | ModifierSource::Enchantment(_))
+    }
+}
+
+// The tool's speed as MINING_SPEED bonus over breaking by hand, and its own modifiers
+pub fn tool_modifiers(tool: &Tool) -> Vec<AttributeModifier> {
+    let mut modifiers = vec![AttributeModifier::add(Attribute::MINING_SPEED, tool.speed - 1.0)];
+    modifiers.extend(tool.modifiers.iter().copied());
+    modifiers
+}
+
+// Enchantment modifiers of a slot: ToolSpeed multiplies the MINING_SPEED base
+// (only on tools), LootLuck adds LUCK, attribute modifiers are scaled by the level
+pub fn enchantment_modifiers(slot: &Slot, registry: &Registry) -> Vec<(ModifierSource, AttributeModifier)> {
+    let is_tool = registry.tools.contains_key(&slot.item.id);
+    let mut modifiers = vec![];
+    for (enchantment, level) in &slot.enchantments {
+        let level = *level as f32;
+        for modifier in &enchantment.modifiers {
+            let modifier = match modifier {
+                StatModifier::ToolSpeed(v) if is_tool => AttributeModifier::multiply_base(Attribute::MINING_SPEED, v * level),
+                StatModifier::LootLuck(v) => AttributeModifier::add(Attribute::LUCK, v * level),
+                StatModifier::Attribute(modifier) => modifier.scaled(level),
+                StatModifier::ToolSpeed(_) | StatModifier::DurabilityUse(_) => continue,
+            };
+            modifiers.push((ModifierSource::Enchantment(enchantment.id), modifier));
+        }
+    }
+    modifiers
+}
+
+// -----
+// STATS
+// -----
+
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    base: HashMap<Attribute, f32>,
+    modifiers: Vec<(ModifierSource, AttributeModifier)>, // In the order they were added
+    cache: RefCell<HashMap<Attribute, f32>>,              // Computed values, cleared on every change
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_base(mut self, attribute: Attribute, value: f32) -> Self {
+        self.set_base(attribute, value);
+        self
+    }
+
+    pub fn set_base(&mut self, attribute: Attribute, value: f32) {
+        self.base.insert(attribute, value);
+        self.cache.get_mut().remove(&attribute);
+    }
+
+    pub fn base(&self, attribute: Attribute) -> f32 {
+        self.base.get(&attribute).copied().unwrap_or_else(|| attribute.default_base())
+    }
+
+    pub fn add_modifier(&mut self, source: ModifierSource, modifier: AttributeModifier) {
+        self.cache.get_mut().remove(&modifier.attribute);
+        self.modifiers.push((source, modifier));
+    }
+
+    pub fn with_modifier(mut self, source: ModifierSource, modifier: AttributeModifier) -> Self {
+        self.add_modifier(source, modifier);
+        self
+    }
+
+    // Removes every modifier of the source, returns how many there were
+    pub fn remove_source(&mut self, source: &ModifierSource) -> usize {
+        self.replace(|s| s == source, vec![])
+    }
+
+    // Replaces every modifier of the source
+    pub fn set_source(&mut self, source: ModifierSource, modifiers: Vec<AttributeModifier>) {
+        let modifiers = modifiers.into_iter().map(|m| (source.clone(), m)).collect();
+        self.replace(|s| *s == source, modifiers);
+    }
+
+    // Replaces the modifiers whose source matches, keeping the cache if
+    // nothing changed. Returns how many were removed.
+    fn replace(&mut self, matches: impl Fn(&ModifierSource) -> bool, modifiers: Vec<(ModifierSource, AttributeModifier)>) -> usize {
+        let old: Vec<&(ModifierSource, AttributeModifier)> = self.modifiers.iter().filter(|(s, _)| matches(s)).collect();
+        let removed = old.len();
+        if old.len() == modifiers.len() && old.iter().zip(&modifiers).all(|(a, b)| *a == b) {
+            return removed;
+        }
+        let cache = self.cache.get_mut();
+        for (_, modifier) in self.modifiers.iter().filter(|(s, _)| matches(s)).chain(&modifiers) {
+            cache.remove(&modifier.attribute);
+        }
+        self.modifiers.retain(|(s, _)| !matches(s));
+        self.modifiers.extend(modifiers);
+        removed
+    }
+
+    pub fn modifiers(&self) -> &[(ModifierSource, AttributeModifier)] {
+        &self.modifiers
+    }
+
+    // Modifiers of the held slot's tool and enchantments, replacing those of
+    // the previously held slot. None for an empty hand.
+    pub fn set_equipment(&mut self, held: Option<&Slot>, registry: &Registry) {
+        let mut modifiers = vec![];
+        if let Some(slot) = held {
+            if let Some(tool) = registry.tools.get(&slot.item.id) {
+                modifiers.extend(tool_modifiers(tool).into_iter().map(|m| (ModifierSource::Tool(tool.id), m)));
+            }
+            modifiers.extend(enchantment_modifiers(slot, registry));
+        }
+        self.replace(ModifierSource::is_equipment, modifiers);
+    }
+
+    pub fn with_equipment(mut self, held: Option<&Slot>, registry: &Registry) -> Self {
+        self.set_equipment(held, registry);
+        self
+    }
+
+    // Modifiers of the active effects, scaled by amplifier + 1, replacing
+    // those of effects that ran out
+    pub fn set_effects(&mut self, effects: &EffectMap, registry: &Registry) {
+        let mut active: Vec<(&ID, u32)> = effects.iter().map(|(id, active)| (id, active.amplifier)).collect();
+        active.sort_by_key(|(id, _)| id.to_string());
+        let mut modifiers = vec![];
+        for (id, amplifier) in active {
+            let Some(effect) = registry.effects.get(id) else {
+                continue;
+            };
+            let scale = (amplifier + 1) as f32;
+            modifiers.extend(effect.modifiers.iter().map(|m| (ModifierSource::Effect(*id), m.scaled(scale))));
+        }
+        self.replace(|s| matches!(s, ModifierSource::Effect(_)), modifiers);
+    }
+
+    // Current value, computed on first use after a change
+    pub fn get(&self, attribute: Attribute) -> f32 {
+        if let Some(value) = self.cache.borrow().get(&attribute) {
+            return *value;
+        }
+        let value = self.compute(attribute);
+        self.cache.borrow_mut().insert(attribute, value);
+        value
+    }
+
+    fn compute(&self, attribute: Attribute) -> f32 {
+        let modifiers = self.modifiers.iter().map(|(_, m)| m).filter(|m| m.attribute == attribute);
+        let (mut add, mut multiply_base, mut multiply_total) = (0.0, 0.0, 1.0);
+        for modifier in modifiers {
+            match modifier.operation {
+                Operation::Add => add += modifier.amount,
+                Operation::MultiplyBase => multiply_base += modifier.amount,
+                Operation::MultiplyTotal => multiply_total *= 1.0 + modifier.amount,
+            }
+        }
+        (self.base(attribute) + add) * (1.0 + multiply_base) * multiply_total
+    }
+
+    // Number of extra loot rolls: the whole part of LUCK plus one more with
+    // the remaining fraction as chance
+    pub fn extra_loot_rolls(&self, rng: &mut RuzRng) -> u32 {
+        let luck = self.get(Attribute::LUCK).max(0.0);
+        luck.trunc() as u32 + rng.chance(luck.fract()) as u32
+    }
+}
+
+// --------
+// ENTITIES
+// --------
+
+impl Entities {
+    // Applies the active effects' modifiers to every entity with Stats and
+    // sets their Health max to MAX_HEALTH, lowering the current health if
+    // needed. Call once per tick after tick_effects.
+    pub fn update_stats(&mut self, registry: &Registry) {
+        for entity in self.iter().collect::<Vec<EntityId>>() {
+            let effects = self.get::<EffectMap>(entity).cloned().unwrap_or_default();
+            let Some(stats) = self.get_mut::<Stats>(entity) else {
+                continue;
+            };
+            stats.set_effects(&effects, registry);
+            let max = stats.get(Attribute::MAX_HEALTH).round().max(1.0) as u32;
+            if let Some(health) = self.get_mut::<Health>(entity) {
+                health.max = max;
+                health.current = health.current.min(max);
+            }
+        }
+    }
+}
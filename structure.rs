@@ -0,0 +1,255 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_yaml::Value;
+
+use crate::registry::{ID, IdError, Registry, Structure};
+use crate::world::BlockState;
+
+// Structure schematics, as text files with sections like sprites or as
+// YAML. In text files each [layer] section is the next layer up; lines
+// starting with '#' are comments, except inside [layer], so '#' can't be a
+// palette key. Placement values are separated by spaces or commas.
+//
+//   [palette]
+//   P = game:planks
+//   D = game:door facing=north
+//   [layer]
+//   PPPPP
+//   P.D.P
+//   [layer]
+//   PPPPP
+//   [placement]
+//   biomes = game:plains, game:forest
+//   rarity = 20
+//
+// The same in YAML:
+//
+//   palette: { P: game:planks, D: game:door facing=north }
+//   layers: [["PPPPP", "P.D.P"], ["PPPPP"]]
+//   biomes: [game:plains, game:forest]
+//   rarity: 20
+
+pub const STRUCTURE_EXTENSION: &str = "structure";
+pub const YAML_EXTENSIONS: [&str; 2] = ["yaml", "yml"];
+
+// ------
+// ERRORS
+// ------
+
+#[derive(Debug)]
+pub enum StructureError {
+    Io(PathBuf, io::Error),
+    InvalidId(PathBuf, IdError),
+    Yaml(serde_yaml::Error),
+    InvalidLine { line: usize, text: String }, // Line number starting at 1
+    InvalidField { field: String, value: String },
+    MissingLayers,
+    UnknownKey { key: char, layer: usize, row: usize }, // Not in the palette, both starting at 0
+    UnknownBlock { key: char, block: ID },              // Not registered
+}
+
+impl Display for StructureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StructureError::Io(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            StructureError::InvalidId(path, e) => write!(f, "no structure ID for {}: {}", path.display(), e),
+            StructureError::Yaml(e) => write!(f, "invalid YAML: {}", e),
+            StructureError::InvalidLine { line, text } => write!(f, "invalid line {}: {}", line, text),
+            StructureError::InvalidField { field, value } => write!(f, "invalid {}: {}", field, value),
+            StructureError::MissingLayers => write!(f, "structure has no layers"),
+            StructureError::UnknownKey { key, layer, row } => {
+                write!(f, "key '{}' in layer {} row {} is not in the palette", key, layer, row)
+            }
+            StructureError::UnknownBlock { key, block } => write!(f, "block {} of key '{}' is not registered", block, key),
+        }
+    }
+}
+
+impl Error for StructureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StructureError::Io(_, e) => Some(e),
+            StructureError::InvalidId(_, e) => Some(e),
+            StructureError::Yaml(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for StructureError {
+    fn from(e: serde_yaml::Error) -> Self {
+        StructureError::Yaml(e)
+    }
+}
+
+// -------
+// LOADING
+// -------
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    None,
+    Palette,
+    Layer,
+    Placement,
+}
+
+fn invalid(field: &str, value: impl Display) -> StructureError {
+    StructureError::InvalidField { field: field.to_string(), value: value.to_string() }
+}
+
+fn parse_key(key: &str) -> Result<char, StructureError> {
+    let mut chars = key.trim().chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c != Structure::KEEP && c != Structure::AIR => Ok(c),
+        _ => Err(invalid("palette key", key.trim())),
+    }
+}
+
+fn parse_block(value: &str) -> Result<BlockState, StructureError> {
+    value.parse().map_err(|e| invalid("block", e))
+}
+
+fn parse_ids(field: &str, values: &[impl AsRef<str>]) -> Result<Vec<ID>, StructureError> {
+    values.iter().map(|v| ID::parse(v.as_ref()).map_err(|e| invalid(field, format!("{} ({})", v.as_ref(), e)))).collect()
+}
+
+fn parse_rarity(value: &str) -> Result<u32, StructureError> {
+    value.trim().parse().map_err(|_| invalid("rarity", value.trim()))
+}
+
+impl Structure {
+    pub fn parse(id: ID, content: &str) -> Result<Structure, StructureError> {
+        let mut section = Section::None;
+        let mut structure = Structure::new(id);
+        for (i, line) in content.lines().enumerate() {
+            let number = i + 1;
+            let next = match line.trim_end() {
+                "[palette]" => Some(Section::Palette),
+                "[layer]" => Some(Section::Layer),
+                "[placement]" => Some(Section::Placement),
+                _ => None,
+            };
+            if let Some(next) = next {
+                if next == Section::Layer {
+                    structure.layers.push(vec![]);
+                }
+                section = next;
+                continue;
+            }
+            let invalid_line = || StructureError::InvalidLine { line: number, text: line.to_string() };
+            match section {
+                Section::Layer => structure.layers.last_mut().unwrap().push(line.trim_end().to_string()),
+                _ if line.trim().is_empty() || line.trim_start().starts_with('#') => {}
+                Section::Palette => {
+                    let (key, value) = line.split_once('=').ok_or_else(invalid_line)?;
+                    structure.palette.insert(parse_key(key)?, parse_block(value)?);
+                }
+                Section::Placement => {
+                    let (field, value) = line.split_once('=').ok_or_else(invalid_line)?;
+                    let values: Vec<&str> = value.split([' ', ',']).filter(|v| !v.is_empty()).collect();
+                    match field.trim() {
+                        "biomes" => structure.biomes = parse_ids("biome", &values)?,
+                        "tags" => structure.tags = parse_ids("tag", &values)?,
+                        "rarity" => structure.rarity = parse_rarity(value)?,
+                        _ => return Err(invalid_line()),
+                    }
+                }
+                Section::None => return Err(invalid_line()),
+            }
+        }
+        // Blank lines before the next section aren't part of the layer
+        for layer in &mut structure.layers {
+            while layer.last().is_some_and(|row| row.trim().is_empty()) {
+                layer.pop();
+            }
+        }
+        structure.check_keys()?;
+        Ok(structure)
+    }
+
+    // Missing fields keep their defaults
+    pub fn parse_yaml(id: ID, content: &str) -> Result<Structure, StructureError> {
+        let value: Value = serde_yaml::from_str(content)?;
+        let field = |name: &str| value.get(name).cloned().unwrap_or(Value::Null);
+        let mut structure = Structure::new(id);
+        let palette: Option<BTreeMap<String, String>> = serde_yaml::from_value(field("palette"))?;
+        for (key, block) in palette.unwrap_or_default() {
+            structure.palette.insert(parse_key(&key)?, parse_block(&block)?);
+        }
+        structure.layers = serde_yaml::from_value::<Option<_>>(field("layers"))?.unwrap_or_default();
+        structure.biomes = parse_ids("biome", &serde_yaml::from_value::<Option<Vec<String>>>(field("biomes"))?.unwrap_or_default())?;
+        structure.tags = parse_ids("tag", &serde_yaml::from_value::<Option<Vec<String>>>(field("tags"))?.unwrap_or_default())?;
+        structure.rarity = serde_yaml::from_value::<Option<u32>>(field("rarity"))?.unwrap_or(0);
+        structure.check_keys()?;
+        Ok(structure)
+    }
+
+    // A .yaml or .yml file is read as YAML, anything else as text
+    pub fn load<P: AsRef<Path>>(id: ID, path: P) -> Result<Structure, StructureError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| StructureError::Io(path.to_path_buf(), e))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(extension) if YAML_EXTENSIONS.contains(&extension) => Structure::parse_yaml(id, &content),
+            _ => Structure::parse(id, &content),
+        }
+    }
+
+    // Every .structure, .yaml and .yml file in `dir`, named
+    // `namespace:<file stem>`, sorted by ID
+    pub fn load_dir<P: AsRef<Path>>(namespace: &str, dir: P) -> Result<Vec<Structure>, StructureError> {
+        let dir = dir.as_ref();
+        let entries = fs::read_dir(dir).map_err(|e| StructureError::Io(dir.to_path_buf(), e))?;
+        let mut structures = vec![];
+        for entry in entries {
+            let path = entry.map_err(|e| StructureError::Io(dir.to_path_buf(), e))?.path();
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+            if extension != STRUCTURE_EXTENSION && !YAML_EXTENSIONS.contains(&extension) {
+                continue;
+            }
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+            let id = ID::try_new(namespace, stem).map_err(|e| StructureError::InvalidId(path.clone(), e))?;
+            structures.push(Structure::load(id, &path)?);
+        }
+        structures.sort_by_key(|s| s.id.to_string());
+        Ok(structures)
+    }
+}
+
+// ----------
+// VALIDATION
+// ----------
+
+impl Structure {
+    // Layers exist and every character is ' ', '.' or a palette key
+    fn check_keys(&self) -> Result<(), StructureError> {
+        if self.layers.iter().all(Vec::is_empty) {
+            return Err(StructureError::MissingLayers);
+        }
+        for (z, layer) in self.layers.iter().enumerate() {
+            for (y, row) in layer.iter().enumerate() {
+                let unknown = row.chars().find(|&c| c != Structure::KEEP && c != Structure::AIR && !self.palette.contains_key(&c));
+                if let Some(key) = unknown {
+                    return Err(StructureError::UnknownKey { key, layer: z, row: y });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Like parsing checks the pattern, and every palette block is registered.
+    // Registration already requires the blocks, this catches blocks deleted
+    // since and structures built in code.
+    pub fn validate(&self, registry: &Registry) -> Result<(), StructureError> {
+        self.check_keys()?;
+        match self.palette.iter().find(|(_, state)| !registry.blocks.contains_key(&state.block)) {
+            Some((&key, state)) => Err(StructureError::UnknownBlock { key, block: state.block }),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::ecs::{Entities, EntityId, Position};
+use crate::inventory::{Inventory, InventoryError, Slot, deserialize_stack, serialize_stack};
+use crate::registry::Registry;
+use crate::world::{BlockPos, BlockState, World};
+
+// Keeps clients' copies of the host's world, entity positions and entity
+// inventories up to date over TCP. Clients get the full state once, then
+// one delta per host update with what changed since the previous one. Every
+// change sets a value, so applying one twice is harmless.
+//
+//   let mut host = SyncHost::start("0.0.0.0:25566", &world, &registry)?;
+//   host.tracker.block_changed(pos); // After the game changed a block
+//   host.update(&world, &entities); // Once per tick
+//
+//   let mut client = SyncClient::connect("localhost:25566", "Steve", &registry)?;
+//   client.poll(&registry)?; // Applies the received deltas to client.replica
+//
+// Wire format, UTF-8 lines with fields separated by spaces:
+//
+//   hello <version> <name>                    Client, right after connecting
+//   reject <reason>                           Host, then it disconnects
+//   welcome <version> <2d|3d> <namespaces>    Host, then per registry namespace:
+//   hash <namespace> <hash>                   Registry::namespace_hashes, in hex
+//   delta <tick> <changes>                    Host, then one line per change:
+//   block <x> <y> <z> <state>                 Block placed or replaced, e.g. "block 4 2 0 game:door facing=north"
+//   block <x> <y> <z> -                       Block removed
+//   entity <entity> <x> <y>                   Entity moved or appeared, e.g. "entity 3v0 10 -4"
+//   entity <entity> -                         Entity despawned or lost its Position
+//   slot <entity> <index> <stack>             Inventory stack in Inventory::stacks order, as in Inventory::serialize
+//   slots <entity> <count>                    Inventory shrank to <count> stacks
+//
+// The first delta after welcome holds the full state. Empty deltas aren't
+// sent. Clients whose registry content differs from the host's disconnect;
+// the version changes with incompatible format changes and the host rejects
+// other versions.
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// How long the host waits for a client's hello
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+// How often the accept loop checks for stop
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// ------
+// ERRORS
+// ------
+
+#[derive(Debug)]
+pub enum SyncError {
+    Io(io::Error),
+    Rejected(String),
+    RegistryMismatch(Vec<String>), // Namespaces whose content differs from the host's
+    InvalidMessage(String),
+    Disconnected,
+}
+
+impl Display for SyncError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::Io(e) => write!(f, "connection failed: {}", e),
+            SyncError::Rejected(reason) => write!(f, "rejected by the host: {}", reason),
+            SyncError::RegistryMismatch(namespaces) => {
+                write!(f, "registry content differs from the host's in {}", namespaces.join(", "))
+            }
+            SyncError::InvalidMessage(message) => write!(f, "invalid message: {}", message),
+            SyncError::Disconnected => write!(f, "disconnected"),
+        }
+    }
+}
+
+impl Error for SyncError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SyncError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SyncError {
+    fn from(e: io::Error) -> Self {
+        SyncError::Io(e)
+    }
+}
+
+// Invalid changes, e.g. unknown items in slots
+impl From<String> for SyncError {
+    fn from(message: String) -> Self {
+        SyncError::InvalidMessage(message)
+    }
+}
+
+// ------
+// DELTAS
+// ------
+
+#[derive(Clone)]
+pub enum Change {
+    Block(BlockPos, Option<BlockState>),
+    Entity(EntityId, Option<Position>), // None if despawned
+    Slot(EntityId, usize, Slot),
+    Slots(EntityId, usize), // Inventory shrank to this many slots
+}
+
+impl Change {
+    pub fn encode(&self) -> String {
+        match self {
+            Change::Block(pos, Some(state)) => format!("block {} {}", pos, state),
+            Change::Block(pos, None) => format!("block {} -", pos),
+            Change::Entity(entity, Some(position)) => format!("entity {} {} {}", entity, position.x, position.y),
+            Change::Entity(entity, None) => format!("entity {} -", entity),
+            Change::Slot(entity, index, slot) => format!("slot {} {} {}", entity, index, serialize_stack(slot)),
+            Change::Slots(entity, count) => format!("slots {} {}", entity, count),
+        }
+    }
+
+    // Items and enchantments of slots are looked up in `registry`
+    pub fn decode(line: &str, registry: &Registry) -> Result<Change, SyncError> {
+        let invalid = || format!("invalid change: {}", line);
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let number = |i: usize| parts.get(i).and_then(|p| p.parse::<i32>().ok()).ok_or_else(invalid);
+        let count = |i: usize| parts.get(i).and_then(|p| p.parse::<usize>().ok()).ok_or_else(invalid);
+        let entity = || parts.get(1).and_then(|p| p.parse::<EntityId>().ok()).ok_or_else(invalid);
+        let change = match parts[..] {
+            ["block", _, _, _, "-"] => Change::Block(BlockPos::new_3d(number(1)?, number(2)?, number(3)?), None),
+            ["block", _, _, _, _, ..] => {
+                let state = parts[4..].join(" ").parse().map_err(|_| invalid())?;
+                Change::Block(BlockPos::new_3d(number(1)?, number(2)?, number(3)?), Some(state))
+            }
+            ["entity", _, "-"] => Change::Entity(entity()?, None),
+            ["entity", _, _, _] => Change::Entity(entity()?, Some(Position { x: number(2)?, y: number(3)? })),
+            ["slot", _, _, _, ..] => {
+                let slot = deserialize_stack(&mut parts[3..].iter().copied(), registry, || InventoryError::InvalidStack(parts[3..].join(" ")))
+                    .map_err(|e| SyncError::InvalidMessage(e.to_string()))?;
+                Change::Slot(entity()?, count(2)?, slot)
+            }
+            ["slots", _, _] => Change::Slots(entity()?, count(2)?),
+            _ => return Err(SyncError::InvalidMessage(invalid())),
+        };
+        Ok(change)
+    }
+}
+
+// Changes of one host update
+#[derive(Clone, Default)]
+pub struct Delta {
+    pub tick: u64,
+    pub changes: Vec<Change>,
+}
+
+impl Delta {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    // "delta <tick> <changes>" and one line per change
+    pub fn encode(&self) -> String {
+        let mut output = format!("delta {} {}\n", self.tick, self.changes.len());
+        for change in &self.changes {
+            output += &change.encode();
+            output += "\n";
+        }
+        output
+    }
+
+    pub fn decode(content: &str, registry: &Registry) -> Result<Delta, SyncError> {
+        let mut lines = content.lines();
+        let header: Vec<&str> = lines.next().map_or(vec![], |l| l.split_whitespace().collect());
+        let (tick, count) = match header[..] {
+            ["delta", tick, count] => match (tick.parse::<u64>(), count.parse::<usize>()) {
+                (Ok(tick), Ok(count)) => (tick, count),
+                _ => return Err(SyncError::InvalidMessage(format!("invalid delta header: {}", header.join(" ")))),
+            },
+            _ => return Err(SyncError::InvalidMessage("missing 'delta <tick> <changes>' header".to_string())),
+        };
+        let changes = lines.map(|line| Change::decode(line, registry)).collect::<Result<Vec<_>, _>>()?;
+        if changes.len() != count {
+            return Err(SyncError::InvalidMessage(format!("delta {} has {} of {} changes", tick, changes.len(), count)));
+        }
+        Ok(Delta { tick, changes })
+    }
+}
+
+// -------
+// TRACKER
+// -------
+
+// What the host last sent, to compute deltas. Entity positions and
+// inventories are compared with the last delta; blocks aren't, the game
+// reports changed positions with block_changed.
+#[derive(Default)]
+pub struct SyncTracker {
+    tick: u64,
+    blocks: BTreeSet<BlockPos>,                  // Changed since the last delta
+    positions: BTreeMap<EntityId, Position>,     // As last sent
+    slots: BTreeMap<EntityId, Vec<String>>,      // Serialized stacks as last sent
+}
+
+impl SyncTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Ticks of the last delta, counting from 0
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn block_changed(&mut self, pos: BlockPos) {
+        self.blocks.insert(pos);
+    }
+
+    // The full current state, for clients that just connected
+    pub fn snapshot(&self, world: &World, entities: &Entities) -> Delta {
+        let mut blocks: Vec<(BlockPos, &BlockState)> = world.iter().collect();
+        blocks.sort_by_key(|(pos, _)| (pos.z, pos.y, pos.x));
+        let mut changes: Vec<Change> = blocks.into_iter().map(|(pos, state)| Change::Block(pos, Some(state.clone()))).collect();
+        changes.extend(entities.query::<Position>().map(|(entity, position)| Change::Entity(entity, Some(*position))));
+        for (entity, inventory) in entities.query::<Inventory>() {
+            changes.extend(inventory.stacks().enumerate().map(|(i, slot)| Change::Slot(entity, i, slot.clone())));
+            changes.push(Change::Slots(entity, inventory.stack_count()));
+        }
+        Delta { tick: self.tick, changes }
+    }
+
+    // Changes since the previous delta, counted as the next tick
+    pub fn delta(&mut self, world: &World, entities: &Entities) -> Delta {
+        self.tick += 1;
+        let mut changes: Vec<Change> =
+            std::mem::take(&mut self.blocks).into_iter().map(|pos| Change::Block(pos, world.get(pos).cloned())).collect();
+
+        let positions: BTreeMap<EntityId, Position> = entities.query::<Position>().map(|(e, p)| (e, *p)).collect();
+        for (entity, position) in &positions {
+            if self.positions.get(entity) != Some(position) {
+                changes.push(Change::Entity(*entity, Some(*position)));
+            }
+        }
+        changes.extend(self.positions.keys().filter(|e| !positions.contains_key(e)).map(|e| Change::Entity(*e, None)));
+        self.positions = positions;
+
+        let mut slots = BTreeMap::new();
+        for (entity, inventory) in entities.query::<Inventory>() {
+            let stacks: Vec<String> = inventory.stacks().map(serialize_stack).collect();
+            let sent = self.slots.remove(&entity).unwrap_or_default();
+            for (i, (stack, slot)) in stacks.iter().zip(inventory.stacks()).enumerate() {
+                if sent.get(i) != Some(stack) {
+                    changes.push(Change::Slot(entity, i, slot.clone()));
+                }
+            }
+            if stacks.len() < sent.len() {
+                changes.push(Change::Slots(entity, stacks.len()));
+            }
+            slots.insert(entity, stacks);
+        }
+        // Left are inventories that were removed
+        changes.extend(self.slots.keys().map(|e| Change::Slots(*e, 0)));
+        self.slots = slots;
+        Delta { tick: self.tick, changes }
+    }
+}
+
+// ----
+// HOST
+// ----
+
+// Serves the state to clients connecting to `address`. Call update once per
+// tick; it sends the delta to connected clients, then the full state to
+// clients that connected since.
+pub struct SyncHost {
+    pub tracker: SyncTracker,
+    address: SocketAddr,
+    clients: Vec<(String, TcpStream)>,
+    pending: Arc<Mutex<Vec<(String, TcpStream)>>>, // Said hello, waiting for the state
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SyncHost {
+    // Clients are welcomed with the registry hashes and the world's
+    // dimensions as of starting, the state follows with the next update
+    pub fn start(address: impl ToSocketAddrs, world: &World, registry: &Registry) -> io::Result<SyncHost> {
+        let hashes = registry.namespace_hashes();
+        let mut welcome = format!("welcome {} {} {}\n", PROTOCOL_VERSION, if world.is_3d() { "3d" } else { "2d" }, hashes.len());
+        for (namespace, hash) in &hashes {
+            welcome += &format!("hash {} {:016x}\n", namespace, hash);
+        }
+        let welcome = Arc::new(welcome);
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+        log::info!("Sync host listening on {}", address);
+        let stop = Arc::new(AtomicBool::new(false));
+        let pending = Arc::new(Mutex::new(vec![]));
+        let thread = {
+            let stop = stop.clone();
+            let pending = pending.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, peer)) => {
+                            let pending = pending.clone();
+                            let welcome = welcome.clone();
+                            thread::spawn(move || match greet(&stream, &welcome) {
+                                Ok(name) => {
+                                    log::info!("Sync client {} connected from {}", name, peer);
+                                    pending.lock().unwrap().push((name, stream));
+                                }
+                                Err(e) => log::warn!("Sync client {} not accepted: {}", peer, e),
+                            });
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                        Err(e) => log::warn!("Sync host accept failed: {}", e),
+                    }
+                }
+            })
+        };
+        Ok(SyncHost { tracker: SyncTracker::new(), address, clients: vec![], pending, stop, thread: Some(thread) })
+    }
+
+    // The bound address, e.g. to find the port after binding to port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    // Names of the clients that got the state
+    pub fn clients(&self) -> Vec<&str> {
+        self.clients.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    // Sends this tick's delta, and the full state to clients that connected
+    // since the last update. Clients that can't be written to are dropped.
+    // Returns the delta.
+    pub fn update(&mut self, world: &World, entities: &Entities) -> Delta {
+        let delta = self.tracker.delta(world, entities);
+        if !delta.is_empty() {
+            let message = delta.encode();
+            self.clients.retain_mut(|(name, stream)| send(name, stream, &message));
+        }
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        if !pending.is_empty() {
+            let message = self.tracker.snapshot(world, entities).encode();
+            for (name, mut stream) in pending {
+                if send(&name, &mut stream, &message) {
+                    self.clients.push((name, stream));
+                }
+            }
+        }
+        delta
+    }
+
+    // Stops accepting clients and disconnects the connected ones
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        for (_, stream) in self.clients.drain(..).chain(self.pending.lock().unwrap().drain(..)) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+            log::info!("Sync host on {} stopped", self.address);
+        }
+    }
+}
+
+impl Drop for SyncHost {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+// Reads the client's hello and answers with `welcome`, rejecting other
+// protocol versions. Returns the client's name.
+fn greet(mut stream: &TcpStream, welcome: &str) -> io::Result<String> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(HELLO_TIMEOUT))?;
+    let mut line = String::new();
+    // Clients send nothing after hello, so the reader can't take more than this line
+    BufReader::new(stream).read_line(&mut line)?;
+    stream.set_read_timeout(None)?;
+    let invalid = |reason: String| io::Error::new(io::ErrorKind::InvalidData, reason);
+    let (version, name) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["hello", version, name] => (version.parse::<u32>().ok(), name.to_string()),
+        _ => return Err(invalid(format!("expected hello, got '{}'", line.trim()))),
+    };
+    if version != Some(PROTOCOL_VERSION) {
+        let reason = format!("unsupported protocol version, expected {}", PROTOCOL_VERSION);
+        writeln!(stream, "reject {}", reason)?;
+        return Err(invalid(reason));
+    }
+    stream.write_all(welcome.as_bytes())?;
+    Ok(name)
+}
+
+// False if the client is gone
+fn send(name: &str, stream: &mut TcpStream, message: &str) -> bool {
+    match stream.write_all(message.as_bytes()).and_then(|_| stream.flush()) {
+        Ok(()) => true,
+        Err(e) => {
+            log::info!("Sync client {} disconnected: {}", name, e);
+            false
+        }
+    }
+}
+
+// ------
+// CLIENT
+// ------
+
+// The host's state as far as received
+pub struct Replica {
+    pub tick: u64,
+    pub world: World,
+    pub positions: BTreeMap<EntityId, Position>,
+    pub inventories: BTreeMap<EntityId, Vec<Slot>>,
+}
+
+impl Replica {
+    pub fn new(three_d: bool) -> Self {
+        let world = if three_d { World::new_3d() } else { World::new() };
+        Replica { tick: 0, world, positions: BTreeMap::new(), inventories: BTreeMap::new() }
+    }
+
+    pub fn apply(&mut self, delta: &Delta) {
+        self.tick = delta.tick;
+        for change in &delta.changes {
+            match change {
+                Change::Block(pos, Some(state)) => {
+                    self.world.set(*pos, state.clone());
+                }
+                Change::Block(pos, None) => {
+                    self.world.remove(*pos);
+                }
+                Change::Entity(entity, Some(position)) => {
+                    self.positions.insert(*entity, *position);
+                }
+                Change::Entity(entity, None) => {
+                    self.positions.remove(entity);
+                }
+                Change::Slot(entity, index, slot) => {
+                    let slots = self.inventories.entry(*entity).or_default();
+                    match *index {
+                        i if i < slots.len() => slots[i] = slot.clone(),
+                        i if i == slots.len() => slots.push(slot.clone()),
+                        i => log::warn!("Sync slot {} of entity {} skipped, it has {} slots", i, entity, slots.len()),
+                    }
+                }
+                Change::Slots(entity, 0) => {
+                    self.inventories.remove(entity);
+                }
+                Change::Slots(entity, count) => {
+                    self.inventories.entry(*entity).or_default().truncate(*count);
+                }
+            }
+        }
+    }
+}
+
+pub struct SyncClient {
+    pub replica: Replica,
+    deltas: Receiver<String>, // Encoded, from the reader thread
+    stream: TcpStream,
+    connected: bool,
+}
+
+impl SyncClient {
+    // Connects and checks the protocol version and registry content. Polls
+    // after the host's next update receive the state.
+    pub fn connect(address: impl ToSocketAddrs, name: &str, registry: &Registry) -> Result<SyncClient, SyncError> {
+        let mut stream = TcpStream::connect(address)?;
+        writeln!(stream, "hello {} {}", PROTOCOL_VERSION, name)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        let line = read_line(&mut reader)?;
+        let (three_d, namespaces) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["reject", ..] => return Err(SyncError::Rejected(line.trim_start_matches("reject").trim().to_string())),
+            ["welcome", _, dimensions @ ("2d" | "3d"), namespaces] => match namespaces.parse::<usize>() {
+                Ok(namespaces) => (dimensions == "3d", namespaces),
+                Err(_) => return Err(SyncError::InvalidMessage(line)),
+            },
+            _ => return Err(SyncError::InvalidMessage(line)),
+        };
+        let mut hashes = BTreeMap::new();
+        for _ in 0..namespaces {
+            let line = read_line(&mut reader)?;
+            match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["hash", namespace, hash] => match u64::from_str_radix(hash, 16) {
+                    Ok(hash) => {
+                        hashes.insert(namespace.to_string(), hash);
+                    }
+                    Err(_) => return Err(SyncError::InvalidMessage(line)),
+                },
+                _ => return Err(SyncError::InvalidMessage(line)),
+            }
+        }
+        let local = registry.namespace_hashes();
+        let mut namespaces: Vec<&String> = hashes.keys().chain(local.keys()).collect();
+        namespaces.sort();
+        namespaces.dedup();
+        let mismatches: Vec<String> = namespaces.into_iter().filter(|ns| hashes.get(*ns) != local.get(*ns)).cloned().collect();
+        if !mismatches.is_empty() {
+            let _ = stream.shutdown(Shutdown::Both);
+            return Err(SyncError::RegistryMismatch(mismatches));
+        }
+
+        let (sender, deltas) = mpsc::channel();
+        thread::spawn(move || {
+            while let Ok(header) = read_line(&mut reader) {
+                let count = header.split_whitespace().nth(2).and_then(|c| c.parse::<usize>().ok()).unwrap_or(0);
+                let mut message = header + "\n";
+                for _ in 0..count {
+                    match read_line(&mut reader) {
+                        Ok(line) => message += &(line + "\n"),
+                        Err(_) => return,
+                    }
+                }
+                if sender.send(message).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(SyncClient { replica: Replica::new(three_d), deltas, stream, connected: true })
+    }
+
+    // Applies every delta received since the last poll, returns how many
+    pub fn poll(&mut self, registry: &Registry) -> Result<usize, SyncError> {
+        let mut applied = 0;
+        loop {
+            match self.deltas.try_recv() {
+                Ok(message) => {
+                    self.replica.apply(&Delta::decode(&message, registry)?);
+                    applied += 1;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.connected = false;
+                    break;
+                }
+            }
+        }
+        Ok(applied)
+    }
+
+    // False once the host closed the connection and every delta was polled
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    pub fn disconnect(self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+// One line without its line break, Disconnected at the end of the stream
+fn read_line(reader: &mut impl BufRead) -> Result<String, SyncError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(SyncError::Disconnected);
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
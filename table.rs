@@ -0,0 +1,256 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::color::{ColorRef, colored_text, visible_length};
+use crate::theme::current_theme;
+
+// Box-drawing characters
+const TOP_LEFT: &str = "╭";
+const TOP_RIGHT: &str = "╮";
+const BOTTOM_LEFT: &str = "╰";
+const BOTTOM_RIGHT: &str = "╯";
+const TEE_DOWN: &str = "┬";
+const TEE_UP: &str = "┴";
+const TEE_RIGHT: &str = "├";
+const TEE_LEFT: &str = "┤";
+const CROSS: &str = "┼";
+const VERTICAL: &str = "│";
+const HORIZONTAL: &str = "─";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Alignment {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Clone, Debug)]
+pub struct Column {
+    pub header: String,
+    pub align: Alignment,
+    pub min_width: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Row {
+    cells: Vec<String>,
+    color: Option<ColorRef<'static>>,
+}
+
+// Bordered table with a header, rows and optional footer lines:
+//
+// ╭──────┬────────╮
+// │ Item │ Amount │
+// ├──────┼────────┤
+// │ Coal │    12x │
+// ├──────┴┬───────┤
+// │ Total │    12 │
+// ╰───────┴───────╯
+//
+// Widths ignore ANSI codes, so cells may contain colored text.
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Row>,
+    footer: Vec<(String, String)>,
+    border_color: Option<ColorRef<'static>>, // The theme's table border color if None
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_column(self, header: &str, align: Alignment) -> Self {
+        self.with_sized_column(header, align, 0)
+    }
+
+    pub fn with_border_color(mut self, color: ColorRef<'static>) -> Self {
+        self.border_color = Some(color);
+        self
+    }
+
+    pub fn with_sized_column(mut self, header: &str, align: Alignment, min_width: usize) -> Self {
+        self.columns.push(Column {
+            header: header.to_string(),
+            align,
+            min_width,
+        });
+        self
+    }
+
+    // Missing cells are left empty, extra cells are ignored
+    pub fn add_row<I, S>(&mut self, cells: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.rows.push(Row {
+            cells: cells.into_iter().map(Into::into).collect(),
+            color: None,
+        });
+    }
+
+    pub fn add_colored_row<I, S>(&mut self, cells: I, color: ColorRef<'static>)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.rows.push(Row {
+            cells: cells.into_iter().map(Into::into).collect(),
+            color: Some(color),
+        });
+    }
+
+    // "label │ value" line below the rows, the value spans the remaining width
+    pub fn add_footer(&mut self, label: &str, value: &str) {
+        self.footer.push((label.to_string(), value.to_string()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    fn column_widths(&self) -> Vec<usize> {
+        let mut widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.cells.get(i))
+                    .map(|cell| visible_length(cell))
+                    .chain([visible_length(&column.header), column.min_width])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        // Widen the last column if the footer doesn't fit
+        let (label, value) = self.footer_widths();
+        if !self.footer.is_empty() && !widths.is_empty() {
+            let needed = label + value + 5;
+            let inner = inner_width(&widths);
+            if needed > inner {
+                *widths.last_mut().unwrap() += needed - inner;
+            }
+        }
+        widths
+    }
+
+    fn footer_widths(&self) -> (usize, usize) {
+        let label = self.footer.iter().map(|(l, _)| visible_length(l)).max().unwrap_or(0);
+        let value = self.footer.iter().map(|(_, v)| visible_length(v)).max().unwrap_or(0);
+        (label, value)
+    }
+
+    pub fn render(&self) -> String {
+        if self.columns.is_empty() {
+            return String::new();
+        }
+        let widths = self.column_widths();
+        let joints = joint_positions(&widths);
+        let border_color = self.border_color.clone().unwrap_or_else(|| current_theme().table_border_color);
+        let border_line = |left: &str, right: &str, width: usize, joint: &dyn Fn(usize) -> Option<&'static str>| {
+            paint(&border(left, right, width, joint), &border_color) + "\n"
+        };
+        let mut output = String::new();
+
+        output += &border_line(TOP_LEFT, TOP_RIGHT, inner_width(&widths), &|i| joints.contains(&i).then_some(TEE_DOWN));
+        let headers: Vec<&str> = self.columns.iter().map(|c| c.header.as_str()).collect();
+        output += &self.line(&widths, &headers, None, &border_color);
+        output += &border_line(TEE_RIGHT, TEE_LEFT, inner_width(&widths), &|i| joints.contains(&i).then_some(CROSS));
+
+        for row in &self.rows {
+            let cells: Vec<&str> = (0..widths.len()).map(|i| row.cells.get(i).map_or("", String::as_str)).collect();
+            output += &self.line(&widths, &cells, row.color.as_ref(), &border_color);
+        }
+
+        if self.footer.is_empty() {
+            output += &border_line(BOTTOM_LEFT, BOTTOM_RIGHT, inner_width(&widths), &|i| joints.contains(&i).then_some(TEE_UP));
+            return output;
+        }
+
+        let (label_width, _) = self.footer_widths();
+        let value_width = inner_width(&widths) - label_width - 5;
+        let footer_joint = label_width + 2;
+        output += &border_line(TEE_RIGHT, TEE_LEFT, inner_width(&widths), &|i| {
+            match (joints.contains(&i), i == footer_joint) {
+                (true, true) => Some(CROSS),
+                (true, false) => Some(TEE_UP),
+                (false, true) => Some(TEE_DOWN),
+                (false, false) => None,
+            }
+        });
+        let vertical = paint(VERTICAL, &border_color);
+        for (label, value) in &self.footer {
+            output += &format!(
+                "{vertical} {} {vertical} {} {vertical}\n",
+                pad(label, label_width, Alignment::Left),
+                pad(value, value_width, Alignment::Right)
+            );
+        }
+        output += &border_line(BOTTOM_LEFT, BOTTOM_RIGHT, inner_width(&widths), &|i| (i == footer_joint).then_some(TEE_UP));
+        output
+    }
+
+    fn line(&self, widths: &[usize], cells: &[&str], color: Option<&ColorRef>, border_color: &ColorRef) -> String {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(widths)
+            .zip(&self.columns)
+            .map(|((cell, width), column)| {
+                let padded = pad(cell, *width, column.align);
+                match color {
+                    Some(color) => colored_text(&padded, color).unwrap_or(padded),
+                    None => padded,
+                }
+            })
+            .collect();
+        let vertical = paint(VERTICAL, border_color);
+        format!("{vertical} {} {vertical}\n", cells.join(&format!(" {vertical} ")))
+    }
+}
+
+impl Display for Table {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render())
+    }
+}
+
+// Width between the outer borders
+fn inner_width(widths: &[usize]) -> usize {
+    widths.iter().map(|w| w + 2).sum::<usize>() + widths.len().saturating_sub(1)
+}
+
+// Offsets of the column separators inside the outer borders
+fn joint_positions(widths: &[usize]) -> Vec<usize> {
+    let mut positions = vec![];
+    let mut x = 0;
+    for width in &widths[..widths.len().saturating_sub(1)] {
+        x += width + 2;
+        positions.push(x);
+        x += 1;
+    }
+    positions
+}
+
+// Without the newline, so it can be colored
+fn border(left: &str, right: &str, width: usize, joint: &dyn Fn(usize) -> Option<&'static str>) -> String {
+    let inner: String = (0..width).map(|i| joint(i).unwrap_or(HORIZONTAL)).collect();
+    format!("{left}{inner}{right}")
+}
+
+fn paint(text: &str, color: &ColorRef) -> String {
+    colored_text(text, color).unwrap_or_else(|_| text.to_string())
+}
+
+// Pads by visible width, so ANSI codes don't shift the columns
+fn pad(text: &str, width: usize, align: Alignment) -> String {
+    let fill = width.saturating_sub(visible_length(text));
+    match align {
+        Alignment::Left => format!("{}{}", text, " ".repeat(fill)),
+        Alignment::Right => format!("{}{}", " ".repeat(fill), text),
+        Alignment::Center => format!("{}{}{}", " ".repeat(fill / 2), text, " ".repeat(fill - fill / 2)),
+    }
+}
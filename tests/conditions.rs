@@ -0,0 +1,95 @@
+use ruztex::conditions::{Condition, ConditionContext};
+use ruztex::id;
+use ruztex::random::RuzRng;
+use ruztex::world::BlockPos;
+
+fn parse(input: &str) -> Condition {
+    input.parse().unwrap_or_else(|e| panic!("{}: {}", input, e))
+}
+
+fn every_condition() -> Condition {
+    Condition::AllOf(vec![
+        Condition::HasTag(id!("ruztex:ores")),
+        Condition::RandomChance(0.25),
+        Condition::ToolLevelAtLeast(2),
+        Condition::QuestCompleted(id!("game:first_steps")),
+        Condition::HasItem(id!("game:key")),
+        Condition::FlagSet("met_merchant".to_string()),
+        Condition::InArea { from: BlockPos::new_3d(-4, 0, 1), to: BlockPos::new_3d(8, 16, 2) },
+        Condition::InBiome(id!("game:forest")),
+        Condition::AnyOf(vec![]),
+        Condition::Not(Box::new(Condition::AllOf(vec![]))),
+    ])
+}
+
+#[test]
+fn display_round_trips_through_from_str() {
+    let condition = every_condition();
+    let text = condition.to_string();
+    assert_eq!(parse(&text), condition);
+    assert_eq!(parse(&text).to_string(), text);
+}
+
+#[test]
+fn display_syntax() {
+    let condition = Condition::AnyOf(vec![
+        Condition::Not(Box::new(Condition::RandomChance(0.5))),
+        Condition::InArea { from: BlockPos::new(1, 2), to: BlockPos::new_3d(3, 4, 5) },
+    ]);
+    assert_eq!(condition.to_string(), "any_of(not(random_chance(0.5)), in_area(1 2 0, 3 4 5))");
+}
+
+#[test]
+fn from_str_ignores_whitespace() {
+    let condition = parse("  all_of( has_tag( ruztex:ores ) ,not(tool_level_at_least(2)) , any_of( ) )  ");
+    assert_eq!(
+        condition,
+        Condition::AllOf(vec![
+            Condition::HasTag(id!("ruztex:ores")),
+            Condition::Not(Box::new(Condition::ToolLevelAtLeast(2))),
+            Condition::AnyOf(vec![]),
+        ])
+    );
+}
+
+#[test]
+fn from_str_rejects_invalid_input() {
+    for input in [
+        "",
+        "unknown(1)",
+        "has_tag(ores)",
+        "has_tag()",
+        "random_chance(often)",
+        "tool_level_at_least(-1)",
+        "in_area(1 2, 3 4 5)",
+        "in_area(1 2 3)",
+        "all_of(has_tag(ruztex:ores)",
+        "not(has_item(game:key)) extra",
+        "all_of(has_item(game:key),)",
+    ] {
+        assert!(input.parse::<Condition>().is_err(), "{} should not parse", input);
+    }
+}
+
+#[test]
+fn empty_lists_and_areas() {
+    let mut rng = RuzRng::new(1);
+    let inside = ConditionContext::new().with_position(BlockPos::new_3d(0, 5, 1));
+    let outside = ConditionContext::new().with_position(BlockPos::new_3d(0, 5, 3));
+    let area = parse("in_area(8 16 2, -4 0 1)"); // Corners in any order
+    assert!(area.test(&inside, &mut rng));
+    assert!(!area.test(&outside, &mut rng));
+    assert!(!area.test(&ConditionContext::new(), &mut rng));
+    assert!(parse("all_of()").test(&outside, &mut rng));
+    assert!(!parse("any_of()").test(&outside, &mut rng));
+    assert!(parse("not(any_of())").test(&outside, &mut rng));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_uses_the_syntax() {
+    let condition = every_condition();
+    let yaml = serde_yaml::to_string(&condition).unwrap();
+    assert_eq!(yaml.trim(), condition.to_string());
+    assert_eq!(serde_yaml::from_str::<Condition>(&yaml).unwrap(), condition);
+}
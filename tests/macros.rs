@@ -0,0 +1,56 @@
+#![cfg(feature = "tui")]
+
+use ruztex::error::ParseError;
+use ruztex::interface::Macros;
+
+fn lines(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|l| l.to_string()).collect()
+}
+
+#[test]
+fn parse_reads_indented_commands_below_their_macro() {
+    let macros = Macros::parse("macro setup\n  give coal 10\n  tp 0 64 0\n\nmacro  empty \nmacro greet\n  say   hi  \n").unwrap();
+    assert_eq!(macros.names().collect::<Vec<_>>(), ["empty", "greet", "setup"]);
+    assert_eq!(macros.get("setup").unwrap(), lines(&["give coal 10", "tp 0 64 0"]));
+    assert!(macros.get("empty").unwrap().is_empty());
+    // Only the indentation is removed
+    assert_eq!(macros.get("greet").unwrap(), lines(&["say   hi  "]));
+}
+
+#[test]
+fn parse_merges_repeated_macros() {
+    let macros = Macros::parse("macro a\n  one\nmacro a\n  two\n").unwrap();
+    assert_eq!(macros.len(), 1);
+    assert_eq!(macros.get("a").unwrap(), lines(&["one", "two"]));
+}
+
+#[test]
+fn parse_rejects_commands_outside_macros() {
+    assert_eq!(
+        Macros::parse("  give coal 10\n").err(),
+        Some(ParseError::InvalidLine { line: 1, text: "  give coal 10".to_string() })
+    );
+    assert_eq!(
+        Macros::parse("macro a\n  one\nunindented\n").err(),
+        Some(ParseError::InvalidLine { line: 3, text: "unindented".to_string() })
+    );
+    assert_eq!(Macros::parse("macro a\n one\n").err(), Some(ParseError::InvalidLine { line: 2, text: " one".to_string() }));
+}
+
+#[test]
+fn serialize_round_trips_escapes() {
+    let mut macros = Macros::new();
+    macros.insert("build", lines(&["fill 0 0 0\n5 5 5 stone", r"say a\nb", r"load C:\saves\", "plain"]));
+    macros.insert("empty", vec![]);
+    let text = macros.serialize();
+    let parsed = Macros::parse(&text).unwrap();
+    assert_eq!(parsed.get("build"), macros.get("build"));
+    assert_eq!(parsed.get("empty"), Some(&[][..]));
+    assert_eq!(parsed.serialize(), text);
+}
+
+#[test]
+fn parse_reads_files_from_before_backslash_escaping() {
+    let macros = Macros::parse("macro multi\n  first\\nsecond\n  keep\\this\n").unwrap();
+    assert_eq!(macros.get("multi").unwrap(), lines(&["first\nsecond", r"keep\this"]));
+}
@@ -0,0 +1,192 @@
+use ruztex::ecs::{Entities, Position};
+use ruztex::id;
+use ruztex::pathfinding::{Navigator, Path, Pathfinder};
+use ruztex::registry::{Block, ID, RegistrableEntity, Registry, Tag};
+use ruztex::world::{BlockPos, BlockState, World};
+
+fn registry() -> Registry {
+    let mut registry = Registry::new();
+    registry.register(RegistrableEntity::Tag(Tag::new(id!("game:passable")))).unwrap();
+    registry.register(RegistrableEntity::Tag(Tag::new(id!("game:slow")))).unwrap();
+    registry.register(RegistrableEntity::Block(Block::new(id!("game:stone"), vec![], 1.0))).unwrap();
+    registry.register(RegistrableEntity::Block(Block::new(id!("game:mud"), vec![id!("game:passable"), id!("game:slow")], 0.5))).unwrap();
+    registry.register(RegistrableEntity::Block(Block::new(id!("game:floor"), vec![id!("game:passable")], 1.0))).unwrap();
+    registry
+}
+
+fn place(world: &mut World, block: &str, positions: &[(i32, i32)]) {
+    for &(x, y) in positions {
+        world.set(BlockPos::new(x, y), BlockState::new(ID::parse(block).unwrap()));
+    }
+}
+
+// Every step goes to a neighbor that can be walked through
+fn assert_connected(path: &Path, world: &World, pathfinder: &Pathfinder, registry: &Registry) {
+    for pair in path.positions.windows(2) {
+        assert!(world.neighbor_positions(pair[0]).contains(&pair[1]), "{:?} -> {:?}", pair[0], pair[1]);
+        assert!(pathfinder.is_walkable(world, pair[1], registry), "{:?}", pair[1]);
+    }
+}
+
+#[test]
+fn straight_path_in_open_space() {
+    let registry = registry();
+    let world = World::new();
+    let path = Pathfinder::new().find_path(&world, BlockPos::new(0, 0), BlockPos::new(3, 0), &registry).unwrap();
+    assert_eq!(path.positions, (0..=3).map(|x| BlockPos::new(x, 0)).collect::<Vec<_>>());
+    assert_eq!(path.len(), 3);
+    assert_eq!(path.cost, 3.0);
+
+    let here = Pathfinder::new().find_path(&world, BlockPos::new(1, 1), BlockPos::new(1, 1), &registry).unwrap();
+    assert!(here.is_empty());
+    assert_eq!(here.cost, 0.0);
+}
+
+#[test]
+fn path_goes_around_walls() {
+    let registry = registry();
+    let mut world = World::new();
+    place(&mut world, "game:stone", &[(1, -1), (1, 0), (1, 1)]);
+    let pathfinder = Pathfinder::new();
+    let path = pathfinder.find_path(&world, BlockPos::new(0, 0), BlockPos::new(2, 0), &registry).unwrap();
+    assert_eq!(path.len(), 6);
+    assert_eq!(path.start(), BlockPos::new(0, 0));
+    assert_eq!(path.goal(), BlockPos::new(2, 0));
+    assert_connected(&path, &world, &pathfinder, &registry);
+}
+
+#[test]
+fn unreachable_goals() {
+    let registry = registry();
+    let mut world = World::new();
+    place(&mut world, "game:stone", &[(4, -1), (3, 0), (5, 0), (4, 1)]);
+    let pathfinder = Pathfinder::new();
+    // Enclosed
+    assert_eq!(pathfinder.find_path(&world, BlockPos::new(0, 0), BlockPos::new(4, 0), &registry), None);
+    // A wall
+    assert_eq!(pathfinder.find_path(&world, BlockPos::new(0, 0), BlockPos::new(3, 0), &registry), None);
+    // Too far for max_nodes
+    let limited = Pathfinder::new().with_max_nodes(10);
+    assert_eq!(limited.find_path(&World::new(), BlockPos::new(0, 0), BlockPos::new(40, 0), &registry), None);
+}
+
+#[test]
+fn costly_blocks_are_avoided_if_cheaper() {
+    let registry = registry();
+    let mut world = World::new();
+    place(&mut world, "game:mud", &[(1, 0)]);
+    let pathfinder = Pathfinder::new().with_walkable(id!("game:passable")).with_cost(id!("game:slow"), 5.0);
+    assert_eq!(pathfinder.cost(&world, BlockPos::new(1, 0), &registry), Some(5.0));
+    let path = pathfinder.find_path(&world, BlockPos::new(0, 0), BlockPos::new(2, 0), &registry).unwrap();
+    assert!(!path.contains(BlockPos::new(1, 0)));
+    assert_eq!(path.cost, 4.0);
+
+    // Walking through is cheaper than the detour
+    let cheap = Pathfinder::new().with_walkable(id!("game:passable")).with_cost(id!("game:slow"), 2.0);
+    let path = cheap.find_path(&world, BlockPos::new(0, 0), BlockPos::new(2, 0), &registry).unwrap();
+    assert!(path.contains(BlockPos::new(1, 0)));
+    assert_eq!(path.cost, 3.0);
+}
+
+#[test]
+fn floors_without_empty_positions() {
+    let registry = registry();
+    let mut world = World::new();
+    place(&mut world, "game:floor", &[(0, 0), (0, 1), (1, 1), (2, 1), (2, 0)]);
+    let pathfinder = Pathfinder::new().with_walkable(id!("game:passable")).with_empty_walkable(false);
+    assert!(!pathfinder.is_walkable(&world, BlockPos::new(1, 0), &registry));
+    let path = pathfinder.find_path(&world, BlockPos::new(0, 0), BlockPos::new(2, 0), &registry).unwrap();
+    assert_eq!(path.len(), 4);
+    assert_connected(&path, &world, &pathfinder, &registry);
+}
+
+#[test]
+fn paths_use_layers_in_3d_worlds() {
+    let registry = registry();
+    let mut world = World::new_3d();
+    for y in -3..=3 {
+        for x in -3..=3 {
+            world.set(BlockPos::new_3d(x, y, 0), BlockState::new(id!("game:stone")));
+        }
+    }
+    world.remove(BlockPos::new_3d(-3, 0, 0));
+    world.remove(BlockPos::new_3d(3, 0, 0));
+    let pathfinder = Pathfinder::new();
+    let path = pathfinder.find_path(&world, BlockPos::new_3d(-3, 0, 0), BlockPos::new_3d(3, 0, 0), &registry).unwrap();
+    assert_eq!(path.len(), 8); // Up, across and down
+    assert!(path.positions.iter().any(|pos| pos.z != 0));
+    assert_connected(&path, &world, &pathfinder, &registry);
+}
+
+#[test]
+fn navigator_repairs_the_path_from_the_step_before_a_change() {
+    let registry = registry();
+    let mut world = World::new();
+    let pathfinder = Pathfinder::new();
+    let start = BlockPos::new(0, 0);
+    let mut navigator = Navigator::new(BlockPos::new(4, 0));
+    assert!(navigator.update(start, &world, &pathfinder, &registry));
+    assert_eq!(navigator.path(), (1..=4).map(|x| BlockPos::new(x, 0)).collect::<Vec<_>>());
+
+    place(&mut world, "game:stone", &[(3, 0)]);
+    navigator.block_changed(BlockPos::new(3, 0));
+    assert!(navigator.update(start, &world, &pathfinder, &registry));
+    let path = navigator.path();
+    assert_eq!(path[..2], [BlockPos::new(1, 0), BlockPos::new(2, 0)]);
+    assert!(!path.contains(&BlockPos::new(3, 0)));
+    assert_eq!(path.last(), Some(&BlockPos::new(4, 0)));
+    assert_eq!(path.len(), 6);
+}
+
+#[test]
+fn navigator_ignores_changes_off_the_path() {
+    let registry = registry();
+    let mut world = World::new();
+    place(&mut world, "game:stone", &[(2, 0)]);
+    let pathfinder = Pathfinder::new();
+    let start = BlockPos::new(0, 0);
+    let mut navigator = Navigator::new(BlockPos::new(4, 0));
+    assert!(navigator.update(start, &world, &pathfinder, &registry));
+    let planned = navigator.path().to_vec();
+
+    // A shortcut opening up doesn't replan
+    world.remove(BlockPos::new(2, 0));
+    navigator.block_changed(BlockPos::new(2, 0));
+    assert!(navigator.update(start, &world, &pathfinder, &registry));
+    assert_eq!(navigator.path(), planned);
+}
+
+#[test]
+fn navigator_retries_unreachable_goals_after_changes() {
+    let registry = registry();
+    let mut world = World::new();
+    place(&mut world, "game:stone", &[(4, -1), (3, 0), (5, 0), (4, 1)]);
+    let pathfinder = Pathfinder::new();
+    let start = BlockPos::new(0, 0);
+    let mut navigator = Navigator::new(BlockPos::new(4, 0));
+    assert!(!navigator.update(start, &world, &pathfinder, &registry));
+    assert!(navigator.path().is_empty());
+    assert_eq!(navigator.next_step(start, &world, &pathfinder, &registry), None);
+
+    world.remove(BlockPos::new(3, 0));
+    navigator.block_changed(BlockPos::new(3, 0));
+    assert!(navigator.update(start, &world, &pathfinder, &registry));
+    assert_eq!(navigator.path().len(), 4);
+}
+
+#[test]
+fn entities_navigate_one_step_per_call() {
+    let registry = registry();
+    let world = World::new();
+    let pathfinder = Pathfinder::new();
+    let mut entities = Entities::new();
+    let walker = entities.spawn_empty();
+    entities.insert(walker, Position { x: 0, y: 0 });
+    entities.insert(walker, Navigator::new(BlockPos::new(2, 0)));
+
+    assert!(entities.navigate(&world, &pathfinder, &registry).is_empty());
+    assert_eq!(entities.get::<Position>(walker), Some(&Position { x: 1, y: 0 }));
+    assert_eq!(entities.navigate(&world, &pathfinder, &registry), [walker]);
+    assert_eq!(entities.get::<Position>(walker), Some(&Position { x: 2, y: 0 }));
+    assert!(entities.navigate(&world, &pathfinder, &registry).is_empty());
+}
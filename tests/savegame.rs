@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ruztex::id;
+use ruztex::inventory::{HOTBAR_SIZE, Slot};
+use ruztex::random::RuzRng;
+use ruztex::registry::{ID, Item, RegistrableEntity, Registry};
+use ruztex::savegame::{Migrations, PlayerProfile, SAVE_VERSION, SaveFiles, SaveGame};
+use ruztex::world::World;
+
+fn registry() -> Registry {
+    let mut registry = Registry::new();
+    for item in ["game:apple", "game:stick", "game:coal"] {
+        registry.register(RegistrableEntity::Item(Item::new(ID::parse(item).unwrap(), vec![], 64))).unwrap();
+    }
+    registry
+}
+
+// Empty directory for one test
+fn save_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ruztex-savegame-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Files of a save from before generations
+fn write_legacy_save(dir: &Path, version: u32, slots: usize) {
+    fs::write(dir.join("save.txt"), format!("savegame {}\nplayer alex\nrng 000000000000002a\nflag intro_done\n", version)).unwrap();
+    let mut inventory = "inventory 36 12\n".to_string();
+    for i in 0..slots {
+        inventory += &format!("slot {} {}\n", if i % 2 == 0 { "game:apple" } else { "game:stick" }, i + 1);
+    }
+    inventory += "selected 2\n";
+    fs::write(dir.join("inventory.txt"), inventory).unwrap();
+    fs::write(dir.join("quests.txt"), "quests alex\n").unwrap();
+    fs::write(dir.join("world.txt"), "world 2d\n").unwrap();
+}
+
+#[test]
+fn fixed_hotbar_moves_the_first_slots_into_the_hotbar() {
+    let dir = save_dir("fixed_hotbar");
+    write_legacy_save(&dir, 2, HOTBAR_SIZE + 2);
+    fs::write(dir.join("recipes.txt"), "recipes alex\nunlocked game:torch\n").unwrap();
+    let save = SaveGame::load(&dir, &registry()).unwrap();
+
+    let inventory = &save.player.inventory;
+    for (position, stack) in inventory.hotbar.iter().enumerate() {
+        let stack = stack.as_ref().unwrap();
+        assert_eq!(stack.count, position as u32 + 1);
+    }
+    let rest: Vec<(ID, u32)> = inventory.slots.iter().map(|s| (s.item.id, s.count)).collect();
+    assert_eq!(rest, [(id!("game:stick"), 10), (id!("game:apple"), 11)]);
+    assert_eq!(inventory.selected, 2);
+    assert_eq!(inventory.owner_money, Some(12));
+    assert_eq!(save.player.recipes.unlocked(), [id!("game:torch")]);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fixed_hotbar_keeps_short_inventories_in_the_hotbar() {
+    let dir = save_dir("short_hotbar");
+    write_legacy_save(&dir, 2, 3);
+    fs::write(dir.join("recipes.txt"), "recipes alex\n").unwrap();
+    let save = SaveGame::load(&dir, &registry()).unwrap();
+
+    let inventory = &save.player.inventory;
+    assert_eq!(inventory.hotbar.iter().filter(|s| s.is_some()).count(), 3);
+    assert!(inventory.hotbar[3..].iter().all(Option::is_none));
+    assert!(inventory.slots.is_empty());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn version_1_saves_get_an_empty_recipe_book_and_a_hotbar() {
+    let dir = save_dir("version_1");
+    write_legacy_save(&dir, 1, 2);
+    let save = SaveGame::load(&dir, &registry()).unwrap();
+
+    assert_eq!(save.player.name, "alex");
+    assert_eq!(save.player.recipes.serialize(), "recipes alex\n");
+    assert_eq!(save.player.inventory.hotbar[1].as_ref().map(|s| s.item.id), Some(id!("game:stick")));
+    assert!(save.player.flags.contains("intro_done"));
+    assert_eq!(save.rng.state(), 42);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn with_step_replaces_a_migration() {
+    fn clear_inventory(files: &mut SaveFiles) -> Result<(), String> {
+        files.insert("inventory.txt".to_string(), "inventory 36 none\n".to_string());
+        Ok(())
+    }
+
+    let dir = save_dir("replaced_step");
+    write_legacy_save(&dir, 2, 4);
+    fs::write(dir.join("recipes.txt"), "recipes alex\n").unwrap();
+    let save = SaveGame::load_with(&dir, &registry(), &Migrations::new().with_step(2, clear_inventory)).unwrap();
+    assert_eq!(save.player.inventory.stack_count(), 0);
+    assert_eq!(save.player.inventory.owner_money, None);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_and_failing_migrations_are_errors() {
+    fn fail(_: &mut SaveFiles) -> Result<(), String> {
+        Err("broken".to_string())
+    }
+
+    let dir = save_dir("failing_step");
+    write_legacy_save(&dir, 0, 1);
+    let error = SaveGame::load(&dir, &registry()).err().unwrap();
+    assert!(error.to_string().contains("no migration from save version 0"), "{}", error);
+
+    write_legacy_save(&dir, 2, 1);
+    let error = SaveGame::load_with(&dir, &registry(), &Migrations::new().with_step(2, fail)).err().unwrap();
+    assert!(error.to_string().contains("migration from save version 2 failed: broken"), "{}", error);
+
+    write_legacy_save(&dir, SAVE_VERSION + 1, 1);
+    assert!(SaveGame::load(&dir, &registry()).is_err());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn current_saves_round_trip_without_migrations() {
+    let registry = registry();
+    let dir = save_dir("round_trip");
+    let mut player = PlayerProfile::new("alex");
+    assert!(player.inventory.insert(Slot::new(registry.items[&id!("game:coal")].clone(), 5)).is_none());
+    player.flags.insert("intro_done".to_string());
+    let save = SaveGame::new(player, World::new(), RuzRng::new(7), &registry);
+    save.save(&dir).unwrap();
+    save.save(&dir).unwrap();
+
+    let loaded = SaveGame::load_with(&dir, &registry, &Migrations::new().with_step(2, |_| Err("ran".to_string()))).unwrap();
+    assert_eq!(loaded.player.inventory.serialize(), save.player.inventory.serialize());
+    assert_eq!(loaded.player.flags, save.player.flags);
+    assert!(loaded.registry_mismatches(&registry).is_empty());
+    // Only the second generation is left
+    let mut files: Vec<String> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name().into_string().unwrap()).collect();
+    files.sort();
+    assert_eq!(files, ["inventory.2.txt", "quests.2.txt", "recipes.2.txt", "save.txt", "world.2.txt"]);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,159 @@
+#![cfg(feature = "net")]
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ruztex::ecs::{Entities, Position};
+use ruztex::id;
+use ruztex::inventory::{Inventory, Slot};
+use ruztex::registry::{Block, ID, Item, RegistrableEntity, Registry};
+use ruztex::sync::{Change, Delta, PROTOCOL_VERSION, SyncClient, SyncError, SyncHost};
+use ruztex::world::{BlockPos, BlockState, World};
+
+fn registry() -> Registry {
+    let mut registry = Registry::new();
+    registry.register(RegistrableEntity::Item(Item::new(id!("game:apple"), vec![], 16))).unwrap();
+    registry.register(RegistrableEntity::Item(Item::new(id!("game:stick"), vec![], 64))).unwrap();
+    registry.register(RegistrableEntity::Block(Block::new(id!("game:stone"), vec![], 1.0))).unwrap();
+    registry
+}
+
+fn stack(registry: &Registry, item: &str, count: u32) -> Slot {
+    Slot::new(registry.items[&ID::parse(item).unwrap()].clone(), count)
+}
+
+// Updates the host and polls the client until `done` or a timeout
+fn sync_until(
+    host: &mut SyncHost,
+    client: &mut SyncClient,
+    (world, entities, registry): (&World, &Entities, &Registry),
+    done: impl Fn(&SyncClient) -> bool,
+) {
+    let start = Instant::now();
+    while !done(client) {
+        assert!(start.elapsed() < Duration::from_secs(5), "client didn't catch up");
+        host.update(world, entities);
+        client.poll(registry).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn deltas_round_trip() {
+    let registry = registry();
+    let mut entities = Entities::new();
+    let entity = entities.spawn_empty();
+    let delta = Delta {
+        tick: 7,
+        changes: vec![
+            Change::Block(BlockPos::new_3d(1, -2, 3), Some("game:stone".parse::<BlockState>().unwrap())),
+            Change::Block(BlockPos::new(4, 5), None),
+            Change::Entity(entity, Some(Position { x: -3, y: 9 })),
+            Change::Entity(entity, None),
+            Change::Slot(entity, 2, stack(&registry, "game:apple", 5).with_max_stack(8)),
+            Change::Slots(entity, 1),
+        ],
+    };
+    let encoded = delta.encode();
+    let decoded = Delta::decode(&encoded, &registry).unwrap();
+    assert_eq!(decoded.tick, 7);
+    assert_eq!(decoded.encode(), encoded);
+    assert!(encoded.starts_with("delta 7 6\n"));
+}
+
+#[test]
+fn invalid_deltas_are_errors() {
+    let registry = registry();
+    for content in ["", "delta x 1\n", "delta 1 2\nblock 0 0 0 -\n", "delta 1 1\nslot 0v0 0 game:unknown 1\n", "delta 1 1\nteleport\n"] {
+        assert!(matches!(Delta::decode(content, &registry), Err(SyncError::InvalidMessage(_))), "{:?}", content);
+    }
+}
+
+#[test]
+fn clients_follow_the_host() {
+    let registry = registry();
+    let mut world = World::new();
+    let mut entities = Entities::new();
+    let stone: BlockState = "game:stone".parse().unwrap();
+    world.set(BlockPos::new(0, 0), stone.clone());
+    let player = entities.spawn_empty();
+    entities.insert(player, Position { x: 1, y: 1 });
+    let mut inventory = Inventory::new(None);
+    inventory.insert(stack(&registry, "game:apple", 3));
+    entities.insert(player, inventory);
+    let zombie = entities.spawn_empty();
+    entities.insert(zombie, Position { x: 5, y: 5 });
+
+    let mut host = SyncHost::start("127.0.0.1:0", &world, &registry).unwrap();
+    let mut client = SyncClient::connect(host.local_addr(), "steve", &registry).unwrap();
+
+    // The full state first
+    sync_until(&mut host, &mut client, (&world, &entities, &registry), |c| c.replica.positions.len() == 2);
+    assert_eq!(host.clients(), vec!["steve"]);
+    assert_eq!(client.replica.world.get(BlockPos::new(0, 0)), Some(&stone));
+    assert_eq!(client.replica.inventories[&player][0].count, 3);
+
+    // Then the changes
+    world.remove(BlockPos::new(0, 0));
+    host.tracker.block_changed(BlockPos::new(0, 0));
+    world.set(BlockPos::new(2, 0), stone.clone());
+    host.tracker.block_changed(BlockPos::new(2, 0));
+    entities.insert(player, Position { x: 2, y: 1 });
+    entities.despawn(zombie);
+    let inventory = entities.get_mut::<Inventory>(player).unwrap();
+    inventory.hotbar[0].as_mut().unwrap().count = 1;
+    inventory.slots.push(stack(&registry, "game:stick", 10));
+    let delta = host.update(&world, &entities);
+    assert_eq!(delta.changes.len(), 6);
+    sync_until(&mut host, &mut client, (&world, &entities, &registry), |c| c.replica.tick == delta.tick);
+    assert_eq!(client.replica.world.get(BlockPos::new(0, 0)), None);
+    assert_eq!(client.replica.world.get(BlockPos::new(2, 0)), Some(&stone));
+    assert_eq!(client.replica.positions.get(&player), Some(&Position { x: 2, y: 1 }));
+    assert!(!client.replica.positions.contains_key(&zombie));
+    let slots = &client.replica.inventories[&player];
+    assert_eq!(slots.iter().map(|s| (s.item.id.to_string(), s.count)).collect::<Vec<_>>(), [
+        ("game:apple".to_string(), 1),
+        ("game:stick".to_string(), 10)
+    ]);
+
+    // Unchanged state sends nothing, shrinking inventories truncate
+    assert!(host.update(&world, &entities).is_empty());
+    entities.get_mut::<Inventory>(player).unwrap().slots.clear();
+    let delta = host.update(&world, &entities);
+    sync_until(&mut host, &mut client, (&world, &entities, &registry), |c| c.replica.tick == delta.tick);
+    assert_eq!(client.replica.inventories[&player].len(), 1);
+
+    host.stop();
+    let start = Instant::now();
+    while client.is_connected() {
+        assert!(start.elapsed() < Duration::from_secs(5), "client didn't notice the host stopping");
+        client.poll(&registry).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn clients_with_other_content_are_refused() {
+    let host_registry = registry();
+    let mut client_registry = registry();
+    client_registry.register(RegistrableEntity::Item(Item::new(id!("mod:gem"), vec![], 64))).unwrap();
+    let host = SyncHost::start("127.0.0.1:0", &World::new(), &host_registry).unwrap();
+    match SyncClient::connect(host.local_addr(), "alex", &client_registry) {
+        Err(SyncError::RegistryMismatch(namespaces)) => assert_eq!(namespaces, ["mod"]),
+        Err(e) => panic!("expected a registry mismatch, got {}", e),
+        Ok(_) => panic!("expected a registry mismatch"),
+    }
+}
+
+#[test]
+fn other_protocol_versions_are_rejected() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpStream;
+
+    let host = SyncHost::start("127.0.0.1:0", &World::new(), &registry()).unwrap();
+    let mut stream = TcpStream::connect(host.local_addr()).unwrap();
+    writeln!(stream, "hello {} steve", PROTOCOL_VERSION + 1).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).unwrap();
+    assert!(line.starts_with("reject "), "{:?}", line);
+}
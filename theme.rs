@@ -0,0 +1,336 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+use crate::color::{ColorRef, adjust_for_contrast, contrast_ratio, resolve_color_ref};
+use crate::notify::{self, Notifications, NotifySink};
+
+// Minimum contrast ratio between selected suggestion fg and bg
+pub const MIN_SELECTION_CONTRAST: f64 = 4.5;
+
+// -----
+// THEME
+// -----
+
+// Colors of the prompt, menus, progress bars, tables and log output, and how
+// notifications reach the player. Named colors of themes built at runtime use
+// ColorRef::Owned.
+#[derive(Clone, Debug)]
+pub struct ColorTheme {
+    pub prompt_color: ColorRef<'static>,
+    pub input_color: ColorRef<'static>,
+    pub suggestion_color: ColorRef<'static>,
+    pub selected_suggestion_color: ColorThemeSelectedSuggestion,
+    pub hint_color: ColorRef<'static>,
+    pub error_color: ColorRef<'static>,
+    pub warning_color: ColorRef<'static>,
+    pub success_color: ColorRef<'static>, // Command results, info log lines
+    pub progress_color: ColorRef<'static>,
+    pub table_border_color: ColorRef<'static>,
+    pub notifications: Notifications,
+}
+
+#[derive(Clone, Debug)]
+pub struct ColorThemeSelectedSuggestion {
+    pub fg: ColorRef<'static>,
+    pub bg: ColorRef<'static>,
+}
+
+impl Default for ColorTheme {
+    fn default() -> Self {
+        ColorTheme {
+            prompt_color: ColorRef::Named("default", "cyan"),
+            input_color: ColorRef::Named("default", "white"),
+            suggestion_color: ColorRef::Named("default", "white"),
+            selected_suggestion_color: ColorThemeSelectedSuggestion {
+                fg: ColorRef::Named("default", "yellow"),
+                bg: ColorRef::Named("default", "dark_gray"),
+            },
+            hint_color: ColorRef::Named("default", "gray"),
+            error_color: ColorRef::Named("default", "red"),
+            warning_color: ColorRef::Named("default", "yellow"),
+            success_color: ColorRef::Named("default", "green"),
+            progress_color: ColorRef::Named("default", "blue"),
+            table_border_color: ColorRef::Named("default", "gray"),
+            notifications: Notifications::new().with(notify::QUEST_COMPLETED, NotifySink::Bell),
+        }
+    }
+}
+
+impl ColorTheme {
+    pub fn dark() -> Self {
+        ColorTheme {
+            prompt_color: ColorRef::Named("default", "light_cyan"),
+            input_color: ColorRef::Named("default", "light_gray"),
+            suggestion_color: ColorRef::Named("default", "light_gray"),
+            selected_suggestion_color: ColorThemeSelectedSuggestion {
+                fg: ColorRef::Named("default", "light_yellow"),
+                bg: ColorRef::Named("default", "dark_gray"),
+            },
+            hint_color: ColorRef::Named("default", "gray"),
+            error_color: ColorRef::Named("default", "light_red"),
+            warning_color: ColorRef::Named("default", "light_yellow"),
+            success_color: ColorRef::Named("default", "light_green"),
+            progress_color: ColorRef::Named("default", "light_blue"),
+            table_border_color: ColorRef::Named("default", "dark_gray"),
+            notifications: Notifications::new().with(notify::QUEST_COMPLETED, NotifySink::Bell),
+        }
+    }
+
+    pub fn vibrant() -> Self {
+        ColorTheme {
+            prompt_color: ColorRef::Named("default", "magenta"),
+            input_color: ColorRef::Named("default", "white"),
+            suggestion_color: ColorRef::Named("default", "white"),
+            selected_suggestion_color: ColorThemeSelectedSuggestion {
+                fg: ColorRef::Named("default", "light_magenta"),
+                bg: ColorRef::Named("default", "dark_magenta"),
+            },
+            hint_color: ColorRef::Named("default", "light_gray"),
+            error_color: ColorRef::Named("default", "red"),
+            warning_color: ColorRef::Named("default", "yellow"),
+            success_color: ColorRef::Named("default", "light_green"),
+            progress_color: ColorRef::Named("default", "magenta"),
+            table_border_color: ColorRef::Named("default", "magenta"),
+            notifications: Notifications::new()
+                .with(notify::QUEST_COMPLETED, NotifySink::Desktop)
+                .with(notify::CRAFTING_FINISHED, NotifySink::Bell),
+        }
+    }
+
+    // Replaces an unreadable selected suggestion fg (contrast against bg below
+    // MIN_SELECTION_CONTRAST) with an adjusted color and warns about it
+    pub fn with_adjusted_contrast(mut self) -> Self {
+        let selected = &self.selected_suggestion_color;
+        if let (Some(fg), Some(bg)) = (resolve_color_ref(&selected.fg), resolve_color_ref(&selected.bg)) {
+            let ratio = contrast_ratio(fg, bg);
+            if ratio < MIN_SELECTION_CONTRAST {
+                let adjusted = adjust_for_contrast(fg, bg, MIN_SELECTION_CONTRAST);
+                log::warn!(
+                    "Selected suggestion colors have low contrast ({:.2}:1), using #{:02x}{:02x}{:02x} as foreground",
+                    ratio, adjusted.r, adjusted.g, adjusted.b
+                );
+                self.selected_suggestion_color.fg = ColorRef::Direct(adjusted);
+            }
+        }
+        self
+    }
+
+    // All color references of this theme, e.g. for color::color_usage_report
+    pub fn color_refs(&self) -> Vec<ColorRef<'static>> {
+        vec![
+            self.prompt_color.clone(),
+            self.input_color.clone(),
+            self.suggestion_color.clone(),
+            self.selected_suggestion_color.fg.clone(),
+            self.selected_suggestion_color.bg.clone(),
+            self.hint_color.clone(),
+            self.error_color.clone(),
+            self.warning_color.clone(),
+            self.success_color.clone(),
+            self.progress_color.clone(),
+            self.table_border_color.clone(),
+        ]
+    }
+
+    // Field of a theme file, e.g. "prompt" or "selected_bg"
+    fn field_mut(&mut self, field: &str) -> Option<&mut ColorRef<'static>> {
+        Some(match field {
+            "prompt" => &mut self.prompt_color,
+            "input" => &mut self.input_color,
+            "suggestion" => &mut self.suggestion_color,
+            "selected_fg" => &mut self.selected_suggestion_color.fg,
+            "selected_bg" => &mut self.selected_suggestion_color.bg,
+            "hint" => &mut self.hint_color,
+            "error" => &mut self.error_color,
+            "warning" => &mut self.warning_color,
+            "success" => &mut self.success_color,
+            "progress" => &mut self.progress_color,
+            "table_border" => &mut self.table_border_color,
+            _ => return None,
+        })
+    }
+}
+
+// ------
+// ERRORS
+// ------
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(PathBuf, io::Error),
+    Yaml(serde_yaml::Error),
+    InvalidName(String),
+    UnknownTheme(String),
+    UnknownField { theme: String, field: String },
+    InvalidColor { theme: String, field: String, value: String },
+    InvalidSink { theme: String, field: String, value: String },
+}
+
+impl Display for ThemeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::Io(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            ThemeError::Yaml(e) => write!(f, "invalid theme file: {}", e),
+            ThemeError::InvalidName(name) => write!(f, "theme name '{}' must contain only [a-z_]", name),
+            ThemeError::UnknownTheme(name) => write!(f, "theme '{}' does not exist", name),
+            ThemeError::UnknownField { theme, field } => write!(f, "theme '{}' has unknown field '{}'", theme, field),
+            ThemeError::InvalidColor { theme, field, value } => {
+                write!(f, "theme '{}' field '{}': '{}' is neither #rrggbb nor namespace::name", theme, field, value)
+            }
+            ThemeError::InvalidSink { theme, field, value } => {
+                write!(f, "theme '{}' field '{}': '{}' is not bell, desktop or silent", theme, field, value)
+            }
+        }
+    }
+}
+
+impl Error for ThemeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ThemeError::Io(_, e) => Some(e),
+            ThemeError::Yaml(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for ThemeError {
+    fn from(e: serde_yaml::Error) -> Self {
+        ThemeError::Yaml(e)
+    }
+}
+
+// --------------
+// THEME REGISTRY
+// --------------
+
+// Themes selectable by name, starting with the built-in "default", "dark"
+// and "vibrant"
+static THEMES: Lazy<RwLock<BTreeMap<String, ColorTheme>>> = Lazy::new(|| {
+    RwLock::new(BTreeMap::from([
+        ("default".to_string(), ColorTheme::default()),
+        ("dark".to_string(), ColorTheme::dark()),
+        ("vibrant".to_string(), ColorTheme::vibrant()),
+    ]))
+});
+
+static ACTIVE_THEME: Lazy<RwLock<String>> = Lazy::new(|| RwLock::new("default".to_string()));
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| matches!(c, 'a'..='z' | '_'))
+}
+
+// Adds a theme or replaces the one with the same name
+pub fn register_theme(name: &str, theme: ColorTheme) -> Result<(), ThemeError> {
+    if !is_valid_name(name) {
+        return Err(ThemeError::InvalidName(name.to_string()));
+    }
+    THEMES.write().unwrap().insert(name.to_string(), theme);
+    Ok(())
+}
+
+pub fn get_theme(name: &str) -> Option<ColorTheme> {
+    THEMES.read().unwrap().get(name).cloned()
+}
+
+// Sorted by name
+pub fn theme_names() -> Vec<String> {
+    THEMES.read().unwrap().keys().cloned().collect()
+}
+
+// Selects the theme used by everything that isn't given one explicitly
+pub fn set_theme(name: &str) -> Result<(), ThemeError> {
+    if !THEMES.read().unwrap().contains_key(name) {
+        return Err(ThemeError::UnknownTheme(name.to_string()));
+    }
+    *ACTIVE_THEME.write().unwrap() = name.to_string();
+    Ok(())
+}
+
+pub fn current_theme_name() -> String {
+    ACTIVE_THEME.read().unwrap().clone()
+}
+
+// The selected theme, the default one if it was removed in the meantime
+pub fn current_theme() -> ColorTheme {
+    get_theme(&current_theme_name()).unwrap_or_default()
+}
+
+// -----------
+// THEME FILES
+// -----------
+
+// Themes by name, every field optional:
+//
+//   solarized:
+//     base: dark               # Theme the other fields default to, "default" if omitted
+//     prompt: "#268bd2"
+//     input: default::white
+//     selected_fg: "#fdf6e3"
+//     selected_bg: "#073642"
+//     table_border: "#586e75"
+//     notify_quest_completed: desktop
+//
+// Fields: prompt, input, suggestion, selected_fg, selected_bg, hint, error,
+// warning, success, progress, table_border, and notify_<event> with bell,
+// desktop or silent for a notification event (see notify)
+pub fn parse_themes(content: &str) -> Result<Vec<(String, ColorTheme)>, ThemeError> {
+    let raw: BTreeMap<String, BTreeMap<String, String>> = serde_yaml::from_str(content)?;
+    let mut themes: Vec<(String, ColorTheme)> = vec![];
+    for (name, fields) in raw {
+        if !is_valid_name(&name) {
+            return Err(ThemeError::InvalidName(name));
+        }
+        // The base may be defined earlier in the same file
+        let mut theme = match fields.get("base") {
+            Some(base) => themes
+                .iter()
+                .find(|(n, _)| n == base)
+                .map(|(_, t)| t.clone())
+                .or_else(|| get_theme(base))
+                .ok_or_else(|| ThemeError::UnknownTheme(base.clone()))?,
+            None => ColorTheme::default(),
+        };
+        for (field, value) in fields.iter().filter(|(f, _)| *f != "base") {
+            if let Some(event) = field.strip_prefix("notify_") {
+                let sink = NotifySink::parse(value).ok_or_else(|| ThemeError::InvalidSink {
+                    theme: name.clone(),
+                    field: field.clone(),
+                    value: value.clone(),
+                })?;
+                theme.notifications.set(event, sink);
+                continue;
+            }
+            let color = ColorRef::parse(value).ok_or_else(|| ThemeError::InvalidColor {
+                theme: name.clone(),
+                field: field.clone(),
+                value: value.clone(),
+            })?;
+            let slot = theme
+                .field_mut(field)
+                .ok_or_else(|| ThemeError::UnknownField { theme: name.clone(), field: field.clone() })?;
+            *slot = color;
+        }
+        themes.push((name, theme));
+    }
+    Ok(themes)
+}
+
+// Registers every theme of a file, see parse_themes. Returns their names.
+pub fn load_themes<P: AsRef<Path>>(path: P) -> Result<Vec<String>, ThemeError> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).map_err(|e| ThemeError::Io(path.to_path_buf(), e))?;
+    let themes = parse_themes(&content)?;
+    let names = themes.iter().map(|(name, _)| name.clone()).collect();
+    for (name, theme) in themes {
+        register_theme(&name, theme)?;
+    }
+    Ok(names)
+}
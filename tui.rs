@@ -0,0 +1,1047 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+use crate::color::ColorRef;
+use crate::conditions::ConditionContext;
+use crate::dialogue::Conversation;
+use crate::interface::{ColorTheme, fullscreen, theme_color};
+use crate::inventory::{Inventory, Slot};
+use crate::localization::Translator;
+use crate::random::RuzRng;
+use crate::recipes::RecipeBook;
+use crate::registry::{Dialogue, DialogueNode, ID, PatternKey, Recipe, Registry, RegistryKey, with_registry};
+use crate::savegame::PlayerProfile;
+use crate::scene::{Scene, SceneContext, Transition};
+use crate::theme::current_theme;
+
+// ----------------
+// REGISTRY BROWSER
+// ----------------
+
+const CATEGORIES: [&str; 14] = [
+    "Items", "Blocks", "Tags", "Tools", "Recipes", "LootTables", "Entities", "Enchantments", "Quests", "Effects", "Dialogues",
+    "Sprites", "Biomes", "Structures",
+];
+
+// One registered entity as shown in the browser
+struct BrowserEntry {
+    category: usize,
+    id: ID,
+    tags: Vec<ID>,
+    details: Vec<String>,
+}
+
+// Row of the tree: a category or an entry inside an expanded category
+enum TreeRow {
+    Category(usize, usize), // Category index, number of matching entries
+    Entry(usize),           // Index into the entries
+}
+
+// Full-screen view of everything in REGISTRY: a tree of categories and
+// entities, a detail pane for the selected one, live search and tag filters.
+//
+// Keys: ↑/↓ move, →/Enter expand, ← collapse, / search, t/T cycle tag filter,
+// q/Esc quit
+pub struct RegistryBrowser {
+    theme: ColorTheme,
+    entries: Vec<BrowserEntry>,
+    expanded: HashSet<usize>,
+    cursor: usize,
+    search: String,
+    searching: bool,
+    tag_filter: Option<usize>, // Index into tag_ids
+    tag_ids: Vec<ID>,
+    scores: HashMap<(RegistryKey, ID), u32>, // Registry::search hits for the search
+}
+
+impl RegistryBrowser {
+    pub fn new() -> Self {
+        RegistryBrowser {
+            theme: current_theme(),
+            entries: vec![],
+            expanded: HashSet::new(),
+            cursor: 0,
+            search: String::new(),
+            searching: false,
+            tag_filter: None,
+            tag_ids: vec![],
+            scores: HashMap::new(),
+        }
+    }
+
+    pub fn with_theme(mut self, theme: ColorTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn run(mut self) -> io::Result<()> {
+        fullscreen(|terminal| {
+            loop {
+                // Reloaded every frame so changes made by other threads show up
+                self.refresh();
+                let rows = self.rows();
+                self.cursor = self.cursor.min(rows.len().saturating_sub(1));
+                terminal.draw(|f| self.draw(f, &rows))?;
+
+                if !event::poll(Duration::from_millis(250))? {
+                    continue;
+                }
+                let Event::Key(key) = event::read()? else {
+                    continue;
+                };
+                if self.searching {
+                    match key.code {
+                        KeyCode::Esc | KeyCode::Enter => self.searching = false,
+                        KeyCode::Backspace => {
+                            self.search.pop();
+                        }
+                        KeyCode::Char(c) => {
+                            self.search.push(c);
+                            self.cursor = 0;
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                match (key.code, key.modifiers) {
+                    (KeyCode::Char('q'), _) | (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => return Ok(()),
+                    (KeyCode::Up, _) => self.cursor = self.cursor.saturating_sub(1),
+                    (KeyCode::Down, _) => self.cursor += 1,
+                    (KeyCode::Right | KeyCode::Enter, _) => {
+                        if let Some(TreeRow::Category(category, _)) = rows.get(self.cursor) {
+                            self.expanded.insert(*category);
+                        }
+                    }
+                    (KeyCode::Left, _) => {
+                        let category = match rows.get(self.cursor) {
+                            Some(TreeRow::Category(category, _)) => Some(*category),
+                            Some(TreeRow::Entry(i)) => Some(self.entries[*i].category),
+                            None => None,
+                        };
+                        if let Some(category) = category {
+                            self.expanded.remove(&category);
+                            self.cursor = rows
+                                .iter()
+                                .position(|row| matches!(row, TreeRow::Category(c, _) if *c == category))
+                                .unwrap_or(0);
+                        }
+                    }
+                    (KeyCode::Char('/'), _) => self.searching = true,
+                    (KeyCode::Char('t'), _) => self.cycle_tag_filter(true),
+                    (KeyCode::Char('T'), _) => self.cycle_tag_filter(false),
+                    _ => {}
+                }
+            }
+        })
+    }
+
+    fn cycle_tag_filter(&mut self, forward: bool) {
+        let len = self.tag_ids.len();
+        self.tag_filter = match (self.tag_filter, forward) {
+            _ if len == 0 => None,
+            (None, true) => Some(0),
+            (None, false) => Some(len - 1),
+            (Some(i), true) => (i + 1 < len).then_some(i + 1),
+            (Some(i), false) => i.checked_sub(1),
+        };
+        self.cursor = 0;
+    }
+
+    fn refresh(&mut self) {
+        let mut tag_ids: Vec<ID> = with_registry(|registry| {
+            self.entries = collect_entries(registry);
+            self.scores = registry.search(&self.search, usize::MAX).into_iter().map(|h| ((h.key, h.id), h.score)).collect();
+            registry.tags.keys().cloned().collect()
+        });
+        tag_ids.sort_by_key(|id| id.to_string());
+        // Keep the selected filter if the tag still exists
+        let selected = self.tag_filter.and_then(|i| self.tag_ids.get(i)).cloned();
+        self.tag_filter = selected.and_then(|id| tag_ids.iter().position(|t| *t == id));
+        self.tag_ids = tag_ids;
+    }
+
+    fn matches(&self, entry: &BrowserEntry) -> bool {
+        let found = self.search.trim().is_empty() || self.scores.contains_key(&entry_key(entry));
+        let tagged = match self.tag_filter.and_then(|i| self.tag_ids.get(i)) {
+            Some(tag) => entry.tags.contains(tag),
+            None => true,
+        };
+        found && tagged
+    }
+
+    fn rows(&self) -> Vec<TreeRow> {
+        let mut rows = vec![];
+        for category in 0..CATEGORIES.len() {
+            let mut matching: Vec<usize> = (0..self.entries.len())
+                .filter(|&i| self.entries[i].category == category && self.matches(&self.entries[i]))
+                .collect();
+            // Best search hits first, the entries are sorted by ID otherwise
+            matching.sort_by_key(|&i| std::cmp::Reverse(self.scores.get(&entry_key(&self.entries[i])).copied().unwrap_or(0)));
+            rows.push(TreeRow::Category(category, matching.len()));
+            // Searching expands all categories with matches
+            if self.expanded.contains(&category) || !self.search.is_empty() {
+                rows.extend(matching.into_iter().map(TreeRow::Entry));
+            }
+        }
+        rows
+    }
+
+    fn draw(&self, f: &mut ratatui::Frame, rows: &[TreeRow]) {
+        let prompt_style = Style::default().fg(theme_color(&self.theme.prompt_color, Color::Cyan));
+        let item_style = Style::default().fg(theme_color(&self.theme.suggestion_color, Color::White));
+        let hint_style = Style::default().fg(theme_color(&self.theme.hint_color, Color::Gray));
+        let selected_style = self.theme.selected_style();
+
+        let vertical = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(3), Constraint::Length(1)])
+            .split(f.area());
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(vertical[1]);
+
+        let tag = self.tag_filter.and_then(|i| self.tag_ids.get(i)).map_or("all".to_string(), |id| format!("#{}", id));
+        let cursor = if self.searching { "_" } else { "" };
+        let header = format!("search: {}{}  tag: {}", self.search, cursor, tag);
+        f.render_widget(Paragraph::new(header).style(prompt_style), vertical[0]);
+
+        let items: Vec<ListItem> = rows
+            .iter()
+            .map(|row| match row {
+                TreeRow::Category(category, count) => {
+                    let arrow = if self.expanded.contains(category) || !self.search.is_empty() { "▾" } else { "▸" };
+                    ListItem::new(format!("{} {} ({})", arrow, CATEGORIES[*category], count))
+                        .style(prompt_style.add_modifier(Modifier::BOLD))
+                }
+                TreeRow::Entry(i) => ListItem::new(format!("    {}", self.entries[*i].id)).style(item_style),
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Registry"))
+            .highlight_style(selected_style);
+        let mut state = ListState::default();
+        state.select((!rows.is_empty()).then_some(self.cursor));
+        f.render_stateful_widget(list, panes[0], &mut state);
+
+        let details: Vec<Line> = match rows.get(self.cursor) {
+            Some(TreeRow::Entry(i)) => {
+                let entry = &self.entries[*i];
+                let mut lines = vec![Line::styled(entry.id.to_string(), prompt_style), Line::raw("")];
+                lines.extend(entry.details.iter().map(|d| Line::raw(d.clone())));
+                if !entry.tags.is_empty() {
+                    let tags: Vec<String> = entry.tags.iter().map(|t| format!("#{}", t)).collect();
+                    lines.push(Line::raw(format!("tags: {}", tags.join(", "))));
+                }
+                lines
+            }
+            Some(TreeRow::Category(category, count)) => vec![Line::raw(format!("{} {}", count, CATEGORIES[*category]))],
+            None => vec![],
+        };
+        f.render_widget(
+            Paragraph::new(details).block(Block::default().borders(Borders::ALL).title("Details")),
+            panes[1],
+        );
+
+        let hint = "↑/↓ move · →/← expand/collapse · / search · t/T tag filter · q quit";
+        f.render_widget(Paragraph::new(hint).style(hint_style), vertical[2]);
+    }
+}
+
+impl Default for RegistryBrowser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The categories are in the order of RegistryKey::BUILTIN
+fn entry_key(entry: &BrowserEntry) -> (RegistryKey, ID) {
+    (RegistryKey::BUILTIN[entry.category], entry.id)
+}
+
+// Tags listing an entity, from the entity's own tags and the tag entries
+fn tags_of(registry: &Registry, key: RegistryKey, id: &ID, own: &[ID]) -> Vec<ID> {
+    let mut tags: Vec<ID> = own.to_vec();
+    for tag in registry.tags.values() {
+        if tag.contains(key, id) && !tags.contains(&tag.id) {
+            tags.push(tag.id);
+        }
+    }
+    tags
+}
+
+fn recipe_details(recipe: &Recipe) -> Vec<String> {
+    let mut details = vec!["ingredients:".to_string()];
+    details.extend(recipe.ingredients.iter().map(|c| format!("  {}x {}", c.count, c.id)));
+    details.push("results:".to_string());
+    details.extend(recipe.results.iter().map(|c| format!("  {}", c)));
+    if let Some(shape) = &recipe.shape {
+        details.push(if shape.mirrored { "pattern (mirrorable):" } else { "pattern:" }.to_string());
+        details.extend(shape.pattern.iter().map(|row| format!("  |{}|", row)));
+        details.extend(shape.keys.iter().map(|(c, key)| match key {
+            PatternKey::Item(id) => format!("  {} = {}", c, id),
+            PatternKey::Tag(tag) => format!("  {} = #{}", c, tag),
+        }));
+    }
+    details
+}
+
+fn collect_entries(registry: &Registry) -> Vec<BrowserEntry> {
+    let mut entries = vec![];
+    for item in registry.items.values() {
+        entries.push(BrowserEntry {
+            category: 0,
+            id: item.id,
+            tags: tags_of(registry, RegistryKey::ITEM, &item.id, &item.tags),
+            details: vec![format!("stack size: {}", item.stack_size)],
+        });
+    }
+    for block in registry.blocks.values() {
+        let loot_table = block.loot_table.as_ref().map_or("none".to_string(), |t| t.id.to_string());
+        entries.push(BrowserEntry {
+            category: 1,
+            id: block.id,
+            tags: tags_of(registry, RegistryKey::BLOCK, &block.id, &block.tags),
+            details: vec![format!("hardness: {}", block.hardness), format!("loot table: {}", loot_table)],
+        });
+    }
+    for tag in registry.tags.values() {
+        let details: Vec<String> = tag.iter().map(|(key, id)| format!("{} {}", key, id)).collect();
+        entries.push(BrowserEntry { category: 2, id: tag.id, tags: tags_of(registry, RegistryKey::TAG, &tag.id, &[]), details });
+    }
+    for tool in registry.tools.values() {
+        entries.push(BrowserEntry {
+            category: 3,
+            id: tool.id,
+            tags: tags_of(registry, RegistryKey::TOOL, &tool.id, &tool.tags),
+            details: vec![
+                format!("durability: {}", tool.durability),
+                format!("level: {}", tool.level),
+                format!("speed: {}", tool.speed),
+            ],
+        });
+    }
+    for recipe in registry.recipes.values() {
+        entries.push(BrowserEntry {
+            category: 4,
+            id: recipe.id,
+            tags: tags_of(registry, RegistryKey::RECIPE, &recipe.id, &[]),
+            details: recipe_details(recipe),
+        });
+    }
+    for loot_table in registry.loot_tables.values() {
+        let details = loot_table
+            .entries
+            .iter()
+            .map(|e| {
+                let items: Vec<String> = e.items.iter().map(ID::to_string).collect();
+                format!("{}-{}x {} ({}%, weight {})", e.min, e.max, items.join(" | "), e.chance * 100.0, e.weight)
+            })
+            .collect();
+        let tags = tags_of(registry, RegistryKey::LOOT_TABLE, &loot_table.id, &[]);
+        entries.push(BrowserEntry { category: 5, id: loot_table.id, tags, details });
+    }
+    for entity in registry.entities.values() {
+        entries.push(BrowserEntry {
+            category: 6,
+            id: entity.id,
+            tags: tags_of(registry, RegistryKey::ENTITY, &entity.id, &entity.tags),
+            details: [Some(format!("max health: {}", entity.max_health)), entity.behavior.as_ref().map(|b| format!("behavior: {}", b))]
+                .into_iter()
+                .flatten()
+                .collect(),
+        });
+    }
+    for enchantment in registry.enchantments.values() {
+        let mut details = vec![format!("max level: {}", enchantment.max_level)];
+        details.extend(enchantment.modifiers.iter().map(|m| format!("{:?}", m)));
+        entries.push(BrowserEntry {
+            category: 7,
+            id: enchantment.id,
+            tags: enchantment.applicable_tags.clone(),
+            details,
+        });
+    }
+    for quest in registry.quests.values() {
+        let mut details: Vec<String> = quest.criteria.iter().map(|c| c.to_string()).collect();
+        details.extend(quest.prerequisites.iter().map(|p| format!("requires: {}", p)));
+        if let Some(reward) = &quest.reward {
+            details.push(format!("reward: {}", reward));
+        }
+        let tags = tags_of(registry, RegistryKey::QUEST, &quest.id, &[]);
+        entries.push(BrowserEntry { category: 8, id: quest.id, tags, details });
+    }
+    for effect in registry.effects.values() {
+        let details = vec![
+            format!("duration: {} ticks", effect.duration),
+            format!("max amplifier: {}", effect.max_amplifier),
+            format!("tick interval: {}", effect.interval),
+        ];
+        let tags = tags_of(registry, RegistryKey::EFFECT, &effect.id, &[]);
+        entries.push(BrowserEntry { category: 9, id: effect.id, tags, details });
+    }
+    for dialogue in registry.dialogues.values() {
+        let details = dialogue
+            .nodes
+            .iter()
+            .map(|n| {
+                let next: Vec<&str> = n.choices.iter().map(|c| c.next.as_deref().unwrap_or("end")).collect();
+                format!("{} -> {}", n.name, if next.is_empty() { "end".to_string() } else { next.join(", ") })
+            })
+            .collect();
+        let tags = tags_of(registry, RegistryKey::DIALOGUE, &dialogue.id, &[]);
+        entries.push(BrowserEntry { category: 10, id: dialogue.id, tags, details });
+    }
+    for sprite in registry.sprites.values() {
+        let details = sprite.render().plain_text().lines().map(str::to_string).collect();
+        let tags = tags_of(registry, RegistryKey::SPRITE, &sprite.id, &[]);
+        entries.push(BrowserEntry { category: 11, id: sprite.id, tags, details });
+    }
+    for biome in registry.biomes.values() {
+        let mut details = vec![format!("temperature: {}", biome.temperature), format!("surface: {}", biome.surface)];
+        details.extend(biome.layers.iter().map(|(block, thickness)| format!("layer: {} x{}", block, thickness)));
+        details.extend(biome.features.iter().map(|f| format!("feature: {}", f)));
+        let tags = tags_of(registry, RegistryKey::BIOME, &biome.id, &biome.tags);
+        entries.push(BrowserEntry { category: 12, id: biome.id, tags, details });
+    }
+    for structure in registry.structures.values() {
+        let size = format!("size: {}x{}x{}", structure.width(), structure.length(), structure.height());
+        let mut details = vec![size, format!("rarity: {}", structure.rarity)];
+        details.extend(structure.biomes.iter().map(|b| format!("biome: {}", b)));
+        details.extend(structure.palette.iter().map(|(key, block)| format!("'{}' = {}", key, block)));
+        let tags = tags_of(registry, RegistryKey::STRUCTURE, &structure.id, &structure.tags);
+        entries.push(BrowserEntry { category: 13, id: structure.id, tags, details });
+    }
+    entries.sort_by_key(|e| (e.category, e.id.to_string()));
+    entries
+}
+
+// ----------------
+// INVENTORY SCREEN
+// ----------------
+
+const GRID_COLUMNS: usize = 9;
+const CELL_WIDTH: u16 = 14;
+const CELL_HEIGHT: u16 = 4;
+
+// Interactive grid of an Inventory's slots. Stacks can be picked up, placed,
+// merged, swapped and split with the keyboard. The inventory is updated when
+// the screen is closed, slots keep the order of the grid.
+//
+// Keys: arrows move, Enter/Space pick up or place, s split (half of a stack,
+// or one item from the hand), q/Esc close
+pub struct InventoryScreen {
+    theme: ColorTheme,
+    title: String,
+}
+
+impl InventoryScreen {
+    pub fn new() -> Self {
+        InventoryScreen {
+            theme: current_theme(),
+            title: "Inventory".to_string(),
+        }
+    }
+
+    pub fn with_theme(mut self, theme: ColorTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn run(&self, inventory: &mut Inventory) -> io::Result<()> {
+        let mut state = InventoryState::new(inventory);
+        let result = fullscreen(|terminal| {
+            loop {
+                terminal.draw(|f| self.draw(f, &state))?;
+                if let Event::Key(key) = event::read()?
+                    && state.handle_key(&key)
+                {
+                    return Ok(());
+                }
+            }
+        });
+        state.apply(inventory);
+        result
+    }
+
+    fn draw(&self, f: &mut ratatui::Frame, state: &InventoryState) {
+        let item_style = Style::default().fg(theme_color(&self.theme.suggestion_color, Color::White));
+        let prompt_style = Style::default().fg(theme_color(&self.theme.prompt_color, Color::Cyan));
+        let hint_style = Style::default().fg(theme_color(&self.theme.hint_color, Color::Gray));
+        let selected_style = self.theme.selected_style();
+
+        let area = f.area();
+        let rows = state.grid.len().div_ceil(GRID_COLUMNS) as u16;
+        let grid_area = Rect::new(area.x, area.y, area.width, (rows * CELL_HEIGHT + 2).min(area.height));
+        f.render_widget(Block::default().borders(Borders::ALL).title(self.title.as_str()), grid_area);
+
+        for (i, slot) in state.grid.iter().enumerate() {
+            let x = grid_area.x + 1 + (i % GRID_COLUMNS) as u16 * CELL_WIDTH;
+            let y = grid_area.y + 1 + (i / GRID_COLUMNS) as u16 * CELL_HEIGHT;
+            if x + CELL_WIDTH > area.right() || y + CELL_HEIGHT > area.bottom() {
+                continue;
+            }
+            let cell = Rect::new(x, y, CELL_WIDTH, CELL_HEIGHT);
+            let style = if i == state.cursor { selected_style } else { item_style };
+            let text = match slot {
+                Some(slot) => vec![
+                    Line::raw(shorten(slot.item.id.name(), CELL_WIDTH as usize - 2)),
+                    Line::raw(format!("{:>width$}", format!("{}x", slot.count), width = CELL_WIDTH as usize - 2)),
+                ],
+                None => vec![],
+            };
+            f.render_widget(Paragraph::new(text).style(style).block(Block::default().borders(Borders::ALL)), cell);
+        }
+
+        let hovered = state.grid[state.cursor].as_ref().map_or("empty".to_string(), |s| format!("{} ({}x)", s.item.id, s.count));
+        let hand = state.hand.as_ref().map_or("nothing".to_string(), |s| format!("{} ({}x)", s.item.id, s.count));
+        let info_y = (grid_area.bottom()).min(area.bottom().saturating_sub(2));
+        let info = Paragraph::new(vec![
+            Line::styled(format!("slot: {}  ·  holding: {}", hovered, hand), prompt_style),
+            Line::styled("arrows move · enter/space pick up/place · s split · q close", hint_style),
+        ]);
+        f.render_widget(info, Rect::new(area.x, info_y, area.width, 2.min(area.height)));
+    }
+}
+
+impl Default for InventoryScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// InventoryScreen as a scene, editing the player's inventory of the
+// SceneContext. The inventory is updated when the scene is popped.
+pub struct InventoryScene {
+    screen: InventoryScreen,
+    state: Option<InventoryState>, // While on the stack
+}
+
+impl InventoryScene {
+    pub fn new() -> Self {
+        InventoryScene { screen: InventoryScreen::new(), state: None }
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.screen = self.screen.with_title(title);
+        self
+    }
+}
+
+impl Default for InventoryScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D> Scene<D> for InventoryScene {
+    fn name(&self) -> &str {
+        "inventory"
+    }
+
+    fn on_enter(&mut self, context: &mut SceneContext<D>) {
+        self.screen.theme = context.theme.clone();
+        self.state = Some(InventoryState::new(&context.player.inventory));
+    }
+
+    fn handle_event(&mut self, event: &Event, _context: &mut SceneContext<D>) -> Transition<D> {
+        if let (Event::Key(key), Some(state)) = (event, self.state.as_mut())
+            && state.handle_key(key)
+        {
+            return Transition::Pop;
+        }
+        Transition::None
+    }
+
+    fn render(&self, frame: &mut ratatui::Frame, _context: &SceneContext<D>) {
+        if let Some(state) = &self.state {
+            self.screen.draw(frame, state);
+        }
+    }
+
+    fn on_exit(&mut self, context: &mut SceneContext<D>) {
+        if let Some(state) = self.state.take() {
+            state.apply(&mut context.player.inventory);
+        }
+    }
+}
+
+// The first cells of the grid are the hotbar positions
+struct InventoryState {
+    grid: Vec<Option<Slot>>,
+    hotbar: usize,
+    hand: Option<Slot>,
+    cursor: usize,
+}
+
+impl InventoryState {
+    fn new(inventory: &Inventory) -> Self {
+        let mut grid = inventory.hotbar().to_vec();
+        let hotbar = grid.len();
+        grid.extend(inventory.slots.iter().cloned().map(Some));
+        grid.resize(inventory.max_slots.max(grid.len()), None);
+        InventoryState { grid, hotbar, hand: None, cursor: 0 }
+    }
+
+    // True if the key closes the screen
+    fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('q'), _) | (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => return true,
+            (KeyCode::Left, _) => self.move_cursor(-1, 0),
+            (KeyCode::Right, _) => self.move_cursor(1, 0),
+            (KeyCode::Up, _) => self.move_cursor(0, -1),
+            (KeyCode::Down, _) => self.move_cursor(0, 1),
+            (KeyCode::Enter | KeyCode::Char(' '), _) => self.click(),
+            (KeyCode::Char('s'), _) => self.split(),
+            _ => {}
+        }
+        false
+    }
+
+    fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let columns = GRID_COLUMNS as isize;
+        let (x, y) = (self.cursor as isize % columns, self.cursor as isize / columns);
+        let (x, y) = ((x + dx).clamp(0, columns - 1), (y + dy).max(0));
+        let target = (y * columns + x) as usize;
+        if target < self.grid.len() {
+            self.cursor = target;
+        }
+    }
+
+    // Picks up the stack under the cursor, or places/merges/swaps the held one
+    fn click(&mut self) {
+        let cell = &mut self.grid[self.cursor];
+        match (cell.take(), self.hand.take()) {
+            (Some(slot), None) => self.hand = Some(slot),
+            (None, Some(held)) => *cell = Some(held),
+            (Some(mut slot), Some(mut held)) if slot.stacks_with(&held) => {
+                let moved = held.count.min(slot.space());
+                slot.count += moved;
+                held.count -= moved;
+                *cell = Some(slot);
+                self.hand = (held.count > 0).then_some(held);
+            }
+            (Some(slot), Some(held)) => {
+                *cell = Some(held);
+                self.hand = Some(slot);
+            }
+            (None, None) => {}
+        }
+    }
+
+    // Takes half of the stack under the cursor, or places a single held item
+    fn split(&mut self) {
+        let cell = &mut self.grid[self.cursor];
+        match (cell.as_mut(), self.hand.as_mut()) {
+            (Some(slot), None) if slot.count > 1 => {
+                let taken = slot.count.div_ceil(2);
+                slot.count -= taken;
+                self.hand = Some(Slot { count: taken, ..slot.clone() });
+            }
+            (None, Some(held)) => {
+                *cell = Some(Slot { count: 1, ..held.clone() });
+                held.count -= 1;
+            }
+            (Some(slot), Some(held)) if slot.stacks_with(held) && slot.space() > 0 => {
+                slot.count += 1;
+                held.count -= 1;
+            }
+            _ => {}
+        }
+        if self.hand.as_ref().is_some_and(|held| held.count == 0) {
+            self.hand = None;
+        }
+    }
+
+    // Writes the grid back, the hotbar cells to their positions and the
+    // others in grid order. A still held stack goes back into the inventory
+    // like a newly added item.
+    fn apply(self, inventory: &mut Inventory) {
+        let mut cells = self.grid.into_iter();
+        for (position, cell) in inventory.hotbar.iter_mut().zip(cells.by_ref().take(self.hotbar)) {
+            *position = cell;
+        }
+        inventory.slots = cells.flatten().collect();
+        if let Some(held) = self.hand
+            && let Err(e) = inventory.add_slot(held)
+        {
+            log::warn!("{}", e);
+        }
+    }
+}
+
+// Cuts text to `width` characters, ending with '…' if shortened
+fn shorten(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(width.saturating_sub(1)).collect();
+    short.push('…');
+    short
+}
+
+// ---------------
+// CRAFTING SCREEN
+// ---------------
+
+// Lists the recipes in REGISTRY with the ingredients needed and owned.
+// Recipes that can't be crafted from the inventory are greyed out.
+//
+// Keys: ↑/↓ move, Enter craft, q/Esc close
+// Without a recipe book every recipe is craftable. With one, locked recipes
+// are greyed out (or hidden with hide_locked) and can't be crafted.
+pub struct CraftingScreen<'a> {
+    theme: ColorTheme,
+    recipe_book: Option<&'a RecipeBook>,
+    hide_locked: bool,
+}
+
+impl<'a> CraftingScreen<'a> {
+    pub fn new() -> Self {
+        CraftingScreen { theme: current_theme(), recipe_book: None, hide_locked: false }
+    }
+
+    pub fn with_theme(mut self, theme: ColorTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_recipe_book(mut self, recipe_book: &'a RecipeBook) -> Self {
+        self.recipe_book = Some(recipe_book);
+        self
+    }
+
+    pub fn hide_locked(mut self, hide: bool) -> Self {
+        self.hide_locked = hide;
+        self
+    }
+
+    fn is_locked(&self, recipe: &Recipe) -> bool {
+        self.recipe_book.is_some_and(|book| !book.is_unlocked(recipe))
+    }
+
+    // Recipes shown, sorted by ID
+    fn recipes(&self) -> Vec<Recipe> {
+        with_registry(|registry| {
+            let mut recipes: Vec<Recipe> =
+                registry.recipes.values().filter(|r| !(self.hide_locked && self.is_locked(r))).cloned().collect();
+            recipes.sort_by_key(|r| r.id.to_string());
+            recipes
+        })
+    }
+
+    // Results with a chance are rolled with `rng`
+    pub fn run(&self, inventory: &mut Inventory, rng: &mut RuzRng) -> io::Result<()> {
+        let mut state = CraftingState::default();
+        fullscreen(|terminal| {
+            loop {
+                let recipes = self.recipes();
+                state.cursor = state.cursor.min(recipes.len().saturating_sub(1));
+                terminal.draw(|f| self.draw(f, &recipes, state.cursor, inventory, &state.status))?;
+
+                if let Event::Key(key) = event::read()?
+                    && self.handle_key(&mut state, &key, &recipes, inventory, rng)
+                {
+                    return Ok(());
+                }
+            }
+        })
+    }
+
+    // True if the key closes the screen
+    fn handle_key(
+        &self,
+        state: &mut CraftingState,
+        key: &KeyEvent,
+        recipes: &[Recipe],
+        inventory: &mut Inventory,
+        rng: &mut RuzRng,
+    ) -> bool {
+        match (key.code, key.modifiers) {
+            (KeyCode::Char('q'), _) | (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => return true,
+            (KeyCode::Up, _) => state.cursor = state.cursor.saturating_sub(1),
+            (KeyCode::Down, _) => state.cursor = (state.cursor + 1).min(recipes.len().saturating_sub(1)),
+            (KeyCode::Enter, _) => {
+                if let Some(recipe) = recipes.get(state.cursor) {
+                    state.status = if self.is_locked(recipe) {
+                        format!("{} is not unlocked yet", recipe.id)
+                    } else {
+                        match with_registry(|registry| inventory.craft(recipe, registry, rng)) {
+                            Ok(results) => {
+                                let results: Vec<String> = results.iter().map(|c| c.to_string()).collect();
+                                format!("Crafted {}: {}", recipe.id, results.join(", "))
+                            }
+                            Err(e) => e.to_string(),
+                        }
+                    };
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn draw(&self, f: &mut ratatui::Frame, recipes: &[Recipe], cursor: usize, inventory: &Inventory, status: &str) {
+        let item_style = Style::default().fg(theme_color(&self.theme.suggestion_color, Color::White));
+        let prompt_style = Style::default().fg(theme_color(&self.theme.prompt_color, Color::Cyan));
+        let hint_style = Style::default().fg(theme_color(&self.theme.hint_color, Color::Gray));
+        let missing_style = Style::default().fg(theme_color(&ColorRef::Named("default", "dark_gray"), Color::DarkGray));
+        let selected_style = self.theme.selected_style();
+
+        let vertical = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(1), Constraint::Length(1)])
+            .split(f.area());
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(vertical[0]);
+
+        let items: Vec<ListItem> = recipes
+            .iter()
+            .map(|recipe| {
+                if self.is_locked(recipe) {
+                    return ListItem::new(format!("{} (locked)", recipe.id)).style(missing_style);
+                }
+                let style = if inventory.can_craft(recipe) { item_style } else { missing_style };
+                ListItem::new(recipe.id.to_string()).style(style)
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Recipes"))
+            .highlight_style(selected_style);
+        let mut state = ListState::default();
+        state.select((!recipes.is_empty()).then_some(cursor));
+        f.render_stateful_widget(list, panes[0], &mut state);
+
+        let mut lines = vec![];
+        if let Some(recipe) = recipes.get(cursor) {
+            lines.push(Line::styled("Ingredients", prompt_style));
+            for (id, needed, owned) in inventory.ingredient_counts(recipe) {
+                let style = if owned >= needed { item_style } else { missing_style };
+                lines.push(Line::styled(format!("  {}  {}/{}", id, owned, needed), style));
+            }
+            lines.push(Line::raw(""));
+            lines.push(Line::styled("Results", prompt_style));
+            for result in &recipe.results {
+                lines.push(Line::styled(format!("  {}", result), item_style));
+            }
+            if let Some(condition) = recipe.unlock_condition.as_ref().filter(|_| self.is_locked(recipe)) {
+                lines.push(Line::raw(""));
+                lines.push(Line::styled("Unlocked by", prompt_style));
+                lines.push(Line::styled(format!("  {}", condition), missing_style));
+            }
+        }
+        f.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("Details")),
+            panes[1],
+        );
+
+        f.render_widget(Paragraph::new(status.to_string()).style(prompt_style), vertical[1]);
+        f.render_widget(Paragraph::new("↑/↓ move · enter craft · q close").style(hint_style), vertical[2]);
+    }
+}
+
+impl Default for CraftingScreen<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Default)]
+struct CraftingState {
+    cursor: usize,
+    status: String,
+}
+
+// CraftingScreen as a scene, crafting from the inventory of the
+// SceneContext's player with the player's recipe book
+#[derive(Default)]
+pub struct CraftingScene {
+    hide_locked: bool,
+    state: CraftingState,
+}
+
+impl CraftingScene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn hide_locked(mut self, hide: bool) -> Self {
+        self.hide_locked = hide;
+        self
+    }
+}
+
+impl<D> Scene<D> for CraftingScene {
+    fn name(&self) -> &str {
+        "crafting"
+    }
+
+    fn handle_event(&mut self, event: &Event, context: &mut SceneContext<D>) -> Transition<D> {
+        let Event::Key(key) = event else {
+            return Transition::None;
+        };
+        let screen = CraftingScreen {
+            theme: context.theme.clone(),
+            recipe_book: Some(&context.player.recipes),
+            hide_locked: self.hide_locked,
+        };
+        let recipes = screen.recipes();
+        self.state.cursor = self.state.cursor.min(recipes.len().saturating_sub(1));
+        if screen.handle_key(&mut self.state, key, &recipes, &mut context.player.inventory, &mut context.rng) {
+            return Transition::Pop;
+        }
+        Transition::None
+    }
+
+    fn render(&self, frame: &mut ratatui::Frame, context: &SceneContext<D>) {
+        let screen = CraftingScreen {
+            theme: context.theme.clone(),
+            recipe_book: Some(&context.player.recipes),
+            hide_locked: self.hide_locked,
+        };
+        let recipes = screen.recipes();
+        let cursor = self.state.cursor.min(recipes.len().saturating_sub(1));
+        screen.draw(frame, &recipes, cursor, &context.player.inventory, &self.state.status);
+    }
+}
+
+// ---------------
+// DIALOGUE PLAYER
+// ---------------
+
+// Plays a dialogue on the full screen: speaker and translated text of the
+// current node above its numbered choices. Choices whose condition doesn't
+// hold are left out, consequences are applied to the player when chosen.
+//
+// Keys: ↑/↓ move, Enter choose, 1-9 choose directly, q/Esc leave
+pub struct DialogueScreen<'a> {
+    theme: ColorTheme,
+    translator: &'a Translator,
+}
+
+impl<'a> DialogueScreen<'a> {
+    pub fn new(translator: &'a Translator) -> Self {
+        DialogueScreen { theme: current_theme(), translator }
+    }
+
+    pub fn with_theme(mut self, theme: ColorTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    // True if the dialogue was played to its end, false if it was left early
+    pub fn run(&self, dialogue: &Dialogue, player: &mut PlayerProfile, rng: &mut RuzRng) -> io::Result<bool> {
+        let mut conversation = Conversation::new(dialogue);
+        let mut cursor = 0;
+        let mut status = String::new();
+        // Conditions are tested once per node, so random ones don't change while choosing
+        let offered = |conversation: &Conversation, player: &PlayerProfile, rng: &mut RuzRng| {
+            with_registry(|registry| {
+                let context = ConditionContext::new().with_registry(registry).with_player(player);
+                conversation.choices(&context, rng).into_iter().map(|(i, _)| i).collect::<Vec<usize>>()
+            })
+        };
+        let mut choices = offered(&conversation, player, rng);
+        fullscreen(|terminal| {
+            loop {
+                let Some(node) = conversation.node() else {
+                    return Ok(true);
+                };
+                terminal.draw(|f| self.draw(f, node, &choices, cursor, &status))?;
+
+                let Event::Key(key) = event::read()? else {
+                    continue;
+                };
+                let chosen = match (key.code, key.modifiers) {
+                    (KeyCode::Char('q'), _) | (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                        return Ok(false);
+                    }
+                    (KeyCode::Up, _) => {
+                        cursor = cursor.saturating_sub(1);
+                        None
+                    }
+                    (KeyCode::Down, _) => {
+                        cursor = (cursor + 1).min(choices.len().saturating_sub(1));
+                        None
+                    }
+                    // A node without (offered) choices ends the dialogue
+                    (KeyCode::Enter, _) if choices.is_empty() => return Ok(conversation.is_finished()),
+                    (KeyCode::Enter, _) => choices.get(cursor).copied(),
+                    (KeyCode::Char(c), _) => c.to_digit(10).and_then(|d| choices.get((d as usize).checked_sub(1)?)).copied(),
+                    _ => None,
+                };
+                if let Some(index) = chosen {
+                    match with_registry(|registry| conversation.choose(index, player, registry)) {
+                        Ok(()) => {
+                            cursor = 0;
+                            status.clear();
+                            choices = offered(&conversation, player, rng);
+                        }
+                        Err(e) => status = e.to_string(),
+                    }
+                }
+            }
+        })
+    }
+
+    fn draw(&self, f: &mut ratatui::Frame, node: &DialogueNode, choices: &[usize], cursor: usize, status: &str) {
+        let speaker_style = Style::default().fg(theme_color(&self.theme.prompt_color, Color::Cyan)).add_modifier(Modifier::BOLD);
+        let text_style = Style::default().fg(theme_color(&self.theme.input_color, Color::White));
+        let choice_style = Style::default().fg(theme_color(&self.theme.suggestion_color, Color::White));
+        let hint_style = Style::default().fg(theme_color(&self.theme.hint_color, Color::Gray));
+        let error_style = Style::default().fg(theme_color(&self.theme.error_color, Color::Red));
+
+        let vertical = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),
+                Constraint::Length(choices.len() as u16 + 2),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .split(f.area());
+
+        let mut lines = vec![];
+        if let Some(speaker) = &node.speaker {
+            lines.push(Line::styled(self.translator.translate(speaker, None), speaker_style));
+        }
+        lines.extend(self.translator.translate(&node.text, None).lines().map(|l| Line::styled(l.to_string(), text_style)));
+        f.render_widget(
+            Paragraph::new(lines).wrap(Wrap { trim: false }).block(Block::default().borders(Borders::ALL)),
+            vertical[0],
+        );
+
+        let items: Vec<ListItem> = choices
+            .iter()
+            .enumerate()
+            .map(|(i, &index)| {
+                let text = self.translator.translate(&node.choices[index].text, None);
+                ListItem::new(format!("{}. {}", i + 1, text)).style(choice_style)
+            })
+            .collect();
+        let list = List::new(items).block(Block::default().borders(Borders::ALL)).highlight_style(self.theme.selected_style());
+        let mut state = ListState::default();
+        state.select((!choices.is_empty()).then_some(cursor));
+        f.render_stateful_widget(list, vertical[1], &mut state);
+
+        f.render_widget(Paragraph::new(status.to_string()).style(error_style), vertical[2]);
+        let keys = if choices.is_empty() { "enter close" } else { "↑/↓ move · enter/1-9 choose · q leave" };
+        f.render_widget(Paragraph::new(keys).style(hint_style), vertical[3]);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::color::{ColorError, ColorRef, GradientDirection, StyledText, colored_styled, gradient_styled, visible_length};
+
+// -----
+// BOXES
+// -----
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoxStyle {
+    Rounded,
+    Square,
+    Double,
+    Heavy,
+    Ascii,
+}
+
+impl BoxStyle {
+    // Top left, top right, bottom left, bottom right, horizontal, vertical
+    fn chars(&self) -> [char; 6] {
+        match self {
+            BoxStyle::Rounded => ['╭', '╮', '╰', '╯', '─', '│'],
+            BoxStyle::Square => ['┌', '┐', '└', '┘', '─', '│'],
+            BoxStyle::Double => ['╔', '╗', '╚', '╝', '═', '║'],
+            BoxStyle::Heavy => ['┏', '┓', '┗', '┛', '━', '┃'],
+            BoxStyle::Ascii => ['+', '+', '+', '+', '-', '|'],
+        }
+    }
+}
+
+// Draws a border around (possibly multi-line, colored) text
+pub fn boxed(text: &str, style: BoxStyle) -> String {
+    let [tl, tr, bl, br, h, v] = style.chars();
+    let lines: Vec<&str> = text.lines().collect();
+    let width = lines.iter().map(|l| visible_length(l)).max().unwrap_or(0);
+    let horizontal: String = std::iter::repeat_n(h, width + 2).collect();
+
+    let mut output = format!("{tl}{horizontal}{tr}\n");
+    for line in lines {
+        let fill = " ".repeat(width - visible_length(line));
+        output += &format!("{v} {line}{fill} {v}\n");
+    }
+    output += &format!("{bl}{horizontal}{br}");
+    output
+}
+
+// -------
+// BANNERS
+// -------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BannerFont {
+    Block, // Filled with '█'
+    Ascii, // Filled with '#'
+}
+
+impl BannerFont {
+    fn fill(&self) -> char {
+        match self {
+            BannerFont::Block => '█',
+            BannerFont::Ascii => '#',
+        }
+    }
+}
+
+const GLYPH_HEIGHT: usize = 5;
+
+// Rows separated by '/', '#' is filled
+fn glyph(c: char) -> &'static str {
+    match c {
+        'A' => ".###./#...#/#####/#...#/#...#",
+        'B' => "####./#...#/####./#...#/####.",
+        'C' => ".####/#..../#..../#..../.####",
+        'D' => "####./#...#/#...#/#...#/####.",
+        'E' => "#####/#..../####./#..../#####",
+        'F' => "#####/#..../####./#..../#....",
+        'G' => ".####/#..../#..##/#...#/.####",
+        'H' => "#...#/#...#/#####/#...#/#...#",
+        'I' => "#####/..#../..#../..#../#####",
+        'J' => "..###/...#./...#./#..#./.##..",
+        'K' => "#...#/#..#./###../#..#./#...#",
+        'L' => "#..../#..../#..../#..../#####",
+        'M' => "#...#/##.##/#.#.#/#...#/#...#",
+        'N' => "#...#/##..#/#.#.#/#..##/#...#",
+        'O' => ".###./#...#/#...#/#...#/.###.",
+        'P' => "####./#...#/####./#..../#....",
+        'Q' => ".###./#...#/#.#.#/#..#./.##.#",
+        'R' => "####./#...#/####./#..#./#...#",
+        'S' => ".####/#..../.###./....#/####.",
+        'T' => "#####/..#../..#../..#../..#..",
+        'U' => "#...#/#...#/#...#/#...#/.###.",
+        'V' => "#...#/#...#/#...#/.#.#./..#..",
+        'W' => "#...#/#...#/#.#.#/##.##/#...#",
+        'X' => "#...#/.#.#./..#../.#.#./#...#",
+        'Y' => "#...#/.#.#./..#../..#../..#..",
+        'Z' => "#####/...#./..#../.#.../#####",
+        '0' => ".###./#..##/#.#.#/##..#/.###.",
+        '1' => "..#../.##../..#../..#../.###.",
+        '2' => ".###./#...#/..##./.#.../#####",
+        '3' => "####./....#/.###./....#/####.",
+        '4' => "#...#/#...#/#####/....#/....#",
+        '5' => "#####/#..../####./....#/####.",
+        '6' => ".###./#..../####./#...#/.###.",
+        '7' => "#####/....#/...#./..#../..#..",
+        '8' => ".###./#...#/.###./#...#/.###.",
+        '9' => ".###./#...#/.####/....#/.###.",
+        '!' => "#/#/#/./#",
+        '.' => "././././#",
+        ':' => "./#/./#/.",
+        '-' => ".../.../###/.../...",
+        ' ' => ".../.../.../.../...",
+        _ => ".###./#...#/..##./...../..#..", // '?'
+    }
+}
+
+// Large title text in a 5 rows high font, e.g. for menus. One color colors the
+// whole banner, two or more form a horizontal gradient.
+pub fn banner(text: &str, font: BannerFont, gradient: &[ColorRef]) -> Result<String, ColorError> {
+    banner_styled(text, font, gradient).map(|b| b.to_ansi())
+}
+
+// banner() as StyledText, e.g. for drawing it with ratatui
+pub fn banner_styled(text: &str, font: BannerFont, gradient: &[ColorRef]) -> Result<StyledText, ColorError> {
+    let fill = font.fill().to_string();
+    let mut rows = vec![String::new(); GLYPH_HEIGHT];
+    for (i, c) in text.to_uppercase().chars().enumerate() {
+        for (row, glyph_row) in rows.iter_mut().zip(glyph(c).split('/')) {
+            if i > 0 {
+                row.push(' ');
+            }
+            row.push_str(&glyph_row.replace('.', " ").replace('#', &fill));
+        }
+    }
+    let banner = rows.iter().map(|r| r.trim_end()).collect::<Vec<_>>().join("\n");
+
+    match gradient {
+        [] => Ok(StyledText::plain(&banner)),
+        [color] => colored_styled(&banner, color),
+        colors => gradient_styled(&banner, colors, GradientDirection::Horizontal, Some(true)),
+    }
+}
@@ -0,0 +1,524 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::conditions::{ConditionContext, LootFilter};
+use crate::error::ParseError;
+use crate::inventory::Slot;
+use crate::random::RuzRng;
+use crate::registry::{Block, ID, LootStack, Registry, Structure, Tool};
+use crate::stats::{Attribute, Stats};
+use crate::structure::StructureError;
+
+pub const CHUNK_SIZE: i32 = 16;
+
+// ------
+// ERRORS
+// ------
+
+#[derive(Debug)]
+pub enum WorldError {
+    UnknownBlock(ID),
+    UnknownStructure(ID),
+    InvalidStructure(ID, StructureError), // See Structure::validate
+    Parse(ParseError),
+}
+
+impl Display for WorldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            WorldError::UnknownBlock(block) => write!(f, "Block with ID {} does not exist", block),
+            WorldError::UnknownStructure(structure) => write!(f, "Structure with ID {} does not exist", structure),
+            WorldError::InvalidStructure(structure, e) => write!(f, "Structure {} is invalid: {}", structure, e),
+            WorldError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for WorldError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WorldError::InvalidStructure(_, e) => Some(e),
+            WorldError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// ---------
+// POSITIONS
+// ---------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32, // Always 0 in 2-D worlds
+}
+
+impl BlockPos {
+    pub fn new(x: i32, y: i32) -> Self {
+        BlockPos { x, y, z: 0 }
+    }
+
+    pub fn new_3d(x: i32, y: i32, z: i32) -> Self {
+        BlockPos { x, y, z }
+    }
+
+    pub fn offset(&self, dx: i32, dy: i32, dz: i32) -> Self {
+        BlockPos::new_3d(self.x + dx, self.y + dy, self.z + dz)
+    }
+
+    pub fn chunk(&self) -> ChunkPos {
+        ChunkPos {
+            x: self.x.div_euclid(CHUNK_SIZE),
+            y: self.y.div_euclid(CHUNK_SIZE),
+            z: self.z,
+        }
+    }
+
+    fn index_in_chunk(&self) -> usize {
+        (self.y.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + self.x.rem_euclid(CHUNK_SIZE)) as usize
+    }
+}
+
+impl Display for BlockPos {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.x, self.y, self.z)
+    }
+}
+
+// Chunks are CHUNK_SIZE x CHUNK_SIZE columns of one z layer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl ChunkPos {
+    // Position with the lowest x and y in the chunk
+    pub fn origin(&self) -> BlockPos {
+        BlockPos::new_3d(self.x * CHUNK_SIZE, self.y * CHUNK_SIZE, self.z)
+    }
+
+    // Every position in the chunk, row by row
+    pub fn positions(&self) -> impl Iterator<Item = BlockPos> {
+        let origin = self.origin();
+        (0..CHUNK_SIZE).flat_map(move |dy| (0..CHUNK_SIZE).map(move |dx| origin.offset(dx, dy, 0)))
+    }
+
+    pub fn contains(&self, pos: BlockPos) -> bool {
+        pos.chunk() == *self
+    }
+}
+
+// ------------
+// BLOCK STATES
+// ------------
+
+// A placed block: the registered Block and its properties, e.g. facing=north
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockState {
+    pub block: ID,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl BlockState {
+    pub fn new(block: ID) -> Self {
+        BlockState { block, properties: BTreeMap::new() }
+    }
+
+    pub fn with_property(mut self, key: &str, value: &str) -> Self {
+        self.properties.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+}
+
+// "ruztex:furnace facing=north lit=true"
+impl Display for BlockState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.block)?;
+        for (key, value) in &self.properties {
+            write!(f, " {}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BlockState {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.split_whitespace();
+        let block = parts.next().ok_or("missing block")?;
+        let mut state = BlockState::new(ID::parse(block).map_err(|e| format!("invalid block '{}': {}", block, e))?);
+        for property in parts {
+            let (key, value) = property.split_once('=').ok_or_else(|| format!("invalid property '{}'", property))?;
+            state = state.with_property(key, value);
+        }
+        Ok(state)
+    }
+}
+
+// -----
+// WORLD
+// -----
+
+struct Chunk {
+    blocks: Vec<Option<BlockState>>,
+    count: usize, // Non-empty blocks, empty chunks are dropped
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Chunk {
+            blocks: vec![None; (CHUNK_SIZE * CHUNK_SIZE) as usize],
+            count: 0,
+        }
+    }
+}
+
+// Chunked grid of block states. 2-D worlds have 4 neighbors per position,
+// 3-D worlds 6.
+pub struct World {
+    chunks: HashMap<ChunkPos, Chunk>,
+    biomes: HashMap<(i32, i32), ID>, // Per column, see column
+    three_d: bool,
+}
+
+impl World {
+    pub fn new() -> Self {
+        World { chunks: HashMap::new(), biomes: HashMap::new(), three_d: false }
+    }
+
+    pub fn new_3d() -> Self {
+        World { chunks: HashMap::new(), biomes: HashMap::new(), three_d: true }
+    }
+
+    pub fn is_3d(&self) -> bool {
+        self.three_d
+    }
+
+    pub fn get(&self, pos: BlockPos) -> Option<&BlockState> {
+        self.chunks.get(&pos.chunk())?.blocks[pos.index_in_chunk()].as_ref()
+    }
+
+    // Sets the block at `pos` and returns the previous one
+    pub fn set(&mut self, pos: BlockPos, state: BlockState) -> Option<BlockState> {
+        let chunk = self.chunks.entry(pos.chunk()).or_insert_with(Chunk::new);
+        let previous = chunk.blocks[pos.index_in_chunk()].replace(state);
+        if previous.is_none() {
+            chunk.count += 1;
+        }
+        previous
+    }
+
+    // Like set, but only for blocks registered in `registry`
+    pub fn place(&mut self, pos: BlockPos, state: BlockState, registry: &Registry) -> Result<Option<BlockState>, WorldError> {
+        if !registry.blocks.contains_key(&state.block) {
+            return Err(WorldError::UnknownBlock(state.block));
+        }
+        Ok(self.set(pos, state))
+    }
+
+    pub fn remove(&mut self, pos: BlockPos) -> Option<BlockState> {
+        let chunk_pos = pos.chunk();
+        let chunk = self.chunks.get_mut(&chunk_pos)?;
+        let previous = chunk.blocks[pos.index_in_chunk()].take();
+        if previous.is_some() {
+            chunk.count -= 1;
+            if chunk.count == 0 {
+                self.chunks.remove(&chunk_pos);
+            }
+        }
+        previous
+    }
+
+    // Sets every position in the box between `from` and `to` (inclusive) and
+    // returns the number of positions
+    pub fn fill(&mut self, from: BlockPos, to: BlockPos, state: &BlockState) -> usize {
+        let mut count = 0;
+        for z in from.z.min(to.z)..=from.z.max(to.z) {
+            for y in from.y.min(to.y)..=from.y.max(to.y) {
+                for x in from.x.min(to.x)..=from.x.max(to.x) {
+                    self.set(BlockPos::new_3d(x, y, z), state.clone());
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    // Sets the structure's blocks with its bottom layer's top left corner at
+    // `pos` and returns the number of positions changed. 2-D worlds only get
+    // the bottom layer.
+    pub fn paste_structure(&mut self, pos: BlockPos, structure: &Structure) -> usize {
+        let mut count = 0;
+        for ([x, y, z], block) in structure.blocks() {
+            if z > 0 && !self.three_d {
+                continue;
+            }
+            let target = pos.offset(x as i32, y as i32, z as i32);
+            match block {
+                Some(state) => self.set(target, state.clone()),
+                None => self.remove(target),
+            };
+            count += 1;
+        }
+        count
+    }
+
+    // Like paste_structure for a registered structure, which must be valid
+    // (see Structure::validate)
+    pub fn place_structure(&mut self, pos: BlockPos, id: &ID, registry: &Registry) -> Result<usize, WorldError> {
+        let structure = registry.structures.get(id).ok_or(WorldError::UnknownStructure(*id))?;
+        structure.validate(registry).map_err(|e| WorldError::InvalidStructure(*id, e))?;
+        Ok(self.paste_structure(pos, structure))
+    }
+
+    // Columns run along z in 3-D worlds and along y in 2-D worlds
+    fn column(&self, pos: BlockPos) -> (i32, i32) {
+        if self.three_d { (pos.x, pos.y) } else { (pos.x, 0) }
+    }
+
+    // Biome of the column at `pos`, usually set by world generation
+    pub fn biome(&self, pos: BlockPos) -> Option<ID> {
+        self.biomes.get(&self.column(pos)).copied()
+    }
+
+    pub fn set_biome(&mut self, pos: BlockPos, biome: ID) {
+        let column = self.column(pos);
+        self.biomes.insert(column, biome);
+    }
+
+    pub fn neighbor_positions(&self, pos: BlockPos) -> Vec<BlockPos> {
+        let mut positions = vec![pos.offset(0, -1, 0), pos.offset(1, 0, 0), pos.offset(0, 1, 0), pos.offset(-1, 0, 0)];
+        if self.three_d {
+            positions.push(pos.offset(0, 0, 1));
+            positions.push(pos.offset(0, 0, -1));
+        }
+        positions
+    }
+
+    // Neighboring positions with their blocks, if any
+    pub fn neighbors(&self, pos: BlockPos) -> Vec<(BlockPos, Option<&BlockState>)> {
+        self.neighbor_positions(pos).into_iter().map(|p| (p, self.get(p))).collect()
+    }
+
+    // All placed blocks, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (BlockPos, &BlockState)> {
+        self.chunks.iter().flat_map(|(chunk_pos, chunk)| {
+            chunk.blocks.iter().enumerate().filter_map(move |(i, state)| {
+                let pos = BlockPos::new_3d(
+                    chunk_pos.x * CHUNK_SIZE + i as i32 % CHUNK_SIZE,
+                    chunk_pos.y * CHUNK_SIZE + i as i32 / CHUNK_SIZE,
+                    chunk_pos.z,
+                );
+                state.as_ref().map(|state| (pos, state))
+            })
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.values().map(|c| c.count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    // "world 2d" followed by one "x y z block key=value..." line per block,
+    // sorted by position, then one "biome x y biome" line per column
+    pub fn serialize(&self) -> String {
+        let mut blocks: Vec<(BlockPos, &BlockState)> = self.iter().collect();
+        blocks.sort_by_key(|(pos, _)| (pos.z, pos.y, pos.x));
+        let mut output = format!("world {}\n", if self.three_d { "3d" } else { "2d" });
+        for (pos, state) in blocks {
+            output += &format!("{} {}\n", pos, state);
+        }
+        let mut biomes: Vec<(&(i32, i32), &ID)> = self.biomes.iter().collect();
+        biomes.sort_by_key(|((x, y), _)| (*y, *x));
+        for ((x, y), biome) in biomes {
+            output += &format!("biome {} {} {}\n", x, y, biome);
+        }
+        output
+    }
+
+    pub fn deserialize(content: &str) -> Result<World, WorldError> {
+        let mut lines = content.lines().enumerate();
+        let mut world = match lines.next().map(|(_, l)| l.trim()) {
+            Some("world 2d") => World::new(),
+            Some("world 3d") => World::new_3d(),
+            _ => return Err(WorldError::Parse(ParseError::MissingHeader("world 2d|3d"))),
+        };
+        for (i, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let error = || WorldError::Parse(ParseError::InvalidLine { line: i + 1, text: line.to_string() });
+            let mut parts = line.split_whitespace().peekable();
+            if parts.next_if_eq(&"biome").is_some() {
+                let mut coordinate = || parts.next().and_then(|p| p.parse::<i32>().ok()).ok_or_else(error);
+                let pos = BlockPos::new_3d(coordinate()?, coordinate()?, 0);
+                let biome = parts.next().and_then(|p| ID::parse(p).ok()).ok_or_else(error)?;
+                world.set_biome(pos, biome);
+                continue;
+            }
+            let mut coordinate = || parts.next().and_then(|p| p.parse::<i32>().ok()).ok_or_else(error);
+            let pos = BlockPos::new_3d(coordinate()?, coordinate()?, coordinate()?);
+            let state = parts.collect::<Vec<_>>().join(" ").parse::<BlockState>().map_err(|_| error())?;
+            world.set(pos, state);
+        }
+        Ok(world)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.serialize())
+    }
+
+    pub fn load(path: &Path) -> io::Result<World> {
+        let content = fs::read_to_string(path)?;
+        World::deserialize(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+// --------
+// BREAKING
+// --------
+
+// Block tags requiring a minimum tool level, matched by tag name in any namespace
+pub const TOOL_LEVEL_TAGS: [(&str, u32); 3] = [("needs_stone_tool", 1), ("needs_iron_tool", 2), ("needs_diamond_tool", 3)];
+
+// Seconds per hardness point with a suitable tool, and without one
+const HARVEST_TIME_FACTOR: f32 = 1.5;
+const NO_HARVEST_TIME_FACTOR: f32 = 5.0;
+
+#[derive(Clone, Debug)]
+pub enum BreakResult {
+    Empty,       // No block at the position
+    Unbreakable, // Negative hardness, e.g. bedrock
+    Broken {
+        block: BlockState,
+        time: f32,       // Seconds it takes to break the block
+        harvested: bool, // Tool level was high enough, drops are only rolled then
+        drops: Vec<LootStack>,
+        tool_broken: bool, // The tool's durability reached 0, see Inventory::remove_broken
+    },
+}
+
+// Minimum tool level for a block, 0 if it has no TOOL_LEVEL_TAGS tag
+pub fn required_tool_level(block: &Block) -> u32 {
+    block
+        .tags
+        .iter()
+        .filter_map(|tag| TOOL_LEVEL_TAGS.iter().find(|(name, _)| tag.name() == *name).map(|(_, level)| *level))
+        .max()
+        .unwrap_or(0)
+}
+
+// Seconds to break a block: hardness scaled by whether the tool can harvest
+// it, divided by the tool's speed (1.0 by hand)
+pub fn break_time(block: &Block, tool: Option<&Tool>) -> f32 {
+    break_time_at(block, tool.map_or(0, |t| t.level), tool.map_or(1.0, |t| t.speed))
+}
+
+// Like break_time with a tool level and mining speed, e.g. Attribute::MINING_SPEED
+pub fn break_time_at(block: &Block, level: u32, speed: f32) -> f32 {
+    let factor = if level >= required_tool_level(block) { HARVEST_TIME_FACTOR } else { NO_HARVEST_TIME_FACTOR };
+    block.hardness * factor / speed.max(0.1)
+}
+
+impl World {
+    // Removes the block at `pos` and rolls its loot table if the tool level is
+    // high enough. A held tool takes one point of damage, other held items
+    // count as breaking by hand. Enchantments on the held slot speed up
+    // breaking and add loot rolls.
+    pub fn break_block(&mut self, pos: BlockPos, held: Option<&mut Slot>, registry: &Registry, rng: &mut RuzRng) -> BreakResult {
+        let stats = Stats::new().with_equipment(held.as_deref(), registry);
+        self.break_block_with(pos, held, &stats, registry, rng)
+    }
+
+    // Like break_block with the breaking entity's Stats: MINING_SPEED sets the
+    // time, LUCK adds loot rolls. The stats should include the held slot,
+    // see Stats::set_equipment.
+    pub fn break_block_with(
+        &mut self,
+        pos: BlockPos,
+        held: Option<&mut Slot>,
+        stats: &Stats,
+        registry: &Registry,
+        rng: &mut RuzRng,
+    ) -> BreakResult {
+        self.break_block_filtered(pos, held, stats, registry, rng, None)
+    }
+
+    // Like break_block_with, the block's loot table only rolls if
+    // `loot_filter` allows it, e.g. a ScriptHost with loot conditions
+    pub fn break_block_filtered(
+        &mut self,
+        pos: BlockPos,
+        held: Option<&mut Slot>,
+        stats: &Stats,
+        registry: &Registry,
+        rng: &mut RuzRng,
+        loot_filter: Option<&dyn LootFilter>,
+    ) -> BreakResult {
+        let Some(state) = self.get(pos) else {
+            return BreakResult::Empty;
+        };
+        let block = registry.blocks.get(&state.block);
+        if block.is_some_and(|b| b.hardness < 0.0) {
+            return BreakResult::Unbreakable;
+        }
+        let state = self.remove(pos).unwrap();
+
+        // Unregistered blocks break instantly and drop nothing
+        let Some(block) = block else {
+            return BreakResult::Broken { block: state, time: 0.0, harvested: false, drops: vec![], tool_broken: false };
+        };
+        let tool = held.as_ref().and_then(|slot| registry.tools.get(&slot.item.id));
+        let level = tool.map_or(0, |t| t.level);
+        let time = break_time_at(block, level, stats.get(Attribute::MINING_SPEED));
+        let harvested = level >= required_tool_level(block);
+        let mut drops = vec![];
+        if harvested && let Some(loot_table) = &block.loot_table {
+            let mut context = ConditionContext::new().with_registry(registry).with_block(block).with_position(pos);
+            if let Some(biome) = self.biome(pos) {
+                context = context.with_biome(biome);
+            }
+            if let Some(tool) = tool {
+                context = context.with_tool(tool);
+            }
+            if let Some(slot) = held.as_deref() {
+                context = context.with_held(slot);
+            }
+            if let Some(filter) = loot_filter {
+                context = context.with_loot_filter(filter);
+            }
+            let rolls = 1 + stats.extra_loot_rolls(rng);
+            for _ in 0..rolls {
+                drops.extend(loot_table.roll_with(&context, rng));
+            }
+        }
+        let tool_broken = match held {
+            Some(slot) if tool.is_some() => slot.damage(1, rng),
+            _ => false,
+        };
+        BreakResult::Broken { block: state, time, harvested, drops, tool_broken }
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,505 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::random::RuzRng;
+use crate::registry::{Biome, ID, PatternKey, Registry, RegistryKey, Structure};
+use crate::world::{BlockPos, BlockState, CHUNK_SIZE, ChunkPos, World};
+
+// Chunks generated on first access by a pluggable ChunkGenerator. Terrain
+// height goes up along z in 3-D worlds; 2-D worlds are a side view with y
+// growing downward, like the Minimap's rows, so the surface of a column at
+// height h lies at y = -h. NoiseTerrain picks a registered Biome per column.
+//
+//   let terrain = NoiseTerrain::new(Layers::new(BlockState::new(id!("game:stone"))))
+//       .with_ore(OreVein::new(id!("ruz:ores"), 6, 4).with_replace(id!("game:stone_like")))
+//       .with_feature(id!("game:clay"), OreVein::new(id!("game:clay_blocks"), 10, 2))
+//       .with_structures(StructurePlacement::new()); // Every registered structure
+//   let mut world = GeneratedWorld::new(World::new(), terrain, &mut rng);
+//   let block = world.get(BlockPos::new(3, 10), &registry); // Generates the chunk
+//   world.load(pos, &registry).break_block(pos, held, &registry, &mut rng);
+
+// ---------
+// GENERATOR
+// ---------
+
+// What a generator knows about the chunk it fills
+pub struct ChunkContext<'a> {
+    pub chunk: ChunkPos,
+    pub seed: u64, // The world's seed, the same for every chunk
+    pub registry: &'a Registry,
+}
+
+impl ChunkContext<'_> {
+    // Deterministic generator for this chunk of this world
+    pub fn rng(&self, system: &str) -> RuzRng {
+        let mut seed = self.seed ^ (self.chunk.x as u64).wrapping_mul(0x9e3779b97f4a7c15);
+        seed ^= (self.chunk.y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f);
+        seed ^= (self.chunk.z as u64).wrapping_mul(0x165667b19e3779f9);
+        RuzRng::new(seed).fork(system)
+    }
+}
+
+// Fills one chunk of the world. Only positions inside context.chunk should
+// be set, neighboring chunks may not exist yet. Closures with the same
+// arguments are generators too.
+pub trait ChunkGenerator {
+    fn generate(&self, world: &mut World, context: &ChunkContext);
+}
+
+impl<F: Fn(&mut World, &ChunkContext)> ChunkGenerator for F {
+    fn generate(&self, world: &mut World, context: &ChunkContext) {
+        self(world, context)
+    }
+}
+
+// -----
+// NOISE
+// -----
+
+fn lattice(seed: u64, x: i32, y: i32) -> f32 {
+    let mixed = seed ^ (x as u64).wrapping_mul(0x9e3779b97f4a7c15) ^ (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f);
+    RuzRng::new(mixed).next_f32()
+}
+
+// Smooth value noise in [0, 1), the same for the same seed and position
+pub fn noise(seed: u64, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (sx, sy) = (fx * fx * (3.0 - 2.0 * fx), fy * fy * (3.0 - 2.0 * fy));
+    let (x0, y0) = (x0 as i32, y0 as i32);
+    let top = lattice(seed, x0, y0) + (lattice(seed, x0 + 1, y0) - lattice(seed, x0, y0)) * sx;
+    let bottom = lattice(seed, x0, y0 + 1) + (lattice(seed, x0 + 1, y0 + 1) - lattice(seed, x0, y0 + 1)) * sx;
+    top + (bottom - top) * sy
+}
+
+// Octaves of noise, each with double the frequency and half the weight
+pub fn fractal_noise(seed: u64, x: f32, y: f32, octaves: u32) -> f32 {
+    let (mut total, mut weight, mut frequency, mut weights) = (0.0, 1.0, 1.0, 0.0);
+    for octave in 0..octaves.max(1) {
+        total += noise(seed.wrapping_add(octave as u64), x * frequency, y * frequency) * weight;
+        weights += weight;
+        weight *= 0.5;
+        frequency *= 2.0;
+    }
+    total / weights
+}
+
+// ------
+// LAYERS
+// ------
+
+// Blocks by depth below the surface: the layers from the top, then the
+// filler all the way down
+#[derive(Clone, Debug)]
+pub struct Layers {
+    pub layers: Vec<(BlockState, u32)>, // Block and thickness
+    pub filler: BlockState,
+}
+
+impl Layers {
+    pub fn new(filler: BlockState) -> Self {
+        Layers { layers: vec![], filler }
+    }
+
+    // The biome's surface block and layers on top of `filler`
+    pub fn of_biome(biome: &Biome, filler: &BlockState) -> Self {
+        let mut layers = Layers::new(filler.clone()).with_layer(BlockState::new(biome.surface), 1);
+        for (block, thickness) in &biome.layers {
+            layers = layers.with_layer(BlockState::new(*block), *thickness);
+        }
+        layers
+    }
+
+    pub fn with_layer(mut self, block: BlockState, thickness: u32) -> Self {
+        self.layers.push((block, thickness));
+        self
+    }
+
+    // Block at `depth` below the surface, 0 is the surface block
+    pub fn block_at(&self, depth: u32) -> &BlockState {
+        let mut top = 0;
+        for (block, thickness) in &self.layers {
+            top += thickness;
+            if depth < top {
+                return block;
+            }
+        }
+        &self.filler
+    }
+}
+
+// Same layers everywhere, the surface at `height`
+pub struct FlatGenerator {
+    pub layers: Layers,
+    pub height: i32,
+    pub structures: Option<StructurePlacement>,
+}
+
+impl FlatGenerator {
+    pub fn new(layers: Layers, height: i32) -> Self {
+        FlatGenerator { layers, height, structures: None }
+    }
+
+    pub fn with_structures(mut self, structures: StructurePlacement) -> Self {
+        self.structures = Some(structures);
+        self
+    }
+}
+
+impl ChunkGenerator for FlatGenerator {
+    fn generate(&self, world: &mut World, context: &ChunkContext) {
+        fill_columns(world, context.chunk, |_, _| (self.height, &self.layers, None));
+        if let Some(structures) = &self.structures {
+            structures.place(world, context, |_, _| self.height);
+        }
+    }
+}
+
+// Sets every position below the surface of its column. `column` gives the
+// surface height, layers and biome for x and y (y is 0 in 2-D worlds).
+fn fill_columns<'a>(world: &mut World, chunk: ChunkPos, column: impl Fn(i32, i32) -> (i32, &'a Layers, Option<ID>)) {
+    let three_d = world.is_3d();
+    for pos in chunk.positions() {
+        let (height, layers, biome) = column(pos.x, if three_d { pos.y } else { 0 });
+        if let Some(biome) = biome {
+            world.set_biome(pos, biome);
+        }
+        let depth = if three_d { height - pos.z } else { pos.y + height };
+        if depth >= 0 {
+            world.set(pos, layers.block_at(depth as u32).clone());
+        }
+    }
+}
+
+// -------
+// TERRAIN
+// -------
+
+// Hills from fractal noise: the surface lies within `amplitude` of
+// `height`. Each column gets the registered biome whose temperature is
+// closest to large-scale noise, with its surface and layers on top of the
+// filler of `layers` (which is used alone without registered biomes). Ore
+// veins are placed everywhere after the terrain, features only in chunks
+// whose center column has a biome listing them, structures last.
+pub struct NoiseTerrain {
+    pub layers: Layers,
+    pub ores: Vec<OreVein>,
+    pub features: HashMap<ID, Box<dyn ChunkGenerator>>,
+    pub structures: Option<StructurePlacement>,
+    pub height: i32,
+    pub amplitude: f32,
+    pub scale: f32, // Blocks per noise cell, larger gives wider hills
+    pub biome_scale: f32,
+    pub octaves: u32,
+}
+
+impl NoiseTerrain {
+    pub fn new(layers: Layers) -> Self {
+        NoiseTerrain {
+            layers,
+            ores: vec![],
+            features: HashMap::new(),
+            structures: None,
+            height: 0,
+            amplitude: 8.0,
+            scale: 32.0,
+            biome_scale: 128.0,
+            octaves: 3,
+        }
+    }
+
+    pub fn with_ore(mut self, ore: OreVein) -> Self {
+        self.ores.push(ore);
+        self
+    }
+
+    // A generator biomes can list in Biome::features
+    pub fn with_feature(mut self, id: ID, feature: impl ChunkGenerator + 'static) -> Self {
+        self.features.insert(id, Box::new(feature));
+        self
+    }
+
+    pub fn with_structures(mut self, structures: StructurePlacement) -> Self {
+        self.structures = Some(structures);
+        self
+    }
+
+    pub fn with_height(mut self, height: i32, amplitude: f32) -> Self {
+        self.height = height;
+        self.amplitude = amplitude.max(0.0);
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32, biome_scale: f32) -> Self {
+        self.scale = scale.max(1.0);
+        self.biome_scale = biome_scale.max(1.0);
+        self
+    }
+
+    pub fn with_octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    // Surface height of the column, `y` is ignored in 2-D worlds (pass 0)
+    pub fn height_at(&self, seed: u64, x: i32, y: i32) -> i32 {
+        let value = fractal_noise(seed, x as f32 / self.scale, y as f32 / self.scale, self.octaves);
+        self.height + ((value * 2.0 - 1.0) * self.amplitude).round() as i32
+    }
+
+    // 0.0 - 1.0, compared with Biome::temperature
+    pub fn temperature_at(&self, seed: u64, x: i32, y: i32) -> f32 {
+        noise(seed.wrapping_add(0x5eed), x as f32 / self.biome_scale, y as f32 / self.biome_scale)
+    }
+
+    // The registered biome closest to the column's temperature, ties go to
+    // the lower ID
+    pub fn biome_at<'a>(&self, seed: u64, x: i32, y: i32, registry: &'a Registry) -> Option<&'a Biome> {
+        let temperature = self.temperature_at(seed, x, y);
+        registry.biomes.values().min_by(|a, b| {
+            let (da, db) = ((a.temperature - temperature).abs(), (b.temperature - temperature).abs());
+            da.total_cmp(&db).then_with(|| a.id.to_string().cmp(&b.id.to_string()))
+        })
+    }
+}
+
+impl ChunkGenerator for NoiseTerrain {
+    fn generate(&self, world: &mut World, context: &ChunkContext) {
+        let layers: HashMap<ID, Layers> =
+            context.registry.biomes.values().map(|b| (b.id, Layers::of_biome(b, &self.layers.filler))).collect();
+        fill_columns(world, context.chunk, |x, y| {
+            let biome = self.biome_at(context.seed, x, y, context.registry);
+            let height = self.height_at(context.seed, x, y);
+            (height, biome.map_or(&self.layers, |b| &layers[&b.id]), biome.map(|b| b.id))
+        });
+        for ore in &self.ores {
+            ore.generate(world, context);
+        }
+        let center = context.chunk.origin().offset(CHUNK_SIZE / 2, CHUNK_SIZE / 2, 0);
+        let y = if world.is_3d() { center.y } else { 0 };
+        if let Some(biome) = self.biome_at(context.seed, center.x, y, context.registry) {
+            for feature in &biome.features {
+                match self.features.get(feature) {
+                    Some(generator) => generator.generate(world, context),
+                    None => log::warn!("Biome {} lists unknown feature {}", biome.id, feature),
+                }
+            }
+        }
+        if let Some(structures) = &self.structures {
+            structures.place(world, context, |x, y| self.height_at(context.seed, x, y));
+        }
+    }
+}
+
+// ----
+// ORES
+// ----
+
+// Random walks of `size` blocks, `per_chunk` of them per chunk. Each vein
+// is one block from the tag's block entries (e.g. #ruz:ores) and only
+// replaces blocks in the `replace` tag, or any block if None. Veins are cut
+// off at the chunk's border.
+#[derive(Clone, Debug)]
+pub struct OreVein {
+    pub tag: ID,
+    pub replace: Option<ID>,
+    pub size: u32,
+    pub per_chunk: u32,
+}
+
+impl OreVein {
+    pub fn new(tag: ID, size: u32, per_chunk: u32) -> Self {
+        OreVein { tag, replace: None, size, per_chunk }
+    }
+
+    pub fn with_replace(mut self, tag: ID) -> Self {
+        self.replace = Some(tag);
+        self
+    }
+
+    fn replaces(&self, state: Option<&BlockState>, registry: &Registry) -> bool {
+        match (state, self.replace) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(state), Some(tag)) => PatternKey::Tag(tag).matches(&state.block, registry),
+        }
+    }
+}
+
+impl ChunkGenerator for OreVein {
+    fn generate(&self, world: &mut World, context: &ChunkContext) {
+        let Some(ores) = context.registry.tags.get(&self.tag).map(|t| t.of_kind(RegistryKey::BLOCK)).filter(|o| !o.is_empty()) else {
+            return;
+        };
+        let mut rng = context.rng(&format!("ore {}", self.tag));
+        let origin = context.chunk.origin();
+        for _ in 0..self.per_chunk {
+            let ore = BlockState::new(ores[rng.range(0, ores.len() as u32 - 1) as usize]);
+            let max = CHUNK_SIZE as u32 - 1;
+            let mut pos = origin.offset(rng.range(0, max) as i32, rng.range(0, max) as i32, 0);
+            for _ in 0..self.size {
+                if context.chunk.contains(pos) && self.replaces(world.get(pos), context.registry) {
+                    world.set(pos, ore.clone());
+                }
+                pos = match rng.range(0, 3) {
+                    0 => pos.offset(1, 0, 0),
+                    1 => pos.offset(-1, 0, 0),
+                    2 => pos.offset(0, 1, 0),
+                    _ => pos.offset(0, -1, 0),
+                };
+            }
+        }
+    }
+}
+
+// ----------
+// STRUCTURES
+// ----------
+
+// Places registered structures (see registry::Structure) on the surface of
+// a terrain generator. Each structure rolls once per column of chunks with a
+// chance of 1 / rarity for a spot inside the chunk, so it isn't cut off by
+// neighboring chunks unless it's wider than one. It's placed resting on the
+// surface there if the spot's biome is one of its biomes, by the chunk that
+// holds the surface block.
+#[derive(Clone, Debug, Default)]
+pub struct StructurePlacement {
+    pub only: Vec<ID>, // Structures to place, every registered one if empty
+}
+
+impl StructurePlacement {
+    pub fn new() -> Self {
+        StructurePlacement::default()
+    }
+
+    pub fn with_structure(mut self, structure: ID) -> Self {
+        self.only.push(structure);
+        self
+    }
+
+    // `surface` gives a column's surface height, like NoiseTerrain::height_at
+    pub fn place(&self, world: &mut World, context: &ChunkContext, surface: impl Fn(i32, i32) -> i32) {
+        let three_d = world.is_3d();
+        let column = ChunkPos { y: if three_d { context.chunk.y } else { 0 }, z: 0, ..context.chunk };
+        let mut rng = ChunkContext { chunk: column, ..*context }.rng("structures");
+        let mut structures: Vec<&Structure> = context
+            .registry
+            .structures
+            .values()
+            .filter(|s| s.rarity > 0 && (self.only.is_empty() || self.only.contains(&s.id)))
+            .collect();
+        structures.sort_by_key(|s| s.id.to_string());
+        let origin = context.chunk.origin();
+        for structure in structures {
+            // Rolled first, so every chunk of the column draws the same numbers
+            let hit = rng.chance(1.0 / structure.rarity as f32);
+            let x = origin.x + rng.range(0, (CHUNK_SIZE - structure.width() as i32).max(0) as u32) as i32;
+            let y = origin.y + rng.range(0, (CHUNK_SIZE - structure.length() as i32).max(0) as u32) as i32;
+            if !hit {
+                continue;
+            }
+            let (pos, ground) = if three_d {
+                let height = surface(x, y);
+                (BlockPos::new_3d(x, y, height + 1), BlockPos::new_3d(x, y, height))
+            } else {
+                let height = surface(x, 0);
+                (BlockPos::new(x, -height - structure.length() as i32), BlockPos::new(x, -height))
+            };
+            let in_biome = structure.biomes.is_empty()
+                || world.biome(ground).is_some_and(|biome| structure.biomes.iter().any(|id| context.registry.biome_matches(&biome, id)));
+            if context.chunk.contains(ground) && in_biome {
+                world.paste_structure(pos, structure);
+            }
+        }
+    }
+}
+
+// ------------
+// LAZY LOADING
+// ------------
+
+// A World whose chunks are generated the first time a position in them is
+// accessed. Blocks broken or placed afterwards stay, generated chunks are
+// never generated again.
+pub struct GeneratedWorld {
+    world: World,
+    generator: Box<dyn ChunkGenerator>,
+    seed: u64,
+    generated: HashSet<ChunkPos>,
+}
+
+impl GeneratedWorld {
+    // Seeded from a "worldgen" fork of `rng`
+    pub fn new(world: World, generator: impl ChunkGenerator + 'static, rng: &mut RuzRng) -> Self {
+        let seed = rng.fork("worldgen").next_u64();
+        Self::with_seed(world, generator, seed)
+    }
+
+    pub fn with_seed(world: World, generator: impl ChunkGenerator + 'static, seed: u64) -> Self {
+        GeneratedWorld { world, generator: Box::new(generator), seed, generated: HashSet::new() }
+    }
+
+    // Marks chunks of a loaded world as generated, see generated_chunks
+    pub fn with_generated(mut self, chunks: impl IntoIterator<Item = ChunkPos>) -> Self {
+        self.generated.extend(chunks);
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn is_generated(&self, chunk: ChunkPos) -> bool {
+        self.generated.contains(&chunk)
+    }
+
+    // Sorted, to be saved next to the world
+    pub fn generated_chunks(&self) -> Vec<ChunkPos> {
+        let mut chunks: Vec<ChunkPos> = self.generated.iter().copied().collect();
+        chunks.sort();
+        chunks
+    }
+
+    // Generates the chunk unless it was before, returns whether it did
+    pub fn generate_chunk(&mut self, chunk: ChunkPos, registry: &Registry) -> bool {
+        if !self.generated.insert(chunk) {
+            return false;
+        }
+        let context = ChunkContext { chunk, seed: self.seed, registry };
+        self.generator.generate(&mut self.world, &context);
+        true
+    }
+
+    pub fn get(&mut self, pos: BlockPos, registry: &Registry) -> Option<&BlockState> {
+        self.generate_chunk(pos.chunk(), registry);
+        self.world.get(pos)
+    }
+
+    // The world with the chunk of `pos` generated, e.g. to break a block
+    pub fn load(&mut self, pos: BlockPos, registry: &Registry) -> &mut World {
+        self.generate_chunk(pos.chunk(), registry);
+        &mut self.world
+    }
+
+    // The world with every chunk in the box between `from` and `to`
+    // (inclusive) generated, e.g. the area shown by a Minimap
+    pub fn load_area(&mut self, from: BlockPos, to: BlockPos, registry: &Registry) -> &mut World {
+        let (a, b) = (from.chunk(), to.chunk());
+        for z in a.z.min(b.z)..=a.z.max(b.z) {
+            for y in a.y.min(b.y)..=a.y.max(b.y) {
+                for x in a.x.min(b.x)..=a.x.max(b.x) {
+                    self.generate_chunk(ChunkPos { x, y, z }, registry);
+                }
+            }
+        }
+        &mut self.world
+    }
+
+    // Only the chunks generated so far
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn into_world(self) -> World {
+        self.world
+    }
+}